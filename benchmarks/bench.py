@@ -31,6 +31,7 @@ def count_cores_per_numa_node():
 
 SECONDS = 60
 
+# supported by the vspace binaries: fill, interleave, smt, roundrobin
 MODES=['fill', 'interleave']
 CORES_PER_NODE = count_cores_per_numa_node()
 NODES = count_numa_nodes()
@@ -0,0 +1,102 @@
+#!/usr/bin/env python3
+
+# Compares the results of the verified node-replication with the results of
+# the unverified upstream node-replication crate for identical workloads.
+#
+# Usage: ./compare.py [data-verified.json] [data-upstream.json]
+#
+# Both files are produced by `bench.py` (see `run_benchmarks.sh`). Runs are
+# matched by data-structure, number of threads, read percentage, number of
+# replicas and NUMA policy. The overhead of the verified implementation is
+# reported relative to upstream: `overhead = 1 - verified / upstream`, i.e.,
+# a positive number means the verified version is slower.
+
+import sys
+import csv
+import json
+import re
+
+VERIFIED = 'data-verified.json'
+UPSTREAM = 'data-upstream.json'
+OUTPUT = 'overhead.csv'
+
+
+def bench_kind(bench_name):
+    """Name of the data-structure, e.g., `vnr_vspace-4-10-fill-run0` -> `vspace`."""
+    m = re.match(r'^\s*v?nr[-_]([a-z0-9]+)', bench_name)
+    if m is None:
+        return bench_name.strip()
+    return m.group(1)
+
+
+def run_key(record):
+    return (bench_kind(record['bench_name']), record['n_threads'], record['reads_pct'],
+            record['n_replicas'], record['numa_policy'])
+
+
+def load(path):
+    """Loads the records of `path`, averaging the throughput of repeated runs."""
+    with open(path) as f:
+        records = json.load(f)
+
+    runs = {}
+    for record in records:
+        runs.setdefault(run_key(record), []).append(record['ops_per_s'])
+    return {key: sum(ops) / len(ops) for key, ops in runs.items()}
+
+
+def compare(verified, upstream):
+    rows = []
+    for key in sorted(verified.keys() & upstream.keys()):
+        (bench, n_threads, reads_pct, n_replicas, numa_policy) = key
+        v = verified[key]
+        u = upstream[key]
+        overhead = 1.0 - v / u if u > 0 else float('nan')
+        rows.append({
+            'bench': bench,
+            'n_threads': n_threads,
+            'reads_pct': reads_pct,
+            'n_replicas': n_replicas,
+            'numa_policy': numa_policy,
+            'verified_ops_per_s': v,
+            'upstream_ops_per_s': u,
+            'overhead': overhead,
+        })
+
+    for key in sorted(verified.keys() ^ upstream.keys()):
+        which = 'upstream' if key in verified else 'verified'
+        print('warning: no %s run for %s' % (which, key), file=sys.stderr)
+
+    return rows
+
+
+def print_rows(rows):
+    print('%-10s %8s %6s %9s %12s %16s %16s %9s' % ('bench', 'threads', 'reads', 'replicas',
+          'numa', 'verified [op/s]', 'upstream [op/s]', 'overhead'))
+    for r in rows:
+        print('%-10s %8d %6d %9d %12s %16.0f %16.0f %8.2f%%' % (r['bench'], r['n_threads'],
+              r['reads_pct'], r['n_replicas'], r['numa_policy'], r['verified_ops_per_s'],
+              r['upstream_ops_per_s'], r['overhead'] * 100))
+
+    if len(rows) > 0:
+        mean = sum(r['overhead'] for r in rows) / len(rows)
+        worst = max(r['overhead'] for r in rows)
+        print('')
+        print('mean overhead: %.2f%%, max overhead: %.2f%%' % (mean * 100, worst * 100))
+
+
+def write_csv(rows, path):
+    with open(path, 'w', newline='') as f:
+        wtr = csv.DictWriter(f, fieldnames=['bench', 'n_threads', 'reads_pct', 'n_replicas',
+                             'numa_policy', 'verified_ops_per_s', 'upstream_ops_per_s', 'overhead'])
+        wtr.writeheader()
+        wtr.writerows(rows)
+
+
+if __name__ == '__main__':
+    verified_path = sys.argv[1] if len(sys.argv) > 1 else VERIFIED
+    upstream_path = sys.argv[2] if len(sys.argv) > 2 else UPSTREAM
+
+    rows = compare(load(verified_path), load(upstream_path))
+    print_rows(rows)
+    write_csv(rows, OUTPUT)
@@ -0,0 +1,121 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Records the build environment for the metadata of the benchmark results
+//! (see `metadata.rs`). The enabled features are recorded by the benchmark
+//! crates themselves, the ones of `bench_utils` don't match them.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Runs `cmd` and returns the first line of its output, if it succeeded.
+fn output_of(cmd: &mut Command) -> Option<String> {
+    let output = cmd.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    stdout.lines().next().map(|l| l.trim().to_string())
+}
+
+/// The sources in the repository the benchmarks are built from, a change to
+/// any of them marks the commit `-dirty`.
+const REPOSITORY_SOURCES: &[&str] = &[
+    "verified-node-replication/src",
+    "verified-node-replication/Cargo.toml",
+    "benchmarks/lib/bench_utils/src",
+    "benchmarks/lib/bench_utils/build.rs",
+    "benchmarks/lib/bench_utils/Cargo.toml",
+    "benchmarks/verified/src",
+    "benchmarks/verified/benches",
+    "benchmarks/verified/build.rs",
+    "benchmarks/verified/Cargo.toml",
+    "benchmarks/upstream/src",
+    "benchmarks/upstream/benches",
+    "benchmarks/upstream/build.rs",
+    "benchmarks/upstream/Cargo.toml",
+];
+
+/// The sources in the Verus submodule the benchmarks are built from.
+const VERUS_SOURCES: &[&str] = &["source/builtin", "source/builtin_macros", "source/vstd"];
+
+/// The commit checked out in `dir`, with a `-dirty` suffix for local changes
+/// to the tracked files in `sources`.
+///
+/// `dir` must be the root of a checkout (or submodule), otherwise git would
+/// report the commit of the enclosing repository. Asks cargo to rerun the
+/// build script when the commit, the index or one of the `sources` changes,
+/// so the suffix follows the edits made between two builds.
+fn git_commit(dir: &Path, sources: &[&str]) -> Option<String> {
+    if !dir.join(".git").exists() {
+        return None;
+    }
+    // a submodule has its git directory in the one of the enclosing repository
+    let git_dir = output_of(
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "--absolute-git-dir"]),
+    )?;
+    rerun_if_changed(&Path::new(&git_dir).join("HEAD"));
+    rerun_if_changed(&Path::new(&git_dir).join("index"));
+    for source in sources {
+        rerun_if_changed(&dir.join(source));
+    }
+
+    let commit = output_of(
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "HEAD"]),
+    )?;
+    let dirty = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["status", "--porcelain", "--untracked-files=no", "--"])
+        .args(sources)
+        .output()
+        .map_or(false, |o| !o.stdout.is_empty());
+    Some(if dirty {
+        format!("{commit}-dirty")
+    } else {
+        commit
+    })
+}
+
+/// Asks cargo to rerun the build script when `path` changes. Directories are
+/// scanned recursively. A missing path would rerun the script on every build,
+/// so it is skipped.
+fn rerun_if_changed(path: &Path) {
+    if path.exists() {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+}
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let repository = Path::new(&manifest_dir).join("../../..");
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = output_of(Command::new(rustc).arg("--version"));
+
+    // Verus has no version number, the commit of the submodule identifies it
+    let verus_version = git_commit(&repository.join("verus"), VERUS_SOURCES);
+    let commit = git_commit(&repository, REPOSITORY_SOURCES);
+
+    println!(
+        "cargo:rustc-env=BENCH_RUSTC_VERSION={}",
+        rustc_version.unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=BENCH_GIT_COMMIT={}",
+        commit.unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=BENCH_VERUS_VERSION={}",
+        verus_version.unwrap_or_default()
+    );
+
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,497 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Baselines for the scalability benchmarks.
+//!
+//! The baselines wrap the data-structure with a synchronization technique and
+//! implement [`DsInterface`] so they can be benchmarked with the same
+//! workloads and harness as node-replication:
+//!
+//!  - [`StdRwLockBaseline`]: `std::sync::RwLock`
+//!  - [`ParkingLotRwLockBaseline`]: `parking_lot::RwLock`
+//!  - [`MutexBaseline`]: a single big `std::sync::Mutex` (reads also take the lock)
+//!  - [`RcuBaseline`]: read-copy-update with epoch-based reclamation
+//!    (`crossbeam-epoch`), reads never wait, every update copies the
+//!    data-structure
+//!  - [`LeftRightBaseline`]: two copies with the `left-right` crate, reads
+//!    never wait, every update is applied to both copies
+//!
+//! The last two are what node-replication is most often compared against
+//! for read-mostly workloads. They need `D: Clone`, and RCU copies the whole
+//! data-structure on every update, so use them with small data-structures.
+//!
+//! The baselines have no notion of replicas, the replica id passed to
+//! `register` is only recorded in the token.
+
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "unverified")]
+use node_replication::Dispatch;
+#[cfg(feature = "verified")]
+use verified_node_replication::Dispatch;
+
+use crate::mkbench::{BenchToken, DsInterface};
+
+/// Whether the baselines should be run, enabled with `--baselines`.
+pub fn baselines_enabled() -> bool {
+    std::env::args().any(|arg| arg == "--baselines")
+}
+
+/// A lock that protects the data-structure of a baseline.
+pub trait BaselineLock<D>: Send + Sync {
+    /// Name of the baseline, used to name the benchmark runs.
+    const NAME: &'static str;
+
+    /// Wraps the data-structure in the lock.
+    fn new(ds: D) -> Self;
+
+    /// Runs `f` with shared access to the data-structure.
+    fn read<R, F: FnOnce(&D) -> R>(&self, f: F) -> R;
+
+    /// Runs `f` with exclusive access to the data-structure.
+    fn write<R, F: FnOnce(&mut D) -> R>(&self, f: F) -> R;
+}
+
+/// Protects the data-structure with `std::sync::RwLock`.
+pub struct StdRwLock<D>(std::sync::RwLock<D>);
+
+impl<D: Send + Sync> BaselineLock<D> for StdRwLock<D> {
+    const NAME: &'static str = "std-rwlock";
+
+    fn new(ds: D) -> Self {
+        StdRwLock(std::sync::RwLock::new(ds))
+    }
+
+    fn read<R, F: FnOnce(&D) -> R>(&self, f: F) -> R {
+        f(&self.0.read().unwrap())
+    }
+
+    fn write<R, F: FnOnce(&mut D) -> R>(&self, f: F) -> R {
+        f(&mut self.0.write().unwrap())
+    }
+}
+
+/// Protects the data-structure with `parking_lot::RwLock`.
+pub struct ParkingLotRwLock<D>(parking_lot::RwLock<D>);
+
+impl<D: Send + Sync> BaselineLock<D> for ParkingLotRwLock<D> {
+    const NAME: &'static str = "parking-lot-rwlock";
+
+    fn new(ds: D) -> Self {
+        ParkingLotRwLock(parking_lot::RwLock::new(ds))
+    }
+
+    fn read<R, F: FnOnce(&D) -> R>(&self, f: F) -> R {
+        f(&self.0.read())
+    }
+
+    fn write<R, F: FnOnce(&mut D) -> R>(&self, f: F) -> R {
+        f(&mut self.0.write())
+    }
+}
+
+/// Protects the data-structure with a single big `std::sync::Mutex`.
+pub struct BigMutex<D>(std::sync::Mutex<D>);
+
+impl<D: Send> BaselineLock<D> for BigMutex<D> {
+    const NAME: &'static str = "mutex";
+
+    fn new(ds: D) -> Self {
+        BigMutex(std::sync::Mutex::new(ds))
+    }
+
+    fn read<R, F: FnOnce(&D) -> R>(&self, f: F) -> R {
+        f(&self.0.lock().unwrap())
+    }
+
+    fn write<R, F: FnOnce(&mut D) -> R>(&self, f: F) -> R {
+        f(&mut self.0.lock().unwrap())
+    }
+}
+
+/// Read-copy-update: readers access the current copy without waiting,
+/// writers (serialized by a mutex) copy it, update the copy and publish it.
+/// The old copy is freed once no reader can access it anymore.
+pub struct EpochRcu<D> {
+    current: crossbeam_epoch::Atomic<D>,
+    writer: parking_lot::Mutex<()>,
+}
+
+impl<D: Clone + Send + Sync> BaselineLock<D> for EpochRcu<D> {
+    const NAME: &'static str = "rcu-epoch";
+
+    fn new(ds: D) -> Self {
+        EpochRcu {
+            current: crossbeam_epoch::Atomic::new(ds),
+            writer: parking_lot::Mutex::new(()),
+        }
+    }
+
+    fn read<R, F: FnOnce(&D) -> R>(&self, f: F) -> R {
+        let guard = crossbeam_epoch::pin();
+        let current = self.current.load(Ordering::Acquire, &guard);
+        // SAFETY: `current` is never null and not freed while `guard` is alive
+        f(unsafe { current.deref() })
+    }
+
+    fn write<R, F: FnOnce(&mut D) -> R>(&self, f: F) -> R {
+        let _writer = self.writer.lock();
+        let guard = crossbeam_epoch::pin();
+        let old = self.current.load(Ordering::Acquire, &guard);
+        // SAFETY: as above, and only writers replace `current`
+        let mut new = unsafe { old.deref() }.clone();
+        let r = f(&mut new);
+        self.current
+            .store(crossbeam_epoch::Owned::new(new), Ordering::Release);
+        // SAFETY: `old` is unreachable now, readers still using it are pinned
+        unsafe { guard.defer_destroy(old) };
+        r
+    }
+}
+
+impl<D> Drop for EpochRcu<D> {
+    fn drop(&mut self) {
+        // SAFETY: we have exclusive access, no reader is left
+        unsafe {
+            let current = self
+                .current
+                .load(Ordering::Relaxed, crossbeam_epoch::unprotected());
+            drop(current.into_owned());
+        }
+    }
+}
+
+/// The token of a thread registered with a baseline.
+#[derive(Debug, Clone, Copy)]
+pub struct BaselineToken {
+    rid: usize,
+    tid: usize,
+}
+
+impl BenchToken for BaselineToken {
+    fn replica_id(&self) -> usize {
+        self.rid
+    }
+
+    fn thread_id(&self) -> usize {
+        self.tid
+    }
+}
+
+/// A data-structure `D` protected by the lock `L`.
+pub struct LockBaseline<D, L: BaselineLock<D>> {
+    lock: L,
+    /// Thread id handed out to the next registering thread
+    next_tid: AtomicUsize,
+    _marker: PhantomData<D>,
+}
+
+impl<D, L: BaselineLock<D>> LockBaseline<D, L> {
+    /// Name of the baseline, used to name the benchmark runs.
+    pub fn name() -> &'static str {
+        L::NAME
+    }
+
+    fn with_ds(ds: D) -> Self {
+        LockBaseline {
+            lock: L::new(ds),
+            next_tid: AtomicUsize::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    fn next_token(&self, rid: usize) -> BaselineToken {
+        BaselineToken {
+            rid,
+            tid: self.next_tid.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+/// `std::sync::RwLock` baseline for `D`.
+pub type StdRwLockBaseline<D> = LockBaseline<D, StdRwLock<D>>;
+
+/// `parking_lot::RwLock` baseline for `D`.
+pub type ParkingLotRwLockBaseline<D> = LockBaseline<D, ParkingLotRwLock<D>>;
+
+/// Single big `std::sync::Mutex` baseline for `D`.
+pub type MutexBaseline<D> = LockBaseline<D, BigMutex<D>>;
+
+/// Read-copy-update baseline for `D` (see [`EpochRcu`]).
+pub type RcuBaseline<D> = LockBaseline<D, EpochRcu<D>>;
+
+#[cfg(feature = "unverified")]
+impl<D, L> DsInterface for LockBaseline<D, L>
+where
+    D: Dispatch + Default + Sync,
+    L: BaselineLock<D>,
+{
+    type D = D;
+    type TT = BaselineToken;
+
+    fn new(_replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Arc<Self> {
+        Arc::new(LockBaseline::with_ds(D::default()))
+    }
+
+    fn register(&self, rid: usize) -> Option<BaselineToken> {
+        Some(self.next_token(rid))
+    }
+
+    fn execute_mut(
+        &self,
+        op: <D as Dispatch>::WriteOperation,
+        _idx: BaselineToken,
+    ) -> <D as Dispatch>::Response {
+        self.lock.write(|ds| ds.dispatch_mut(op))
+    }
+
+    fn execute(
+        &self,
+        op: <D as Dispatch>::ReadOperation,
+        _idx: BaselineToken,
+    ) -> <D as Dispatch>::Response {
+        self.lock.read(|ds| ds.dispatch(op))
+    }
+}
+
+#[cfg(feature = "verified")]
+impl<D, L> DsInterface for LockBaseline<D, L>
+where
+    D: Dispatch + Default + Sync,
+    L: BaselineLock<D>,
+{
+    type D = D;
+    type TT = BaselineToken;
+
+    fn new(_replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Self {
+        LockBaseline::with_ds(D::default())
+    }
+
+    fn register(&mut self, rid: usize) -> Option<BaselineToken> {
+        Some(self.next_token(rid))
+    }
+
+    fn execute_mut(
+        &self,
+        op: <D as Dispatch>::WriteOperation,
+        idx: BaselineToken,
+    ) -> Result<(<D as Dispatch>::Response, BaselineToken), BaselineToken> {
+        Ok((self.lock.write(|ds| ds.dispatch_mut(op)), idx))
+    }
+
+    fn execute(
+        &self,
+        op: <D as Dispatch>::ReadOperation,
+        idx: BaselineToken,
+    ) -> Result<(<D as Dispatch>::Response, BaselineToken), BaselineToken> {
+        Ok((self.lock.read(|ds| ds.dispatch(op)), idx))
+    }
+}
+
+/// Clones the update operations of `D`, the left-right baseline applies
+/// every update twice.
+pub trait CloneWriteOp: Dispatch {
+    fn clone_write_op_of(op: &Self::WriteOperation) -> Self::WriteOperation;
+}
+
+#[cfg(feature = "unverified")]
+impl<D: Dispatch> CloneWriteOp for D
+where
+    D::WriteOperation: Clone,
+{
+    fn clone_write_op_of(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+}
+
+#[cfg(feature = "verified")]
+impl<D: Dispatch> CloneWriteOp for D {
+    fn clone_write_op_of(op: &Self::WriteOperation) -> Self::WriteOperation {
+        D::clone_write_op(op)
+    }
+}
+
+/// An update of a [`LeftRightBaseline`].
+pub struct LeftRightOp<D: Dispatch> {
+    op: D::WriteOperation,
+    /// Response of the first application, taken by the writer
+    response: Arc<parking_lot::Mutex<Option<D::Response>>>,
+}
+
+/// One of the two copies of a [`LeftRightBaseline`].
+#[derive(Clone)]
+pub struct LeftRightCopy<D>(D);
+
+impl<D: CloneWriteOp + Clone> left_right::Absorb<LeftRightOp<D>> for LeftRightCopy<D> {
+    fn absorb_first(&mut self, op: &mut LeftRightOp<D>, _other: &Self) {
+        let response = self.0.dispatch_mut(D::clone_write_op_of(&op.op));
+        *op.response.lock() = Some(response);
+    }
+
+    fn absorb_second(&mut self, op: LeftRightOp<D>, _other: &Self) {
+        // before the first publish, updates are only applied with this
+        let response = self.0.dispatch_mut(op.op);
+        op.response.lock().get_or_insert(response);
+    }
+
+    fn sync_with(&mut self, first: &Self) {
+        *self = first.clone();
+    }
+}
+
+/// Maximum number of threads registered with a [`LeftRightBaseline`].
+pub const LEFT_RIGHT_MAX_THREADS: usize = 1024;
+
+/// A read handle of a thread, only used by that thread.
+type ReaderSlot<D> =
+    crossbeam_utils::CachePadded<UnsafeCell<Option<left_right::ReadHandle<LeftRightCopy<D>>>>>;
+
+/// Left-right baseline for `D`: readers use one copy without waiting, a
+/// writer (serialized by a mutex) updates the other copy, swaps the copies,
+/// waits for the readers to leave the old copy and updates it as well.
+///
+/// Every thread gets its own read handle on `register`.
+pub struct LeftRightBaseline<D: CloneWriteOp + Clone> {
+    writer: parking_lot::Mutex<left_right::WriteHandle<LeftRightCopy<D>, LeftRightOp<D>>>,
+    factory: left_right::ReadHandleFactory<LeftRightCopy<D>>,
+    /// Read handles, indexed by the thread id of the token
+    readers: Box<[ReaderSlot<D>]>,
+    /// Thread id handed out to the next registering thread
+    next_tid: AtomicUsize,
+}
+
+// SAFETY: a slot of `readers` is only written when its thread id is handed
+// out in `register`, and afterwards only used by the thread with that token.
+unsafe impl<D: CloneWriteOp + Clone> Sync for LeftRightBaseline<D> where
+    left_right::ReadHandle<LeftRightCopy<D>>: Send
+{
+}
+
+impl<D> LeftRightBaseline<D>
+where
+    D: CloneWriteOp + Clone + Default,
+{
+    /// Name of the baseline, used to name the benchmark runs.
+    pub fn name() -> &'static str {
+        "left-right"
+    }
+
+    fn with_ds(ds: D) -> Self {
+        let (mut writer, reader) = left_right::new_from_empty(LeftRightCopy(ds));
+        // leave the initialization phase, updates are applied twice from now on
+        writer.publish();
+        LeftRightBaseline {
+            writer: parking_lot::Mutex::new(writer),
+            factory: reader.factory(),
+            readers: (0..LEFT_RIGHT_MAX_THREADS)
+                .map(|_| crossbeam_utils::CachePadded::new(UnsafeCell::new(None)))
+                .collect(),
+            next_tid: AtomicUsize::new(0),
+        }
+    }
+
+    fn next_token(&self, rid: usize) -> Option<BaselineToken> {
+        let tid = self.next_tid.fetch_add(1, Ordering::Relaxed);
+        if tid >= LEFT_RIGHT_MAX_THREADS {
+            return None;
+        }
+        // SAFETY: `tid` was never handed out before, nobody else uses the slot
+        unsafe { *self.readers[tid].get() = Some(self.factory.handle()) };
+        Some(BaselineToken { rid, tid })
+    }
+
+    fn read<R, F: FnOnce(&D) -> R>(&self, idx: BaselineToken, f: F) -> R {
+        // SAFETY: only the thread with the token `idx` uses this slot
+        let reader = unsafe { &*self.readers[idx.tid].get() };
+        let copy = reader
+            .as_ref()
+            .and_then(|r| r.enter())
+            .expect("thread not registered or writer gone");
+        f(&copy.0)
+    }
+
+    fn write(&self, op: <D as Dispatch>::WriteOperation) -> <D as Dispatch>::Response {
+        let response = Arc::new(parking_lot::Mutex::new(None));
+        let mut writer = self.writer.lock();
+        writer.append(LeftRightOp {
+            op,
+            response: response.clone(),
+        });
+        writer.publish();
+        drop(writer);
+        let response = response.lock().take();
+        response.expect("update was not applied")
+    }
+}
+
+#[cfg(feature = "unverified")]
+impl<D> DsInterface for LeftRightBaseline<D>
+where
+    D: CloneWriteOp + Clone + Default + Send + Sync,
+    <D as Dispatch>::Response: Send,
+{
+    type D = D;
+    type TT = BaselineToken;
+
+    fn new(_replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Arc<Self> {
+        Arc::new(LeftRightBaseline::with_ds(D::default()))
+    }
+
+    fn register(&self, rid: usize) -> Option<BaselineToken> {
+        self.next_token(rid)
+    }
+
+    fn execute_mut(
+        &self,
+        op: <D as Dispatch>::WriteOperation,
+        _idx: BaselineToken,
+    ) -> <D as Dispatch>::Response {
+        self.write(op)
+    }
+
+    fn execute(
+        &self,
+        op: <D as Dispatch>::ReadOperation,
+        idx: BaselineToken,
+    ) -> <D as Dispatch>::Response {
+        self.read(idx, |ds| ds.dispatch(op))
+    }
+}
+
+#[cfg(feature = "verified")]
+impl<D> DsInterface for LeftRightBaseline<D>
+where
+    D: CloneWriteOp + Clone + Default + Send + Sync,
+    <D as Dispatch>::Response: Send,
+{
+    type D = D;
+    type TT = BaselineToken;
+
+    fn new(_replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Self {
+        LeftRightBaseline::with_ds(D::default())
+    }
+
+    fn register(&mut self, rid: usize) -> Option<BaselineToken> {
+        self.next_token(rid)
+    }
+
+    fn execute_mut(
+        &self,
+        op: <D as Dispatch>::WriteOperation,
+        idx: BaselineToken,
+    ) -> Result<(<D as Dispatch>::Response, BaselineToken), BaselineToken> {
+        Ok((self.write(op), idx))
+    }
+
+    fn execute(
+        &self,
+        op: <D as Dispatch>::ReadOperation,
+        idx: BaselineToken,
+    ) -> Result<(<D as Dispatch>::Response, BaselineToken), BaselineToken> {
+        Ok((self.read(idx, |ds| ds.dispatch(op)), idx))
+    }
+}
@@ -147,9 +147,9 @@ impl TestHarness {
 
 impl TestHarness {
     pub fn new(d: Duration) -> Self {
-        if cfg!(feature = "smokebench") {
-            log::warn!("smokebench enabled, force execution to 500 ms");
-            let d = Duration::from_millis(500);
+        if crate::smoke_enabled() {
+            log::warn!("smoke run, force execution to 1 s");
+            let d = Duration::from_secs(1);
             TestHarness { duration: d }
         } else {
             TestHarness { duration: d }
@@ -159,8 +159,8 @@ impl TestHarness {
 
 impl Default for TestHarness {
     fn default() -> Self {
-        if cfg!(feature = "smokebench") {
-            TestHarness::new(Duration::from_millis(500))
+        if crate::smoke_enabled() {
+            TestHarness::new(Duration::from_secs(1))
         } else {
             TestHarness::new(Duration::from_secs(5))
         }
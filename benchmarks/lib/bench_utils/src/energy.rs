@@ -0,0 +1,116 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Package energy of a benchmark run (feature `energy`).
+//!
+//! The energy is read from the RAPL counters that Linux exposes with the
+//! powercap framework (`/sys/class/powercap/intel-rapl:<n>/energy_uj`, also
+//! on AMD). Only the package domains are read, they include the cores, the
+//! caches and the memory controller of a socket but not the DRAM itself. The
+//! counters are machine-wide: everything else running on the machine is
+//! included as well.
+//!
+//! Most kernels only allow root to read the counters, if they can't be read
+//! the energy is reported as `None`. Without the `energy` feature no counters
+//! are read at all.
+
+use serde::Serialize;
+
+/// The energy consumed during a run.
+#[derive(Serialize, Debug, Default, Copy, Clone, PartialEq)]
+pub struct EnergySample {
+    /// Energy of all packages in joules
+    pub joules: f64,
+    /// Number of package domains that were read
+    pub packages: usize,
+}
+
+/// The RAPL package counters of the machine.
+#[cfg(all(feature = "energy", target_os = "linux"))]
+pub struct EnergyCounters {
+    /// Directory, value at `start` and wrap-around range of every package.
+    domains: Vec<(std::path::PathBuf, u64, u64)>,
+}
+
+#[cfg(all(feature = "energy", target_os = "linux"))]
+impl EnergyCounters {
+    const POWERCAP: &'static str = "/sys/class/powercap";
+
+    fn read_u64(path: &std::path::Path) -> Option<u64> {
+        std::fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
+    /// Finds the package domains, the counters are stopped.
+    pub fn new() -> EnergyCounters {
+        let mut domains = Vec::new();
+        let entries = match std::fs::read_dir(Self::POWERCAP) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Can't read {}: {}", Self::POWERCAP, e);
+                return EnergyCounters { domains };
+            }
+        };
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            let name = std::fs::read_to_string(dir.join("name")).unwrap_or_default();
+            // sub-domains (`intel-rapl:0:0`) are part of their package
+            let top_level = entry.file_name().to_string_lossy().matches(':').count() == 1;
+            if !top_level || !name.starts_with("package") {
+                continue;
+            }
+            match (
+                Self::read_u64(&dir.join("energy_uj")),
+                Self::read_u64(&dir.join("max_energy_range_uj")),
+            ) {
+                (Some(_), Some(range)) => domains.push((dir, 0, range)),
+                _ => log::warn!("Can't read the energy of {} (needs root?)", dir.display()),
+            }
+        }
+        EnergyCounters { domains }
+    }
+
+    /// Reads the current values of the counters.
+    pub fn start(&mut self) {
+        for (dir, start, _) in self.domains.iter_mut() {
+            *start = Self::read_u64(&dir.join("energy_uj")).unwrap_or(0);
+        }
+    }
+
+    /// Returns the energy consumed since the last `start`.
+    pub fn stop(&mut self) -> Option<EnergySample> {
+        if self.domains.is_empty() {
+            return None;
+        }
+        let mut uj = 0;
+        for (dir, start, range) in self.domains.iter() {
+            let now = Self::read_u64(&dir.join("energy_uj"))?;
+            // the counter wraps around at `max_energy_range_uj`
+            uj += if now >= *start {
+                now - start
+            } else {
+                range - start + now
+            };
+        }
+        Some(EnergySample {
+            joules: uj as f64 / 1e6,
+            packages: self.domains.len(),
+        })
+    }
+}
+
+/// No energy counters without the `energy` feature (or on other OSes).
+#[cfg(not(all(feature = "energy", target_os = "linux")))]
+pub struct EnergyCounters;
+
+#[cfg(not(all(feature = "energy", target_os = "linux")))]
+impl EnergyCounters {
+    pub fn new() -> EnergyCounters {
+        EnergyCounters
+    }
+
+    pub fn start(&mut self) {}
+
+    pub fn stop(&mut self) -> Option<EnergySample> {
+        None
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Backs memory regions with transparent huge pages.
+//!
+//! The log of the verified implementation is a regular heap allocation, so it
+//! can't be mapped with `MAP_HUGETLB` directly. Instead the region is marked
+//! with `madvise(MADV_HUGEPAGE)` before it is touched, and the kernel backs
+//! the 2 MiB aligned part of it with transparent huge pages. 1 GiB pages are
+//! only available through hugetlbfs and are not supported for heap memory.
+//!
+//! If transparent huge pages are disabled or the region is too small, the
+//! memory stays backed by base pages, [`log_mem_fn`] only logs a warning.
+
+use std::io;
+
+/// The size of a transparent huge page.
+pub const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Advises the kernel to back the region `[ptr, ptr + len)` with huge pages.
+///
+/// Only the 2 MiB aligned part of the region can be backed by huge pages,
+/// returns the number of bytes of that part.
+#[cfg(target_os = "linux")]
+pub fn advise_huge_pages(ptr: *const u8, len: usize) -> io::Result<usize> {
+    let start = (ptr as usize + HUGE_PAGE_SIZE - 1) & !(HUGE_PAGE_SIZE - 1);
+    let end = (ptr as usize + len) & !(HUGE_PAGE_SIZE - 1);
+    if start >= end {
+        return Ok(0);
+    }
+
+    let r = unsafe {
+        libc::madvise(
+            start as *mut libc::c_void,
+            end - start,
+            libc::MADV_HUGEPAGE,
+        )
+    };
+    if r < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(end - start)
+}
+
+/// The amount of anonymous memory of this process that is backed by
+/// transparent huge pages, in bytes.
+#[cfg(target_os = "linux")]
+pub fn anon_huge_pages() -> io::Result<usize> {
+    let smaps = std::fs::read_to_string("/proc/self/smaps_rollup")?;
+    for line in smaps.lines() {
+        if let Some(kb) = line.strip_prefix("AnonHugePages:") {
+            let kb = kb.trim().trim_end_matches("kB").trim();
+            return kb
+                .parse::<usize>()
+                .map(|kb| kb * 1024)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no AnonHugePages entry in /proc/self/smaps_rollup",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn advise_huge_pages(_ptr: *const u8, _len: usize) -> io::Result<usize> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "transparent huge pages are only supported on Linux",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn anon_huge_pages() -> io::Result<usize> {
+    advise_huge_pages(std::ptr::null(), 0)
+}
+
+/// The function to pass to `LogMemFn::new`, backs the log with huge pages
+/// and falls back to base pages if that's not possible.
+pub fn log_mem_fn(ptr: *const u8, len: usize) {
+    match advise_huge_pages(ptr, len) {
+        Ok(0) => log::warn!("Log of {} bytes is too small for huge pages", len),
+        Ok(bytes) => log::info!("Backing {} of {} bytes of the log with huge pages", bytes, len),
+        Err(e) => log::warn!("Can't back the log with huge pages, using base pages: {}", e),
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Operation latencies of the benchmark threads.
+//!
+//! Latencies are recorded in nanoseconds into a log-linear histogram: every
+//! power of two is split into 8 linear sub-buckets, so a percentile is off by
+//! at most 12.5%. Recording is a few instructions and the histogram has a
+//! fixed size, independent of the number of recorded operations.
+//!
+//! Without an offered load the threads run in a closed loop, and only every
+//! [`CLOSED_LOOP_SAMPLE`]-th operation is timed to keep the clock reads out of
+//! the throughput. With an offered load (see `ScaleBenchBuilder::offered_loads`)
+//! every operation is timed from the time it was scheduled, not from the time
+//! it started, so the latencies include the time an operation waited for its
+//! predecessors (no coordinated omission).
+
+use std::ops::AddAssign;
+use std::time::Duration;
+
+/// Only every n-th operation is timed in a closed loop.
+pub const CLOSED_LOOP_SAMPLE: usize = 64;
+
+/// Linear sub-buckets per power of two (as bits).
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+
+/// Number of buckets to cover all `u64` values.
+const BUCKETS: usize = (u64::BITS - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS;
+
+/// A histogram of operation latencies in nanoseconds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: Vec<u64>,
+    count: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> LatencyHistogram {
+        LatencyHistogram::new()
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new() -> LatencyHistogram {
+        LatencyHistogram {
+            buckets: vec![0; BUCKETS],
+            count: 0,
+        }
+    }
+
+    fn bucket(ns: u64) -> usize {
+        if ns < SUB_BUCKETS as u64 {
+            return ns as usize;
+        }
+        let exp = u64::BITS - 1 - ns.leading_zeros();
+        let sub = (ns >> (exp - SUB_BUCKET_BITS)) as usize & (SUB_BUCKETS - 1);
+        (exp - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS + sub
+    }
+
+    /// The smallest latency that falls into bucket `idx`.
+    fn bucket_start(idx: usize) -> u64 {
+        if idx < SUB_BUCKETS {
+            return idx as u64;
+        }
+        let exp = (idx / SUB_BUCKETS) as u32 + SUB_BUCKET_BITS - 1;
+        let sub = (idx % SUB_BUCKETS) as u64;
+        (SUB_BUCKETS as u64 + sub) << (exp - SUB_BUCKET_BITS)
+    }
+
+    /// Records the latency of an operation.
+    #[inline(always)]
+    pub fn record(&mut self, latency: Duration) {
+        let ns = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.buckets[Self::bucket(ns)] += 1;
+        self.count += 1;
+    }
+
+    /// Number of recorded operations.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The `p`-th percentile (0-100) in nanoseconds, rounded down to the start
+    /// of its bucket, `None` if nothing was recorded.
+    pub fn percentile(&self, p: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((p / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (idx, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(Self::bucket_start(idx));
+            }
+        }
+        Some(Self::bucket_start(BUCKETS - 1))
+    }
+}
+
+impl AddAssign<&LatencyHistogram> for LatencyHistogram {
+    fn add_assign(&mut self, other: &LatencyHistogram) {
+        for (a, b) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *a += b;
+        }
+        self.count += other.count;
+    }
+}
@@ -17,9 +17,22 @@ compile_error!("must define either feature \"verified\" or \"unverified\" ");
 
 use std::fmt::Debug;
 
+pub mod baseline;
 pub mod benchmark;
+pub mod energy;
+pub mod hugepages;
+pub mod latency;
+pub mod metadata;
 pub mod mkbench;
+pub mod numa;
+pub mod perf;
+pub mod pinning;
+#[cfg(feature = "plot")]
+pub mod plot;
+pub mod results;
 pub mod topology;
+pub mod trace;
+pub mod ycsb;
 
 /// A wrapper type to distinguish between arbitrary generated read or write operations
 /// in the test harness.
@@ -43,17 +56,22 @@ impl Debug for Operation<(), ()> {
 /// On MacOS this is not guaranteed.
 pub type ThreadId = u64;
 
-// Pin a thread to a core
-#[cfg(target_os = "linux")]
-pub fn pin_thread(core_id: topology::Cpu) {
-    core_affinity::set_for_current(core_affinity::CoreId {
-        id: core_id as usize,
-    });
+/// Whether the benchmarks run as a quick smoke test, with the `smokebench`
+/// feature or if `--smoke` is passed on the command line.
+///
+/// A smoke run executes every configuration for a second with few threads,
+/// a small log and no warmup, and checks that the data structure applied all
+/// updates the threads completed instead of reporting measurements. This
+/// exercises the benchmark code without the hours a full run takes.
+pub fn smoke_enabled() -> bool {
+    cfg!(feature = "smokebench") || std::env::args().any(|arg| arg == "--smoke")
 }
 
-#[cfg(not(target_os = "linux"))]
-pub fn pin_thread(_core_id: topology::Cpu) {
-    log::warn!("Can't pin threads explicitly for benchmarking.");
+/// Pin the calling thread to a core, warns if that's not possible.
+pub fn pin_thread(core_id: topology::Cpu) {
+    if let Err(e) = pinning::pin_current_thread_to(core_id) {
+        log::warn!("Can't pin thread to CPU {}: {}", core_id, e);
+    }
 }
 
 #[cfg(target_os = "linux")]
@@ -0,0 +1,72 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! The machine and build a benchmark ran on.
+//!
+//! Every result file embeds the [`Metadata`] (see `results.rs`), so results
+//! stay comparable after the machine, the kernel or the toolchain changed.
+//! The build information is recorded by `build.rs` when `bench_utils` is
+//! compiled, empty values mean it couldn't be determined (e.g., no `git`).
+//! The enabled features are the ones of the benchmark crate, which passes
+//! them with [`set_features`].
+
+use std::fs;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use crate::topology::{MachineTopology, MACHINE_TOPOLOGY};
+
+lazy_static! {
+    pub static ref METADATA: Metadata = Metadata::new();
+}
+
+/// The features passed with [`set_features`].
+static FEATURES: Mutex<&'static str> = Mutex::new("");
+
+/// Records the enabled cargo features of the benchmark, a comma-separated
+/// list. Must be called before the first result is written.
+///
+/// The benchmark crates export their features as `BENCH_FEATURES` in their
+/// `build.rs`: `set_features(env!("BENCH_FEATURES"))`.
+pub fn set_features(features: &'static str) {
+    *FEATURES.lock().unwrap() = features;
+}
+
+/// The machine and build of a benchmark run.
+#[derive(Serialize, Debug)]
+pub struct Metadata {
+    /// Host name of the machine
+    pub hostname: Option<String>,
+    /// Kernel release (`uname -r`)
+    pub kernel: Option<String>,
+    /// Version of the compiler that built the benchmark
+    pub rustc: &'static str,
+    /// Commit of the Verus submodule
+    pub verus: &'static str,
+    /// Commit of the repository, `-dirty` if the benchmarked sources had local
+    /// changes
+    pub git_commit: &'static str,
+    /// Enabled cargo features of the benchmark
+    pub features: Vec<&'static str>,
+    /// Topology of the machine
+    pub topology: &'static MachineTopology,
+}
+
+impl Metadata {
+    fn new() -> Metadata {
+        let read = |path: &str| fs::read_to_string(path).ok().map(|s| s.trim().to_string());
+        let features: &'static str = *FEATURES.lock().unwrap();
+
+        Metadata {
+            hostname: read("/proc/sys/kernel/hostname"),
+            kernel: read("/proc/sys/kernel/osrelease"),
+            rustc: env!("BENCH_RUSTC_VERSION"),
+            verus: env!("BENCH_VERUS_VERSION"),
+            git_commit: env!("BENCH_GIT_COMMIT"),
+            features: features.split(',').filter(|f| !f.is_empty()).collect(),
+            topology: &MACHINE_TOPOLOGY,
+        }
+    }
+}
@@ -11,21 +11,22 @@
 
 use std::collections::HashMap;
 use std::fmt::{self};
-use std::fs::OpenOptions;
-use std::fs::File;
 use std::hint::black_box;
 use std::marker::{PhantomData, Send, Sync};
 use std::num::NonZeroUsize;
-use std::path::Path;
 use std::sync::{Arc, Barrier};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
-use std::io::Write;
-use csv::WriterBuilder;
 use log::*;
 
 const MY_DEFAULT_LOG_BYTES: usize = 2 * 1024 * 1024;
 
+/// The largest number of threads of a smoke run.
+const SMOKE_MAX_THREADS: usize = 4;
+
+/// The size of the log of a smoke run.
+const SMOKE_LOG_BYTES: usize = 64 * 1024;
+
 #[cfg(feature = "unverified")]
 use node_replication::{Dispatch, Log, Replica, ReplicaToken, MAX_REPLICAS_PER_LOG};
 
@@ -51,7 +52,7 @@ impl ThreadToken {
 type ReplicaId = usize;
 
 #[cfg(feature = "verified")]
-use verified_node_replication::{Dispatch, /* AffinityFn, NodeReplicated, NR,*/ ReplicaId, ThreadToken};
+use verified_node_replication::{Dispatch, /* AffinityFn, NodeReplicated, NR,*/ ReplicaId, ShardedThreadToken, ThreadToken};
 
 
 use rand::seq::SliceRandom;
@@ -61,6 +62,11 @@ use rand_chacha::ChaCha8Rng;
 use serde::Serialize;
 
 pub use crate::topology::ThreadMapping;
+use crate::latency::{LatencyHistogram, CLOSED_LOOP_SAMPLE};
+use crate::results::{CombinerSample, RunConfig, RunResult, ThreadMeasurement};
+use crate::energy::EnergyCounters;
+use crate::perf::PerfCounters;
+use crate::trace::{Replay, ReplayMode};
 use crate::{benchmark::*, topology::*, Operation};
 
 pub fn chg_affinity(rid: ReplicaId) {
@@ -74,11 +80,20 @@ pub fn chg_affinity(rid: ReplicaId) {
         rid,
         MACHINE_TOPOLOGY.cpus_on_node(rid as u64)
     );
+    let mut num_cpus = 0;
     for ncpu in MACHINE_TOPOLOGY.cpus_on_node(rid as u64) {
+        if !MACHINE_TOPOLOGY.is_allowed(ncpu.cpu) {
+            continue;
+        }
         debug!("ncpu is {:?}", ncpu);
         cpu_set
             .set(ncpu.cpu as usize)
             .expect("Can't toggle CPU in cpu_set");
+        num_cpus += 1;
+    }
+    if num_cpus == 0 {
+        warn!("No allowed CPUs for replica {}, not changing affinity", rid);
+        return;
     }
     debug!(
         "we are on cpu {} node {} and should handle things for replica {} now, changing affinity to {:?}",
@@ -86,6 +101,11 @@ pub fn chg_affinity(rid: ReplicaId) {
     );
     nix::sched::sched_setaffinity(nix::unistd::Pid::from_raw(0), &cpu_set)
         .expect("Can't change thread affinity");
+
+    // Don't rely on first-touch, the replica is allocated right after this
+    if let Err(e) = crate::numa::bind_current_thread(rid as Node, crate::numa::MemPolicy::Preferred) {
+        warn!("Can't bind memory of replica {} to its node: {}", rid, e);
+    }
 }
 
 /// Threshold after how many iterations we log a warning for busy spinning loops.
@@ -94,46 +114,60 @@ pub fn chg_affinity(rid: ReplicaId) {
 /// Should be a power of two to avoid divisions.
 pub const WARN_THRESHOLD: usize = 1 << 28;
 
-/// Record a thread records during benchmarking, stored to a file later.
-#[derive(Serialize)]
-struct Record {
-    name: String,
-    rs: ReplicaStrategy,
-    tm: ThreadMapping,
-    batch_size: usize,
-    threads: usize,
-    duration: f64,
-    thread_id: usize,
-    core_id: u64,
-    exp_time_in_sec: usize,
-    iterations: usize,
-}
-
 /// The function that executes the benchmark operation.
-#[cfg(feature = "unverified")]
 type BenchFn<R> = fn(
     tid: crate::ThreadId,
-    idx: ThreadToken,
+    idx: <R as DsInterface>::TT,
     replica: &Arc<R>,
     operations: &Operation<
         <<R as DsInterface>::D as Dispatch>::ReadOperation,
         <<R as DsInterface>::D as Dispatch>::WriteOperation,
     >,
     usize,
-) -> ThreadToken;
+) -> <R as DsInterface>::TT;
+
+/// A token that identifies a thread registered with a data-structure.
+pub trait BenchToken: Send {
+    /// The replica the thread is registered with.
+    fn replica_id(&self) -> usize;
+
+    /// The thread id within the replica.
+    fn thread_id(&self) -> usize;
+}
+
+#[cfg(feature = "unverified")]
+impl BenchToken for ThreadToken {
+    fn replica_id(&self) -> usize {
+        ThreadToken::replica_id(self)
+    }
+
+    fn thread_id(&self) -> usize {
+        ThreadToken::thread_id(self)
+    }
+}
 
 #[cfg(feature = "verified")]
-/// The function that executes the benchmark operation.
-type BenchFn<R> = fn(
-    tid: crate::ThreadId,
-    idx: ThreadToken<<R as DsInterface>::D>,
-    replica: &Arc<R>,
-    operations: &Operation<
-        <<R as DsInterface>::D as Dispatch>::ReadOperation,
-        <<R as DsInterface>::D as Dispatch>::WriteOperation,
-    >,
-    usize,
-) -> ThreadToken<<R as DsInterface>::D>;
+impl<D: Dispatch> BenchToken for ThreadToken<D> {
+    fn replica_id(&self) -> usize {
+        ThreadToken::<D>::replica_id(self)
+    }
+
+    fn thread_id(&self) -> usize {
+        ThreadToken::<D>::thread_id(self) as usize
+    }
+}
+
+/// A sharded token is registered with the same replica of every shard.
+#[cfg(feature = "verified")]
+impl<D: Dispatch> BenchToken for ShardedThreadToken<D> {
+    fn replica_id(&self) -> usize {
+        ThreadToken::<D>::replica_id(self.tokens[0].as_ref().unwrap())
+    }
+
+    fn thread_id(&self) -> usize {
+        ThreadToken::<D>::thread_id(self.tokens[0].as_ref().unwrap()) as usize
+    }
+}
 
 /// The interface a data-structure must implement to be benchmarked by
 /// `ScaleBench`.
@@ -141,6 +175,9 @@ type BenchFn<R> = fn(
 pub trait DsInterface {
     type D: Dispatch + Default + Sync;
 
+    /// The token a registered thread uses to access the data-structure.
+    type TT: BenchToken + Copy;
+
     /// Allocate a new data-structure.
     ///
     /// - `replicas`: How many replicas the data-structure should maintain.
@@ -150,21 +187,37 @@ pub trait DsInterface {
     /// Register a thread with a data-structure.
     ///
     /// - `rid` indicates which replica the thread should use.
-    fn register(&self, rid: ReplicaId) -> Option<ThreadToken>;
+    fn register(&self, rid: ReplicaId) -> Option<Self::TT>;
 
     /// Apply a mutable operation to the data-structure.
     fn execute_mut(
         &self,
         op: <Self::D as Dispatch>::WriteOperation,
-        idx: ThreadToken,
+        idx: Self::TT,
     ) -> <Self::D as Dispatch>::Response;
 
     /// Apply a immutable operation to the data-structure.
     fn execute(
         &self,
         op: <Self::D as Dispatch>::ReadOperation,
-        idx: ThreadToken,
+        idx: Self::TT,
     ) -> <Self::D as Dispatch>::Response;
+
+    /// Statistics of the combiners over all replicas and of the appends to
+    /// the log, `None` if the data-structure doesn't record them.
+    fn combiner_stats(&self) -> Option<CombinerSample> {
+        None
+    }
+
+    /// Sets the statistics of the combiners and the log back to zero.
+    fn reset_combiner_stats(&self) {}
+
+    /// The number of update operations the data-structure applied so far,
+    /// `None` if it can't tell. Smoke runs check it against the updates the
+    /// threads completed.
+    fn updates_applied(&self) -> Option<u64> {
+        None
+    }
 }
 
 #[cfg(feature = "unverified")]
@@ -242,6 +295,7 @@ where
 #[cfg(feature = "unverified")]
 impl<'a, T: Dispatch + Sync + Default> DsInterface for NodeReplicated<'_, T> {
     type D = T;
+    type TT = ThreadToken;
 
     fn new(replicas: NonZeroUsize, _logs: NonZeroUsize, log_size: usize) -> Arc<Self> {
         Arc::new(
@@ -277,6 +331,9 @@ impl<'a, T: Dispatch + Sync + Default> DsInterface for NodeReplicated<'_, T> {
 pub trait DsInterface {
     type D: Dispatch + Default + Sync;
 
+    /// The token a registered thread uses to access the data-structure.
+    type TT: BenchToken;
+
     /// Allocate a new data-structure.
     ///
     /// - `replicas`: How many replicas the data-structure should maintain.
@@ -286,21 +343,37 @@ pub trait DsInterface {
     /// Register a thread with a data-structure.
     ///
     /// - `rid` indicates which replica the thread should use.
-    fn register(&mut self, rid: ReplicaId) -> Option<ThreadToken<Self::D >>;
+    fn register(&mut self, rid: ReplicaId) -> Option<Self::TT>;
 
     /// Apply a mutable operation to the data-structure.
     fn execute_mut(
         &self,
         op: <Self::D as Dispatch>::WriteOperation,
-        idx: ThreadToken<Self::D >,
-    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D >), ThreadToken<Self::D >>;
+        idx: Self::TT,
+    ) -> Result<(<Self::D as Dispatch>::Response, Self::TT), Self::TT>;
 
     /// Apply a immutable operation to the data-structure.
     fn execute(
         &self,
         op: <Self::D as Dispatch>::ReadOperation,
-        idx: ThreadToken<Self::D >,
-    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D >), ThreadToken<Self::D >>;
+        idx: Self::TT,
+    ) -> Result<(<Self::D as Dispatch>::Response, Self::TT), Self::TT>;
+
+    /// Statistics of the combiners over all replicas and of the appends to
+    /// the log, `None` if the data-structure doesn't record them.
+    fn combiner_stats(&self) -> Option<CombinerSample> {
+        None
+    }
+
+    /// Sets the statistics of the combiners and the log back to zero.
+    fn reset_combiner_stats(&self) {}
+
+    /// The number of update operations the data-structure applied so far,
+    /// `None` if it can't tell. Smoke runs check it against the updates the
+    /// threads completed.
+    fn updates_applied(&self) -> Option<u64> {
+        None
+    }
 }
 
 
@@ -319,6 +392,9 @@ pub enum ReplicaStrategy {
     Socket,
     /// One for every hardware thread.
     PerThread,
+    /// A fixed number of replicas, the threads are split into contiguous
+    /// groups of (almost) equal size.
+    Count(usize),
 }
 
 impl fmt::Display for ReplicaStrategy {
@@ -330,6 +406,7 @@ impl fmt::Display for ReplicaStrategy {
             ReplicaStrategy::L3 => write!(f, "L3"),
             ReplicaStrategy::Socket => write!(f, "Socket"),
             ReplicaStrategy::PerThread => write!(f, "PerThread"),
+            ReplicaStrategy::Count(n) => write!(f, "Count({})", n),
         }
     }
 }
@@ -343,8 +420,118 @@ impl fmt::Debug for ReplicaStrategy {
             ReplicaStrategy::L3 => write!(f, "RS=L3"),
             ReplicaStrategy::Socket => write!(f, "RS=Socket"),
             ReplicaStrategy::PerThread => write!(f, "RS=PerThread"),
+            ReplicaStrategy::Count(n) => write!(f, "RS=Count({})", n),
+        }
+    }
+}
+
+/// What to do if the replicas and the log of a run don't fit into the memory
+/// of a NUMA node.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MemoryLimit {
+    /// Print a warning and run anyway (the kernel allocates remote memory).
+    Warn,
+    /// Panic.
+    Fail,
+}
+
+/// The warmup phase of a run.
+///
+/// Threads execute operations for at least `duration` and at least `ops`
+/// operations before the measured phase starts, this excludes effects like
+/// page faults, the first wrap-around of the log and cold replica caches from
+/// the results. No warmup by default.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct Warmup {
+    /// Minimal duration of the warmup
+    pub duration: Duration,
+    /// Minimal number of operations per thread
+    pub ops: usize,
+    /// Report the throughput of the warmup phase separately
+    pub report: bool,
+}
+
+impl Warmup {
+    pub fn is_enabled(&self) -> bool {
+        !self.duration.is_zero() || self.ops > 0
+    }
+}
+
+/// The operation mix of the individual threads of a run.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ThreadMix {
+    /// All threads execute the operations of the benchmark (default).
+    Uniform,
+    /// The percentage of reads of each thread, by thread index. Threads with a
+    /// higher index than the list's length wrap around.
+    PerThread(Vec<usize>),
+    /// The first `writers` threads only update, all other threads only read.
+    Writers(usize),
+    /// The first `writers` threads of every replica only update, all other
+    /// threads only read.
+    WritersPerReplica(usize),
+}
+
+impl ThreadMix {
+    /// The read percentage of thread `idx`, the `replica_idx`-th thread of
+    /// its replica, or `None` for the global mix.
+    fn reads_pct(&self, idx: usize, replica_idx: usize) -> Option<usize> {
+        match self {
+            ThreadMix::Uniform => None,
+            ThreadMix::PerThread(pcts) => Some(pcts[idx % pcts.len()]),
+            ThreadMix::Writers(writers) if idx < *writers => Some(0),
+            ThreadMix::Writers(_) => Some(100),
+            ThreadMix::WritersPerReplica(writers) if replica_idx < *writers => Some(0),
+            ThreadMix::WritersPerReplica(_) => Some(100),
         }
     }
+
+    /// Builds the operations for thread `idx`, the `replica_idx`-th thread of
+    /// its replica, from the reads and writes in `operations`, which must
+    /// contain the kinds of operations the mix needs.
+    fn operations_for<RO: Clone, WO: Clone + PartialEq>(
+        &self,
+        idx: usize,
+        replica_idx: usize,
+        operations: &Vec<Operation<RO, WO>>,
+    ) -> Vec<Operation<RO, WO>> {
+        let reads_pct = match self.reads_pct(idx, replica_idx) {
+            Some(pct) => pct,
+            None => return operations.clone(),
+        };
+        assert!(reads_pct <= 100, "read percentage must be at most 100");
+
+        let (reads, writes): (Vec<_>, Vec<_>) = operations
+            .iter()
+            .cloned()
+            .partition(|op| matches!(op, Operation::ReadOperation(_)));
+        assert!(
+            reads_pct == 0 || !reads.is_empty(),
+            "thread {} needs read operations, but the workload has none",
+            idx
+        );
+        assert!(
+            reads_pct == 100 || !writes.is_empty(),
+            "thread {} needs write operations, but the workload has none",
+            idx
+        );
+
+        (0..operations.len())
+            .map(|i| {
+                if i % 100 < reads_pct {
+                    reads[i % reads.len()].clone()
+                } else {
+                    writes[i % writes.len()].clone()
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for ThreadMix {
+    fn default() -> ThreadMix {
+        ThreadMix::Uniform
+    }
 }
 
 /// How logs are mapped to cores/threads.
@@ -409,15 +596,25 @@ where
         >,
     >,
     duration: Duration,
+    /// Warmup phase before the measurement
+    warmup: Warmup,
+    /// Operation mix of the individual threads
+    thread_mix: ThreadMix,
     /// Batch-size (passed as a parameter to benchmark funtion `f`)
     batch_size: usize,
+    /// Operations per second offered by all threads, `None` for a closed loop
+    offered_load: Option<u64>,
+    /// Replays the operations as a trace instead of mixing them per thread
+    replay: Option<Replay>,
     /// Benchmark function to execute
     f: BenchFn<R>,
     read_pct: usize,
     ///
     file_name: String,
-    /// Thread handles
-    handles: Vec<JoinHandle<(Core, Vec<usize>)>>,
+    /// Thread handles, return the measurements of the thread
+    handles: Vec<JoinHandle<ThreadMeasurement>>,
+    /// The data-structure of the run, to read its statistics at the end
+    ds: Option<Arc<R>>,
 }
 
 impl<R: 'static> ScaleBenchmark<R>
@@ -438,6 +635,8 @@ where
         ts: usize,
         log_size: usize,
         duration: Duration,
+        warmup: Warmup,
+        thread_mix: ThreadMix,
         operations: Vec<
             Operation<
                 <R::D as Dispatch>::ReadOperation,
@@ -445,13 +644,15 @@ where
             >,
         >,
         batch_size: usize,
+        offered_load: Option<u64>,
+        replay: Option<Replay>,
         read_pct: usize,
         f: BenchFn<R>,
     ) -> ScaleBenchmark<R>
     where
         R: Sync,
     {
-        // Log the per-thread runtimes to the CSV file
+        // Log the per-thread runtimes to the CSV file, the summary to JSON
         let file_name = format!("nr_benchmarks_{name}.csv");
 
         ScaleBenchmark {
@@ -463,12 +664,68 @@ where
             log_size,
             rm: ScaleBenchmark::<R>::replica_core_allocation(topology, rs, tm, ts),
             duration,
+            warmup,
+            thread_mix,
             operations: Arc::new(operations),
             batch_size,
+            offered_load,
+            replay,
             f,
             file_name,
             read_pct,
             handles: Default::default(),
+            ds: None,
+        }
+    }
+
+    /// Estimates how many bytes the replicas and the log need on each node.
+    ///
+    /// Every replica needs `replica_bytes`, the log needs `log_size` bytes
+    /// and is allocated along with the first replica.
+    fn memory_footprint(&self, topology: &MachineTopology, replica_bytes: u64) -> HashMap<Node, u64> {
+        let nodes = topology.nodes();
+        let mut footprint: HashMap<Node, u64> = HashMap::new();
+
+        for (rid, cores) in self.rm.iter() {
+            // `chg_affinity` places replica `rid` on node `rid` if it exists
+            let node = if nodes.contains(&(*rid as Node)) {
+                *rid as Node
+            } else {
+                cores.first().and_then(|c| topology.node_of(*c)).unwrap_or(0)
+            };
+            *footprint.entry(node).or_default() += replica_bytes;
+        }
+        *footprint.entry(0).or_default() += self.log_size as u64;
+
+        footprint
+    }
+
+    /// Checks that the replicas and the log fit into the memory of their
+    /// nodes, instead of letting the kernel silently allocate remote memory.
+    fn check_memory(&self, topology: &MachineTopology, replica_bytes: u64, limit: MemoryLimit) {
+        for (node, bytes) in self.memory_footprint(topology, replica_bytes) {
+            let available = match topology.node_memory(node) {
+                Some(available) => available,
+                None => continue,
+            };
+            if bytes > available {
+                match limit {
+                    MemoryLimit::Fail => panic!(
+                        "{} needs ~{} MiB on node {}, but the node has only {} MiB",
+                        self.name,
+                        bytes >> 20,
+                        node,
+                        available >> 20
+                    ),
+                    MemoryLimit::Warn => warn!(
+                        "{} needs ~{} MiB on node {}, but the node has only {} MiB, results will include remote memory accesses",
+                        self.name,
+                        bytes >> 20,
+                        node,
+                        available >> 20
+                    ),
+                }
+            }
         }
     }
 
@@ -483,61 +740,40 @@ where
         self.rm.len()
     }
 
-    /// Terminate the worker threads by sending 0 to the iter channel:
-    fn terminate(self) -> std::io::Result<()> {
-        let mut all_results = Vec::<(Core, usize, Vec<usize>)>::with_capacity(self.handles.len());
-        let mut everything =
-            Vec::<usize>::with_capacity(self.handles.len() * self.duration.as_secs() as usize);
-
-        let num_threads =  self.threads();
-        let num_replicas = self.replicas();
-        let duration_sec = self.duration.as_secs();
-        let name = self.name.clone();
-        let tm = self.tm;
+    /// Terminate the worker threads, collect and store the results.
+    fn terminate(self) -> std::io::Result<RunResult> {
+        let config = RunConfig {
+            name: self.name.clone(),
+            rs: self.rs,
+            tm: self.tm,
+            ls: self.ls,
+            threads: self.threads(),
+            replicas: self.replicas(),
+            log_size: self.log_size,
+            batch_size: self.batch_size,
+            reads_pct: self.read_pct,
+            duration: self.duration,
+            warmup: self.warmup.duration,
+            offered_load: self.offered_load,
+            replay: self.replay.as_ref().map(|r| r.mode),
+        };
+        let mut result = RunResult::new(config);
 
-        for (tid, handle) in self.handles.into_iter().enumerate() {
-            let (cid, thread_results) = handle.join().unwrap();
-            everything.extend(&thread_results);
-            all_results.push((cid, tid, thread_results));
+        for handle in self.handles.into_iter() {
+            result.add_thread(handle.join().unwrap());
         }
+        result.combiner = self.ds.as_ref().and_then(|ds| ds.combiner_stats());
 
-        if cfg!(not(feature = "smokebench")) {
-            let avg = crate::benchmark::mean(&everything).unwrap();
-            let sum = crate::benchmark::sum(&everything).unwrap();
-            let ops_per_sec = sum / (duration_sec as f64);
-            let stdev = crate::benchmark::std_deviation(&everything).unwrap();
-
-            // XXX: some hacky way to get JSON output
-            let mut json_file = File::create(self.file_name.replace("csv", "json"))?;
-
-            let _ = json_file.write_all("{\n".as_bytes());
-            let _ = json_file.write_all(format!("    \"bench_name\": \"{name}\",\n").as_bytes());
-            let _ = json_file.write_all(format!("    \"n_threads\": {},\n", num_threads).as_bytes());
-            let _ = json_file.write_all(format!("    \"reads_pct\": {},\n", self.read_pct).as_bytes());
-            let _ = json_file.write_all(format!("    \"n_replicas\": {},\n", num_replicas).as_bytes());
-            let _ = json_file.write_all(format!("    \"run_seconds\": {},\n", duration_sec).as_bytes());
-            let _ = json_file.write_all(format!("    \"numa_policy\": \"{tm}\",\n").as_bytes());
-            let _ = json_file.write_all("    \"core_policy\": 0,\n".as_bytes());
-            let _ = json_file.write_all("    \"reads\": 0,\n".as_bytes());
-            let _ = json_file.write_all("    \"updates\": 0,\n".as_bytes());
-            let _ = json_file.write_all("    \"total_ops\": 0,\n".as_bytes());
-            let _ = json_file.write_all("    \"reads_per_s\": 0,\n".as_bytes());
-            let _ = json_file.write_all("    \"updates_per_s\": 0,\n".as_bytes());
-            let _ = json_file.write_all(format!("    \"ops_per_s\": {ops_per_sec},\n").as_bytes());
-            let _ = json_file.write_all(format!("    \"stdev\": {stdev}\n").as_bytes());
-            let _ = json_file.write_all("}\n".as_bytes());
-
-
-            println!(
-                "Run({:?} {:?} {:3} {:?} BS={}) => {:20.5} ({:.5})",
-                self.rs,
-                self.tm,
-                self.ts,
-                self.ls,
-                self.batch_size,
-                avg * (self.ts as f64),
-                stdev
-            );
+        if !crate::smoke_enabled() {
+            result.write_json(self.file_name.replace("csv", "json"))?;
+            println!("{}", result);
+            if let Some(perf) = result.perf() {
+                println!("Perf: {:?}", perf);
+            }
+            if let (Some(energy), Some(per_op)) = (result.energy(), result.joules_per_op()) {
+                println!("Energy: {:.2} J ({:.2} nJ/op)", energy.joules, per_op * 1e9);
+            }
+            crate::results::print_combining_distribution(&result);
         } else {
             println!(
                 "Run({:?} {:?} {:?} {:?} BS={}) => not measured",
@@ -545,37 +781,12 @@ where
             );
         }
 
-        let write_headers = !Path::new(&self.file_name).exists(); // write headers only to new file
-        let csv_file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(self.file_name)?;
-
-        let mut wtr = WriterBuilder::new()
-            .has_headers(write_headers)
-            .from_writer(csv_file);
-
-        for (cid, tid, ops_per_sec) in all_results.iter() {
-            for (idx, ops) in ops_per_sec.iter().enumerate() {
-                let record = Record {
-                    name: self.name.clone(),
-                    rs: self.rs,
-                    tm: self.tm,
-                    batch_size: self.batch_size,
-                    threads: self.ts,
-                    duration: Duration::from_secs(10).as_secs_f64(),
-                    thread_id: *tid,
-                    core_id: *cid,
-                    exp_time_in_sec: idx + 1, // start at 1 (for first second)
-                    iterations: *ops,
-                };
-                wtr.serialize(record)
-                    .expect("Failed to serialize benchmark record");
-            }
+        result.append_csv(&self.file_name)?;
+        if self.warmup.report && self.warmup.is_enabled() {
+            println!("Warmup (ops/s): {:?}", result.warmup_ops_per_sec());
+            result.append_warmup_csv(self.file_name.replace(".csv", "_warmup.csv"))?;
         }
-        wtr.flush()?;
-
-        Ok(())
+        Ok(result)
     }
 
     fn startup(&mut self) {
@@ -585,16 +796,19 @@ where
         let replicas = NonZeroUsize::new(self.replicas()).unwrap();
 
         let mut ds = R::new(replicas, NonZeroUsize::new(1).unwrap(), self.log_size);
+        // `chg_affinity` bound the memory of this thread to the last replica's node
+        if let Err(e) = crate::numa::reset_current_thread() {
+            debug!("Can't reset the memory policy: {}", e);
+        }
 
         #[cfg(feature = "verified")]
         let mut thread_tokens = {
             let mut thread_tokens =  HashMap::with_capacity(replicas.into());
             for (rid, cores) in self.rm.clone().into_iter() {
                 if thread_tokens.contains_key(&rid) {
-                    let tks : &mut Vec<ThreadToken<R::D>> = thread_tokens.get_mut(&rid).unwrap();
+                    let tks : &mut Vec<R::TT> = thread_tokens.get_mut(&rid).unwrap();
                     tks.extend(cores.iter().map(|_| ds.register(rid).unwrap()));
                 } else {
-                    // let tks :  Vec<ThreadToken<R::D>> = Vec::new();
                     thread_tokens.insert(rid, cores.iter().map(|_| ds.register(rid).unwrap()).collect());
                 }
             }
@@ -603,13 +817,15 @@ where
 
         #[cfg(feature = "verified")]
         let ds = Arc::new(ds);
+        self.ds = Some(ds.clone());
 
         println!(
             "Execute benchmark {} with the following replica: [core_id] mapping: {:#?}",
             self.name, self.rm
         );
+        let mut thread_idx = 0;
         for (rid, cores) in self.rm.clone().into_iter() {
-            for core_id in cores {
+            for (replica_idx, core_id) in cores.into_iter().enumerate() {
                 // Pin thread to force the allocations below (`operations` etc.)
                 // with the correct NUMA affinity
                 crate::pin_thread(core_id);
@@ -619,10 +835,19 @@ where
                 let ds = ds.clone();
                 let f = self.f.clone();
                 let batch_size = self.batch_size;
+                // every thread offers an equal share of the load
+                let interval = self
+                    .offered_load
+                    .map(|load| Duration::from_secs_f64(thread_num as f64 / load as f64));
                 let log_period = Duration::from_secs(1);
                 let name = self.name.clone();
                 let operations = self.operations.clone();
                 let duration = self.duration.clone();
+                let warmup = self.warmup;
+                let thread_mix = self.thread_mix.clone();
+                let replay = self.replay.clone();
+                let idx = thread_idx;
+                thread_idx += 1;
 
                 #[cfg(feature = "verified")]
                 let mut thread_token = thread_tokens.get_mut(&rid).unwrap().pop().expect("Can't register replica, out of slots?");
@@ -635,9 +860,19 @@ where
                         .register(rid)
                         .expect("Can't register replica, out of slots?");
 
-                    // Copy the actual Vec<Operations> data within the thread
-                    let mut operations = (*operations).clone();
-                    operations.shuffle(&mut ChaCha8Rng::seed_from_u64(42 + core_id));
+                    // Copy the actual Vec<Operations> data within the thread,
+                    // with the operation mix of this thread, or its share of
+                    // the replayed trace in trace order
+                    let (operations, timestamps) = match &replay {
+                        Some(replay) => replay.operations_for(idx, thread_num, &operations),
+                        None => {
+                            let mut operations =
+                                thread_mix.operations_for(idx, replica_idx, &operations);
+                            operations.shuffle(&mut ChaCha8Rng::seed_from_u64(42 + core_id));
+                            (operations, None)
+                        }
+                    };
+                    let replay_period = replay.as_ref().map_or(Duration::ZERO, |r| r.period());
 
                     debug!(
                         "Running {:?} on core {} replica#{} rtoken#{:?}.{:?} for {:?}",
@@ -651,16 +886,107 @@ where
 
                     let mut operations_per_second: Vec<usize> = Vec::with_capacity(128);
                     let mut operations_completed: usize = 0;
+                    let mut reads_completed: usize = 0;
+                    let mut updates_completed: usize = 0;
                     let mut iter: usize = 0;
                     let nop: usize = operations.len();
 
+                    let mut perf_counters = PerfCounters::new();
+                    // the energy is machine-wide, one thread measures it
+                    let mut energy_counters = if idx == 0 { Some(EnergyCounters::new()) } else { None };
+                    let mut latency = LatencyHistogram::new();
+                    let mut issued: usize = 0;
+
                     start_sync.wait();
+
+                    // Warmup phase: runs for at least the warmup duration and
+                    // operations, nothing of it goes into the measurement
+                    let mut warmup_per_second: Vec<usize> = Vec::new();
+                    let mut warmup_perf = None;
+                    if warmup.is_enabled() {
+                        perf_counters.start();
+                        let start = Instant::now();
+                        let end_warmup = start + warmup.duration;
+                        let mut next_log = start + log_period;
+                        let mut warmup_completed: usize = 0;
+                        let mut warmup_total: usize = 0;
+
+                        while Instant::now() < end_warmup || warmup_total < warmup.ops {
+                            for _i in 0..batch_size {
+                                thread_token = black_box((f)(
+                                    core_id,
+                                    thread_token,
+                                    &ds,
+                                    &operations[iter],
+                                    batch_size,
+                                ));
+                                iter = (iter + 1) % nop;
+                            }
+                            warmup_completed += batch_size;
+                            warmup_total += batch_size;
+
+                            if Instant::now() >= next_log {
+                                warmup_per_second.push(warmup_completed);
+                                warmup_completed = 0;
+                                next_log += log_period;
+                            }
+                        }
+
+                        warmup_perf = perf_counters.stop();
+
+                        // the combining statistics only cover the measurement
+                        start_sync.wait();
+                        if idx == 0 {
+                            ds.reset_combiner_stats();
+                        }
+
+                        // start the measurement at the same time on all threads
+                        start_sync.wait();
+                    }
+
+                    perf_counters.start();
+                    if let Some(energy_counters) = energy_counters.as_mut() {
+                        energy_counters.start();
+                    }
                     let start = Instant::now();
                     let end_experiment = start + duration;
                     let mut next_log = start + log_period;
+                    let mut next_op = start;
+                    // a timed replay starts the trace over with the measurement
+                    let mut replay_start = start;
+                    if timestamps.is_some() {
+                        iter = 0;
+                    }
 
                     while Instant::now() < end_experiment {
                         for _i in 0..batch_size {
+                            match &operations[iter] {
+                                Operation::ReadOperation(_) => reads_completed += 1,
+                                Operation::WriteOperation(_) => updates_completed += 1,
+                            }
+                            // with an offered load, wait for the operation's
+                            // slot and time it from there
+                            let timed_from = match (&timestamps, interval) {
+                                (Some(timestamps), _) => {
+                                    let scheduled = replay_start + timestamps[iter];
+                                    while Instant::now() < scheduled {
+                                        std::hint::spin_loop();
+                                    }
+                                    Some(scheduled)
+                                }
+                                (None, Some(interval)) => {
+                                    let scheduled = next_op;
+                                    next_op += interval;
+                                    while Instant::now() < scheduled {
+                                        std::hint::spin_loop();
+                                    }
+                                    Some(scheduled)
+                                }
+                                (None, None) if issued % CLOSED_LOOP_SAMPLE == 0 => {
+                                    Some(Instant::now())
+                                }
+                                (None, None) => None,
+                            };
                             thread_token = black_box((f)(
                                 core_id,
                                 thread_token,
@@ -668,8 +994,15 @@ where
                                 &operations[iter],
                                 batch_size,
                             ));
+                            if let Some(t) = timed_from {
+                                latency.record(t.elapsed());
+                            }
+                            issued += 1;
 
                             iter = (iter + 1) % nop;
+                            if iter == 0 {
+                                replay_start += replay_period;
+                            }
                         }
                         operations_completed += 1 * batch_size;
 
@@ -682,6 +1015,8 @@ where
                         }
                     }
 
+                    let perf = perf_counters.stop();
+
                     debug!(
                         "Completed {:?} on core {} replica#{} rtoken#{:?}.{:?} did {} ops in {:?}",
                         thread::current().id(),
@@ -700,7 +1035,19 @@ where
                     }
 
                     start_sync.wait();
-                    (core_id, operations_per_second)
+                    let energy = energy_counters.as_mut().and_then(|e| e.stop());
+                    ThreadMeasurement {
+                        thread_id: idx,
+                        core_id,
+                        ops_per_sec: operations_per_second,
+                        reads: reads_completed,
+                        updates: updates_completed,
+                        warmup_ops_per_sec: warmup_per_second,
+                        perf,
+                        warmup_perf,
+                        latency,
+                        energy,
+                    }
                 }));
             }
         }
@@ -711,13 +1058,13 @@ where
     /// This is a function based on how many threads we have, how we map
     /// them onto the CPUs, the granularity of replicas, and the topology of the
     /// underlying hardware.
-    fn replica_core_allocation(
-        topology: &MachineTopology,
+    fn replica_core_allocation<T: TopologyProvider + ?Sized>(
+        topology: &T,
         rs: ReplicaStrategy,
         tm: ThreadMapping,
         ts: usize,
     ) -> HashMap<usize, Vec<Cpu>> {
-        let cpus = topology.allocate(tm, ts, true);
+        let cpus = tm.allocate(topology, ts, true);
         debug_assert_eq!(ts, cpus.len());
 
         trace!(
@@ -749,7 +1096,7 @@ where
             }
             ReplicaStrategy::L1 => match tm {
                 ThreadMapping::None => {}
-                ThreadMapping::Sequential => {
+                ThreadMapping::Sequential | ThreadMapping::SmtPairs | ThreadMapping::Explicit(_) => {
                     let mut l1: Vec<L1> = cpus.iter().map(|t| t.l1).collect();
                     l1.sort();
                     l1.dedup();
@@ -766,7 +1113,7 @@ where
                 }
                 // Giving replica number based on L1 number won't work in this case, as the
                 // L1 numbers are allocated to Node-0 first and then to Node-1, and so on.
-                ThreadMapping::Interleave => {
+                ThreadMapping::Interleave | ThreadMapping::RoundRobinCore => {
                     let mut l1: Vec<L1> = cpus.iter().map(|t| t.l1).collect();
                     l1.sort();
                     l1.dedup();
@@ -818,12 +1165,41 @@ where
                     rm.insert(idx, vec![core]);
                 }
             }
+            ReplicaStrategy::Count(n) => {
+                // Can't have more replicas than threads
+                let n = std::cmp::max(std::cmp::min(n, cpus.len()), 1);
+                for (idx, core) in cpus.iter().map(|c| c.cpu).enumerate() {
+                    rm.entry(idx * n / cpus.len()).or_default().push(core);
+                }
+            }
         };
 
         rm
     }
 }
 
+/// Checks that the data-structure of a smoke run applied exactly the updates
+/// its threads completed, panics otherwise.
+fn check_smoke_run<R: DsInterface>(result: &RunResult, ds: Option<&R>) {
+    if let Some(applied) = ds.and_then(|ds| ds.updates_applied()) {
+        assert_eq!(
+            applied as usize,
+            result.total_updates(),
+            "{}: the data-structure applied {} updates, the threads completed {}",
+            result.config.name,
+            applied,
+            result.total_updates()
+        );
+    }
+    println!(
+        "Run({:?} {:?} {} threads) => ok, {} updates",
+        result.config.rs,
+        result.config.tm,
+        result.config.threads,
+        result.total_updates()
+    );
+}
+
 /// A generic benchmark configurator for node-replication scalability benchmarks.
 pub struct ScaleBenchBuilder<R: DsInterface>
 where
@@ -849,6 +1225,24 @@ where
         Operation<<R::D as Dispatch>::ReadOperation, <R::D as Dispatch>::WriteOperation>,
     >,
     read_pct: usize,
+    /// Warmup phase before each run
+    warmup: Warmup,
+    /// Operation mix of the individual threads
+    thread_mix: ThreadMix,
+    /// What to do if a run needs more threads than CPUs are allowed
+    cpu_limit: CpuLimit,
+    /// Kind of cores to run on, `None` for all cores
+    core_kind: Option<CoreKind>,
+    /// Estimated memory footprint of a replica in bytes
+    replica_footprint: usize,
+    /// What to do if a node doesn't have enough memory for a run
+    memory_limit: MemoryLimit,
+    /// Write the results of all runs to one file (see `sweep`)
+    sweep: bool,
+    /// Offered loads in operations per second, `None` for a closed loop
+    offered_loads: Vec<Option<u64>>,
+    /// Replays the operations as a trace (see `replay`)
+    replay: Option<Replay>,
     /// Marker for R
     _marker: PhantomData<R>,
 }
@@ -882,6 +1276,15 @@ where
             batches: vec![1usize],
             operations: ops,
             read_pct: 100,
+            warmup: Warmup::default(),
+            thread_mix: ThreadMix::default(),
+            cpu_limit: CpuLimit::Warn,
+            core_kind: Some(CoreKind::Performance),
+            replica_footprint: std::mem::size_of::<R::D>(),
+            memory_limit: MemoryLimit::Warn,
+            sweep: false,
+            offered_loads: vec![None],
+            replay: None,
             _marker: PhantomData,
         }
     }
@@ -898,12 +1301,13 @@ where
     }
 
     pub fn thread_defaults(&mut self) -> &mut Self {
-        let topology = MachineTopology::new();
-        let max_cores = topology.cores();
+        let topology = self.topology();
+        let max_cores = topology.allowed().len();
 
         let sockets = topology.sockets();
         let cores_on_s0 = topology.cpus_on_socket(sockets[0]);
-        let step_size = cores_on_s0.len() / 4;
+        // at least 1, small machines have fewer than 4 cores per socket
+        let step_size = std::cmp::max(cores_on_s0.len() / 4, 1);
         for t in (0..(max_cores + 1)).step_by(step_size) {
             if t == 0 {
                 // Can't run on 0 threads
@@ -934,6 +1338,94 @@ where
         self
     }
 
+    /// Sweeps over the number of threads and replicas.
+    ///
+    /// Runs the benchmark with 1, 2, 4, ... threads up to all (allowed) cores
+    /// and with 1, 2, 4, ... replicas up to one replica per socket. Every run
+    /// starts with a fresh data-structure, runs with more replicas than
+    /// threads are skipped. The summaries of all runs are written to
+    /// `nr_benchmarks_<name>_sweep.json`.
+    ///
+    /// Replaces the thread counts and replica strategies configured so far.
+    pub fn sweep(&mut self) -> &mut Self {
+        let topology = self.topology();
+        let max_cores = topology.allowed().len();
+        let sockets = topology.sockets().len();
+
+        self.threads.clear();
+        let mut t = 1;
+        while t < max_cores {
+            self.threads.push(t);
+            t *= 2;
+        }
+        self.threads.push(max_cores);
+
+        self.replica_strategies.clear();
+        let mut r = 1;
+        while r < sockets {
+            self.replica_strategies.push(ReplicaStrategy::Count(r));
+            r *= 2;
+        }
+        self.replica_strategies.push(ReplicaStrategy::Count(sockets));
+
+        self.sweep = true;
+        self
+    }
+
+    /// Switches to a sweep (see `sweep`) if `--sweep` is passed on the
+    /// command line, and adds the offered loads passed with
+    /// `--offered-loads 1000000,2000000` (see `offered_loads`).
+    pub fn sweep_from_args(&mut self) -> &mut Self {
+        let args: Vec<String> = std::env::args().collect();
+        if args.iter().any(|arg| arg == "--sweep") {
+            self.sweep();
+        }
+        if let Some(pos) = args.iter().position(|arg| arg == "--offered-loads") {
+            let loads: Vec<u64> = args
+                .get(pos + 1)
+                .expect("--offered-loads needs a list of operations per second")
+                .split(',')
+                .map(|l| l.trim().parse().expect("invalid offered load"))
+                .collect();
+            self.offered_loads(&loads);
+        }
+        self
+    }
+
+    /// Additionally run every configuration with the threads offering `loads`
+    /// operations per second in total (in addition to the closed loop, where
+    /// every thread issues its next operation as soon as the previous one
+    /// completed).
+    ///
+    /// The threads issue operations at a fixed rate and the latency of an
+    /// operation is measured from when it was due, so overloaded runs show
+    /// the queueing delay. Together with `sweep` this gives the points of the
+    /// Pareto report (`nr_benchmarks_<name>_pareto.csv`).
+    pub fn offered_loads(&mut self, loads: &[u64]) -> &mut Self {
+        for load in loads.iter() {
+            assert!(*load > 0, "offered load must be positive");
+            self.offered_loads.push(Some(*load));
+        }
+        self
+    }
+
+    /// Replays the operations as a trace, see [`crate::trace`].
+    ///
+    /// The operations must be the ones of the trace in trace order. Every
+    /// thread issues its share of them in order, and with
+    /// [`ReplayMode::Timestamps`] each at its timestamp relative to the start
+    /// of the measurement. The thread mix doesn't apply to a replay, and a
+    /// timed replay runs without the offered loads.
+    pub fn replay(&mut self, replay: Replay) -> &mut Self {
+        assert_eq!(
+            replay.len(),
+            self.operations.len(),
+            "replayed operations don't match the trace"
+        );
+        self.replay = Some(replay);
+        self
+    }
+
     /// Run benchmark with `t` threads.
     pub fn threads(&mut self, t: usize) -> &mut Self {
         self.threads.push(t);
@@ -946,6 +1438,18 @@ where
         self
     }
 
+    /// Overrides the thread mappings and thread counts if an explicit CPU list
+    /// is passed on the command line (`--cpus 0,4,8,12`).
+    ///
+    /// The benchmark then runs once with one thread on each of the given CPUs.
+    pub fn cpus_from_args(&mut self) -> &mut Self {
+        if let Some(tm @ ThreadMapping::Explicit(cpus)) = ThreadMapping::from_args() {
+            self.thread_mappings = vec![tm];
+            self.threads = vec![cpus.len()];
+        }
+        self
+    }
+
     /// Run benchmark with given replication strategy.
     pub fn replica_strategy(&mut self, rs: ReplicaStrategy) -> &mut Self {
         self.replica_strategies.push(rs);
@@ -964,6 +1468,76 @@ where
         self
     }
 
+    /// Warm up for at least `duration` before the measurement of each run.
+    pub fn warmup(&mut self, duration: Duration) -> &mut Self {
+        self.warmup.duration = duration;
+        self
+    }
+
+    /// Warm up for at least `ops` operations per thread before the
+    /// measurement of each run.
+    pub fn warmup_ops(&mut self, ops: usize) -> &mut Self {
+        self.warmup.ops = ops;
+        self
+    }
+
+    /// Configure the operation mix of the individual threads, e.g., to have
+    /// dedicated writer threads.
+    pub fn thread_mix(&mut self, mix: ThreadMix) -> &mut Self {
+        self.thread_mix = mix;
+        self
+    }
+
+    /// Report the throughput during the warmup phase, to see whether the
+    /// throughput converged before the measurement started.
+    pub fn report_warmup(&mut self, report: bool) -> &mut Self {
+        self.warmup.report = report;
+        self
+    }
+
+    /// Set what happens if a run needs more threads than the process is
+    /// allowed to use CPUs (default: warn and oversubscribe).
+    pub fn cpu_limit(&mut self, limit: CpuLimit) -> &mut Self {
+        self.cpu_limit = limit;
+        self
+    }
+
+    /// Set the kind of cores the threads run on, `None` for all cores
+    /// (default: performance cores only).
+    ///
+    /// Only matters on hybrid machines, where a combiner on an efficiency
+    /// core makes the results incomparable. Set this before `thread_defaults`
+    /// and `sweep`, which size the runs to the selected cores.
+    pub fn core_kind(&mut self, kind: Option<CoreKind>) -> &mut Self {
+        self.core_kind = kind;
+        self
+    }
+
+    /// The machine topology, restricted to the selected kind of cores.
+    fn topology(&self) -> MachineTopology {
+        let mut topology = MachineTopology::new();
+        topology.set_cpu_limit(self.cpu_limit);
+        topology.set_core_kind(self.core_kind);
+        topology
+    }
+
+    /// Set the estimated memory footprint of a replica in bytes, used to
+    /// check that the replicas fit into the memory of their NUMA nodes.
+    ///
+    /// Defaults to the size of the data-structure type, which doesn't include
+    /// its heap allocations.
+    pub fn replica_footprint(&mut self, bytes: usize) -> &mut Self {
+        self.replica_footprint = bytes;
+        self
+    }
+
+    /// Set what happens if the replicas and the log of a run don't fit into
+    /// the memory of a node (default: warn and run anyway).
+    pub fn memory_limit(&mut self, limit: MemoryLimit) -> &mut Self {
+        self.memory_limit = limit;
+        self
+    }
+
     /// Creates a benchmark to evalute the scalability properties of the
     /// log for a given data-structure.
     ///
@@ -973,7 +1547,9 @@ where
     ///
     /// TestHarness will be configured to create a run for every
     /// possible triplet: (replica strategy, thread mapping, #threads).
-    pub fn configure(&self, c: &mut TestHarness, name: &str, f: BenchFn<R>)
+    ///
+    /// Returns the results of all runs.
+    pub fn configure(&self, c: &mut TestHarness, name: &str, f: BenchFn<R>) -> Vec<RunResult>
     where
         <R::D as Dispatch>::WriteOperation: Sync + Send + Copy + PartialEq + 'static,
         <R::D as Dispatch>::ReadOperation: Sync + Send + Copy + Clone,
@@ -981,15 +1557,55 @@ where
         R: DsInterface + Sync + Send,
         R::D: 'static + Send + Sync,
     {
-        let topology = MachineTopology::new();
+        let topology = self.topology();
+        if topology.is_hybrid() {
+            match self.core_kind {
+                Some(kind) => println!("Hybrid machine, running on {:?} cores only", kind),
+                None => warn!("Hybrid machine, running on performance and efficiency cores"),
+            }
+        }
         crate::disable_dvfs();
         println!("{}", name);
 
+        // a timed replay issues the operations at the times of the trace
+        let mut offered_loads = self.offered_loads.clone();
+        if self.replay.as_ref().map(|r| r.mode) == Some(ReplayMode::Timestamps) {
+            if offered_loads.iter().any(|l| l.is_some()) {
+                warn!("Ignoring the offered loads, replaying the trace at its timestamps");
+            }
+            offered_loads = vec![None];
+        }
+
+        let mut results = Vec::new();
+
+        // a smoke run only checks a few threads with a small log, without warmup
+        let smoke = crate::smoke_enabled();
+        let mut threads = self.threads.clone();
+        let mut log_size = self.log_size;
+        let mut warmup = self.warmup;
+        if smoke {
+            threads.retain(|t| *t <= SMOKE_MAX_THREADS);
+            if threads.is_empty() {
+                threads.extend(self.threads.iter().min());
+            }
+            log_size = std::cmp::min(log_size, SMOKE_LOG_BYTES);
+            warmup = Warmup::default();
+        }
+
         for rs in self.replica_strategies.iter() {
             for ls in self.log_strategies.iter() {
                 for tm in self.thread_mappings.iter() {
-                    for ts in self.threads.iter() {
-                        for b in self.batches.iter() {
+                    for ts in threads.iter() {
+                        if let ReplicaStrategy::Count(n) = rs {
+                            if *n > *ts {
+                                continue;
+                            }
+                        }
+                        for (b, load) in self
+                            .batches
+                            .iter()
+                            .flat_map(|b| offered_loads.iter().map(move |l| (b, l)))
+                        {
                             let mut runner = ScaleBenchmark::<R>::new(
                                 String::from(name),
                                 &topology,
@@ -997,21 +1613,55 @@ where
                                 *ls,
                                 *tm,
                                 *ts,
-                                self.log_size,
+                                log_size,
                                 c.duration,
+                                warmup,
+                                self.thread_mix.clone(),
                                 self.operations.to_vec(),
                                 *b,
+                                *load,
+                                self.replay.clone(),
                                 self.read_pct,
                                 f,
                             );
+                            runner.check_memory(
+                                &topology,
+                                self.replica_footprint as u64,
+                                self.memory_limit,
+                            );
                             runner.startup();
-                            runner
+                            let ds = runner.ds.clone();
+                            let result = runner
                                 .terminate()
                                 .expect("Couldn't terminate the experiment");
+                            if smoke {
+                                check_smoke_run(&result, ds.as_deref());
+                            }
+                            results.push(result);
                         }
                     }
                 }
             }
         }
+
+        if self.sweep && !smoke {
+            let file_name = format!("nr_benchmarks_{name}_sweep.json");
+            if let Err(e) = crate::results::write_json_all(&file_name, &results) {
+                warn!("Couldn't write {}: {}", file_name, e);
+            }
+            let file_name = format!("nr_benchmarks_{name}_pareto.csv");
+            if let Err(e) = crate::results::write_pareto_csv(&file_name, &results) {
+                warn!("Couldn't write {}: {}", file_name, e);
+            }
+        }
+
+        #[cfg(feature = "plot")]
+        if !smoke {
+            if let Err(e) = crate::plot::plot_results(name, &results) {
+                warn!("Couldn't plot the results of {}: {}", name, e);
+            }
+        }
+
+        results
     }
 }
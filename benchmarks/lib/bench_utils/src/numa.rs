@@ -0,0 +1,243 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Binds memory to NUMA nodes and checks where pages ended up.
+//!
+//! Pinning a thread only influences where its memory is placed through the
+//! kernel's first-touch policy. The functions in this module set the memory
+//! policy explicitly (`set_mempolicy`), allocate memory on a given node
+//! (like `numa_alloc_onnode`, with `mmap` + `mbind`) and query the node of
+//! every page (`move_pages`), so a benchmark can make sure a replica really
+//! lives on its node.
+//!
+//! The functions talk to the kernel directly and don't need libnuma. They
+//! are only supported on Linux, elsewhere they return an `Unsupported` error.
+
+use std::io;
+
+use crate::topology::Node;
+
+/// How strictly memory is bound to a node.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MemPolicy {
+    /// Allocate on the node only, fail if it runs out of memory.
+    Bind,
+    /// Allocate on the node if possible, fall back to other nodes.
+    Preferred,
+}
+
+/// Where the pages of a memory region are placed.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct Placement {
+    /// Pages on the expected node
+    pub local: usize,
+    /// Pages on another node
+    pub remote: usize,
+    /// Pages that were not touched yet (or can't be queried)
+    pub not_present: usize,
+}
+
+impl Placement {
+    /// Whether all present pages are on the expected node.
+    pub fn is_local(&self) -> bool {
+        self.remote == 0
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn node_mask(node: Node) -> Vec<libc::c_ulong> {
+    let bits = libc::c_ulong::BITS as u64;
+    let mut mask = vec![0; (node / bits + 1) as usize];
+    mask[(node / bits) as usize] |= 1 << (node % bits);
+    mask
+}
+
+#[cfg(target_os = "linux")]
+fn mode(policy: MemPolicy) -> libc::c_int {
+    match policy {
+        MemPolicy::Bind => libc::MPOL_BIND,
+        MemPolicy::Preferred => libc::MPOL_PREFERRED,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn check(r: libc::c_long) -> io::Result<libc::c_long> {
+    if r < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(r)
+    }
+}
+
+/// Binds all future allocations of the calling thread to `node`.
+#[cfg(target_os = "linux")]
+pub fn bind_current_thread(node: Node, policy: MemPolicy) -> io::Result<()> {
+    let mask = node_mask(node);
+    // The kernel ignores the last bit of `maxnode`.
+    let maxnode = mask.len() * libc::c_ulong::BITS as usize + 1;
+    check(unsafe {
+        libc::syscall(libc::SYS_set_mempolicy, mode(policy), mask.as_ptr(), maxnode)
+    })?;
+    Ok(())
+}
+
+/// Interleaves all future allocations of the calling thread page by page
+/// across `nodes`.
+#[cfg(target_os = "linux")]
+pub fn interleave_current_thread(nodes: &[Node]) -> io::Result<()> {
+    let mut mask: Vec<libc::c_ulong> = Vec::new();
+    for node in nodes.iter() {
+        for (i, bits) in node_mask(*node).into_iter().enumerate() {
+            if i == mask.len() {
+                mask.push(0);
+            }
+            mask[i] |= bits;
+        }
+    }
+    let maxnode = mask.len() * libc::c_ulong::BITS as usize + 1;
+    check(unsafe {
+        libc::syscall(libc::SYS_set_mempolicy, libc::MPOL_INTERLEAVE, mask.as_ptr(), maxnode)
+    })?;
+    Ok(())
+}
+
+/// Restores the default memory policy (first-touch) of the calling thread.
+#[cfg(target_os = "linux")]
+pub fn reset_current_thread() -> io::Result<()> {
+    check(unsafe {
+        libc::syscall(
+            libc::SYS_set_mempolicy,
+            libc::MPOL_DEFAULT,
+            std::ptr::null::<libc::c_ulong>(),
+            0usize,
+        )
+    })?;
+    Ok(())
+}
+
+/// Allocates `len` bytes of zeroed memory on `node`.
+///
+/// The memory is page aligned and must be released with [`free_on_node`].
+#[cfg(target_os = "linux")]
+pub fn alloc_on_node(len: usize, node: Node, policy: MemPolicy) -> io::Result<*mut u8> {
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mask = node_mask(node);
+    let maxnode = mask.len() * libc::c_ulong::BITS as usize + 1;
+    let r = check(unsafe {
+        libc::syscall(libc::SYS_mbind, ptr, len, mode(policy), mask.as_ptr(), maxnode, 0)
+    });
+    if let Err(e) = r {
+        unsafe { libc::munmap(ptr, len) };
+        return Err(e);
+    }
+
+    Ok(ptr as *mut u8)
+}
+
+/// Releases memory allocated by [`alloc_on_node`].
+///
+/// # Safety
+/// `ptr` and `len` must be the result and the argument of a previous call to
+/// `alloc_on_node` and the memory must not be used afterwards.
+#[cfg(target_os = "linux")]
+pub unsafe fn free_on_node(ptr: *mut u8, len: usize) {
+    libc::munmap(ptr as *mut libc::c_void, len);
+}
+
+/// The node of every page of the region `[ptr, ptr + len)`.
+///
+/// Pages that are not present (never touched) are `None`.
+#[cfg(target_os = "linux")]
+pub fn page_nodes(ptr: *const u8, len: usize) -> io::Result<Vec<Option<Node>>> {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let start = ptr as usize & !(page_size - 1);
+    let end = ptr as usize + len;
+
+    let pages: Vec<*mut libc::c_void> = (start..end)
+        .step_by(page_size)
+        .map(|p| p as *mut libc::c_void)
+        .collect();
+    let mut status: Vec<libc::c_int> = vec![0; pages.len()];
+
+    // Without a target node array `move_pages` only reports the node of each page.
+    check(unsafe {
+        libc::syscall(
+            libc::SYS_move_pages,
+            0,
+            pages.len(),
+            pages.as_ptr(),
+            std::ptr::null::<libc::c_int>(),
+            status.as_mut_ptr(),
+            0,
+        )
+    })?;
+
+    Ok(status
+        .into_iter()
+        .map(|s| if s < 0 { None } else { Some(s as Node) })
+        .collect())
+}
+
+/// Checks how many pages of the region `[ptr, ptr + len)` are on `node`.
+pub fn check_placement(ptr: *const u8, len: usize, node: Node) -> io::Result<Placement> {
+    let mut placement = Placement::default();
+    for page in page_nodes(ptr, len)? {
+        match page {
+            Some(n) if n == node => placement.local += 1,
+            Some(_) => placement.remote += 1,
+            None => placement.not_present += 1,
+        }
+    }
+    Ok(placement)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported<T>() -> io::Result<T> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "NUMA memory binding is only supported on Linux",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn bind_current_thread(_node: Node, _policy: MemPolicy) -> io::Result<()> {
+    unsupported()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn interleave_current_thread(_nodes: &[Node]) -> io::Result<()> {
+    unsupported()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn reset_current_thread() -> io::Result<()> {
+    unsupported()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn alloc_on_node(_len: usize, _node: Node, _policy: MemPolicy) -> io::Result<*mut u8> {
+    unsupported()
+}
+
+/// # Safety
+/// Never succeeds to allocate on this platform, there is nothing to free.
+#[cfg(not(target_os = "linux"))]
+pub unsafe fn free_on_node(_ptr: *mut u8, _len: usize) {}
+
+#[cfg(not(target_os = "linux"))]
+pub fn page_nodes(_ptr: *const u8, _len: usize) -> io::Result<Vec<Option<Node>>> {
+    unsupported()
+}
@@ -0,0 +1,138 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Hardware performance counters of the benchmark threads (feature `perf`).
+//!
+//! Every benchmark thread opens its own counters with `perf_event_open` and
+//! samples them for the warmup and the measured phase. The following events
+//! are recorded:
+//!
+//!  - cache misses (last-level cache)
+//!  - remote DRAM accesses (reads that miss the local NUMA node)
+//!  - stalled cycles (frontend and backend)
+//!
+//! Not all events are supported by every CPU (or allowed by
+//! `perf_event_paranoid`), unsupported events are reported as `None`.
+//! Without the `perf` feature no counters are opened at all.
+
+use std::ops::Add;
+
+use serde::Serialize;
+
+/// The counter values of a thread for one phase of a run.
+#[derive(Serialize, Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct PerfSample {
+    pub cache_misses: Option<u64>,
+    pub remote_dram_accesses: Option<u64>,
+    pub stalled_cycles_frontend: Option<u64>,
+    pub stalled_cycles_backend: Option<u64>,
+}
+
+impl Add for PerfSample {
+    type Output = PerfSample;
+
+    fn add(self, other: PerfSample) -> PerfSample {
+        let add = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, None) => a,
+            (None, b) => b,
+        };
+        PerfSample {
+            cache_misses: add(self.cache_misses, other.cache_misses),
+            remote_dram_accesses: add(self.remote_dram_accesses, other.remote_dram_accesses),
+            stalled_cycles_frontend: add(self.stalled_cycles_frontend, other.stalled_cycles_frontend),
+            stalled_cycles_backend: add(self.stalled_cycles_backend, other.stalled_cycles_backend),
+        }
+    }
+}
+
+/// The performance counters of the calling thread.
+#[cfg(feature = "perf")]
+pub struct PerfCounters {
+    cache_misses: Option<perf_event::Counter>,
+    remote_dram_accesses: Option<perf_event::Counter>,
+    stalled_cycles_frontend: Option<perf_event::Counter>,
+    stalled_cycles_backend: Option<perf_event::Counter>,
+}
+
+#[cfg(feature = "perf")]
+impl PerfCounters {
+    /// Opens the counters for the calling thread, the counters are stopped.
+    pub fn new() -> PerfCounters {
+        use perf_event::events::{Cache, CacheOp, CacheResult, Event, Hardware, WhichCache};
+
+        let open = |event: Event| match perf_event::Builder::new().kind(event).build() {
+            Ok(counter) => Some(counter),
+            Err(e) => {
+                log::debug!("Can't open perf counter: {}", e);
+                None
+            }
+        };
+
+        PerfCounters {
+            cache_misses: open(Hardware::CACHE_MISSES.into()),
+            remote_dram_accesses: open(
+                Cache {
+                    which: WhichCache::Node,
+                    operation: CacheOp::READ,
+                    result: CacheResult::MISS,
+                }
+                .into(),
+            ),
+            stalled_cycles_frontend: open(Hardware::STALLED_CYCLES_FRONTEND.into()),
+            stalled_cycles_backend: open(Hardware::STALLED_CYCLES_BACKEND.into()),
+        }
+    }
+
+    fn counters(&mut self) -> [&mut Option<perf_event::Counter>; 4] {
+        [
+            &mut self.cache_misses,
+            &mut self.remote_dram_accesses,
+            &mut self.stalled_cycles_frontend,
+            &mut self.stalled_cycles_backend,
+        ]
+    }
+
+    /// Resets and starts the counters.
+    pub fn start(&mut self) {
+        for counter in self.counters().into_iter().flatten() {
+            let _ = counter.reset();
+            let _ = counter.enable();
+        }
+    }
+
+    /// Stops the counters and returns their values since the last `start`.
+    pub fn stop(&mut self) -> Option<PerfSample> {
+        let mut values = [None; 4];
+        for (value, counter) in values.iter_mut().zip(self.counters()) {
+            if let Some(counter) = counter {
+                let _ = counter.disable();
+                *value = counter.read().ok();
+            }
+        }
+
+        Some(PerfSample {
+            cache_misses: values[0],
+            remote_dram_accesses: values[1],
+            stalled_cycles_frontend: values[2],
+            stalled_cycles_backend: values[3],
+        })
+    }
+}
+
+/// No performance counters without the `perf` feature.
+#[cfg(not(feature = "perf"))]
+pub struct PerfCounters;
+
+#[cfg(not(feature = "perf"))]
+impl PerfCounters {
+    pub fn new() -> PerfCounters {
+        PerfCounters
+    }
+
+    pub fn start(&mut self) {}
+
+    pub fn stop(&mut self) -> Option<PerfSample> {
+        None
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Pins threads to the CPUs chosen by [`MachineTopology`].
+//!
+//! The topology only plans where threads should run, the functions in this
+//! module apply the plan (`sched_setaffinity` on Linux,
+//! `SetThreadAffinityMask` on Windows) and read the affinity back to verify
+//! that the pin took effect. A pin can silently fail, e.g., if the CPU is not
+//! in the cpuset of the process.
+//!
+//! [`MachineTopology`]: crate::topology::MachineTopology
+
+use std::fmt;
+use std::thread::JoinHandle;
+
+use crate::topology::{Cpu, CpuInfo};
+
+/// Why a thread couldn't be pinned.
+#[derive(Debug)]
+pub enum PinError {
+    /// The OS refused to change the affinity.
+    Os(std::io::Error),
+    /// The affinity was changed but the thread isn't restricted to `cpu`.
+    NotPinned { cpu: Cpu, actual: Vec<Cpu> },
+    /// Pinning threads isn't supported on this platform.
+    Unsupported,
+}
+
+impl fmt::Display for PinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PinError::Os(e) => write!(f, "can't set the affinity: {}", e),
+            PinError::NotPinned { cpu, actual } => {
+                write!(f, "thread should run on CPU {} but may run on {:?}", cpu, actual)
+            }
+            PinError::Unsupported => write!(f, "pinning threads is not supported on this platform"),
+        }
+    }
+}
+
+impl std::error::Error for PinError {}
+
+/// Pins the calling thread to `cpu` and verifies the new affinity.
+pub fn pin_current_thread(cpu: &CpuInfo) -> Result<(), PinError> {
+    pin_current_thread_to(cpu.cpu)
+}
+
+/// Pins the thread of `handle` to `cpu` and verifies the new affinity.
+pub fn pin_thread<T>(handle: &JoinHandle<T>, cpu: &CpuInfo) -> Result<(), PinError> {
+    pin_thread_to(handle, cpu.cpu)
+}
+
+/// Pins the calling thread to the CPU with id `cpu`.
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread_to(cpu: Cpu) -> Result<(), PinError> {
+    use nix::sched::{sched_getaffinity, sched_setaffinity, CpuSet};
+    use nix::unistd::Pid;
+
+    let os_err = |e: nix::Error| PinError::Os(std::io::Error::from_raw_os_error(e as i32));
+
+    let mut cpu_set = CpuSet::new();
+    cpu_set.set(cpu as usize).map_err(os_err)?;
+    sched_setaffinity(Pid::from_raw(0), &cpu_set).map_err(os_err)?;
+
+    let actual = sched_getaffinity(Pid::from_raw(0)).map_err(os_err)?;
+    verify(
+        cpu,
+        (0..CpuSet::count()).filter(|c| actual.is_set(*c).unwrap_or(false)),
+    )
+}
+
+/// Pins the thread of `handle` to the CPU with id `cpu`.
+#[cfg(target_os = "linux")]
+pub fn pin_thread_to<T>(handle: &JoinHandle<T>, cpu: Cpu) -> Result<(), PinError> {
+    use std::os::unix::thread::JoinHandleExt;
+
+    let thread = handle.as_pthread_t();
+    let size = std::mem::size_of::<libc::cpu_set_t>();
+
+    // Safety: `cpu_set_t` is plain data and `thread` is alive as long as
+    // `handle` is not joined.
+    unsafe {
+        let mut cpu_set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu as usize, &mut cpu_set);
+        let r = libc::pthread_setaffinity_np(thread, size, &cpu_set);
+        if r != 0 {
+            return Err(PinError::Os(std::io::Error::from_raw_os_error(r)));
+        }
+
+        let mut actual: libc::cpu_set_t = std::mem::zeroed();
+        let r = libc::pthread_getaffinity_np(thread, size, &mut actual);
+        if r != 0 {
+            return Err(PinError::Os(std::io::Error::from_raw_os_error(r)));
+        }
+        verify(
+            cpu,
+            (0..libc::CPU_SETSIZE as usize).filter(|c| libc::CPU_ISSET(*c, &actual)),
+        )
+    }
+}
+
+/// Pins the calling thread to the CPU with id `cpu`.
+///
+/// Only CPUs of the first processor group (< 64) are supported.
+#[cfg(windows)]
+pub fn pin_current_thread_to(cpu: Cpu) -> Result<(), PinError> {
+    use windows_sys::Win32::System::Threading::GetCurrentThread;
+
+    // Safety: the pseudo handle of the current thread is always valid.
+    unsafe { set_thread_affinity_mask(GetCurrentThread(), cpu) }
+}
+
+/// Pins the thread of `handle` to the CPU with id `cpu`.
+///
+/// Only CPUs of the first processor group (< 64) are supported.
+#[cfg(windows)]
+pub fn pin_thread_to<T>(handle: &JoinHandle<T>, cpu: Cpu) -> Result<(), PinError> {
+    use std::os::windows::io::AsRawHandle;
+
+    // Safety: the handle is valid as long as `handle` is not joined.
+    unsafe { set_thread_affinity_mask(handle.as_raw_handle() as _, cpu) }
+}
+
+#[cfg(windows)]
+unsafe fn set_thread_affinity_mask(
+    thread: windows_sys::Win32::Foundation::HANDLE,
+    cpu: Cpu,
+) -> Result<(), PinError> {
+    use windows_sys::Win32::System::Threading::SetThreadAffinityMask;
+
+    if cpu as usize >= usize::BITS as usize {
+        return Err(PinError::Unsupported);
+    }
+    let mask = 1usize << cpu;
+    if SetThreadAffinityMask(thread, mask) == 0 {
+        return Err(PinError::Os(std::io::Error::last_os_error()));
+    }
+
+    // Setting the mask again returns the mask that is in effect.
+    let actual = SetThreadAffinityMask(thread, mask);
+    if actual == 0 {
+        return Err(PinError::Os(std::io::Error::last_os_error()));
+    }
+    verify(cpu, (0..usize::BITS as usize).filter(|c| actual & (1 << c) != 0))
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn pin_current_thread_to(_cpu: Cpu) -> Result<(), PinError> {
+    Err(PinError::Unsupported)
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn pin_thread_to<T>(_handle: &JoinHandle<T>, _cpu: Cpu) -> Result<(), PinError> {
+    Err(PinError::Unsupported)
+}
+
+/// Checks that the affinity `actual` consists of `cpu` only.
+#[cfg(any(target_os = "linux", windows))]
+fn verify(cpu: Cpu, actual: impl Iterator<Item = usize>) -> Result<(), PinError> {
+    let actual: Vec<Cpu> = actual.map(|c| c as Cpu).collect();
+    if actual == [cpu] {
+        Ok(())
+    } else {
+        Err(PinError::NotPinned { cpu, actual })
+    }
+}
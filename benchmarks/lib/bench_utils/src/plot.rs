@@ -0,0 +1,159 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Plots the scalability curves of benchmark results (feature `plot`).
+//!
+//! For every benchmark two SVGs are produced, with one line for each
+//! combination of thread mapping and replica count:
+//!
+//!  - `nr_benchmarks_<name>_throughput.svg`: throughput vs. number of threads
+//!  - `nr_benchmarks_<name>_latency.svg`: mean latency vs. throughput
+//!
+//! We don't measure latencies of individual operations, the mean latency is
+//! derived from the throughput as every thread executes its operations back to
+//! back (Little's law): `latency = threads / throughput`.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+use plotters::prelude::*;
+
+use crate::results::RunResult;
+
+/// Size of the generated plots in pixels.
+const PLOT_SIZE: (u32, u32) = (1024, 768);
+
+/// A line in the plot: label and (x, y) points
+type Series = (String, Vec<(f64, f64)>);
+
+/// Mean latency of an operation in microseconds.
+fn mean_latency_us(r: &RunResult) -> f64 {
+    let ops_per_sec = r.ops_per_sec();
+    if ops_per_sec > 0.0 {
+        (r.config.threads as f64 / ops_per_sec) * 1_000_000.0
+    } else {
+        0.0
+    }
+}
+
+/// Groups the results by thread mapping and replica count, ordered by threads.
+fn group_results(results: &[RunResult]) -> BTreeMap<String, Vec<&RunResult>> {
+    let mut groups: BTreeMap<String, Vec<&RunResult>> = BTreeMap::new();
+    for r in results.iter() {
+        let label = format!("{} R={}", r.config.tm, r.config.replicas);
+        groups.entry(label).or_default().push(r);
+    }
+
+    for runs in groups.values_mut() {
+        runs.sort_by_key(|r| r.config.threads);
+    }
+    groups
+}
+
+fn draw_lines(
+    path: &Path,
+    caption: &str,
+    x_desc: &str,
+    y_desc: &str,
+    series: &[Series],
+) -> Result<(), Box<dyn Error>> {
+    let max_x = series
+        .iter()
+        .flat_map(|(_, pts)| pts.iter().map(|p| p.0))
+        .fold(1.0, f64::max);
+    let max_y = series
+        .iter()
+        .flat_map(|(_, pts)| pts.iter().map(|p| p.1))
+        .fold(1.0, f64::max);
+
+    let root = SVGBackend::new(path, PLOT_SIZE).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(caption, ("sans-serif", 24))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(80)
+        .build_cartesian_2d(0f64..max_x * 1.05, 0f64..max_y * 1.05)?;
+
+    chart
+        .configure_mesh()
+        .x_desc(x_desc)
+        .y_desc(y_desc)
+        .draw()?;
+
+    for (idx, (label, points)) in series.iter().enumerate() {
+        let color = Palette99::pick(idx).to_rgba();
+        chart
+            .draw_series(LineSeries::new(points.iter().copied(), color.stroke_width(2)))?
+            .label(label.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        chart.draw_series(
+            points
+                .iter()
+                .map(|p| Circle::new(*p, 3, color.filled())),
+        )?;
+    }
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// Plots throughput vs. threads of the given results to `path`.
+pub fn plot_throughput<P: AsRef<Path>>(
+    name: &str,
+    results: &[RunResult],
+    path: P,
+) -> Result<(), Box<dyn Error>> {
+    let series: Vec<Series> = group_results(results)
+        .into_iter()
+        .map(|(label, runs)| {
+            let points = runs
+                .iter()
+                .map(|r| (r.config.threads as f64, r.ops_per_sec()))
+                .collect();
+            (label, points)
+        })
+        .collect();
+
+    draw_lines(path.as_ref(), name, "# threads", "throughput [ops/s]", &series)
+}
+
+/// Plots mean latency vs. throughput of the given results to `path`.
+pub fn plot_latency<P: AsRef<Path>>(
+    name: &str,
+    results: &[RunResult],
+    path: P,
+) -> Result<(), Box<dyn Error>> {
+    let series: Vec<Series> = group_results(results)
+        .into_iter()
+        .map(|(label, runs)| {
+            let points = runs
+                .iter()
+                .map(|r| (r.ops_per_sec(), mean_latency_us(r)))
+                .collect();
+            (label, points)
+        })
+        .collect();
+
+    draw_lines(path.as_ref(), name, "throughput [ops/s]", "mean latency [us]", &series)
+}
+
+/// Produces all plots for the results of the benchmark `name`.
+pub fn plot_results(name: &str, results: &[RunResult]) -> Result<(), Box<dyn Error>> {
+    if results.is_empty() {
+        return Ok(());
+    }
+
+    plot_throughput(name, results, format!("nr_benchmarks_{name}_throughput.svg"))?;
+    plot_latency(name, results, format!("nr_benchmarks_{name}_latency.svg"))?;
+    Ok(())
+}
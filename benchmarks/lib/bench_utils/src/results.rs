@@ -0,0 +1,694 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Structured output of benchmark results.
+//!
+//! Every run of a benchmark produces a [`RunResult`] that captures the run's
+//! configuration and its measurements. The result is written to two files:
+//!
+//!  - `nr_benchmarks_<name>.json`: a summary of the run (one JSON object per file)
+//!  - `nr_benchmarks_<name>.csv`: the per-thread, per-second operation counts
+//!    (appended to, one row per thread and second)
+//!
+//! Sweeps (see `ScaleBenchBuilder::sweep`) additionally write the summaries of
+//! all their runs to `nr_benchmarks_<name>_sweep.json` (one JSON array), and
+//! the throughput-latency points of all runs to `nr_benchmarks_<name>_pareto.csv`
+//! (one row per run, see [`write_pareto_csv`]).
+//!
+//! The summaries embed the machine and build the run was on (see
+//! [`crate::metadata`]).
+//!
+//! The field names of both files are part of the schema and are consumed by
+//! `bench.py` and `plot.py`. Only add fields, never rename or remove them, and
+//! bump [`SCHEMA_VERSION`] when doing so.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use csv::WriterBuilder;
+use serde::Serialize;
+
+use crate::energy::EnergySample;
+use crate::latency::LatencyHistogram;
+use crate::metadata::{Metadata, METADATA};
+use crate::mkbench::{LogStrategy, ReplicaStrategy};
+use crate::perf::PerfSample;
+use crate::topology::{Core, ThreadMapping};
+use crate::trace::ReplayMode;
+
+/// Version of the result file schema.
+pub const SCHEMA_VERSION: u32 = 9;
+
+/// The configuration of a single benchmark run.
+#[derive(Serialize, Clone, Debug)]
+pub struct RunConfig {
+    /// Name of the benchmark
+    pub name: String,
+    /// Replica strategy used by the run
+    pub rs: ReplicaStrategy,
+    /// Thread mapping used by the run
+    pub tm: ThreadMapping,
+    /// Log strategy used by the run
+    pub ls: LogStrategy,
+    /// Number of threads
+    pub threads: usize,
+    /// Number of replicas
+    pub replicas: usize,
+    /// Size of the operation log
+    pub log_size: usize,
+    /// Batch size passed to the benchmark function
+    pub batch_size: usize,
+    /// Percentage of read operations in the workload
+    pub reads_pct: usize,
+    /// How long the run was measured
+    pub duration: Duration,
+    /// Minimal duration of the warmup before the measurement
+    pub warmup: Duration,
+    /// Operations per second offered by all threads together, `None` if the
+    /// threads ran in a closed loop
+    pub offered_load: Option<u64>,
+    /// How the operations were replayed from a trace, `None` for a synthetic
+    /// workload
+    pub replay: Option<ReplayMode>,
+}
+
+/// The measurements of a single thread.
+#[derive(Clone, Debug)]
+pub struct ThreadMeasurement {
+    /// Index of the thread in the run
+    pub thread_id: usize,
+    /// Core the thread was pinned to
+    pub core_id: Core,
+    /// Completed operations for each second of the run
+    pub ops_per_sec: Vec<usize>,
+    /// Total number of completed read operations
+    pub reads: usize,
+    /// Total number of completed update operations
+    pub updates: usize,
+    /// Completed operations for each second of the warmup (not measured)
+    pub warmup_ops_per_sec: Vec<usize>,
+    /// Performance counters of the measured phase (feature `perf`)
+    pub perf: Option<PerfSample>,
+    /// Performance counters of the warmup phase (feature `perf`)
+    pub warmup_perf: Option<PerfSample>,
+    /// Latencies of the operations of the measured phase
+    pub latency: LatencyHistogram,
+    /// Package energy of the whole machine during the measured phase (feature
+    /// `energy`), only recorded by the first thread
+    pub energy: Option<EnergySample>,
+}
+
+/// Number of buckets of a [`Histogram`].
+pub const HISTOGRAM_BUCKETS: usize = 16;
+
+/// A histogram with power-of-two buckets, as recorded by the `metrics` of the
+/// verified implementation: bucket 0 counts the value 0, bucket `i > 0` the
+/// values in `[2^(i-1), 2^i)`, and the last bucket also all larger values.
+pub type Histogram = [u64; HISTOGRAM_BUCKETS];
+
+/// The smallest value that falls into bucket `i` of a [`Histogram`].
+pub fn bucket_start(i: usize) -> u64 {
+    if i == 0 {
+        0
+    } else {
+        1 << (i - 1)
+    }
+}
+
+/// Adds the counts of `src` to `dst`, `src` may have fewer buckets.
+pub fn add_histogram(dst: &mut Histogram, src: &[u64]) {
+    for (d, s) in dst.iter_mut().zip(src.iter()) {
+        *d += s;
+    }
+}
+
+/// The start of the bucket that contains the `p`-th percentile of the
+/// histogram, `None` if it is empty.
+pub fn histogram_percentile(h: &Histogram, p: f64) -> Option<u64> {
+    let total = h.iter().sum::<u64>();
+    if total == 0 {
+        return None;
+    }
+    let rank = ((p / 100.0) * total as f64).ceil().max(1.0) as u64;
+    let mut seen = 0;
+    for (i, count) in h.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return Some(bucket_start(i));
+        }
+    }
+    Some(bucket_start(HISTOGRAM_BUCKETS - 1))
+}
+
+/// The combining statistics of a run, summed over all replicas.
+///
+/// Only recorded by data-structures that support it (the verified
+/// implementation with the `metrics` feature).
+#[derive(Serialize, Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct CombinerSample {
+    /// Number of times a thread acquired a combiner lock and combined
+    pub combines: u64,
+    /// Number of update operations collected by the combiners
+    pub ops: u64,
+    /// Distribution of the update operations per combine
+    pub ops_per_combine: Histogram,
+    /// Number of successful compare-and-swaps of the log tail (appends)
+    pub appends: u64,
+    /// Number of log entries reserved by the appends
+    pub entries: u64,
+    /// Number of failed compare-and-swaps of the log tail
+    pub cas_failures: u64,
+    /// Distribution of the log entries per successful compare-and-swap
+    pub entries_per_cas: Histogram,
+}
+
+impl CombinerSample {
+    /// The average number of update operations per combine (the batch size).
+    pub fn ops_per_combine(&self) -> f64 {
+        if self.combines == 0 {
+            0.0
+        } else {
+            self.ops as f64 / self.combines as f64
+        }
+    }
+
+    /// The average number of log entries per successful compare-and-swap of
+    /// the tail.
+    pub fn entries_per_cas(&self) -> f64 {
+        if self.appends == 0 {
+            0.0
+        } else {
+            self.entries as f64 / self.appends as f64
+        }
+    }
+}
+
+/// The configuration and measurements of a benchmark run.
+#[derive(Clone, Debug)]
+pub struct RunResult {
+    pub config: RunConfig,
+    pub threads: Vec<ThreadMeasurement>,
+    /// Combiner statistics of the measured phase
+    pub combiner: Option<CombinerSample>,
+}
+
+/// Summary record, stored as JSON.
+#[derive(Serialize)]
+struct SummaryRecord<'a> {
+    schema_version: u32,
+    bench_name: &'a str,
+    n_threads: usize,
+    reads_pct: usize,
+    n_replicas: usize,
+    run_seconds: u64,
+    warmup_seconds: f64,
+    numa_policy: String,
+    replica_strategy: String,
+    log_strategy: String,
+    log_size: usize,
+    batch_size: usize,
+    core_policy: usize,
+    reads: usize,
+    updates: usize,
+    total_ops: usize,
+    reads_per_s: f64,
+    updates_per_s: f64,
+    ops_per_s: f64,
+    stdev: f64,
+    offered_load: Option<u64>,
+    replay: Option<String>,
+    latency_p50_ns: Option<u64>,
+    latency_p99_ns: Option<u64>,
+    cache_misses: Option<u64>,
+    remote_dram_accesses: Option<u64>,
+    stalled_cycles_frontend: Option<u64>,
+    stalled_cycles_backend: Option<u64>,
+    energy_joules: Option<f64>,
+    joules_per_op: Option<f64>,
+    combines: Option<u64>,
+    ops_per_combine: Option<f64>,
+    ops_per_combine_p50: Option<u64>,
+    ops_per_combine_p99: Option<u64>,
+    ops_per_combine_hist: Option<Histogram>,
+    appends: Option<u64>,
+    cas_failures: Option<u64>,
+    entries_per_cas: Option<f64>,
+    entries_per_cas_p50: Option<u64>,
+    entries_per_cas_p99: Option<u64>,
+    entries_per_cas_hist: Option<Histogram>,
+    metadata: &'static Metadata,
+}
+
+/// Per-thread record, stored as CSV.
+#[derive(Serialize)]
+struct ThreadRecord<'a> {
+    schema_version: u32,
+    name: &'a str,
+    rs: String,
+    tm: String,
+    ls: String,
+    log_size: usize,
+    replicas: usize,
+    batch_size: usize,
+    threads: usize,
+    reads_pct: usize,
+    duration: f64,
+    thread_id: usize,
+    core_id: Core,
+    exp_time_in_sec: usize,
+    iterations: usize,
+}
+
+impl RunResult {
+    pub fn new(config: RunConfig) -> RunResult {
+        RunResult {
+            config,
+            threads: Vec::new(),
+            combiner: None,
+        }
+    }
+
+    /// Adds the measurements of a thread to the result.
+    pub fn add_thread(&mut self, measurement: ThreadMeasurement) {
+        self.threads.push(measurement);
+    }
+
+    /// All per-second samples of all threads.
+    fn samples(&self) -> Vec<usize> {
+        self.threads
+            .iter()
+            .flat_map(|t| t.ops_per_sec.iter().copied())
+            .collect()
+    }
+
+    /// Total number of completed operations.
+    pub fn total_ops(&self) -> usize {
+        self.threads.iter().map(|t| t.ops_per_sec.iter().sum::<usize>()).sum()
+    }
+
+    /// Total number of completed update operations.
+    pub fn total_updates(&self) -> usize {
+        self.threads.iter().map(|t| t.updates).sum()
+    }
+
+    /// Throughput over all threads in operations per second.
+    pub fn ops_per_sec(&self) -> f64 {
+        self.total_ops() as f64 / self.config.duration.as_secs_f64()
+    }
+
+    /// Standard deviation of the per-thread, per-second samples.
+    pub fn stdev(&self) -> f64 {
+        crate::benchmark::std_deviation(&self.samples()).unwrap_or(0.0)
+    }
+
+    /// Performance counters of the measured phase summed over all threads.
+    pub fn perf(&self) -> Option<PerfSample> {
+        self.threads.iter().filter_map(|t| t.perf).reduce(|a, b| a + b)
+    }
+
+    /// Performance counters of the warmup phase summed over all threads.
+    pub fn warmup_perf(&self) -> Option<PerfSample> {
+        self.threads.iter().filter_map(|t| t.warmup_perf).reduce(|a, b| a + b)
+    }
+
+    /// Package energy of the measured phase.
+    pub fn energy(&self) -> Option<EnergySample> {
+        self.threads.iter().find_map(|t| t.energy)
+    }
+
+    /// Energy per completed operation in joules.
+    pub fn joules_per_op(&self) -> Option<f64> {
+        match (self.energy(), self.total_ops()) {
+            (Some(e), ops) if ops > 0 => Some(e.joules / ops as f64),
+            _ => None,
+        }
+    }
+
+    /// Latencies of the measured phase over all threads.
+    pub fn latency(&self) -> LatencyHistogram {
+        let mut latency = LatencyHistogram::new();
+        for t in self.threads.iter() {
+            latency += &t.latency;
+        }
+        latency
+    }
+
+    fn summary(&self) -> SummaryRecord {
+        let secs = self.config.duration.as_secs_f64();
+        let perf = self.perf().unwrap_or_default();
+        let reads = self.threads.iter().map(|t| t.reads).sum::<usize>();
+        let updates = self.threads.iter().map(|t| t.updates).sum::<usize>();
+        let c = self.combiner;
+        let latency = self.latency();
+
+        SummaryRecord {
+            schema_version: SCHEMA_VERSION,
+            bench_name: &self.config.name,
+            n_threads: self.config.threads,
+            reads_pct: self.config.reads_pct,
+            n_replicas: self.config.replicas,
+            run_seconds: self.config.duration.as_secs(),
+            warmup_seconds: self.config.warmup.as_secs_f64(),
+            numa_policy: format!("{}", self.config.tm),
+            replica_strategy: format!("{}", self.config.rs),
+            log_strategy: format!("{}", self.config.ls),
+            log_size: self.config.log_size,
+            batch_size: self.config.batch_size,
+            core_policy: 0,
+            reads,
+            updates,
+            total_ops: self.total_ops(),
+            reads_per_s: reads as f64 / secs,
+            updates_per_s: updates as f64 / secs,
+            ops_per_s: self.ops_per_sec(),
+            stdev: self.stdev(),
+            offered_load: self.config.offered_load,
+            replay: self.config.replay.map(|r| format!("{}", r)),
+            latency_p50_ns: latency.percentile(50.0),
+            latency_p99_ns: latency.percentile(99.0),
+            cache_misses: perf.cache_misses,
+            remote_dram_accesses: perf.remote_dram_accesses,
+            stalled_cycles_frontend: perf.stalled_cycles_frontend,
+            stalled_cycles_backend: perf.stalled_cycles_backend,
+            energy_joules: self.energy().map(|e| e.joules),
+            joules_per_op: self.joules_per_op(),
+            combines: c.map(|c| c.combines),
+            ops_per_combine: c.map(|c| c.ops_per_combine()),
+            ops_per_combine_p50: c.and_then(|c| histogram_percentile(&c.ops_per_combine, 50.0)),
+            ops_per_combine_p99: c.and_then(|c| histogram_percentile(&c.ops_per_combine, 99.0)),
+            ops_per_combine_hist: c.map(|c| c.ops_per_combine),
+            appends: c.map(|c| c.appends),
+            cas_failures: c.map(|c| c.cas_failures),
+            entries_per_cas: c.map(|c| c.entries_per_cas()),
+            entries_per_cas_p50: c.and_then(|c| histogram_percentile(&c.entries_per_cas, 50.0)),
+            entries_per_cas_p99: c.and_then(|c| histogram_percentile(&c.entries_per_cas, 99.0)),
+            entries_per_cas_hist: c.map(|c| c.entries_per_cas),
+            metadata: &METADATA,
+        }
+    }
+
+    /// Throughput over all threads for each second of the warmup.
+    pub fn warmup_ops_per_sec(&self) -> Vec<usize> {
+        let secs = self.threads.iter().map(|t| t.warmup_ops_per_sec.len()).max().unwrap_or(0);
+        (0..secs)
+            .map(|sec| {
+                self.threads
+                    .iter()
+                    .filter_map(|t| t.warmup_ops_per_sec.get(sec))
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Writes the summary of the run as a JSON object to `path`.
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut json_file = File::create(path)?;
+        serde_json::to_writer_pretty(&mut json_file, &self.summary())?;
+        json_file.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Appends the per-thread measurements to the CSV file at `path`.
+    ///
+    /// The header is only written if the file doesn't exist yet.
+    pub fn append_csv<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        self.append_samples(path, |t| &t.ops_per_sec)
+    }
+
+    /// Appends the per-thread warmup samples to the CSV file at `path`, in
+    /// the same format as [`RunResult::append_csv`].
+    pub fn append_warmup_csv<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        self.append_samples(path, |t| &t.warmup_ops_per_sec)
+    }
+
+    fn append_samples<P, F>(&self, path: P, samples: F) -> std::io::Result<()>
+    where
+        P: AsRef<Path>,
+        F: Fn(&ThreadMeasurement) -> &Vec<usize>,
+    {
+        let write_headers = !path.as_ref().exists();
+        let csv_file = OpenOptions::new().append(true).create(true).open(path)?;
+
+        let mut wtr = WriterBuilder::new()
+            .has_headers(write_headers)
+            .from_writer(csv_file);
+
+        for t in self.threads.iter() {
+            for (idx, ops) in samples(t).iter().enumerate() {
+                let record = ThreadRecord {
+                    schema_version: SCHEMA_VERSION,
+                    name: &self.config.name,
+                    rs: format!("{}", self.config.rs),
+                    tm: format!("{}", self.config.tm),
+                    ls: format!("{}", self.config.ls),
+                    log_size: self.config.log_size,
+                    replicas: self.config.replicas,
+                    batch_size: self.config.batch_size,
+                    threads: self.config.threads,
+                    reads_pct: self.config.reads_pct,
+                    duration: self.config.duration.as_secs_f64(),
+                    thread_id: t.thread_id,
+                    core_id: t.core_id,
+                    exp_time_in_sec: idx + 1, // start at 1 (for first second)
+                    iterations: *ops,
+                };
+                wtr.serialize(record)?;
+            }
+        }
+        wtr.flush()?;
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for RunResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Run({:?} {:?} {:3} {:?} BS={}) => {:20.5} ({:.5})",
+            self.config.rs,
+            self.config.tm,
+            self.config.threads,
+            self.config.ls,
+            self.config.batch_size,
+            self.ops_per_sec(),
+            self.stdev()
+        )
+    }
+}
+
+/// Writes the summaries of all `results` as one JSON array to `path`.
+pub fn write_json_all<P: AsRef<Path>>(path: P, results: &[RunResult]) -> std::io::Result<()> {
+    let summaries: Vec<SummaryRecord> = results.iter().map(|r| r.summary()).collect();
+    let mut json_file = File::create(path)?;
+    serde_json::to_writer_pretty(&mut json_file, &summaries)?;
+    json_file.write_all(b"\n")?;
+    Ok(())
+}
+
+/// A row of the Pareto report, stored as CSV.
+#[derive(Serialize)]
+struct ParetoRecord<'a> {
+    schema_version: u32,
+    bench_name: &'a str,
+    replica_strategy: String,
+    numa_policy: String,
+    log_strategy: String,
+    n_threads: usize,
+    n_replicas: usize,
+    batch_size: usize,
+    reads_pct: usize,
+    /// empty for closed-loop runs
+    offered_load: Option<u64>,
+    ops_per_s: f64,
+    latency_p99_ns: Option<u64>,
+    pareto_optimal: bool,
+}
+
+/// Writes one row per run with its offered load, achieved throughput and p99
+/// latency to the CSV file at `path`, to pick operating points from a sweep.
+///
+/// A run is `pareto_optimal` if no other run has at least its throughput
+/// with at most its p99 latency (and is strictly better in one of them).
+/// Runs without latencies are never optimal.
+pub fn write_pareto_csv<P: AsRef<Path>>(path: P, results: &[RunResult]) -> std::io::Result<()> {
+    let points: Vec<(f64, Option<u64>)> = results
+        .iter()
+        .map(|r| (r.ops_per_sec(), r.latency().percentile(99.0)))
+        .collect();
+    let dominated = |ops: f64, p99: u64| {
+        points.iter().any(|(o, l)| match l {
+            Some(l) => *o >= ops && *l <= p99 && (*o > ops || *l < p99),
+            None => false,
+        })
+    };
+
+    let mut wtr = WriterBuilder::new().has_headers(true).from_path(path)?;
+    for (r, (ops, p99)) in results.iter().zip(points.iter()) {
+        wtr.serialize(ParetoRecord {
+            schema_version: SCHEMA_VERSION,
+            bench_name: &r.config.name,
+            replica_strategy: format!("{}", r.config.rs),
+            numa_policy: format!("{}", r.config.tm),
+            log_strategy: format!("{}", r.config.ls),
+            n_threads: r.config.threads,
+            n_replicas: r.config.replicas,
+            batch_size: r.config.batch_size,
+            reads_pct: r.config.reads_pct,
+            offered_load: r.config.offered_load,
+            ops_per_s: *ops,
+            latency_p99_ns: *p99,
+            pareto_optimal: p99.map_or(false, |p99| !dominated(*ops, p99)),
+        })?;
+    }
+    wtr.flush()?;
+
+    Ok(())
+}
+
+/// Prints a table comparing the throughput of runs with the same configuration.
+///
+/// There is one row per (replica strategy, thread mapping, #threads) and one
+/// column per benchmark name. The first benchmark is used as the reference,
+/// the other columns show the throughput relative to it.
+pub fn print_comparison(results: &[RunResult]) {
+    let mut names: Vec<&str> = Vec::new();
+    for r in results.iter() {
+        if !names.contains(&r.config.name.as_str()) {
+            names.push(&r.config.name);
+        }
+    }
+    if names.is_empty() {
+        return;
+    }
+
+    let mut rows: BTreeMap<(String, String, usize), Vec<Option<f64>>> = BTreeMap::new();
+    for r in results.iter() {
+        let key = (
+            format!("{}", r.config.rs),
+            format!("{}", r.config.tm),
+            r.config.threads,
+        );
+        let col = names.iter().position(|n| *n == r.config.name).unwrap();
+        rows.entry(key).or_insert_with(|| vec![None; names.len()])[col] = Some(r.ops_per_sec());
+    }
+
+    print!("{:>10} {:>12} {:>8}", "RS", "TM", "threads");
+    for name in names.iter() {
+        print!(" {:>24}", name);
+    }
+    println!();
+
+    for ((rs, tm, threads), cols) in rows.iter() {
+        print!("{:>10} {:>12} {:>8}", rs, tm, threads);
+        for (idx, col) in cols.iter().enumerate() {
+            match (idx, col, cols[0]) {
+                (_, None, _) => print!(" {:>24}", "-"),
+                (0, Some(ops), _) => print!(" {:>24.0}", ops),
+                (_, Some(ops), Some(reference)) if reference > 0.0 => {
+                    print!(" {:>14.0} ({:>6.2}x)", ops, ops / reference)
+                }
+                (_, Some(ops), _) => print!(" {:>24.0}", ops),
+            }
+        }
+        println!();
+    }
+}
+
+/// Prints a table with the throughput and the energy of every run, one row
+/// per run, to compare the energy per operation of different data-structures.
+/// Runs without energy readings show `-`.
+pub fn print_energy(results: &[RunResult]) {
+    let width = results.iter().map(|r| r.config.name.len()).max().unwrap_or(0).max(4);
+    println!(
+        "{:width$} {:>8} {:>16} {:>12} {:>12}",
+        "name", "threads", "ops/s", "joules", "nJ/op"
+    );
+    for r in results.iter() {
+        print!("{:width$} {:>8} {:>16.0}", r.config.name, r.config.threads, r.ops_per_sec());
+        match (r.energy(), r.joules_per_op()) {
+            (Some(e), Some(per_op)) => println!(" {:>12.2} {:>12.2}", e.joules, per_op * 1e9),
+            _ => println!(" {:>12} {:>12}", "-", "-"),
+        }
+    }
+}
+
+/// Prints a table with the throughput and the combining statistics of every
+/// run, one row per run.
+pub fn print_combiner_stats(results: &[RunResult]) {
+    let width = results.iter().map(|r| r.config.name.len()).max().unwrap_or(0).max(4);
+    println!(
+        "{:width$} {:>8} {:>16} {:>12} {:>14} {:>6} {:>6} {:>12} {:>12}",
+        "name", "threads", "ops/s", "combines", "ops/combine", "p50", "p99", "entries/cas", "cas-fails"
+    );
+    for r in results.iter() {
+        print!("{:width$} {:>8} {:>16.0}", r.config.name, r.config.threads, r.ops_per_sec());
+        match r.combiner {
+            Some(c) => {
+                let p = |pct| {
+                    histogram_percentile(&c.ops_per_combine, pct)
+                        .map_or("-".to_string(), |v| v.to_string())
+                };
+                println!(
+                    " {:>12} {:>14.2} {:>6} {:>6} {:>12.2} {:>12}",
+                    c.combines,
+                    c.ops_per_combine(),
+                    p(50.0),
+                    p(99.0),
+                    c.entries_per_cas(),
+                    c.cas_failures
+                )
+            }
+            None => println!(
+                " {:>12} {:>14} {:>6} {:>6} {:>12} {:>12}",
+                "-", "-", "-", "-", "-", "-"
+            ),
+        }
+    }
+}
+
+/// Prints the distributions of the update operations per combine and of the
+/// log entries per compare-and-swap of a run, one row per non-empty bucket.
+pub fn print_combining_distribution(result: &RunResult) {
+    let c = match result.combiner {
+        Some(c) => c,
+        None => return,
+    };
+    let share = |count: u64, total: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            100.0 * count as f64 / total as f64
+        }
+    };
+    let combines = c.ops_per_combine.iter().sum::<u64>();
+    let appends = c.entries_per_cas.iter().sum::<u64>();
+
+    println!("{}: combining distribution", result.config.name);
+    println!("{:>12} {:>12} {:>8} {:>12} {:>8}", "bucket", "combines", "%", "cas", "%");
+    for i in 0..HISTOGRAM_BUCKETS {
+        let (nc, na) = (c.ops_per_combine[i], c.entries_per_cas[i]);
+        if nc == 0 && na == 0 {
+            continue;
+        }
+        let bucket = if i == 0 {
+            "0".to_string()
+        } else if i == HISTOGRAM_BUCKETS - 1 {
+            format!(">={}", bucket_start(i))
+        } else {
+            format!("{}-{}", bucket_start(i), bucket_start(i + 1) - 1)
+        };
+        println!(
+            "{:>12} {:>12} {:>8.2} {:>12} {:>8.2}",
+            bucket,
+            nc,
+            share(nc, combines),
+            na,
+            share(na, appends)
+        );
+    }
+}
@@ -3,11 +3,19 @@
 
 //! Allows to query information about the machine's CPU topology.
 
+use std::collections::BTreeMap;
+#[cfg(target_os = "linux")]
+use std::collections::HashMap;
 use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
 
+#[cfg(feature = "hwloc")]
 use hwloc2::*;
 use lazy_static::lazy_static;
-use serde::Serialize;
+use log::warn;
+use serde::{Deserialize, Serialize};
 
 pub type Node = u64;
 pub type Socket = u64;
@@ -17,6 +25,22 @@ pub type L1 = u64;
 pub type L2 = u64;
 pub type L3 = u64;
 
+/// Environment variable with the path of a topology snapshot to use instead
+/// of the machine's topology (see [`MachineTopology::to_file`]).
+pub const TOPOLOGY_ENV: &str = "BENCH_TOPOLOGY";
+
+/// Where Linux exposes the CPUs.
+#[cfg(target_os = "linux")]
+const SYSFS_CPU: &str = "/sys/devices/system/cpu";
+
+/// The CPUs of the efficiency cores of Intel hybrid processors.
+#[cfg(target_os = "linux")]
+const SYSFS_INTEL_ATOM_CPUS: &str = "/sys/devices/cpu_atom/cpus";
+
+/// Where Linux exposes the NUMA nodes.
+#[cfg(target_os = "linux")]
+const SYSFS_NODE: &str = "/sys/devices/system/node";
+
 lazy_static! {
     pub static ref MACHINE_TOPOLOGY: MachineTopology = MachineTopology::new();
 }
@@ -33,6 +57,56 @@ pub enum ThreadMapping {
     /// Spread thread allocation out across sockets (as much as possible).
     #[allow(unused)]
     Interleave,
+    /// Place pairs of threads on the hyperthread siblings of a core, filling
+    /// up a socket before moving to the next one.
+    #[allow(unused)]
+    SmtPairs,
+    /// Assign threads round-robin across the physical cores of all sockets,
+    /// hyperthreads are only used once all physical cores are taken.
+    #[allow(unused)]
+    RoundRobinCore,
+    /// Use the given CPUs in the given order.
+    ///
+    /// Create with [`ThreadMapping::explicit`].
+    #[allow(unused)]
+    Explicit(&'static [Cpu]),
+}
+
+impl ThreadMapping {
+    /// Parses the name of a mapping as used on the command line.
+    pub fn parse(s: &str) -> Option<ThreadMapping> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Some(ThreadMapping::None),
+            "sequential" => Some(ThreadMapping::Sequential),
+            "fill" | "numafill" => Some(ThreadMapping::NUMAFill),
+            "interleave" => Some(ThreadMapping::Interleave),
+            "smt" | "smtpairs" => Some(ThreadMapping::SmtPairs),
+            "roundrobin" | "roundrobincore" => Some(ThreadMapping::RoundRobinCore),
+            list => ThreadMapping::parse_cpu_list(list),
+        }
+    }
+
+    /// Creates an explicit mapping to `cpus`.
+    ///
+    /// The list lives for the rest of the program, this is meant to be called
+    /// once per configuration (e.g., when parsing the command line).
+    pub fn explicit(cpus: Vec<Cpu>) -> ThreadMapping {
+        ThreadMapping::Explicit(Box::leak(cpus.into_boxed_slice()))
+    }
+
+    /// Parses an explicit mapping from a comma-separated CPU list, e.g., `0,4,8,12`.
+    fn parse_cpu_list(s: &str) -> Option<ThreadMapping> {
+        let cpus: Option<Vec<Cpu>> = s.split(',').map(|cpu| cpu.trim().parse().ok()).collect();
+        cpus.filter(|cpus| !cpus.is_empty()).map(ThreadMapping::explicit)
+    }
+
+    /// Returns the mapping passed with `--cpus <list>` on the command line.
+    pub fn from_args() -> Option<ThreadMapping> {
+        let args: Vec<String> = std::env::args().collect();
+        let idx = args.iter().position(|arg| arg == "--cpus")?;
+        let list = args.get(idx + 1).expect("--cpus requires a list of CPUs, e.g., 0,4,8,12");
+        Some(ThreadMapping::parse_cpu_list(list).expect("--cpus requires a list of CPUs, e.g., 0,4,8,12"))
+    }
 }
 
 impl fmt::Display for ThreadMapping {
@@ -42,6 +116,9 @@ impl fmt::Display for ThreadMapping {
             ThreadMapping::Sequential => write!(f, "Sequential"),
             ThreadMapping::Interleave => write!(f, "Interleave"),
             ThreadMapping::NUMAFill => write!(f, "NUMAFill"),
+            ThreadMapping::SmtPairs => write!(f, "SmtPairs"),
+            ThreadMapping::RoundRobinCore => write!(f, "RoundRobinCore"),
+            ThreadMapping::Explicit(_) => write!(f, "Explicit"),
         }
     }
 }
@@ -53,12 +130,15 @@ impl fmt::Debug for ThreadMapping {
             ThreadMapping::Sequential => write!(f, "TM=Sequential"),
             ThreadMapping::Interleave => write!(f, "TM=Interleave"),
             ThreadMapping::NUMAFill => write!(f, "TM=NUMAFill"),
+            ThreadMapping::SmtPairs => write!(f, "TM=SmtPairs"),
+            ThreadMapping::RoundRobinCore => write!(f, "TM=RoundRobinCore"),
+            ThreadMapping::Explicit(cpus) => write!(f, "TM=Explicit({:?})", cpus),
         }
     }
 }
 
 /// NUMA Node information.
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
 pub struct NodeInfo {
     /// Node index
     pub node: Node,
@@ -66,8 +146,33 @@ pub struct NodeInfo {
     pub memory: u64,
 }
 
+/// The kind of core a CPU belongs to on heterogeneous machines.
+///
+/// Intel hybrid processors have performance (P) and efficiency (E) cores,
+/// ARM big.LITTLE systems big and little cores. On homogeneous machines all
+/// cores are performance cores.
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub enum CoreKind {
+    /// A performance (P, big) core.
+    #[default]
+    Performance,
+    /// An efficiency (E, LITTLE) core.
+    Efficiency,
+}
+
+impl CoreKind {
+    /// Parses `performance`/`p` or `efficiency`/`e`.
+    pub fn parse(s: &str) -> Option<CoreKind> {
+        match s.to_lowercase().as_str() {
+            "performance" | "p" => Some(CoreKind::Performance),
+            "efficiency" | "e" => Some(CoreKind::Efficiency),
+            _ => None,
+        }
+    }
+}
+
 /// Information about a CPU in the system.
-#[derive(Eq, PartialEq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
 pub struct CpuInfo {
     pub node: Option<NodeInfo>,
     pub socket: Socket,
@@ -76,91 +181,415 @@ pub struct CpuInfo {
     pub l1: L1,
     pub l2: L2,
     pub l3: L3,
+    /// snapshots taken before core kinds were discovered have performance cores only
+    #[serde(default)]
+    pub kind: CoreKind,
+}
+
+impl CpuInfo {
+    /// The NUMA node of the CPU, CPUs without a node belong to node 0.
+    pub fn node_id(&self) -> Node {
+        self.node.map_or(0, |n| n.node)
+    }
 }
 
 impl std::fmt::Debug for CpuInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "CpuInfo {{ core/l1/l2: {}/{}/{}, cpu: {}, socket/l3/node: {}/{}/{:?} }}",
-            self.core, self.l1, self.l2, self.cpu, self.socket, self.l3, self.node
+            "CpuInfo {{ core/l1/l2: {}/{}/{}, cpu: {}, socket/l3/node: {}/{}/{:?}, kind: {:?} }}",
+            self.core, self.l1, self.l2, self.cpu, self.socket, self.l3, self.node, self.kind
         )
     }
 }
 
-#[derive(Debug)]
+/// What to do if more threads are requested than CPUs are allowed.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CpuLimit {
+    /// Print a warning and place several threads on the same CPUs.
+    Warn,
+    /// Panic.
+    Fail,
+}
+
+/// The view of a topology that the [`ThreadMapping`] strategies allocate
+/// CPUs from.
+///
+/// [`MachineTopology`] provides the topology of the local machine, tests can
+/// provide synthetic topologies (e.g., many sockets, missing NUMA information
+/// or sockets with different numbers of cores) and check the placements.
+pub trait TopologyProvider {
+    /// All CPUs of the topology.
+    fn cpus(&self) -> &[CpuInfo];
+
+    /// The CPUs that can be allocated.
+    fn allowed(&self) -> Vec<&CpuInfo> {
+        self.cpus().iter().filter(|c| self.is_allowed(c.cpu)).collect()
+    }
+
+    /// Whether `cpu` can be allocated.
+    fn is_allowed(&self, _cpu: Cpu) -> bool {
+        true
+    }
+
+    /// What to do if more threads are requested than CPUs are allowed.
+    fn cpu_limit(&self) -> CpuLimit {
+        CpuLimit::Warn
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct MachineTopology {
     data: Vec<CpuInfo>,
+    /// CPUs the process is allowed to run on, `None` if unknown.
+    allowed: Option<Vec<Cpu>>,
+    /// What to do if an allocation exceeds the allowed CPUs.
+    limit: CpuLimit,
+    /// Only allocate CPUs of this kind, `None` for all CPUs.
+    #[serde(default)]
+    kind: Option<CoreKind>,
+}
+
+/// Returns the CPUs the process is allowed to run on.
+///
+/// This is the affinity mask of the process, which reflects restrictions
+/// imposed by cgroup cpusets (containers), `taskset` or `numactl`.
+#[cfg(target_os = "linux")]
+pub fn allowed_cpus() -> Option<Vec<Cpu>> {
+    use nix::sched::{sched_getaffinity, CpuSet};
+
+    let cpu_set = sched_getaffinity(nix::unistd::Pid::from_raw(0)).ok()?;
+    let cpus: Vec<Cpu> = (0..CpuSet::count())
+        .filter(|cpu| cpu_set.is_set(*cpu).unwrap_or(false))
+        .map(|cpu| cpu as Cpu)
+        .collect();
+    if cpus.is_empty() {
+        None
+    } else {
+        Some(cpus)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn allowed_cpus() -> Option<Vec<Cpu>> {
+    None
 }
 
 impl MachineTopology {
+    /// Discovers the topology of the machine.
+    ///
+    /// Uses hwloc if it is enabled (feature `hwloc`) and able to resolve the
+    /// topology, otherwise falls back to parsing sysfs on Linux, and to a
+    /// single node with one core per CPU everywhere else.
+    ///
+    /// Only the CPUs the process is allowed to run on (see [`allowed_cpus`])
+    /// are handed out by [`MachineTopology::allocate`].
+    ///
+    /// If [`TOPOLOGY_ENV`] is set, the snapshot it points to is used instead.
     pub fn new() -> MachineTopology {
-        let mut data: Vec<CpuInfo> = Default::default();
+        if let Ok(path) = std::env::var(TOPOLOGY_ENV) {
+            return MachineTopology::from_file(&path)
+                .unwrap_or_else(|e| panic!("Can't load the topology from {}: {}", path, e));
+        }
 
-        let topo = Topology::new().expect("Can't retrieve Topology");
-        let cpus = topo
-            .objects_with_type(&ObjectType::PU)
-            .expect("Can't find CPUs");
+        MachineTopology {
+            data: MachineTopology::discover(),
+            allowed: allowed_cpus(),
+            limit: CpuLimit::Warn,
+            kind: None,
+        }
+    }
 
-        for cpu in cpus {
-            let mut parent = cpu.parent();
+    /// Writes a snapshot of the topology to `path` (as JSON).
+    ///
+    /// The snapshot includes the allowed CPUs, it can be replayed with
+    /// [`MachineTopology::from_file`] on another machine, e.g., to test the
+    /// allocation strategies against a 4-socket machine in CI.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
 
-            // Find the parent core of the CPU
-            while parent.is_some() && parent.unwrap().object_type() != ObjectType::Core {
-                parent = parent.unwrap().parent();
-            }
-            let core = parent.expect("PU has no Core?");
-
-            // Find the parent L1 cache of the CPU
-            while parent.is_some()
-                && (parent.unwrap().object_type() != ObjectType::L1Cache
-                    || parent.unwrap().cache_attributes().unwrap().depth() < 1)
-            {
-                parent = parent.unwrap().parent();
-            }
-            let l1 = parent.expect("Core doesn't have a L1 cache?");
-
-            // Find the parent L2 cache of the CPU
-            while parent.is_some()
-                && (parent.unwrap().object_type() != ObjectType::L2Cache
-                    || parent.unwrap().cache_attributes().unwrap().depth() < 2)
-            {
-                parent = parent.unwrap().parent();
-            }
-            let l2 = parent.expect("Core doesn't have a L2 cache?");
-
-            // Find the parent socket/L3 cache of the CPU
-            while parent.is_some()
-                && (parent.unwrap().object_type() != ObjectType::L3Cache
-                    || parent.unwrap().cache_attributes().unwrap().depth() < 3)
-            {
-                parent = parent.unwrap().parent();
+    /// Loads a snapshot written by [`MachineTopology::to_file`].
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<MachineTopology> {
+        let file = fs::File::open(path)?;
+        Ok(serde_json::from_reader(io::BufReader::new(file))?)
+    }
+
+    fn discover() -> Vec<CpuInfo> {
+        #[allow(unused_mut)]
+        let mut data = MachineTopology::discover_cpus();
+        #[cfg(target_os = "linux")]
+        MachineTopology::discover_core_kinds(&mut data);
+        data
+    }
+
+    fn discover_cpus() -> Vec<CpuInfo> {
+        #[cfg(feature = "hwloc")]
+        match MachineTopology::from_hwloc() {
+            Some(data) => return data,
+            None => warn!("Can't retrieve the topology with hwloc, falling back"),
+        }
+
+        #[cfg(target_os = "linux")]
+        match MachineTopology::from_sysfs() {
+            Some(data) => return data,
+            None => warn!("Can't retrieve the topology from sysfs, falling back"),
+        }
+
+        MachineTopology::single_node()
+    }
+
+    /// Marks the efficiency cores of heterogeneous machines.
+    ///
+    /// Intel hybrid processors list their CPUs under separate PMUs
+    /// (`/sys/devices/cpu_core` and `/sys/devices/cpu_atom`), ARM systems
+    /// expose the relative capacity of each CPU (`cpu_capacity`), where CPUs
+    /// below the largest capacity are efficiency cores. The maximum frequency
+    /// from cpufreq is not used: on homogeneous Intel machines with favored
+    /// cores (Turbo Boost Max 3.0) it differs between identical cores.
+    #[cfg(target_os = "linux")]
+    fn discover_core_kinds(data: &mut [CpuInfo]) {
+        if let Some(atoms) = sysfs::read_cpu_list(Path::new(SYSFS_INTEL_ATOM_CPUS)) {
+            for cpu in data.iter_mut().filter(|c| atoms.contains(&c.cpu)) {
+                cpu.kind = CoreKind::Efficiency;
             }
-            let socket = parent.expect("Core doesn't have a L3 cache (socket)?");
+            return;
+        }
 
-            // Find the parent NUMA node of the CPU
-            while parent.is_some() && parent.unwrap().object_type() != ObjectType::NUMANode {
-                parent = parent.unwrap().parent();
+        let cpu_dir = Path::new(SYSFS_CPU);
+        let capacities: Vec<Option<u64>> = data
+            .iter()
+            .map(|c| sysfs::read_u64(&cpu_dir.join(format!("cpu{}/cpu_capacity", c.cpu))))
+            .collect();
+        if let Some(max) = capacities.iter().flatten().max().copied() {
+            for (cpu, capacity) in data.iter_mut().zip(capacities) {
+                if capacity.map_or(false, |c| c < max) {
+                    cpu.kind = CoreKind::Efficiency;
+                }
             }
-            let numa_node = parent.map(|n| NodeInfo {
-                node: n.os_index() as Node,
-                memory: n.total_memory(),
+        }
+    }
+
+    /// Sets what happens if more threads are requested than CPUs are allowed.
+    pub fn set_cpu_limit(&mut self, limit: CpuLimit) {
+        self.limit = limit;
+    }
+
+    /// Only allocates CPUs of the given kind, or of all kinds with `None`.
+    ///
+    /// Mixing efficiency cores into a run makes its results incomparable to
+    /// runs on performance cores only, e.g., if an efficiency core ends up
+    /// as the combiner. Explicit CPU lists are not filtered.
+    pub fn set_core_kind(&mut self, kind: Option<CoreKind>) {
+        self.kind = kind;
+    }
+
+    /// Whether the machine has both performance and efficiency cores.
+    pub fn is_hybrid(&self) -> bool {
+        self.data.iter().any(|c| c.kind == CoreKind::Performance)
+            && self.data.iter().any(|c| c.kind == CoreKind::Efficiency)
+    }
+
+    /// Whether the process is allowed to run on `cpu`.
+    pub fn is_allowed(&self, cpu: Cpu) -> bool {
+        self.allowed.as_ref().map_or(true, |allowed| allowed.contains(&cpu))
+    }
+
+    /// Returns the hyperthread siblings of `cpu`, i.e., the other CPUs of its core.
+    pub fn siblings_of(&self, cpu: Cpu) -> Vec<&CpuInfo> {
+        let core = match self.data.iter().find(|c| c.cpu == cpu) {
+            Some(c) => c.core,
+            None => return Vec::new(),
+        };
+        self.data
+            .iter()
+            .filter(|c| c.core == core && c.cpu != cpu)
+            .collect()
+    }
+
+    /// The CPUs of the machine the process is allowed to run on, of the
+    /// selected kind (see [`MachineTopology::set_core_kind`]).
+    pub fn allowed(&self) -> Vec<&CpuInfo> {
+        self.data
+            .iter()
+            .filter(|c| self.is_allowed(c.cpu))
+            .filter(|c| self.kind.map_or(true, |kind| c.kind == kind))
+            .collect()
+    }
+
+    /// Discovers the topology with hwloc.
+    #[cfg(feature = "hwloc")]
+    fn from_hwloc() -> Option<Vec<CpuInfo>> {
+        let mut data: Vec<CpuInfo> = Default::default();
+
+        let topo = Topology::new()?;
+        let cpus = topo.objects_with_type(&ObjectType::PU).ok()?;
+
+        let is_cache = |o: &TopologyObject, t: ObjectType, depth: u64| {
+            o.object_type() == t
+                && o.cache_attributes().map_or(false, |c| u64::from(c.depth()) >= depth)
+        };
+
+        for cpu in cpus {
+            // Find the closest ancestor of the CPU matching `pred`
+            let ancestor = |pred: &dyn Fn(&TopologyObject) -> bool| {
+                let mut parent = cpu.parent();
+                while parent.is_some() && !pred(parent.unwrap()) {
+                    parent = parent.unwrap().parent();
+                }
+                parent
+            };
+
+            // Find the parent core of the CPU, a CPU without a core is its own core
+            let core = ancestor(&|o| o.object_type() == ObjectType::Core)
+                .map_or(cpu.logical_index() as Core, |o| o.logical_index() as Core);
+
+            // Find the parent L1/L2 caches of the CPU, without them the core is used
+            let l1 = ancestor(&|o| is_cache(o, ObjectType::L1Cache, 1))
+                .map_or(core as L1, |o| o.logical_index() as L1);
+            let l2 = ancestor(&|o| is_cache(o, ObjectType::L2Cache, 2))
+                .map_or(core as L2, |o| o.logical_index() as L2);
+
+            // Find the parent socket/L3 cache of the CPU, fall back to the
+            // package (or a single socket) on machines without an L3 cache
+            let socket = match ancestor(&|o| is_cache(o, ObjectType::L3Cache, 3)) {
+                Some(l3) => l3.logical_index() as Socket,
+                None => ancestor(&|o| o.object_type() == ObjectType::Package)
+                    .map_or(0, |p| p.logical_index() as Socket),
+            };
+
+            // Find the parent NUMA node of the CPU, if any
+            let numa_node = ancestor(&|o| o.object_type() == ObjectType::NUMANode).map(|n| {
+                NodeInfo {
+                    node: n.os_index() as Node,
+                    memory: n.total_memory(),
+                }
             });
 
             let cpu_info = CpuInfo {
                 node: numa_node,
-                socket: socket.logical_index() as Socket,
-                core: core.logical_index() as Core,
+                socket,
+                core,
                 cpu: cpu.os_index() as Cpu,
-                l1: l1.logical_index() as L1,
-                l2: l2.logical_index() as L2,
-                l3: socket.logical_index() as L3,
+                l1,
+                l2,
+                l3: socket as L3,
+                kind: CoreKind::Performance,
             };
 
             data.push(cpu_info);
         }
 
-        MachineTopology { data }
+        if data.is_empty() {
+            return None;
+        }
+        Some(data)
+    }
+
+    /// Discovers the topology by parsing `/sys/devices/system/{cpu,node}`.
+    #[cfg(target_os = "linux")]
+    fn from_sysfs() -> Option<Vec<CpuInfo>> {
+        let cpu_dir = Path::new(SYSFS_CPU);
+        let online = sysfs::read_cpu_list(&cpu_dir.join("online"))?;
+
+        // map CPUs to their NUMA node, no nodes are exposed on non-NUMA machines
+        let mut cpu_to_node: HashMap<Cpu, NodeInfo> = HashMap::new();
+        if let Ok(entries) = fs::read_dir(SYSFS_NODE) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().into_string().unwrap_or_default();
+                let node = match name.strip_prefix("node").and_then(|n| n.parse::<Node>().ok()) {
+                    Some(node) => node,
+                    None => continue,
+                };
+                let info = NodeInfo {
+                    node,
+                    memory: sysfs::read_node_memory(&entry.path().join("meminfo")).unwrap_or(0),
+                };
+                for cpu in sysfs::read_cpu_list(&entry.path().join("cpulist")).unwrap_or_default() {
+                    cpu_to_node.insert(cpu, info);
+                }
+            }
+        }
+
+        // sysfs ids are not contiguous (core ids are only unique per package), so
+        // we hand out logical indices in the order the objects are discovered
+        let mut sockets: HashMap<u64, Socket> = HashMap::new();
+        let mut cores: HashMap<(u64, u64), Core> = HashMap::new();
+        let mut caches: HashMap<(u64, String), u64> = HashMap::new();
+        let mut data: Vec<CpuInfo> = Vec::with_capacity(online.len());
+
+        for cpu in online {
+            let topo_dir = cpu_dir.join(format!("cpu{}/topology", cpu));
+            // some VMs don't expose packages or cores, assume a single package
+            // with one core per CPU then
+            let package = sysfs::read_u64(&topo_dir.join("physical_package_id")).unwrap_or(0);
+            let core_id = sysfs::read_u64(&topo_dir.join("core_id")).unwrap_or(cpu);
+
+            let next = sockets.len() as Socket;
+            let socket = *sockets.entry(package).or_insert(next);
+            let next = cores.len() as Core;
+            let core = *cores.entry((package, core_id)).or_insert(next);
+
+            // caches are identified by their level and the CPUs sharing them
+            let mut levels: HashMap<u64, u64> = HashMap::new();
+            let cache_dir = cpu_dir.join(format!("cpu{}/cache", cpu));
+            if let Ok(entries) = fs::read_dir(&cache_dir) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let kind = fs::read_to_string(path.join("type")).unwrap_or_default();
+                    if kind.trim() == "Instruction" {
+                        continue;
+                    }
+                    let level = match sysfs::read_u64(&path.join("level")) {
+                        Some(level) => level,
+                        None => continue,
+                    };
+                    let shared = fs::read_to_string(path.join("shared_cpu_list")).unwrap_or_default();
+                    let next = caches.keys().filter(|(l, _)| *l == level).count() as u64;
+                    let idx = *caches.entry((level, shared.trim().to_string())).or_insert(next);
+                    levels.insert(level, idx);
+                }
+            }
+
+            // CPUs without exposed caches get private L1/L2 caches and the L3 of the socket
+            let l1 = levels.get(&1).copied().unwrap_or(core);
+            let l2 = levels.get(&2).copied().unwrap_or(core);
+            let l3 = levels.get(&3).copied().unwrap_or(socket);
+
+            data.push(CpuInfo {
+                node: cpu_to_node.get(&cpu).copied(),
+                socket,
+                core,
+                cpu,
+                l1,
+                l2,
+                l3,
+                kind: CoreKind::Performance,
+            });
+        }
+
+        if data.is_empty() {
+            return None;
+        }
+        Some(data)
+    }
+
+    /// A machine with a single node and socket, every CPU is a separate core.
+    fn single_node() -> Vec<CpuInfo> {
+        (0..num_cpus::get() as u64)
+            .map(|cpu| CpuInfo {
+                node: None,
+                socket: 0,
+                core: cpu,
+                cpu,
+                l1: cpu,
+                l2: cpu,
+                l3: 0,
+                kind: CoreKind::Performance,
+            })
+            .collect()
     }
 
     /// Return how many processing units that the system has
@@ -179,13 +608,27 @@ impl MachineTopology {
         let mut nodes: Vec<Cpu> = self
             .data
             .iter()
-            .map(|t| t.node.map_or_else(|| 0, |n| n.node))
+            .map(|t| t.node_id())
             .collect();
         nodes.sort();
         nodes.dedup();
         nodes
     }
 
+    /// The memory of `node` in bytes, `None` if unknown.
+    pub fn node_memory(&self, node: Node) -> Option<u64> {
+        self.data
+            .iter()
+            .filter_map(|t| t.node)
+            .find(|n| n.node == node && n.memory > 0)
+            .map(|n| n.memory)
+    }
+
+    /// The NUMA node of `cpu`, `None` if the CPU doesn't exist.
+    pub fn node_of(&self, cpu: Cpu) -> Option<Node> {
+        self.data.iter().find(|t| t.cpu == cpu).map(|t| t.node_id())
+    }
+
     pub fn cpus_on_node(&self, node: Node) -> Vec<&CpuInfo> {
         self.data.iter().filter(|t| t.socket == node).collect()
     }
@@ -194,16 +637,127 @@ impl MachineTopology {
         self.data.iter().filter(|t| t.socket == socket).collect()
     }
 
+    /// Allocates `how_many` of the allowed CPUs according to `strategy`.
+    ///
+    /// See [`ThreadMapping::allocate`].
     pub fn allocate(&self, strategy: ThreadMapping, how_many: usize, use_ht: bool) -> Vec<CpuInfo> {
+        strategy.allocate(self, how_many, use_ht)
+    }
+}
+
+impl TopologyProvider for MachineTopology {
+    fn cpus(&self) -> &[CpuInfo] {
+        &self.data
+    }
+
+    fn allowed(&self) -> Vec<&CpuInfo> {
+        MachineTopology::allowed(self)
+    }
+
+    fn is_allowed(&self, cpu: Cpu) -> bool {
+        MachineTopology::is_allowed(self, cpu)
+    }
+
+    fn cpu_limit(&self) -> CpuLimit {
+        self.limit
+    }
+}
+
+impl ThreadMapping {
+    /// Allocates `how_many` of the allowed CPUs of `topology` according to
+    /// this strategy.
+    ///
+    /// If fewer CPUs are allowed than requested, this either panics or places
+    /// several threads on the same CPUs, depending on the [`CpuLimit`].
+    pub fn allocate<T: TopologyProvider + ?Sized>(
+        self,
+        topology: &T,
+        how_many: usize,
+        use_ht: bool,
+    ) -> Vec<CpuInfo> {
+        if let ThreadMapping::Explicit(cpus) = self {
+            return ThreadMapping::allocate_explicit(topology, cpus, how_many);
+        }
+
+        let available = topology.allowed().len();
+        if how_many <= available || self == ThreadMapping::None {
+            return self.allocate_from(topology, how_many, use_ht);
+        }
+
+        match topology.cpu_limit() {
+            CpuLimit::Fail => panic!(
+                "Requested {} threads, but the process is only allowed to run on {} CPUs",
+                how_many, available
+            ),
+            CpuLimit::Warn => {
+                warn!(
+                    "Requested {} threads, but the process is only allowed to run on {} CPUs, oversubscribing",
+                    how_many, available
+                );
+                let cpus = self.allocate_from(topology, available, use_ht);
+                cpus.iter().cycle().take(how_many).copied().collect()
+            }
+        }
+    }
+
+    /// Allocates the first `how_many` CPUs of `cpus`, in order.
+    ///
+    /// Panics if a CPU doesn't exist, isn't allowed or is listed twice.
+    fn allocate_explicit<T: TopologyProvider + ?Sized>(
+        topology: &T,
+        cpus: &[Cpu],
+        how_many: usize,
+    ) -> Vec<CpuInfo> {
+        let mut allocated: Vec<CpuInfo> = Vec::with_capacity(cpus.len());
+        for cpu in cpus.iter() {
+            let info = topology
+                .cpus()
+                .iter()
+                .find(|c| c.cpu == *cpu)
+                .unwrap_or_else(|| panic!("CPU {} doesn't exist on this machine", cpu));
+            assert!(topology.is_allowed(*cpu), "The process isn't allowed to run on CPU {}", cpu);
+            assert!(!allocated.contains(info), "CPU {} is listed more than once", cpu);
+            allocated.push(*info);
+        }
+
+        if how_many <= allocated.len() {
+            allocated.truncate(how_many);
+            return allocated;
+        }
+
+        match topology.cpu_limit() {
+            CpuLimit::Fail => panic!(
+                "Requested {} threads, but only {} CPUs were given",
+                how_many,
+                allocated.len()
+            ),
+            CpuLimit::Warn => {
+                warn!(
+                    "Requested {} threads, but only {} CPUs were given, oversubscribing",
+                    how_many,
+                    allocated.len()
+                );
+                allocated.iter().cycle().take(how_many).copied().collect()
+            }
+        }
+    }
+
+    /// Allocates `how_many` of the allowed CPUs, there must be enough of them.
+    fn allocate_from<T: TopologyProvider + ?Sized>(
+        self,
+        topology: &T,
+        how_many: usize,
+        use_ht: bool,
+    ) -> Vec<CpuInfo> {
         let v = Vec::with_capacity(how_many);
-        let mut cpus = self.data.clone();
+        let mut cpus: Vec<CpuInfo> = topology.allowed().into_iter().copied().collect();
 
         if !use_ht {
             cpus.sort_by_key(|c| c.core);
             cpus.dedup_by(|a, b| a.core == b.core);
         }
 
-        match strategy {
+        match self {
             ThreadMapping::None => v,
             ThreadMapping::Interleave => {
                 let mut ht1 = cpus.clone();
@@ -222,8 +776,10 @@ impl MachineTopology {
                 ht2.sort_by_key(|c| c.core);
                 ht1.extend(ht2);
 
-                // now get the sockets
-                let sockets = self.sockets();
+                // now get the sockets (that have allowed CPUs)
+                let mut sockets: Vec<Socket> = ht1.iter().map(|c| c.socket).collect();
+                sockets.sort();
+                sockets.dedup();
                 let num_sockets = sockets.len();
 
                 // calculate how many CPUs we need per socket, rounded up to the next core
@@ -237,11 +793,12 @@ impl MachineTopology {
                     }
                     // if we already reached the target on that node, skip that core
                     // XXX: assumes all node have the same number of cores
-                    if allocated[cpu.socket as usize].len() == cpus_per_socket {
+                    let sidx = sockets.binary_search(&cpu.socket).unwrap();
+                    if allocated[sidx].len() == cpus_per_socket {
                         continue;
                     }
 
-                    allocated.get_mut(cpu.socket as usize).unwrap().push(cpu);
+                    allocated.get_mut(sidx).unwrap().push(cpu);
                     num_alloc_cpus += 1;
                 }
 
@@ -285,6 +842,255 @@ impl MachineTopology {
 
                 ht1.into_iter().take(how_many).collect()
             }
+            ThreadMapping::Explicit(cpus) => {
+                ThreadMapping::allocate_explicit(topology, cpus, how_many)
+            }
+            ThreadMapping::RoundRobinCore => {
+                // split the CPUs into the first CPU of every core and the hyperthreads
+                cpus.sort_by_key(|c| (c.core, c.cpu));
+                let mut ht1 = cpus.clone();
+                ht1.dedup_by(|a, b| a.core == b.core);
+                let ht2: Vec<CpuInfo> = cpus.into_iter().filter(|c| !ht1.contains(c)).collect();
+
+                // deal out the CPUs of each class one socket after the other
+                let round_robin = |class: Vec<CpuInfo>| {
+                    let mut per_socket: BTreeMap<Socket, Vec<CpuInfo>> = BTreeMap::new();
+                    for cpu in class.into_iter() {
+                        per_socket.entry(cpu.socket).or_default().push(cpu);
+                    }
+                    let rounds = per_socket.values().map(|c| c.len()).max().unwrap_or(0);
+                    let mut ordered = Vec::new();
+                    for i in 0..rounds {
+                        for socket_cpus in per_socket.values() {
+                            if let Some(cpu) = socket_cpus.get(i) {
+                                ordered.push(*cpu);
+                            }
+                        }
+                    }
+                    ordered
+                };
+
+                let mut ordered = round_robin(ht1);
+                ordered.extend(round_robin(ht2));
+                ordered.into_iter().take(how_many).collect()
+            }
+            ThreadMapping::SmtPairs => {
+                // group the CPUs by their core, cores ordered by socket
+                let mut cores: Vec<Vec<CpuInfo>> = Vec::new();
+                cpus.sort_by_key(|c| (c.socket, c.core, c.cpu));
+                for cpu in cpus.into_iter() {
+                    match cores.last_mut() {
+                        Some(core) if core[0].core == cpu.core => core.push(cpu),
+                        _ => cores.push(vec![cpu]),
+                    }
+                }
+
+                // only cores with (at least) two allowed siblings can host a pair
+                let (pairs, singles): (Vec<Vec<CpuInfo>>, Vec<Vec<CpuInfo>>) =
+                    cores.into_iter().partition(|core| core.len() >= 2);
+                if pairs.len() * 2 < how_many {
+                    warn!(
+                        "Only {} pairs of hyperthread siblings for {} threads, placing the rest on single CPUs",
+                        pairs.len(),
+                        how_many
+                    );
+                }
+
+                pairs
+                    .into_iter()
+                    .flat_map(|core| core.into_iter().take(2))
+                    .chain(singles.into_iter().flatten())
+                    .take(how_many)
+                    .collect()
+            }
         }
     }
 }
+
+/// Helpers to parse the sysfs files describing the topology.
+#[cfg(target_os = "linux")]
+mod sysfs {
+    use std::fs;
+    use std::path::Path;
+
+    use super::Cpu;
+
+    /// Reads a file containing a single number.
+    pub(super) fn read_u64(path: &Path) -> Option<u64> {
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
+    /// Reads a CPU list in the kernel's list format, e.g., `0-3,8,10-11`.
+    pub(super) fn read_cpu_list(path: &Path) -> Option<Vec<Cpu>> {
+        parse_cpu_list(fs::read_to_string(path).ok()?.trim())
+    }
+
+    pub(super) fn parse_cpu_list(list: &str) -> Option<Vec<Cpu>> {
+        let mut cpus = Vec::new();
+        for range in list.split(',').filter(|r| !r.is_empty()) {
+            match range.split_once('-') {
+                Some((start, end)) => {
+                    let start: Cpu = start.parse().ok()?;
+                    let end: Cpu = end.parse().ok()?;
+                    cpus.extend(start..=end);
+                }
+                None => cpus.push(range.parse().ok()?),
+            }
+        }
+        Some(cpus)
+    }
+
+    /// Reads the total memory of a node in bytes from its `meminfo` file.
+    pub(super) fn read_node_memory(path: &Path) -> Option<u64> {
+        let meminfo = fs::read_to_string(path).ok()?;
+        // Node 0 MemTotal:       32768000 kB
+        let line = meminfo.lines().find(|l| l.contains("MemTotal:"))?;
+        let kb: u64 = line.split_whitespace().rev().nth(1)?.parse().ok()?;
+        Some(kb * 1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A synthetic machine with the given number of cores per socket.
+    struct Synthetic {
+        cpus: Vec<CpuInfo>,
+        allowed: Option<Vec<Cpu>>,
+        limit: CpuLimit,
+    }
+
+    impl Synthetic {
+        /// Numbers the CPUs like Linux: the first hyperthread of all cores,
+        /// then the second one, and so on. Without `numa` the CPUs don't
+        /// have NUMA information.
+        fn new(cores_per_socket: &[u64], smt: u64, numa: bool) -> Synthetic {
+            let total_cores: u64 = cores_per_socket.iter().sum();
+            let mut cpus = Vec::new();
+            for thread in 0..smt {
+                let mut core = 0;
+                for (socket, cores) in cores_per_socket.iter().enumerate() {
+                    let socket = socket as Socket;
+                    for _ in 0..*cores {
+                        cpus.push(CpuInfo {
+                            node: numa.then_some(NodeInfo {
+                                node: socket,
+                                memory: 1 << 30,
+                            }),
+                            socket,
+                            core,
+                            cpu: thread * total_cores + core,
+                            l1: core,
+                            l2: core,
+                            l3: socket,
+                            kind: CoreKind::Performance,
+                        });
+                        core += 1;
+                    }
+                }
+            }
+            Synthetic {
+                cpus,
+                allowed: None,
+                limit: CpuLimit::Fail,
+            }
+        }
+    }
+
+    impl TopologyProvider for Synthetic {
+        fn cpus(&self) -> &[CpuInfo] {
+            &self.cpus
+        }
+
+        fn is_allowed(&self, cpu: Cpu) -> bool {
+            self.allowed.as_ref().map_or(true, |allowed| allowed.contains(&cpu))
+        }
+
+        fn cpu_limit(&self) -> CpuLimit {
+            self.limit
+        }
+    }
+
+    fn cpus(allocated: &[CpuInfo]) -> Vec<Cpu> {
+        allocated.iter().map(|c| c.cpu).collect()
+    }
+
+    fn sockets(allocated: &[CpuInfo]) -> Vec<Socket> {
+        allocated.iter().map(|c| c.socket).collect()
+    }
+
+    #[test]
+    fn sequential_fills_a_socket_first() {
+        let topology = Synthetic::new(&[4, 4], 2, true);
+        let allocated = ThreadMapping::Sequential.allocate(&topology, 4, true);
+        assert_eq!(cpus(&allocated), vec![0, 1, 2, 3]);
+        assert_eq!(sockets(&allocated), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn interleave_spreads_over_many_sockets() {
+        let topology = Synthetic::new(&[2; 8], 1, true);
+        let allocated = ThreadMapping::Interleave.allocate(&topology, 8, true);
+        assert_eq!(sockets(&allocated), (0..8).collect::<Vec<Socket>>());
+    }
+
+    #[test]
+    fn round_robin_with_asymmetric_sockets() {
+        let topology = Synthetic::new(&[4, 2], 1, true);
+        let allocated = ThreadMapping::RoundRobinCore.allocate(&topology, 6, true);
+        assert_eq!(sockets(&allocated), vec![0, 1, 0, 1, 0, 0]);
+        assert_eq!(cpus(&allocated), vec![0, 4, 1, 5, 2, 3]);
+    }
+
+    #[test]
+    fn numa_fill_without_numa_information() {
+        let topology = Synthetic::new(&[2, 2], 2, false);
+        let allocated = ThreadMapping::NUMAFill.allocate(&topology, 3, true);
+        // cores before hyperthreads, socket by socket
+        assert_eq!(cpus(&allocated), vec![0, 1, 2]);
+        assert!(allocated
+            .iter()
+            .all(|c| c.node.is_none() && c.node_id() == 0));
+    }
+
+    #[test]
+    fn smt_pairs_share_a_core() {
+        let topology = Synthetic::new(&[2], 2, true);
+        let allocated = ThreadMapping::SmtPairs.allocate(&topology, 4, true);
+        assert_eq!(cpus(&allocated), vec![0, 2, 1, 3]);
+    }
+
+    #[test]
+    fn only_allowed_cpus_are_allocated() {
+        let mut topology = Synthetic::new(&[4], 1, true);
+        topology.allowed = Some(vec![1, 3]);
+        let allocated = ThreadMapping::Sequential.allocate(&topology, 2, true);
+        assert_eq!(cpus(&allocated), vec![1, 3]);
+    }
+
+    #[test]
+    fn oversubscribes_with_warn() {
+        let mut topology = Synthetic::new(&[4], 1, true);
+        topology.allowed = Some(vec![0, 1]);
+        topology.limit = CpuLimit::Warn;
+        let allocated = ThreadMapping::Sequential.allocate(&topology, 4, true);
+        assert_eq!(cpus(&allocated), vec![0, 1, 0, 1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fails_beyond_the_allowed_cpus() {
+        let mut topology = Synthetic::new(&[4], 1, true);
+        topology.allowed = Some(vec![0, 1]);
+        ThreadMapping::Sequential.allocate(&topology, 4, true);
+    }
+
+    #[test]
+    #[should_panic]
+    fn explicit_rejects_cpus_not_allowed() {
+        let mut topology = Synthetic::new(&[4], 1, true);
+        topology.allowed = Some(vec![0, 1]);
+        ThreadMapping::Explicit(&[3]).allocate(&topology, 1, true);
+    }
+}
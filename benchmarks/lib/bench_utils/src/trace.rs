@@ -0,0 +1,400 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Operation traces for the key-value benchmarks.
+//!
+//! Instead of a synthetic YCSB workload, the benchmarks can replay a trace
+//! recorded from a production system, so NR can be evaluated on its actual
+//! access pattern. A trace is a text file with one operation per line:
+//!
+//! `<timestamp>,<op>,<key>,<size>`
+//!
+//!  - `timestamp`: when the operation was issued, in nanoseconds (any origin,
+//!    non-decreasing)
+//!  - `op`: `read`, `update`, `insert`, `scan` or `rmw`
+//!  - `key`: the key of the operation
+//!  - `size`: the number of keys for a scan, the value size otherwise
+//!
+//! Empty lines, lines starting with `#` and a leading header line are ignored.
+//!
+//! A trace is replayed either as fast as possible or respecting its
+//! timestamps, see [`ReplayMode`]. In both cases the operations are split
+//! round-robin among the threads, every thread issues its share in trace order.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::ycsb::YcsbOp;
+
+/// The type of a traced operation.
+#[derive(Serialize, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TraceOpKind {
+    Read,
+    Update,
+    Insert,
+    Scan,
+    ReadModifyWrite,
+}
+
+impl TraceOpKind {
+    /// Parses an operation type (`read`, `update`, `insert`, `scan`, `rmw`,
+    /// case-insensitive).
+    pub fn parse(s: &str) -> Option<TraceOpKind> {
+        match s.to_ascii_lowercase().as_str() {
+            "read" | "get" => Some(TraceOpKind::Read),
+            "update" | "put" => Some(TraceOpKind::Update),
+            "insert" => Some(TraceOpKind::Insert),
+            "scan" => Some(TraceOpKind::Scan),
+            "rmw" | "readmodifywrite" => Some(TraceOpKind::ReadModifyWrite),
+            _ => None,
+        }
+    }
+
+    /// Whether this operation mutates the key-value store.
+    pub fn is_write(&self) -> bool {
+        !matches!(self, TraceOpKind::Read | TraceOpKind::Scan)
+    }
+}
+
+/// A single operation of a trace.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct TraceOp {
+    /// When the operation is due, relative to the first operation of the trace
+    pub timestamp: Duration,
+    pub kind: TraceOpKind,
+    pub key: u64,
+    /// Number of keys for a scan, size of the value otherwise
+    pub size: usize,
+}
+
+/// An operation trace, loaded from a file.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    /// The name used to identify runs of this trace (the file stem)
+    pub name: String,
+    pub ops: Vec<TraceOp>,
+}
+
+impl Trace {
+    /// Loads the trace from the file at `path`.
+    pub fn load(path: &Path) -> io::Result<Trace> {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("trace"));
+        let file = File::open(path)?;
+        Trace::parse(name, BufReader::new(file))
+    }
+
+    /// Parses a trace, see the module documentation for the format.
+    pub fn parse<R: BufRead>(name: String, reader: R) -> io::Result<Trace> {
+        let invalid = |lineno: usize, msg: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("trace {} line {}: {}", name, lineno + 1, msg),
+            )
+        };
+
+        let mut ops = Vec::new();
+        let mut header = true;
+        let mut first: Option<u64> = None;
+        let mut last: u64 = 0;
+        for (lineno, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+            if fields.len() != 4 {
+                return Err(invalid(
+                    lineno,
+                    format!("expected 4 fields, got {}", fields.len()),
+                ));
+            }
+            let timestamp = match fields[0].parse::<u64>() {
+                Ok(ts) => ts,
+                Err(_) if header => {
+                    header = false;
+                    continue;
+                }
+                Err(_) => {
+                    return Err(invalid(
+                        lineno,
+                        format!("invalid timestamp '{}'", fields[0]),
+                    ))
+                }
+            };
+            header = false;
+            let kind = TraceOpKind::parse(fields[1])
+                .ok_or_else(|| invalid(lineno, format!("unknown operation '{}'", fields[1])))?;
+            let key = fields[2]
+                .parse::<u64>()
+                .map_err(|_| invalid(lineno, format!("invalid key '{}'", fields[2])))?;
+            let size = fields[3]
+                .parse::<usize>()
+                .map_err(|_| invalid(lineno, format!("invalid size '{}'", fields[3])))?;
+
+            let origin = *first.get_or_insert(timestamp);
+            if timestamp < last {
+                return Err(invalid(
+                    lineno,
+                    format!("timestamp {} goes backwards", timestamp),
+                ));
+            }
+            last = timestamp;
+
+            ops.push(TraceOp {
+                timestamp: Duration::from_nanos(timestamp - origin),
+                kind,
+                key,
+                size,
+            });
+        }
+
+        if ops.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("trace {} has no operations", name),
+            ));
+        }
+        Ok(Trace { name, ops })
+    }
+
+    /// Loads the trace given with `--trace <file>` and the replay mode given
+    /// with `--replay <fast|timed>` (default: `fast`), if a trace is given.
+    ///
+    /// Unknown arguments (e.g., `--bench` passed by cargo) are ignored.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Option<(Trace, ReplayMode)> {
+        let mut path = None;
+        let mut mode = ReplayMode::AsFastAsPossible;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--trace" => {
+                    path = Some(args.next().expect("--trace needs a file"));
+                }
+                "--replay" => {
+                    let val = args.next().expect("--replay needs a mode");
+                    mode = ReplayMode::parse(&val)
+                        .unwrap_or_else(|| panic!("unknown replay mode '{}'", val));
+                }
+                _ => log::debug!("ignoring argument '{}'", arg),
+            }
+        }
+
+        path.map(|path| {
+            let trace = Trace::load(Path::new(&path))
+                .unwrap_or_else(|e| panic!("can't load trace {}: {}", path, e));
+            (trace, mode)
+        })
+    }
+
+    /// The operations of the trace, as YCSB operations.
+    ///
+    /// The size of updates, inserts and read-modify-writes is used as value.
+    pub fn ycsb_ops(&self) -> Vec<YcsbOp> {
+        self.ops
+            .iter()
+            .map(|op| match op.kind {
+                TraceOpKind::Read => YcsbOp::Read(op.key),
+                TraceOpKind::Update => YcsbOp::Update(op.key, op.size as u64),
+                TraceOpKind::Insert => YcsbOp::Insert(op.key, op.size as u64),
+                TraceOpKind::Scan => YcsbOp::Scan(op.key, op.size.max(1)),
+                TraceOpKind::ReadModifyWrite => YcsbOp::ReadModifyWrite(op.key, op.size as u64),
+            })
+            .collect()
+    }
+
+    /// Percentage of operations that do not mutate the store.
+    pub fn reads_pct(&self) -> usize {
+        let reads = self.ops.iter().filter(|op| !op.kind.is_write()).count();
+        reads * 100 / self.ops.len()
+    }
+
+    /// The time between the first and the last operation.
+    pub fn duration(&self) -> Duration {
+        self.ops.last().map_or(Duration::ZERO, |op| op.timestamp)
+    }
+}
+
+/// How a trace is replayed.
+#[derive(Serialize, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ReplayMode {
+    /// Every thread issues its next operation as soon as the previous one
+    /// completed (closed loop).
+    AsFastAsPossible,
+    /// Every operation is issued at its timestamp, relative to the start of
+    /// the measurement (open loop). The latency of an operation is measured
+    /// from its timestamp.
+    Timestamps,
+}
+
+impl ReplayMode {
+    /// Parses a replay mode (`fast` or `timed`).
+    pub fn parse(s: &str) -> Option<ReplayMode> {
+        match s.to_ascii_lowercase().as_str() {
+            "fast" => Some(ReplayMode::AsFastAsPossible),
+            "timed" | "timestamps" => Some(ReplayMode::Timestamps),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ReplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayMode::AsFastAsPossible => write!(f, "fast"),
+            ReplayMode::Timestamps => write!(f, "timed"),
+        }
+    }
+}
+
+/// Replays a trace in a scale-out benchmark, see
+/// [`crate::mkbench::ScaleBenchBuilder::replay`].
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub mode: ReplayMode,
+    /// The timestamps of the operations of the trace
+    timestamps: Arc<Vec<Duration>>,
+    /// Time between the first operation of two passes over the trace
+    period: Duration,
+}
+
+impl Replay {
+    /// Replays `trace` in the given mode.
+    pub fn new(trace: &Trace, mode: ReplayMode) -> Replay {
+        let timestamps: Vec<Duration> = trace.ops.iter().map(|op| op.timestamp).collect();
+        // leave the average gap between the last operation and the next pass
+        let gap = trace.duration() / trace.ops.len() as u32;
+        Replay {
+            mode,
+            timestamps: Arc::new(timestamps),
+            period: trace.duration() + gap,
+        }
+    }
+
+    /// Number of operations in the trace.
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// Time between the first operation of two passes over the trace, if the
+    /// benchmark runs longer than the trace.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the operations of thread `idx` out of `threads` in trace order,
+    /// with their timestamps if the trace is replayed respecting them.
+    pub fn operations_for<T: Clone>(
+        &self,
+        idx: usize,
+        threads: usize,
+        operations: &[T],
+    ) -> (Vec<T>, Option<Vec<Duration>>) {
+        assert_eq!(
+            operations.len(),
+            self.len(),
+            "replayed operations don't match the trace"
+        );
+        assert!(
+            idx < operations.len(),
+            "the trace has {} operations, too few for {} threads",
+            operations.len(),
+            threads
+        );
+
+        let ops = operations
+            .iter()
+            .skip(idx)
+            .step_by(threads)
+            .cloned()
+            .collect();
+        let timestamps = match self.mode {
+            ReplayMode::AsFastAsPossible => None,
+            ReplayMode::Timestamps => Some(
+                self.timestamps
+                    .iter()
+                    .skip(idx)
+                    .step_by(threads)
+                    .copied()
+                    .collect(),
+            ),
+        };
+        (ops, timestamps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> io::Result<Trace> {
+        Trace::parse(String::from("test"), s.as_bytes())
+    }
+
+    #[test]
+    fn parse_trace() {
+        let trace = parse(
+            "timestamp,op,key,size\n\
+             # a comment\n\
+             1000,read,1,8\n\
+             \n\
+             1500,UPDATE,2,16\n\
+             2000,scan,3,10\n",
+        )
+        .unwrap();
+
+        assert_eq!(trace.ops.len(), 3);
+        assert_eq!(trace.ops[0].timestamp, Duration::ZERO);
+        assert_eq!(trace.ops[1].kind, TraceOpKind::Update);
+        assert_eq!(trace.ops[2].timestamp, Duration::from_nanos(1000));
+        assert_eq!(trace.duration(), Duration::from_nanos(1000));
+        assert_eq!(trace.reads_pct(), 66);
+        assert_eq!(
+            trace.ycsb_ops(),
+            vec![YcsbOp::Read(1), YcsbOp::Update(2, 16), YcsbOp::Scan(3, 10)]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("0,read,1\n").is_err());
+        assert!(parse("0,delete,1,8\n").is_err());
+        assert!(parse("10,read,1,8\n5,read,2,8\n").is_err());
+        assert!(parse("0,read,1,8\nx,read,2,8\n").is_err());
+    }
+
+    #[test]
+    fn replay_round_robin() {
+        let trace =
+            parse("0,read,0,1\n10,read,1,1\n20,read,2,1\n30,read,3,1\n40,read,4,1\n").unwrap();
+        let operations: Vec<u64> = (0..5).collect();
+
+        let replay = Replay::new(&trace, ReplayMode::AsFastAsPossible);
+        assert_eq!(replay.operations_for(1, 2, &operations), (vec![1, 3], None));
+
+        let replay = Replay::new(&trace, ReplayMode::Timestamps);
+        let (ops, timestamps) = replay.operations_for(0, 2, &operations);
+        assert_eq!(ops, vec![0, 2, 4]);
+        assert_eq!(
+            timestamps.unwrap(),
+            vec![
+                Duration::ZERO,
+                Duration::from_nanos(20),
+                Duration::from_nanos(40)
+            ]
+        );
+        assert_eq!(replay.period(), Duration::from_nanos(48));
+    }
+}
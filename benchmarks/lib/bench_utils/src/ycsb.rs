@@ -0,0 +1,389 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! YCSB-style workload definitions for the key-value benchmarks.
+//!
+//! Implements the core workloads A-F of the Yahoo! Cloud Serving Benchmark
+//! (Cooper et al., SoCC'10) so that our numbers can be compared with the
+//! literature on replicated and concurrent maps:
+//!
+//!  - A: update heavy (50% read, 50% update, zipfian)
+//!  - B: read mostly (95% read, 5% update, zipfian)
+//!  - C: read only (100% read, zipfian)
+//!  - D: read latest (95% read, 5% insert, latest)
+//!  - E: short ranges (95% scan, 5% insert, zipfian)
+//!  - F: read-modify-write (50% read, 50% read-modify-write, zipfian)
+//!
+//! Every workload comes with its defaults which can be overridden from the
+//! command line, see [`WorkloadSpec::from_args`].
+
+use std::fmt;
+
+use rand::distributions::Distribution;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+use zipf::ZipfDistribution;
+
+/// Skew of the zipfian key distribution as used by YCSB.
+pub const ZIPF_THETA: f64 = 0.99;
+
+/// Number of records the key-value store is loaded with.
+#[cfg(feature = "smokebench")]
+pub const DEFAULT_RECORD_COUNT: usize = 100_000;
+#[cfg(not(feature = "smokebench"))]
+pub const DEFAULT_RECORD_COUNT: usize = 1_000_000;
+
+/// Maximum number of records returned by a scan (workload E).
+pub const DEFAULT_MAX_SCAN_LEN: usize = 100;
+
+/// The core YCSB workloads.
+#[derive(Serialize, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Workload {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+}
+
+impl Workload {
+    /// All core workloads in order.
+    pub const ALL: [Workload; 6] = [
+        Workload::A,
+        Workload::B,
+        Workload::C,
+        Workload::D,
+        Workload::E,
+        Workload::F,
+    ];
+
+    /// Parses a workload name (`a`..`f`, case-insensitive).
+    pub fn parse(s: &str) -> Option<Workload> {
+        match s.to_ascii_lowercase().as_str() {
+            "a" => Some(Workload::A),
+            "b" => Some(Workload::B),
+            "c" => Some(Workload::C),
+            "d" => Some(Workload::D),
+            "e" => Some(Workload::E),
+            "f" => Some(Workload::F),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Workload::A => write!(f, "A"),
+            Workload::B => write!(f, "B"),
+            Workload::C => write!(f, "C"),
+            Workload::D => write!(f, "D"),
+            Workload::E => write!(f, "E"),
+            Workload::F => write!(f, "F"),
+        }
+    }
+}
+
+/// How keys are picked for an operation.
+#[derive(Serialize, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum KeyDistribution {
+    /// Every existing key is equally likely.
+    Uniform,
+    /// Popular keys are picked more often (`ZIPF_THETA`).
+    Zipfian,
+    /// Recently inserted keys are picked more often.
+    Latest,
+}
+
+impl KeyDistribution {
+    /// Parses a distribution name (`uniform`, `zipfian`, `latest`).
+    pub fn parse(s: &str) -> Option<KeyDistribution> {
+        match s.to_ascii_lowercase().as_str() {
+            "uniform" => Some(KeyDistribution::Uniform),
+            "zipfian" | "skewed" => Some(KeyDistribution::Zipfian),
+            "latest" => Some(KeyDistribution::Latest),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KeyDistribution::Uniform => write!(f, "uniform"),
+            KeyDistribution::Zipfian => write!(f, "zipfian"),
+            KeyDistribution::Latest => write!(f, "latest"),
+        }
+    }
+}
+
+/// A single generated YCSB operation.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum YcsbOp {
+    /// Read the value of a key.
+    Read(u64),
+    /// Overwrite the value of an existing key.
+    Update(u64, u64),
+    /// Insert a new key.
+    Insert(u64, u64),
+    /// Read up to `len` consecutive keys starting at the given key.
+    Scan(u64, usize),
+    /// Read a key and write back a new value.
+    ReadModifyWrite(u64, u64),
+}
+
+impl YcsbOp {
+    /// Whether this operation mutates the key-value store.
+    pub fn is_write(&self) -> bool {
+        match self {
+            YcsbOp::Read(_) | YcsbOp::Scan(_, _) => false,
+            YcsbOp::Update(_, _) | YcsbOp::Insert(_, _) | YcsbOp::ReadModifyWrite(_, _) => true,
+        }
+    }
+}
+
+/// The operation mix and key distribution of a workload.
+///
+/// The proportions are given in percent and must add up to 100.
+#[derive(Serialize, Copy, Clone, Eq, PartialEq, Debug)]
+pub struct WorkloadSpec {
+    pub workload: Workload,
+    pub read_pct: usize,
+    pub update_pct: usize,
+    pub insert_pct: usize,
+    pub scan_pct: usize,
+    pub rmw_pct: usize,
+    pub distribution: KeyDistribution,
+    /// Number of records loaded before the run
+    pub record_count: usize,
+    /// Maximum length of a scan operation
+    pub max_scan_len: usize,
+}
+
+impl WorkloadSpec {
+    /// Returns the default parameters of the given workload.
+    pub fn defaults(workload: Workload) -> WorkloadSpec {
+        let spec = WorkloadSpec {
+            workload,
+            read_pct: 0,
+            update_pct: 0,
+            insert_pct: 0,
+            scan_pct: 0,
+            rmw_pct: 0,
+            distribution: KeyDistribution::Zipfian,
+            record_count: DEFAULT_RECORD_COUNT,
+            max_scan_len: DEFAULT_MAX_SCAN_LEN,
+        };
+
+        match workload {
+            Workload::A => WorkloadSpec {
+                read_pct: 50,
+                update_pct: 50,
+                ..spec
+            },
+            Workload::B => WorkloadSpec {
+                read_pct: 95,
+                update_pct: 5,
+                ..spec
+            },
+            Workload::C => WorkloadSpec {
+                read_pct: 100,
+                ..spec
+            },
+            Workload::D => WorkloadSpec {
+                read_pct: 95,
+                insert_pct: 5,
+                distribution: KeyDistribution::Latest,
+                ..spec
+            },
+            Workload::E => WorkloadSpec {
+                scan_pct: 95,
+                insert_pct: 5,
+                ..spec
+            },
+            Workload::F => WorkloadSpec {
+                read_pct: 50,
+                rmw_pct: 50,
+                ..spec
+            },
+        }
+    }
+
+    /// Builds the workload specifications from the command line arguments.
+    ///
+    /// Supported arguments:
+    ///  - `--workload <a-f>`: select a workload (may be given multiple times,
+    ///    default: all workloads)
+    ///  - `--read <pct>`, `--update <pct>`, `--insert <pct>`, `--scan <pct>`,
+    ///    `--rmw <pct>`: override the operation mix
+    ///  - `--distribution <uniform|zipfian|latest>`: override the key distribution
+    ///  - `--records <n>`: override the number of records loaded
+    ///  - `--scan-len <n>`: override the maximum scan length
+    ///
+    /// Unknown arguments (e.g., `--bench` passed by cargo) are ignored.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Vec<WorkloadSpec> {
+        let mut workloads = Vec::new();
+        let mut overrides: Vec<(String, String)> = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--workload" | "--read" | "--update" | "--insert" | "--scan" | "--rmw"
+                | "--distribution" | "--records" | "--scan-len" => {
+                    let val = args
+                        .next()
+                        .unwrap_or_else(|| panic!("missing value for argument {}", arg));
+                    if arg == "--workload" {
+                        workloads.push(
+                            Workload::parse(&val)
+                                .unwrap_or_else(|| panic!("unknown YCSB workload '{}'", val)),
+                        );
+                    } else {
+                        overrides.push((arg, val));
+                    }
+                }
+                _ => log::debug!("ignoring argument '{}'", arg),
+            }
+        }
+
+        if workloads.is_empty() {
+            workloads.extend_from_slice(&Workload::ALL);
+        }
+
+        workloads
+            .into_iter()
+            .map(|w| {
+                let mut spec = WorkloadSpec::defaults(w);
+                for (arg, val) in overrides.iter() {
+                    spec.apply_override(arg, val);
+                }
+                spec.validate();
+                spec
+            })
+            .collect()
+    }
+
+    fn apply_override(&mut self, arg: &str, val: &str) {
+        let parse_num = |val: &str| {
+            val.parse::<usize>()
+                .unwrap_or_else(|_| panic!("argument {} expects a number, got '{}'", arg, val))
+        };
+
+        match arg {
+            "--read" => self.read_pct = parse_num(val),
+            "--update" => self.update_pct = parse_num(val),
+            "--insert" => self.insert_pct = parse_num(val),
+            "--scan" => self.scan_pct = parse_num(val),
+            "--rmw" => self.rmw_pct = parse_num(val),
+            "--records" => self.record_count = parse_num(val),
+            "--scan-len" => self.max_scan_len = parse_num(val),
+            "--distribution" => {
+                self.distribution = KeyDistribution::parse(val)
+                    .unwrap_or_else(|| panic!("unknown key distribution '{}'", val))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn validate(&self) {
+        let total =
+            self.read_pct + self.update_pct + self.insert_pct + self.scan_pct + self.rmw_pct;
+        assert!(
+            total == 100,
+            "operation mix of workload {} adds up to {}%, expected 100%",
+            self.workload,
+            total
+        );
+        assert!(self.record_count > 0, "need at least one record");
+        assert!(self.max_scan_len > 0, "scan length must be at least 1");
+    }
+
+    /// Percentage of operations that do not mutate the store.
+    pub fn reads_pct(&self) -> usize {
+        self.read_pct + self.scan_pct
+    }
+
+    /// Generates `nop` operations following this specification.
+    ///
+    /// Inserted keys are allocated sequentially starting at `record_count`,
+    /// the generated sequence is deterministic for a given specification.
+    pub fn generate(&self, nop: usize) -> Vec<YcsbOp> {
+        let mut ops = Vec::with_capacity(nop);
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let mut keys = KeyChooser::new(self.distribution, self.record_count);
+
+        for _i in 0..nop {
+            let choice = rng.gen_range(0..100);
+            let op = if choice < self.read_pct {
+                YcsbOp::Read(keys.next_key(&mut rng))
+            } else if choice < self.read_pct + self.update_pct {
+                YcsbOp::Update(keys.next_key(&mut rng), rng.next_u64())
+            } else if choice < self.read_pct + self.update_pct + self.insert_pct {
+                YcsbOp::Insert(keys.insert_key(), rng.next_u64())
+            } else if choice < self.read_pct + self.update_pct + self.insert_pct + self.scan_pct {
+                let len = rng.gen_range(1..=self.max_scan_len);
+                YcsbOp::Scan(keys.next_key(&mut rng), len)
+            } else {
+                YcsbOp::ReadModifyWrite(keys.next_key(&mut rng), rng.next_u64())
+            };
+            ops.push(op);
+        }
+
+        ops
+    }
+
+    /// The name used to identify runs of this workload.
+    pub fn name(&self) -> String {
+        format!(
+            "ycsb{}-r{}-u{}-i{}-s{}-m{}-{}",
+            self.workload,
+            self.read_pct,
+            self.update_pct,
+            self.insert_pct,
+            self.scan_pct,
+            self.rmw_pct,
+            self.distribution
+        )
+    }
+}
+
+/// Picks the keys for the generated operations.
+struct KeyChooser {
+    distribution: KeyDistribution,
+    /// Number of keys that exist at this point of the sequence
+    num_keys: usize,
+    /// Zipfian distribution over the initially loaded records
+    zipf: ZipfDistribution,
+}
+
+impl KeyChooser {
+    fn new(distribution: KeyDistribution, record_count: usize) -> KeyChooser {
+        KeyChooser {
+            distribution,
+            num_keys: record_count,
+            zipf: ZipfDistribution::new(record_count, ZIPF_THETA).unwrap(),
+        }
+    }
+
+    /// Picks an existing key.
+    fn next_key(&mut self, rng: &mut ChaCha8Rng) -> u64 {
+        match self.distribution {
+            KeyDistribution::Uniform => rng.gen_range(0..self.num_keys as u64),
+            // zipf samples from [1, record_count]
+            KeyDistribution::Zipfian => (self.zipf.sample(rng) - 1) as u64,
+            KeyDistribution::Latest => {
+                let offset = self.zipf.sample(rng).min(self.num_keys);
+                (self.num_keys - offset) as u64
+            }
+        }
+    }
+
+    /// Allocates a new key for an insert.
+    fn insert_key(&mut self) -> u64 {
+        let key = self.num_keys as u64;
+        self.num_keys += 1;
+        key
+    }
+}
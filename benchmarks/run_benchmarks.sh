@@ -19,6 +19,15 @@ echo "running upstream comparison"
 (cd upstream && ../.python/bin/python ../bench.py)
 cp upstream/data.json data-upstream.json
 
+echo "comparing verified with upstream"
+./.python/bin/python compare.py data-verified.json data-upstream.json
+
+# only compare against upstream, skip the other implementations
+if [ "$1" = "--upstream-only" ] ; then
+    echo "ondemand" | sudo tee /sys/devices/system/cpu/cpu*/cpufreq/scaling_governor
+    exit 0
+fi
+
 echo "running ironsync comparison"
 (cd ironsync && cargo bench)
 cp ../ironsync-osdi2023/concurrency/node-replication/data.json data-ironsync.json
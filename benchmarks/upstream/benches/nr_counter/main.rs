@@ -13,9 +13,11 @@ use rand::seq::SliceRandom;
 use rand::prelude::*;
 use rand_chacha::ChaCha8Rng;
 
+use bench_utils::baseline::{self, MutexBaseline, ParkingLotRwLockBaseline, StdRwLockBaseline};
 use bench_utils::benchmark::*;
 use bench_utils::mkbench::{self, DsInterface, NodeReplicated};
 use bench_utils::topology::ThreadMapping;
+use bench_utils::results::{self, RunResult};
 use bench_utils::Operation;
 use node_replication::{Dispatch};
 
@@ -127,7 +129,7 @@ pub fn generate_operations(
 }
 
 /// Compare scale-out behaviour of synthetic data-structure.
-fn counter_scale_out<R>(c: &mut TestHarness, name: &str, write_ratio: usize)
+fn counter_scale_out<R>(c: &mut TestHarness, name: &str, write_ratio: usize) -> Vec<RunResult>
 where
     R: DsInterface + Send + Sync + 'static,
     R::D: Send,
@@ -153,6 +155,8 @@ where
         // .replica_strategy(mkbench::ReplicaStrategy::One)
         .replica_strategy(mkbench::ReplicaStrategy::Socket)
         .thread_mapping(ThreadMapping::Interleave)
+        .sweep_from_args()
+        .cpus_from_args()
         .log_strategy(mkbench::LogStrategy::One)
         .configure(
             c,
@@ -167,13 +171,14 @@ where
                     tkn
                 }
             },
-        );
+        )
 }
 
 fn main() {
     let _r = env_logger::try_init();
-    if cfg!(feature = "smokebench") {
-        warn!("Running with feature 'smokebench' may not get the desired results");
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
     }
 
     bench_utils::disable_dvfs();
@@ -182,7 +187,7 @@ fn main() {
 
     let write_ratios = if cfg!(feature = "exhaustive") {
         vec![0, 10, 20, 40, 60, 80, 100]
-    } else if cfg!(feature = "smokebench") {
+    } else if bench_utils::smoke_enabled() {
         vec![10]
     } else {
         vec![0, 10, 50, 100]
@@ -190,6 +195,24 @@ fn main() {
 
     //hashmap_single_threaded(&mut harness);
     for write_ratio in write_ratios.into_iter() {
-        counter_scale_out::<NodeReplicated<NrCounter>>(&mut harness, " nr-counter", write_ratio);
+        let mut results = counter_scale_out::<NodeReplicated<NrCounter>>(&mut harness, " nr-counter", write_ratio);
+        if baseline::baselines_enabled() {
+            results.extend(counter_scale_out::<StdRwLockBaseline<NrCounter>>(
+                &mut harness,
+                StdRwLockBaseline::<NrCounter>::name(),
+                write_ratio,
+            ));
+            results.extend(counter_scale_out::<ParkingLotRwLockBaseline<NrCounter>>(
+                &mut harness,
+                ParkingLotRwLockBaseline::<NrCounter>::name(),
+                write_ratio,
+            ));
+            results.extend(counter_scale_out::<MutexBaseline<NrCounter>>(
+                &mut harness,
+                MutexBaseline::<NrCounter>::name(),
+                write_ratio,
+            ));
+            results::print_comparison(&results);
+        }
     }
 }
\ No newline at end of file
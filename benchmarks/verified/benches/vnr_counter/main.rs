@@ -14,9 +14,14 @@ use rand::seq::SliceRandom;
 use rand::prelude::*;
 use rand_chacha::ChaCha8Rng;
 
+use bench_utils::baseline::{
+    self, LeftRightBaseline, MutexBaseline, ParkingLotRwLockBaseline, RcuBaseline,
+    StdRwLockBaseline,
+};
 use bench_utils::benchmark::*;
 use bench_utils::mkbench::{self, DsInterface};
 use bench_utils::topology::ThreadMapping;
+use bench_utils::results::{self, RunResult};
 use bench_utils::Operation;
 use verified_node_replication::{Dispatch, AffinityFn, NodeReplicated, ReplicaId, ThreadToken, NodeReplicatedT};
 
@@ -124,6 +129,7 @@ struct VNRWrapper {
 /// `ScaleBench`.
 impl DsInterface for VNRWrapper {
     type D = NrCounter; //: Dispatch + Default + Sync;
+    type TT = ThreadToken<Self::D>;
 
     /// Allocate a new data-structure.
     ///
@@ -165,6 +171,11 @@ impl DsInterface for VNRWrapper {
             Err((tkn, _)) => Err(tkn),
         }
     }
+
+    /// Every increment is one entry of the log, the version of the log is the counter value.
+    fn updates_applied(&self) -> Option<u64> {
+        Some(NodeReplicatedT::current_version(&self.val, Tracked::assume_new()).0)
+    }
 }
 
 /// Generate a random sequence of operations
@@ -192,7 +203,7 @@ pub fn generate_operations(nop: usize, write_ratio: usize) -> Vec<Operation<OpRd
 }
 
 /// Compare scale-out behaviour of synthetic data-structure.
-fn counter_scale_out<R>(c: &mut TestHarness, name: &str, write_ratio: usize)
+fn counter_scale_out<R>(c: &mut TestHarness, name: &str, write_ratio: usize) -> Vec<RunResult>
 where
     R: DsInterface + Send + Sync + 'static,
     R::D: Send,
@@ -218,6 +229,8 @@ where
         // .replica_strategy(mkbench::ReplicaStrategy::One)
         .replica_strategy(mkbench::ReplicaStrategy::Socket)
         .thread_mapping(ThreadMapping::Interleave)
+        .sweep_from_args()
+        .cpus_from_args()
         .log_strategy(mkbench::LogStrategy::One)
         .configure(
             c,
@@ -232,13 +245,14 @@ where
                     Err(r) => r,
                 },
             },
-        );
+        )
 }
 
 fn main() {
     let _r = env_logger::try_init();
-    if cfg!(feature = "smokebench") {
-        warn!("Running with feature 'smokebench' may not get the desired results");
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
     }
 
     bench_utils::disable_dvfs();
@@ -247,7 +261,7 @@ fn main() {
 
     let write_ratios = if cfg!(feature = "exhaustive") {
         vec![0, 10, 20, 40, 60, 80, 100]
-    } else if cfg!(feature = "smokebench") {
+    } else if bench_utils::smoke_enabled() {
         vec![10]
     } else {
         vec![0, 10, 50, 100]
@@ -255,6 +269,34 @@ fn main() {
 
     //hashmap_single_threaded(&mut harness);
     for write_ratio in write_ratios.into_iter() {
-        counter_scale_out::<VNRWrapper>(&mut harness, "vnr-counter", write_ratio);
+        let mut results = counter_scale_out::<VNRWrapper>(&mut harness, "vnr-counter", write_ratio);
+        if baseline::baselines_enabled() {
+            results.extend(counter_scale_out::<StdRwLockBaseline<NrCounter>>(
+                &mut harness,
+                StdRwLockBaseline::<NrCounter>::name(),
+                write_ratio,
+            ));
+            results.extend(counter_scale_out::<ParkingLotRwLockBaseline<NrCounter>>(
+                &mut harness,
+                ParkingLotRwLockBaseline::<NrCounter>::name(),
+                write_ratio,
+            ));
+            results.extend(counter_scale_out::<MutexBaseline<NrCounter>>(
+                &mut harness,
+                MutexBaseline::<NrCounter>::name(),
+                write_ratio,
+            ));
+            results.extend(counter_scale_out::<RcuBaseline<NrCounter>>(
+                &mut harness,
+                RcuBaseline::<NrCounter>::name(),
+                write_ratio,
+            ));
+            results.extend(counter_scale_out::<LeftRightBaseline<NrCounter>>(
+                &mut harness,
+                LeftRightBaseline::<NrCounter>::name(),
+                write_ratio,
+            ));
+            results::print_comparison(&results);
+        }
     }
 }
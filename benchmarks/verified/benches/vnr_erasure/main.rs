@@ -0,0 +1,425 @@
+// Ghost-erasure overhead microbenchmark for verified NR
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Measures the cost of the ghost-token plumbing of the verified
+//! implementation.
+//!
+//! The verified code path is compared against `ErasedNr`, a hand-written
+//! unverified equivalent that performs the same sequence of atomic operations
+//! and memory accesses (flat-combining context, combiner lock, log append,
+//! log execution and the read fast path) but has no tracked or ghost state.
+//! If ghost state is erased properly the two should be within noise of each
+//! other; a difference points at runtime overhead introduced by a
+//! proof-driven refactoring.
+//!
+//! Both are measured single-threaded with a single replica, which isolates the
+//! uncontended paths:
+//!
+//!  - `read`: the read fast path (version check + read lock + dispatch)
+//!  - `update`: a full combining round (collect, append, execute, respond)
+#![allow(dead_code)]
+use std::cell::UnsafeCell;
+use std::hint::black_box;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use logging::warn;
+
+use bench_utils::mkbench::{self, DsInterface};
+use verified_node_replication::constants::{GC_FROM_HEAD, LOG_SIZE, MAX_THREADS_PER_REPLICA};
+use verified_node_replication::{Dispatch, AffinityFn, NodeReplicated, ReplicaId, ThreadToken, NodeReplicatedT};
+
+use builtin::Tracked;
+
+// Number of operations per measurement.
+#[cfg(feature = "smokebench")]
+pub const NOP: usize = 1_000_000;
+#[cfg(not(feature = "smokebench"))]
+pub const NOP: usize = 50_000_000;
+
+// Number of measurements per path, the fastest one is reported.
+#[cfg(feature = "smokebench")]
+pub const REPETITIONS: usize = 1;
+#[cfg(not(feature = "smokebench"))]
+pub const REPETITIONS: usize = 5;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpWr {
+    /// Increment the Counter
+    Inc,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpRd {
+    /// Get the counter value
+    Get,
+}
+
+/// Single-threaded implementation of the counter
+#[derive(Debug, Clone)]
+pub struct NrCounter {
+    counter: u64,
+}
+
+impl Default for NrCounter {
+    fn default() -> NrCounter {
+        NrCounter::init()
+    }
+}
+
+impl Dispatch for NrCounter {
+    type ReadOperation = OpRd;
+    type WriteOperation = OpWr;
+    type Response = Result<u64, ()>;
+    type View = NrCounter;
+
+    fn init() -> Self {
+        NrCounter { counter: 0 }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        op.clone()
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            OpRd::Get => Ok(self.counter),
+        }
+    }
+
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            OpWr::Inc => {
+                self.counter += 1;
+                Ok(self.counter)
+            }
+        }
+    }
+}
+
+/// The verified implementation.
+struct VNRWrapper {
+    val: NodeReplicated<NrCounter>,
+}
+
+impl DsInterface for VNRWrapper {
+    type D = NrCounter;
+    type TT = ThreadToken<Self::D>;
+
+    fn new(replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Self {
+        VNRWrapper {
+            val: NodeReplicatedT::<NrCounter>::new(replicas.into(), AffinityFn::new(mkbench::chg_affinity)),
+        }
+    }
+
+    fn register(&mut self, rid: ReplicaId) -> Option<ThreadToken<Self::D>> {
+        NodeReplicatedT::<NrCounter>::register(&mut self.val, rid)
+    }
+
+    fn execute_mut(
+        &self,
+        op: <Self::D as Dispatch>::WriteOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute_mut(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+
+    fn execute(
+        &self,
+        op: <Self::D as Dispatch>::ReadOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+}
+
+/// An entry of the erased log.
+struct LogEntry<D: Dispatch> {
+    op: UnsafeCell<Option<(D::WriteOperation, usize)>>,
+    alive: AtomicBool,
+}
+
+/// The per-thread flat-combining context of the erased replica.
+struct Context<D: Dispatch> {
+    /// The number of operations in this context (0 or 1)
+    atomic: AtomicU64,
+    op: UnsafeCell<Option<D::WriteOperation>>,
+    resp: UnsafeCell<Option<D::Response>>,
+}
+
+/// A single-replica node-replication without ghost state.
+///
+/// Mirrors the exec code of `NrLog` and `Replica` step by step, the comments
+/// name the corresponding steps of the verified implementation.
+struct ErasedNr<D: Dispatch> {
+    // NrLog
+    slog: Vec<LogEntry<D>>,
+    head: AtomicU64,
+    tail: AtomicU64,
+    version_upper_bound: AtomicU64,
+    local_version: AtomicU64,
+    // Replica
+    combiner: AtomicU64,
+    contexts: Vec<Context<D>>,
+    collected_operations: UnsafeCell<Vec<D::WriteOperation>>,
+    collected_operations_per_thread: UnsafeCell<Vec<usize>>,
+    responses: UnsafeCell<Vec<D::Response>>,
+    data: RwLock<D>,
+    next: AtomicUsize,
+}
+
+// Safety: the cells are protected by the combiner lock and the context atomics.
+unsafe impl<D: Dispatch + Sync> Sync for ErasedNr<D> {}
+unsafe impl<D: Dispatch + Send> Send for ErasedNr<D> {}
+
+impl<D: Dispatch> ErasedNr<D> {
+    fn new() -> ErasedNr<D> {
+        let mut slog = Vec::with_capacity(LOG_SIZE);
+        for _ in 0..LOG_SIZE {
+            slog.push(LogEntry { op: UnsafeCell::new(None), alive: AtomicBool::new(false) });
+        }
+        let mut contexts = Vec::with_capacity(MAX_THREADS_PER_REPLICA);
+        for _ in 0..MAX_THREADS_PER_REPLICA {
+            contexts.push(Context {
+                atomic: AtomicU64::new(0),
+                op: UnsafeCell::new(None),
+                resp: UnsafeCell::new(None),
+            });
+        }
+        ErasedNr {
+            slog,
+            head: AtomicU64::new(0),
+            tail: AtomicU64::new(0),
+            version_upper_bound: AtomicU64::new(0),
+            local_version: AtomicU64::new(0),
+            combiner: AtomicU64::new(0),
+            contexts,
+            collected_operations: UnsafeCell::new(Vec::with_capacity(MAX_THREADS_PER_REPLICA)),
+            collected_operations_per_thread: UnsafeCell::new(Vec::with_capacity(MAX_THREADS_PER_REPLICA)),
+            responses: UnsafeCell::new(Vec::with_capacity(MAX_THREADS_PER_REPLICA)),
+            data: RwLock::new(D::init()),
+            next: AtomicUsize::new(1),
+        }
+    }
+
+    fn register(&self) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn index(&self, logical: u64) -> usize {
+        (logical as usize) % LOG_SIZE
+    }
+
+    fn is_alive_value(&self, logical: u64) -> bool {
+        ((logical as usize) / LOG_SIZE) % 2 == 0
+    }
+
+    /// NrLog::execute
+    fn exec_log(&self, data: &mut D, responses: &mut Vec<D::Response>) {
+        let local_version = self.local_version.load(Ordering::Relaxed);
+        let global_tail = self.tail.load(Ordering::Relaxed);
+        if local_version == global_tail {
+            return;
+        }
+        for i in local_version..global_tail {
+            let e = &self.slog[self.index(i)];
+            while e.alive.load(Ordering::Acquire) != self.is_alive_value(i) {
+                std::hint::spin_loop();
+            }
+            let (op, _nid) = unsafe { (*e.op.get()).as_ref().unwrap() };
+            responses.push(data.dispatch_mut(D::clone_write_op(op)));
+        }
+        self.version_upper_bound.fetch_max(global_tail, Ordering::Relaxed);
+        self.local_version.store(global_tail, Ordering::Release);
+    }
+
+    /// NrLog::advance_head (single replica, the min local version is ours)
+    fn advance_head(&self, data: &mut D, responses: &mut Vec<D::Response>) {
+        self.exec_log(data, responses);
+        let min_local_version = self.local_version.load(Ordering::Relaxed);
+        self.head.store(min_local_version, Ordering::Relaxed);
+    }
+
+    /// NrLog::append
+    fn append(&self, operations: &Vec<D::WriteOperation>, responses: &mut Vec<D::Response>, data: &mut D) {
+        let nops = operations.len() as u64;
+        loop {
+            let tail = self.tail.load(Ordering::Relaxed);
+            let head = self.head.load(Ordering::Relaxed);
+            if tail > head + (LOG_SIZE - GC_FROM_HEAD) as u64 {
+                self.advance_head(data, responses);
+                responses.clear();
+                continue;
+            }
+            let new_tail = tail + nops;
+            if self.tail.compare_exchange_weak(tail, new_tail, Ordering::Acquire, Ordering::Relaxed).is_err() {
+                continue;
+            }
+            for (i, op) in operations.iter().enumerate() {
+                let logical = tail + i as u64;
+                let e = &self.slog[self.index(logical)];
+                unsafe { *e.op.get() = Some((D::clone_write_op(op), 0)) };
+                e.alive.store(self.is_alive_value(logical), Ordering::Release);
+            }
+            return;
+        }
+    }
+
+    /// Replica::collect_thread_ops
+    fn collect_thread_ops(&self, operations: &mut Vec<D::WriteOperation>, num_ops_per_thread: &mut Vec<usize>) {
+        for ctx in self.contexts.iter() {
+            let num_ops = ctx.atomic.load(Ordering::Acquire) as usize;
+            if num_ops > 0 {
+                let op = unsafe { (*ctx.op.get()).as_ref().unwrap() };
+                operations.push(D::clone_write_op(op));
+            }
+            num_ops_per_thread.push(num_ops);
+        }
+    }
+
+    /// Replica::distribute_thread_resps
+    fn distribute_thread_resps(&self, responses: &mut Vec<D::Response>, num_ops_per_thread: &mut Vec<usize>) {
+        let mut resp_idx = 0;
+        for (ctx, num_ops) in self.contexts.iter().zip(num_ops_per_thread.iter()) {
+            if *num_ops > 0 {
+                unsafe { *ctx.resp.get() = Some(D::clone_response(&responses[resp_idx])) };
+                ctx.atomic.store(0, Ordering::Release);
+                resp_idx += 1;
+            }
+        }
+        responses.clear();
+        num_ops_per_thread.clear();
+    }
+
+    /// Replica::combine
+    fn combine(&self) {
+        let responses = unsafe { &mut *self.responses.get() };
+        let operations = unsafe { &mut *self.collected_operations.get() };
+        let num_ops_per_thread = unsafe { &mut *self.collected_operations_per_thread.get() };
+
+        self.collect_thread_ops(operations, num_ops_per_thread);
+        let mut data = self.data.write().unwrap();
+        self.append(operations, responses, &mut data);
+        self.exec_log(&mut data, responses);
+        drop(data);
+        operations.clear();
+        self.distribute_thread_resps(responses, num_ops_per_thread);
+    }
+
+    /// Replica::try_combine
+    fn try_combine(&self) {
+        if self.combiner.compare_exchange_weak(0, 1, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+            self.combine();
+            self.combiner.store(0, Ordering::Release);
+        }
+    }
+
+    /// Replica::execute_mut
+    fn execute_mut(&self, op: D::WriteOperation, tid: usize) -> D::Response {
+        // make_pending
+        let ctx = &self.contexts[tid];
+        unsafe { *ctx.op.get() = Some(op) };
+        ctx.atomic.store(1, Ordering::Release);
+        // get_response
+        loop {
+            self.try_combine();
+            if ctx.atomic.load(Ordering::Acquire) == 0 {
+                return unsafe { (*ctx.resp.get()).take().unwrap() };
+            }
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Replica::execute
+    fn execute(&self, op: D::ReadOperation, _tid: usize) -> D::Response {
+        // get_version_upper_bound, is_replica_synced_for_reads
+        let version_upper_bound = self.version_upper_bound.load(Ordering::Relaxed);
+        while self.local_version.load(Ordering::Relaxed) < version_upper_bound {
+            self.try_combine();
+            std::hint::spin_loop();
+        }
+        self.data.read().unwrap().dispatch(op)
+    }
+}
+
+/// Runs `f` `NOP` times and returns the fastest time per operation in ns.
+fn measure<F: FnMut()>(mut f: F) -> f64 {
+    let mut best = Duration::MAX;
+    for _ in 0..REPETITIONS {
+        let start = Instant::now();
+        for _ in 0..NOP {
+            f();
+        }
+        best = best.min(start.elapsed());
+    }
+    best.as_nanos() as f64 / NOP as f64
+}
+
+fn report(path: &str, verified: f64, erased: f64) {
+    println!(
+        "{:>8} {:>14.2} {:>14.2} {:>9.2}%",
+        path,
+        verified,
+        erased,
+        (verified / erased - 1.0) * 100.0
+    );
+}
+
+fn main() {
+    let _r = env_logger::try_init();
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
+    }
+
+    bench_utils::disable_dvfs();
+    bench_utils::pin_thread(0);
+
+    let one = NonZeroUsize::new(1).unwrap();
+    let mut verified = VNRWrapper::new(one, one, LOG_SIZE);
+    let mut tkn = Some(verified.register(0).expect("couldn't register with replica"));
+    let erased = ErasedNr::<NrCounter>::new();
+    let tid = erased.register();
+
+    println!("{:>8} {:>14} {:>14} {:>10}", "path", "verified [ns]", "erased [ns]", "overhead");
+
+    let verified_read = measure(|| {
+        tkn = Some(match verified.execute(OpRd::Get, tkn.take().unwrap()) {
+            Ok((r, t)) => {
+                black_box(r);
+                t
+            }
+            Err(t) => t,
+        });
+    });
+    let erased_read = measure(|| {
+        black_box(erased.execute(OpRd::Get, tid));
+    });
+    report("read", verified_read, erased_read);
+
+    let verified_update = measure(|| {
+        tkn = Some(match verified.execute_mut(OpWr::Inc, tkn.take().unwrap()) {
+            Ok((r, t)) => {
+                black_box(r);
+                t
+            }
+            Err(t) => t,
+        });
+    });
+    let erased_update = measure(|| {
+        black_box(erased.execute_mut(OpWr::Inc, tid));
+    });
+    report("update", verified_update, erased_update);
+}
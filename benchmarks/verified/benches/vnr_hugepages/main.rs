@@ -0,0 +1,248 @@
+// Huge-page backed log benchmark for verified NR
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Compares the throughput of the verified implementation with the log backed
+//! by base pages and by transparent huge pages.
+//!
+//! The log has `LOG_SIZE` entries of 128 bytes each (64 MiB), every combiner
+//! appends to it and every replica reads it, so with base pages the log
+//! accesses cause a lot of TLB misses on large machines.
+#![allow(dead_code)]
+use std::fmt::Debug;
+use std::marker::Sync;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use logging::{info, warn};
+use rand::seq::SliceRandom;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use bench_utils::benchmark::*;
+use bench_utils::hugepages;
+use bench_utils::mkbench::{self, DsInterface};
+use bench_utils::results::{self, RunResult};
+use bench_utils::topology::ThreadMapping;
+use bench_utils::Operation;
+use verified_node_replication::{
+    AffinityFn, Dispatch, LogMemFn, NoPreemptGuard, NodeReplicated, NodeReplicatedT, ReplicaId,
+    StdWait, ThreadToken,
+};
+
+use builtin::Tracked;
+
+// Number of operation for test-harness.
+#[cfg(feature = "smokebench")]
+pub const NOP: usize = 2_500_000;
+#[cfg(not(feature = "smokebench"))]
+pub const NOP: usize = 25_000_000;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpWr {
+    /// Increment the Counter
+    Inc,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpRd {
+    /// Get the counter value
+    Get,
+}
+
+/// Single-threaded implementation of the counter
+#[derive(Debug, Clone)]
+pub struct NrCounter {
+    counter: u64,
+}
+
+impl Default for NrCounter {
+    fn default() -> NrCounter {
+        NrCounter::init()
+    }
+}
+
+impl Dispatch for NrCounter {
+    type ReadOperation = OpRd;
+    type WriteOperation = OpWr;
+    type Response = Result<u64, ()>;
+    type View = NrCounter;
+
+    fn init() -> Self {
+        NrCounter { counter: 0 }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        op.clone()
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            OpRd::Get => Ok(self.counter),
+        }
+    }
+
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            OpWr::Inc => {
+                self.counter += 1;
+                Ok(self.counter)
+            }
+        }
+    }
+}
+
+/// The verified implementation, with the log backed by huge pages if `HUGE`.
+struct VNRWrapper<const HUGE: bool> {
+    val: NodeReplicated<NrCounter>,
+}
+
+impl<const HUGE: bool> VNRWrapper<HUGE> {
+    fn name() -> &'static str {
+        if HUGE {
+            "vnr-hugepages"
+        } else {
+            "vnr-basepages"
+        }
+    }
+}
+
+impl<const HUGE: bool> DsInterface for VNRWrapper<HUGE> {
+    type D = NrCounter;
+    type TT = ThreadToken<Self::D>;
+
+    fn new(replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Self {
+        let log_mem = if HUGE {
+            LogMemFn::new(hugepages::log_mem_fn)
+        } else {
+            LogMemFn::none()
+        };
+        let val = NodeReplicated::new_with_hooks::<NoPreemptGuard, StdWait>(
+            replicas.into(),
+            AffinityFn::new(mkbench::chg_affinity),
+            log_mem,
+        );
+        match hugepages::anon_huge_pages() {
+            Ok(bytes) => info!("{}: {} bytes backed by huge pages", Self::name(), bytes),
+            Err(e) => warn!("Can't read the huge page usage: {}", e),
+        }
+        VNRWrapper { val }
+    }
+
+    fn register(&mut self, rid: ReplicaId) -> Option<ThreadToken<Self::D>> {
+        NodeReplicatedT::<NrCounter>::register(&mut self.val, rid)
+    }
+
+    fn execute_mut(
+        &self,
+        op: <Self::D as Dispatch>::WriteOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute_mut(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+
+    fn execute(
+        &self,
+        op: <Self::D as Dispatch>::ReadOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+}
+
+/// Generate a random sequence of operations with the given write ratio
+pub fn generate_operations(nop: usize, write_ratio: usize) -> Vec<Operation<OpRd, OpWr>> {
+    let mut ops = Vec::with_capacity(nop);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+
+    for idx in 0..nop {
+        if idx % 100 < write_ratio {
+            ops.push(Operation::WriteOperation(OpWr::Inc));
+        } else {
+            ops.push(Operation::ReadOperation(OpRd::Get));
+        }
+    }
+
+    ops.shuffle(&mut rng);
+    ops
+}
+
+/// Compare scale-out behaviour with the log backed by base or huge pages.
+fn log_pages_scale_out<R>(c: &mut TestHarness, name: &str, write_ratio: usize) -> Vec<RunResult>
+where
+    R: DsInterface + Send + Sync + 'static,
+    R::D: Send,
+    R::D: Dispatch<ReadOperation = OpRd>,
+    R::D: Dispatch<WriteOperation = OpWr>,
+    <R::D as Dispatch>::WriteOperation: Send + Sync,
+    <R::D as Dispatch>::ReadOperation: Send + Sync,
+    <R::D as Dispatch>::Response: Sync + Send + Debug,
+{
+    let ops = generate_operations(NOP, write_ratio);
+    let bench_name = format!("{}-scaleout-wr{}", name, write_ratio);
+
+    mkbench::ScaleBenchBuilder::<R>::new(ops)
+        .thread_defaults()
+        .update_batch(32)
+        .replica_strategy(mkbench::ReplicaStrategy::Socket)
+        .thread_mapping(ThreadMapping::Interleave)
+        .sweep_from_args()
+        .cpus_from_args()
+        .log_strategy(mkbench::LogStrategy::One)
+        .configure(
+            c,
+            &bench_name,
+            |_cid, tkn, replica, op, _batch_size| match op {
+                Operation::ReadOperation(op) => match replica.execute(*op, tkn) {
+                    Ok(r) => r.1,
+                    Err(r) => r,
+                },
+                Operation::WriteOperation(op) => match replica.execute_mut(*op, tkn) {
+                    Ok(r) => r.1,
+                    Err(r) => r,
+                },
+            },
+        )
+}
+
+fn main() {
+    let _r = env_logger::try_init();
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
+    }
+
+    bench_utils::disable_dvfs();
+
+    let mut harness = TestHarness::new(Duration::from_secs(10));
+
+    let write_ratios = if bench_utils::smoke_enabled() {
+        vec![100]
+    } else {
+        vec![10, 100]
+    };
+
+    for write_ratio in write_ratios.into_iter() {
+        let mut results = log_pages_scale_out::<VNRWrapper<false>>(
+            &mut harness,
+            VNRWrapper::<false>::name(),
+            write_ratio,
+        );
+        results.extend(log_pages_scale_out::<VNRWrapper<true>>(
+            &mut harness,
+            VNRWrapper::<true>::name(),
+            write_ratio,
+        ));
+        results::print_comparison(&results);
+    }
+}
@@ -0,0 +1,153 @@
+// Microbenchmarks of the building blocks of verified NR
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Measures single operations of the building blocks with criterion, on a counter: appending an
+//! update to the log, the read fast path, taking the combiner lock of a lagging replica, and
+//! appending once the log has wrapped around and the head has to be advanced.
+//!
+//! Unlike the scale-out benchmarks, these run on a single thread and report the latency of one
+//! operation with confidence intervals, to catch regressions of refactorings at the operation
+//! level. Run with `cargo bench --bench vnr_micro`.
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use builtin::Tracked;
+use verified_node_replication::constants::LOG_SIZE;
+use verified_node_replication::{
+    AffinityFn, Dispatch, NodeReplicated, NodeReplicatedT, ThreadToken,
+};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpWr {
+    Inc,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpRd {
+    Get,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NrCounter {
+    counter: u64,
+}
+
+impl Dispatch for NrCounter {
+    type ReadOperation = OpRd;
+    type WriteOperation = OpWr;
+    type Response = u64;
+    type View = NrCounter;
+
+    fn init() -> Self {
+        NrCounter { counter: 0 }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        *op
+    }
+
+    fn clone_response(resp: &Self::Response) -> Self::Response {
+        *resp
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            OpRd::Get => self.counter,
+        }
+    }
+
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            OpWr::Inc => {
+                self.counter += 1;
+                self.counter
+            }
+        }
+    }
+}
+
+fn no_affinity() -> AffinityFn {
+    AffinityFn::new(|_replica| {})
+}
+
+fn update(nr: &NodeReplicated<NrCounter>, tkn: ThreadToken<NrCounter>) -> ThreadToken<NrCounter> {
+    match nr.execute_mut(OpWr::Inc, tkn, Tracked::assume_new()) {
+        Ok((resp, tkn, _)) => {
+            black_box(resp);
+            tkn
+        }
+        Err(_) => panic!("thread token of another data structure"),
+    }
+}
+
+fn read(nr: &NodeReplicated<NrCounter>, tkn: ThreadToken<NrCounter>) -> ThreadToken<NrCounter> {
+    match nr.execute(OpRd::Get, tkn, Tracked::assume_new()) {
+        Ok((resp, tkn, _)) => {
+            black_box(resp);
+            tkn
+        }
+        Err(_) => panic!("thread token of another data structure"),
+    }
+}
+
+/// an update on a single replica: the combiner lock, the append to the log and the execution
+fn bench_append(c: &mut Criterion) {
+    let mut nr = <NodeReplicated<NrCounter> as NodeReplicatedT<NrCounter>>::new(1, no_affinity());
+    let mut tkn = Some(nr.register(0).unwrap());
+    c.bench_function("append", |b| {
+        b.iter(|| tkn = Some(update(&nr, tkn.take().unwrap())))
+    });
+}
+
+/// a read on a replica that is up to date with the log
+fn bench_read_fast_path(c: &mut Criterion) {
+    let mut nr = <NodeReplicated<NrCounter> as NodeReplicatedT<NrCounter>>::new(1, no_affinity());
+    let mut tkn = Some(nr.register(0).unwrap());
+    tkn = Some(update(&nr, tkn.take().unwrap()));
+    c.bench_function("read_fast_path", |b| {
+        b.iter(|| tkn = Some(read(&nr, tkn.take().unwrap())))
+    });
+}
+
+/// taking the combiner lock of a replica that lags one update behind and applying the update
+fn bench_combiner_lock(c: &mut Criterion) {
+    let mut nr = NodeReplicated::<NrCounter>::builder()
+        .replicas(2)
+        .steal_lagging(1)
+        .replica_nodes(vec![0, 0])
+        .build()
+        .unwrap();
+    let mut writer = Some(nr.register(0).unwrap());
+    let helper = nr.register(0).unwrap();
+    c.bench_function("combiner_lock", |b| {
+        b.iter_batched(
+            // replica 1 lags behind by the update
+            || writer = Some(update(&nr, writer.take().unwrap())),
+            |_| assert_eq!(nr.help_lagging(&helper), Some(1)),
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+/// an update on a single replica once the log has wrapped around, includes the advancement of
+/// the head that makes the entries of the log reusable
+fn bench_gc(c: &mut Criterion) {
+    let mut nr = <NodeReplicated<NrCounter> as NodeReplicatedT<NrCounter>>::new(1, no_affinity());
+    let mut tkn = Some(nr.register(0).unwrap());
+    for _ in 0..LOG_SIZE {
+        tkn = Some(update(&nr, tkn.take().unwrap()));
+    }
+    c.bench_function("append_wrapped", |b| {
+        b.iter(|| tkn = Some(update(&nr, tkn.take().unwrap())))
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_append,
+    bench_read_fast_path,
+    bench_combiner_lock,
+    bench_gc
+);
+criterion_main!(benches);
@@ -0,0 +1,304 @@
+// NUMA placement benchmark for verified NR
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Compares the throughput of the verified implementation with the replicas
+//! placed on different NUMA nodes.
+//!
+//! The threads of a replica always run on the node of the replica, only the
+//! memory of the replica (and the log, which is allocated with the affinity
+//! of the first replica) is moved:
+//!
+//!  - `local`: on the node of the replica, this is what `mkbench::chg_affinity`
+//!    does and what the other benchmarks use.
+//!  - `interleaved`: page by page across all nodes.
+//!  - `remote`: on the next node, every access of the replica is remote.
+//!
+//! The difference between `local` and the other two is what the NUMA-aware
+//! allocation hooks (`AffinityFn`) buy us.
+#![allow(dead_code)]
+use std::fmt::Debug;
+use std::marker::Sync;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use logging::{info, warn};
+use rand::seq::SliceRandom;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use bench_utils::benchmark::*;
+use bench_utils::mkbench::{self, DsInterface};
+use bench_utils::numa::{self, MemPolicy};
+use bench_utils::results::{self, RunResult};
+use bench_utils::topology::{Node, ThreadMapping, MACHINE_TOPOLOGY};
+use bench_utils::Operation;
+use verified_node_replication::{
+    AffinityFn, Dispatch, LogMemFn, NoPreemptGuard, NodeReplicated, NodeReplicatedT, ReplicaId,
+    StdWait, ThreadToken,
+};
+
+use builtin::Tracked;
+
+// Number of operation for test-harness.
+#[cfg(feature = "smokebench")]
+pub const NOP: usize = 2_500_000;
+#[cfg(not(feature = "smokebench"))]
+pub const NOP: usize = 25_000_000;
+
+/// Memory of a replica on its own node
+pub const LOCAL: u8 = 0;
+/// Memory of a replica interleaved across all nodes
+pub const INTERLEAVED: u8 = 1;
+/// Memory of a replica on the next node
+pub const REMOTE: u8 = 2;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpWr {
+    /// Increment the Counter
+    Inc,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpRd {
+    /// Get the counter value
+    Get,
+}
+
+/// Single-threaded implementation of the counter
+#[derive(Debug, Clone)]
+pub struct NrCounter {
+    counter: u64,
+}
+
+impl Default for NrCounter {
+    fn default() -> NrCounter {
+        NrCounter::init()
+    }
+}
+
+impl Dispatch for NrCounter {
+    type ReadOperation = OpRd;
+    type WriteOperation = OpWr;
+    type Response = Result<u64, ()>;
+    type View = NrCounter;
+
+    fn init() -> Self {
+        NrCounter { counter: 0 }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        op.clone()
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            OpRd::Get => Ok(self.counter),
+        }
+    }
+
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            OpWr::Inc => {
+                self.counter += 1;
+                Ok(self.counter)
+            }
+        }
+    }
+}
+
+/// The node that holds the memory of replica `rid` with the given placement,
+/// `None` if it is interleaved.
+fn memory_node(placement: u8, rid: ReplicaId) -> Option<Node> {
+    let nodes = MACHINE_TOPOLOGY.nodes();
+    match placement {
+        LOCAL => Some(rid as Node),
+        INTERLEAVED => None,
+        REMOTE => Some(nodes[(rid + 1) % nodes.len()]),
+        _ => unreachable!("unknown placement {}", placement),
+    }
+}
+
+/// The affinity function for the given placement.
+///
+/// Moves the allocating thread to the node of the replica like
+/// `mkbench::chg_affinity`, then sets the memory policy for the placement.
+fn placement_affinity(placement: u8) -> AffinityFn {
+    AffinityFn::new(move |rid: ReplicaId| {
+        mkbench::chg_affinity(rid);
+        let r = match memory_node(placement, rid) {
+            Some(node) => numa::bind_current_thread(node, MemPolicy::Bind),
+            None => numa::interleave_current_thread(&MACHINE_TOPOLOGY.nodes()),
+        };
+        if let Err(e) = r {
+            warn!("Can't set the memory policy of replica {}: {}", rid, e);
+        }
+    })
+}
+
+/// The verified implementation, with the memory of the replicas placed
+/// according to `PLACEMENT`.
+struct VNRWrapper<const PLACEMENT: u8> {
+    val: NodeReplicated<NrCounter>,
+}
+
+impl<const PLACEMENT: u8> VNRWrapper<PLACEMENT> {
+    fn name() -> &'static str {
+        match PLACEMENT {
+            LOCAL => "vnr-numa-local",
+            INTERLEAVED => "vnr-numa-interleaved",
+            REMOTE => "vnr-numa-remote",
+            _ => unreachable!("unknown placement {}", PLACEMENT),
+        }
+    }
+}
+
+impl<const PLACEMENT: u8> DsInterface for VNRWrapper<PLACEMENT> {
+    type D = NrCounter;
+    type TT = ThreadToken<Self::D>;
+
+    fn new(replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Self {
+        let val = NodeReplicated::new_with_hooks::<NoPreemptGuard, StdWait>(
+            replicas.into(),
+            placement_affinity(PLACEMENT),
+            LogMemFn::none(),
+        );
+        // don't leave the policy of the last replica on the benchmark thread
+        if let Err(e) = numa::reset_current_thread() {
+            warn!("Can't reset the memory policy: {}", e);
+        }
+        for rid in 0..replicas.get() {
+            match memory_node(PLACEMENT, rid) {
+                Some(node) => info!("{}: replica {} memory on node {}", Self::name(), rid, node),
+                None => info!("{}: replica {} memory interleaved", Self::name(), rid),
+            }
+        }
+        VNRWrapper { val }
+    }
+
+    fn register(&mut self, rid: ReplicaId) -> Option<ThreadToken<Self::D>> {
+        NodeReplicatedT::<NrCounter>::register(&mut self.val, rid)
+    }
+
+    fn execute_mut(
+        &self,
+        op: <Self::D as Dispatch>::WriteOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute_mut(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+
+    fn execute(
+        &self,
+        op: <Self::D as Dispatch>::ReadOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+}
+
+/// Generate a random sequence of operations with the given write ratio
+pub fn generate_operations(nop: usize, write_ratio: usize) -> Vec<Operation<OpRd, OpWr>> {
+    let mut ops = Vec::with_capacity(nop);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+
+    for idx in 0..nop {
+        if idx % 100 < write_ratio {
+            ops.push(Operation::WriteOperation(OpWr::Inc));
+        } else {
+            ops.push(Operation::ReadOperation(OpRd::Get));
+        }
+    }
+
+    ops.shuffle(&mut rng);
+    ops
+}
+
+/// Compare scale-out behaviour with the replicas placed in different ways.
+fn placement_scale_out<R>(c: &mut TestHarness, name: &str, write_ratio: usize) -> Vec<RunResult>
+where
+    R: DsInterface + Send + Sync + 'static,
+    R::D: Send,
+    R::D: Dispatch<ReadOperation = OpRd>,
+    R::D: Dispatch<WriteOperation = OpWr>,
+    <R::D as Dispatch>::WriteOperation: Send + Sync,
+    <R::D as Dispatch>::ReadOperation: Send + Sync,
+    <R::D as Dispatch>::Response: Sync + Send + Debug,
+{
+    let ops = generate_operations(NOP, write_ratio);
+    let bench_name = format!("{}-scaleout-wr{}", name, write_ratio);
+
+    mkbench::ScaleBenchBuilder::<R>::new(ops)
+        .thread_defaults()
+        .update_batch(32)
+        .replica_strategy(mkbench::ReplicaStrategy::Socket)
+        .thread_mapping(ThreadMapping::Interleave)
+        .sweep_from_args()
+        .cpus_from_args()
+        .log_strategy(mkbench::LogStrategy::One)
+        .configure(
+            c,
+            &bench_name,
+            |_cid, tkn, replica, op, _batch_size| match op {
+                Operation::ReadOperation(op) => match replica.execute(*op, tkn) {
+                    Ok(r) => r.1,
+                    Err(r) => r,
+                },
+                Operation::WriteOperation(op) => match replica.execute_mut(*op, tkn) {
+                    Ok(r) => r.1,
+                    Err(r) => r,
+                },
+            },
+        )
+}
+
+fn main() {
+    let _r = env_logger::try_init();
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
+    }
+    if MACHINE_TOPOLOGY.nodes().len() < 2 {
+        warn!("Only one NUMA node, all placements are local");
+    }
+
+    bench_utils::disable_dvfs();
+
+    let mut harness = TestHarness::new(Duration::from_secs(10));
+
+    let write_ratios = if bench_utils::smoke_enabled() {
+        vec![100]
+    } else {
+        vec![0, 10, 100]
+    };
+
+    for write_ratio in write_ratios.into_iter() {
+        let mut results = placement_scale_out::<VNRWrapper<LOCAL>>(
+            &mut harness,
+            VNRWrapper::<LOCAL>::name(),
+            write_ratio,
+        );
+        results.extend(placement_scale_out::<VNRWrapper<INTERLEAVED>>(
+            &mut harness,
+            VNRWrapper::<INTERLEAVED>::name(),
+            write_ratio,
+        ));
+        results.extend(placement_scale_out::<VNRWrapper<REMOTE>>(
+            &mut harness,
+            VNRWrapper::<REMOTE>::name(),
+            write_ratio,
+        ));
+        // the local placement is the reference of the comparison
+        results::print_comparison(&results);
+    }
+}
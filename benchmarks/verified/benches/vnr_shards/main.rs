@@ -0,0 +1,287 @@
+// Sharding benchmark for verified NR
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Compares partitioning with replication: a hash-map whose keyspace is split
+//! across several independent `NodeReplicated` instances (one log each, see
+//! `ShardedNodeReplicated`) against a single instance.
+//!
+//! Every shard has its own log, so updates to different shards don't contend
+//! on the same log tail and combiner, but a thread has to register with, and
+//! the replicas of every shard take memory on, every node. The runs use one
+//! replica per socket and one replica per L3 cache, the single instance with
+//! more replicas is the replication alternative to more shards.
+//!
+//! The threads register once with the `ShardedNodeReplicated` and use the
+//! same token for all shards.
+#![allow(dead_code)]
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::marker::Sync;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use logging::warn;
+use rand::prelude::*;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+
+use bench_utils::benchmark::*;
+use bench_utils::mkbench::{self, DsInterface};
+use bench_utils::results::{self, RunResult};
+use bench_utils::topology::{ThreadMapping, MACHINE_TOPOLOGY};
+use bench_utils::Operation;
+use verified_node_replication::{
+    AffinityFn, Dispatch, NodeReplicated, NodeReplicatedT, ReplicaId, ShardedNodeReplicated,
+    ShardedThreadToken,
+};
+
+use builtin::Tracked;
+
+// Number of operation for test-harness.
+#[cfg(feature = "smokebench")]
+pub const NOP: usize = 2_500_000;
+#[cfg(not(feature = "smokebench"))]
+pub const NOP: usize = 25_000_000;
+
+/// Biggest key in the hash-map
+pub const KEY_SPACE: u64 = 1_000_000;
+
+/// Operations that mutate the hash-map.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpWr {
+    /// Insert or update an item in the hash-map.
+    Put(u64, u64),
+}
+
+/// Operations that only read the hash-map.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpRd {
+    /// Get item from the hash-map.
+    Get(u64),
+}
+
+impl OpWr {
+    fn key(&self) -> u64 {
+        match self {
+            OpWr::Put(key, _) => *key,
+        }
+    }
+}
+
+impl OpRd {
+    fn key(&self) -> u64 {
+        match self {
+            OpRd::Get(key) => *key,
+        }
+    }
+}
+
+/// Single-threaded implementation of the hash-map, a shard only holds the
+/// keys routed to it.
+#[derive(Debug, Clone, Default)]
+pub struct NrHashMap {
+    storage: HashMap<u64, u64>,
+}
+
+impl Dispatch for NrHashMap {
+    type ReadOperation = OpRd;
+    type WriteOperation = OpWr;
+    type Response = Option<u64>;
+    type View = NrHashMap;
+
+    fn init() -> Self {
+        NrHashMap::default()
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        op.clone()
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            OpRd::Get(key) => self.storage.get(&key).copied(),
+        }
+    }
+
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            OpWr::Put(key, val) => self.storage.insert(key, val),
+        }
+    }
+}
+
+/// The hash-map with its keys partitioned across `SHARDS` instances.
+///
+/// A single shard is a plain `NodeReplicated` that still goes through the
+/// sharded registration, to compare it with the multi-shard runs.
+struct ShardedWrapper<const SHARDS: usize> {
+    val: ShardedNodeReplicated<NrHashMap>,
+}
+
+impl<const SHARDS: usize> ShardedWrapper<SHARDS> {
+    fn name() -> String {
+        if SHARDS == 1 {
+            String::from("vnr-single")
+        } else {
+            format!("vnr-shards{}", SHARDS)
+        }
+    }
+
+    /// the shard of `key`
+    fn shard(key: u64) -> usize {
+        (key % SHARDS as u64) as usize
+    }
+}
+
+impl<const SHARDS: usize> DsInterface for ShardedWrapper<SHARDS> {
+    type D = NrHashMap;
+    type TT = ShardedThreadToken<Self::D>;
+
+    fn new(replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Self {
+        let shards = (0..SHARDS)
+            .map(|_| {
+                NodeReplicatedT::<Self::D>::new(
+                    replicas.into(),
+                    AffinityFn::new(mkbench::chg_affinity),
+                )
+            })
+            .collect::<Vec<NodeReplicated<NrHashMap>>>();
+        ShardedWrapper {
+            val: ShardedNodeReplicated::new(shards),
+        }
+    }
+
+    fn register(&mut self, rid: ReplicaId) -> Option<Self::TT> {
+        self.val.register(rid)
+    }
+
+    fn execute_mut(
+        &self,
+        op: <Self::D as Dispatch>::WriteOperation,
+        idx: Self::TT,
+    ) -> Result<(<Self::D as Dispatch>::Response, Self::TT), Self::TT> {
+        let shard = Self::shard(op.key());
+        match self.val.execute_mut(shard, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+
+    fn execute(
+        &self,
+        op: <Self::D as Dispatch>::ReadOperation,
+        idx: Self::TT,
+    ) -> Result<(<Self::D as Dispatch>::Response, Self::TT), Self::TT> {
+        let shard = Self::shard(op.key());
+        match self.val.execute(shard, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+}
+
+/// Generate a random sequence of operations with the given write ratio and
+/// uniformly distributed keys
+pub fn generate_operations(nop: usize, write_ratio: usize) -> Vec<Operation<OpRd, OpWr>> {
+    let mut ops = Vec::with_capacity(nop);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+
+    for idx in 0..nop {
+        let key = rng.gen_range(0..KEY_SPACE);
+        if idx % 100 < write_ratio {
+            ops.push(Operation::WriteOperation(OpWr::Put(key, rng.gen())));
+        } else {
+            ops.push(Operation::ReadOperation(OpRd::Get(key)));
+        }
+    }
+
+    ops.shuffle(&mut rng);
+    ops
+}
+
+/// Runs the workload on all allowed cores, with one replica per socket and
+/// one replica per L3 cache in every shard.
+fn shards_scale_out<R>(c: &mut TestHarness, name: &str, write_ratio: usize) -> Vec<RunResult>
+where
+    R: DsInterface + Send + Sync + 'static,
+    R::D: Send,
+    R::D: Dispatch<ReadOperation = OpRd>,
+    R::D: Dispatch<WriteOperation = OpWr>,
+    <R::D as Dispatch>::WriteOperation: Send + Sync,
+    <R::D as Dispatch>::ReadOperation: Send + Sync,
+    <R::D as Dispatch>::Response: Sync + Send + Debug,
+{
+    let ops = generate_operations(NOP, write_ratio);
+    let bench_name = format!("{}-wr{}", name, write_ratio);
+
+    mkbench::ScaleBenchBuilder::<R>::new(ops)
+        .threads(MACHINE_TOPOLOGY.allowed().len())
+        .update_batch(32)
+        .replica_strategy(mkbench::ReplicaStrategy::Socket)
+        .replica_strategy(mkbench::ReplicaStrategy::L3)
+        .thread_mapping(ThreadMapping::Interleave)
+        .cpus_from_args()
+        .log_strategy(mkbench::LogStrategy::One)
+        .configure(
+            c,
+            &bench_name,
+            |_cid, tkn, replica, op, _batch_size| match op {
+                Operation::ReadOperation(op) => match replica.execute(*op, tkn) {
+                    Ok(r) => r.1,
+                    Err(r) => r,
+                },
+                Operation::WriteOperation(op) => match replica.execute_mut(*op, tkn) {
+                    Ok(r) => r.1,
+                    Err(r) => r,
+                },
+            },
+        )
+}
+
+fn main() {
+    let _r = env_logger::try_init();
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
+    }
+
+    bench_utils::disable_dvfs();
+
+    let mut harness = TestHarness::new(Duration::from_secs(10));
+
+    let write_ratios = if bench_utils::smoke_enabled() {
+        vec![10]
+    } else {
+        vec![0, 10, 50, 100]
+    };
+
+    for write_ratio in write_ratios.into_iter() {
+        // the single instance is the reference of the comparison
+        let mut results = shards_scale_out::<ShardedWrapper<1>>(
+            &mut harness,
+            &ShardedWrapper::<1>::name(),
+            write_ratio,
+        );
+        results.extend(shards_scale_out::<ShardedWrapper<2>>(
+            &mut harness,
+            &ShardedWrapper::<2>::name(),
+            write_ratio,
+        ));
+        results.extend(shards_scale_out::<ShardedWrapper<4>>(
+            &mut harness,
+            &ShardedWrapper::<4>::name(),
+            write_ratio,
+        ));
+        results.extend(shards_scale_out::<ShardedWrapper<8>>(
+            &mut harness,
+            &ShardedWrapper::<8>::name(),
+            write_ratio,
+        ));
+        results::print_comparison(&results);
+    }
+}
@@ -0,0 +1,225 @@
+// Data-structure Benchmarks for verified NR
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Scale-out benchmarks of data-structures with a different read/write
+//! asymmetry than the hash-map:
+//!
+//!  - `skiplist`: ordered map, reads and writes traverse O(log n) nodes
+//!  - `stack`: all writes contend on the top of the stack
+//!  - `queue`: writes touch both ends of the queue
+//!
+//! Run a single data-structure by passing its name, e.g.:
+//!
+//! `cargo bench --bench vnr_structures -- stack`
+//!
+//! Pass `--baselines` to also run the lock-based baselines and print a
+//! comparison table.
+#![allow(dead_code)]
+use std::fmt::Debug;
+use std::marker::Sync;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use logging::warn;
+
+use bench_utils::baseline::{self, MutexBaseline, ParkingLotRwLockBaseline, StdRwLockBaseline};
+use bench_utils::benchmark::*;
+use bench_utils::mkbench::{self, DsInterface};
+use bench_utils::topology::ThreadMapping;
+use bench_utils::results::{self, RunResult};
+use bench_utils::Operation;
+use verified_node_replication::{Dispatch, AffinityFn, NodeReplicated, ReplicaId, ThreadToken, NodeReplicatedT};
+
+use builtin::Tracked;
+
+mod queue;
+mod skiplist;
+mod stack;
+
+// Number of operation for test-harness.
+#[cfg(feature = "smokebench")]
+pub const NOP: usize = 2_500_000;
+#[cfg(not(feature = "smokebench"))]
+pub const NOP: usize = 25_000_000;
+
+/// Wraps any replicated data-structure `D` for the benchmark harness.
+struct VNRWrapper<D: Dispatch + Sync> {
+    val: NodeReplicated<D>,
+}
+
+/// The interface a data-structure must implement to be benchmarked by
+/// `ScaleBench`.
+impl<D> DsInterface for VNRWrapper<D>
+where
+    D: Dispatch + Default + Sync,
+{
+    type D = D;
+    type TT = ThreadToken<Self::D>;
+
+    /// Allocate a new data-structure.
+    ///
+    /// - `replicas`: How many replicas the data-structure should maintain.
+    /// - `logs`: How many logs the data-structure should be partitioned over.
+    fn new(replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Self {
+        VNRWrapper {
+            val: NodeReplicatedT::<D>::new(replicas.into(), AffinityFn::new(mkbench::chg_affinity)),
+        }
+    }
+
+    /// Register a thread with a data-structure.
+    ///
+    /// - `rid` indicates which replica the thread should use.
+    fn register(&mut self, rid: ReplicaId) -> Option<ThreadToken<Self::D>> {
+        NodeReplicatedT::<D>::register(&mut self.val, rid)
+    }
+
+    /// Apply a mutable operation to the data-structure.
+    fn execute_mut(
+        &self,
+        op: <Self::D as Dispatch>::WriteOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute_mut(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+
+    /// Apply a immutable operation to the data-structure.
+    fn execute(
+        &self,
+        op: <Self::D as Dispatch>::ReadOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+}
+
+/// Compare scale-out behaviour of a data-structure.
+fn structure_scale_out<R>(
+    c: &mut TestHarness,
+    name: &str,
+    write_ratio: usize,
+    ops: Vec<Operation<<R::D as Dispatch>::ReadOperation, <R::D as Dispatch>::WriteOperation>>,
+) -> Vec<RunResult>
+where
+    R: DsInterface + Send + Sync + 'static,
+    R::D: Send,
+    <R::D as Dispatch>::WriteOperation: Send + Sync + Copy + PartialEq,
+    <R::D as Dispatch>::ReadOperation: Send + Sync + Copy,
+    <R::D as Dispatch>::Response: Sync + Send + Debug,
+{
+    let bench_name = format!("{}-scaleout-wr{}", name, write_ratio);
+
+    mkbench::ScaleBenchBuilder::<R>::new(ops)
+        .thread_defaults()
+        .update_batch(32)
+        .log_size(2 * 1024 * 1024)
+        .replica_strategy(mkbench::ReplicaStrategy::One)
+        .replica_strategy(mkbench::ReplicaStrategy::Socket)
+        .thread_mapping(ThreadMapping::Interleave)
+        .sweep_from_args()
+        .cpus_from_args()
+        .read_pct(100 - write_ratio)
+        .log_strategy(mkbench::LogStrategy::One)
+        .configure(
+            c,
+            &bench_name,
+            |_cid, tkn, replica, op, _batch_size| match op {
+                Operation::ReadOperation(op) => match replica.execute(*op, tkn) {
+                    Ok(r) => r.1,
+                    Err(r) => r,
+                },
+                Operation::WriteOperation(op) => match replica.execute_mut(*op, tkn) {
+                    Ok(r) => r.1,
+                    Err(r) => r,
+                },
+            },
+        )
+}
+
+/// Runs `D` with verified NR and, if enabled, with the lock-based baselines.
+fn run_structure<D>(
+    c: &mut TestHarness,
+    name: &str,
+    write_ratio: usize,
+    generate: fn(usize, usize) -> Vec<Operation<D::ReadOperation, D::WriteOperation>>,
+) where
+    D: Dispatch + Default + Send + Sync + 'static,
+    D::WriteOperation: Send + Sync + Copy + PartialEq,
+    D::ReadOperation: Send + Sync + Copy,
+    D::Response: Sync + Send + Debug,
+{
+    let ops = generate(NOP, write_ratio);
+
+    let mut results =
+        structure_scale_out::<VNRWrapper<D>>(c, &format!("vnr-{}", name), write_ratio, ops.clone());
+    if baseline::baselines_enabled() {
+        results.extend(structure_scale_out::<StdRwLockBaseline<D>>(
+            c,
+            &format!("{}-{}", StdRwLockBaseline::<D>::name(), name),
+            write_ratio,
+            ops.clone(),
+        ));
+        results.extend(structure_scale_out::<ParkingLotRwLockBaseline<D>>(
+            c,
+            &format!("{}-{}", ParkingLotRwLockBaseline::<D>::name(), name),
+            write_ratio,
+            ops.clone(),
+        ));
+        results.extend(structure_scale_out::<MutexBaseline<D>>(
+            c,
+            &format!("{}-{}", MutexBaseline::<D>::name(), name),
+            write_ratio,
+            ops,
+        ));
+        results::print_comparison(&results);
+    }
+}
+
+fn main() {
+    let _r = env_logger::try_init();
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
+    }
+
+    bench_utils::disable_dvfs();
+
+    let mut harness = TestHarness::new(Duration::from_secs(10));
+
+    let write_ratios = if cfg!(feature = "exhaustive") {
+        vec![0, 10, 20, 40, 60, 80, 100]
+    } else if bench_utils::smoke_enabled() {
+        vec![10]
+    } else {
+        vec![0, 10, 50, 100]
+    };
+
+    // Run all data-structures unless some are selected on the command line
+    let selected: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| ["skiplist", "stack", "queue"].contains(&arg.as_str()))
+        .collect();
+    let enabled = |name: &str| selected.is_empty() || selected.iter().any(|s| s == name);
+
+    for write_ratio in write_ratios.into_iter() {
+        if enabled("skiplist") {
+            run_structure::<skiplist::NrSkipList>(
+                &mut harness,
+                "skiplist",
+                write_ratio,
+                skiplist::generate_operations,
+            );
+        }
+        if enabled("stack") {
+            run_structure::<stack::NrStack>(&mut harness, "stack", write_ratio, stack::generate_operations);
+        }
+        if enabled("queue") {
+            run_structure::<queue::NrQueue>(&mut harness, "queue", write_ratio, queue::generate_operations);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// FIFO queue for the data-structure benchmarks
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! A FIFO queue that can be replicated.
+//!
+//! Writes touch both ends of the queue (`Enqueue` the tail, `Dequeue` the
+//! head), reads (`Front`, `Len`) are cheap.
+
+use std::collections::VecDeque;
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use bench_utils::Operation;
+use verified_node_replication::Dispatch;
+
+/// Number of elements the queue is initialized with
+#[cfg(feature = "smokebench")]
+pub const INITIAL_ELEMENTS: usize = 50_000;
+#[cfg(not(feature = "smokebench"))]
+pub const INITIAL_ELEMENTS: usize = 1_000_000;
+
+/// Operations that mutate the queue.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpWr {
+    /// Append an element at the tail of the queue.
+    Enqueue(u64),
+    /// Remove the element at the head of the queue.
+    Dequeue,
+}
+
+/// Operations that only read the queue.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpRd {
+    /// Return the element at the head of the queue.
+    Front,
+    /// Return the number of elements.
+    Len,
+}
+
+/// Single-threaded implementation of the queue
+#[derive(Debug, Clone)]
+pub struct NrQueue {
+    storage: VecDeque<u64>,
+}
+
+impl NrQueue {
+    pub fn enqueue(&mut self, val: u64) -> Option<u64> {
+        self.storage.push_back(val);
+        None
+    }
+
+    pub fn dequeue(&mut self) -> Option<u64> {
+        self.storage.pop_front()
+    }
+
+    pub fn front(&self) -> Option<u64> {
+        self.storage.front().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.storage.len()
+    }
+}
+
+impl Default for NrQueue {
+    fn default() -> NrQueue {
+        NrQueue::init()
+    }
+}
+
+impl Dispatch for NrQueue {
+    type ReadOperation = OpRd;
+    type WriteOperation = OpWr;
+    type Response = Option<u64>;
+    type View = NrQueue;
+
+    /// Return a queue with `INITIAL_ELEMENTS` elements.
+    fn init() -> Self {
+        let mut storage = VecDeque::with_capacity(2 * INITIAL_ELEMENTS);
+        storage.extend(0..INITIAL_ELEMENTS as u64);
+        NrQueue { storage }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        op.clone()
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            OpRd::Front => self.front(),
+            OpRd::Len => Some(self.len() as u64),
+        }
+    }
+
+    /// Implements how we execute operation from the log against our local queue
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            OpWr::Enqueue(val) => self.enqueue(val),
+            OpWr::Dequeue => self.dequeue(),
+        }
+    }
+}
+
+/// Generate a random sequence of operations
+///
+/// Writes alternate between `Enqueue` and `Dequeue` so the queue neither runs
+/// empty nor grows without bound. Reads are split evenly between `Front` and
+/// `Len`.
+///
+/// # Arguments
+///  - `nop`: Number of operations to generate
+///  - `write_ratio`: Percentage of writes
+pub fn generate_operations(nop: usize, write_ratio: usize) -> Vec<Operation<OpRd, OpWr>> {
+    let mut ops = Vec::with_capacity(nop);
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+
+    for idx in 0..nop {
+        let op = if idx % 100 < write_ratio {
+            if idx % 2 == 0 {
+                Operation::WriteOperation(OpWr::Enqueue(idx as u64))
+            } else {
+                Operation::WriteOperation(OpWr::Dequeue)
+            }
+        } else if rng.gen::<bool>() {
+            Operation::ReadOperation(OpRd::Front)
+        } else {
+            Operation::ReadOperation(OpRd::Len)
+        };
+        ops.push(op);
+    }
+
+    ops.shuffle(&mut rng);
+    ops
+}
@@ -0,0 +1,274 @@
+// Skiplist for the data-structure benchmarks
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! A sequential (not concurrency-friendly) skiplist that can be replicated.
+//!
+//! The nodes live in an arena and are linked by index, removed nodes are put
+//! on a free-list and reused by later inserts.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use bench_utils::Operation;
+use verified_node_replication::Dispatch;
+
+/// Number of keys the skiplist is initialized with
+#[cfg(feature = "smokebench")]
+pub const INITIAL_KEYS: usize = 50_000;
+#[cfg(not(feature = "smokebench"))]
+pub const INITIAL_KEYS: usize = 1_000_000;
+
+/// Biggest key in the skiplist
+pub const KEY_SPACE: usize = 2 * INITIAL_KEYS;
+
+/// Maximum height of a tower
+const MAX_LEVEL: usize = 20;
+
+/// Marks the end of a level
+const NIL: usize = usize::MAX;
+
+/// Operations that mutate the skiplist.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpWr {
+    /// Insert or update a key, returns the old value.
+    Insert(u64, u64),
+    /// Remove a key, returns its value.
+    Remove(u64),
+}
+
+/// Operations that only read the skiplist.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpRd {
+    /// Get the value of a key.
+    Get(u64),
+    /// Returns the smallest key that is greater or equal than the given key.
+    Ceil(u64),
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    key: u64,
+    value: u64,
+    /// Successor on each level of the tower
+    next: Vec<usize>,
+}
+
+/// Single-threaded implementation of the skiplist
+#[derive(Debug, Clone)]
+pub struct NrSkipList {
+    /// All nodes, `nodes[0]` is the head sentinel with a full tower
+    nodes: Vec<Node>,
+    /// Unused slots in `nodes`
+    free: Vec<usize>,
+    /// Current height of the skiplist
+    level: usize,
+    /// State of the tower-height generator (xorshift)
+    seed: u64,
+}
+
+impl NrSkipList {
+    fn new() -> NrSkipList {
+        NrSkipList {
+            nodes: vec![Node {
+                key: 0,
+                value: 0,
+                next: vec![NIL; MAX_LEVEL],
+            }],
+            free: Vec::new(),
+            level: 1,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    /// Height of a new tower, every level is taken with probability 1/2.
+    ///
+    /// The generator is deterministic, so every replica builds the same towers.
+    fn random_level(&mut self) -> usize {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        std::cmp::min(self.seed.trailing_ones() as usize + 1, MAX_LEVEL)
+    }
+
+    /// The last node on every level whose key is smaller than `key`.
+    fn predecessors(&self, key: u64) -> [usize; MAX_LEVEL] {
+        let mut preds = [0; MAX_LEVEL];
+        let mut cur = 0;
+        for l in (0..self.level).rev() {
+            loop {
+                let next = self.nodes[cur].next[l];
+                if next != NIL && self.nodes[next].key < key {
+                    cur = next;
+                } else {
+                    break;
+                }
+            }
+            preds[l] = cur;
+        }
+        preds
+    }
+
+    /// The first node with a key greater or equal than `key`.
+    fn lower_bound(&self, key: u64) -> usize {
+        let mut cur = 0;
+        for l in (0..self.level).rev() {
+            loop {
+                let next = self.nodes[cur].next[l];
+                if next != NIL && self.nodes[next].key < key {
+                    cur = next;
+                } else {
+                    break;
+                }
+            }
+        }
+        self.nodes[cur].next[0]
+    }
+
+    pub fn get(&self, key: u64) -> Option<u64> {
+        match self.lower_bound(key) {
+            NIL => None,
+            n if self.nodes[n].key == key => Some(self.nodes[n].value),
+            _ => None,
+        }
+    }
+
+    pub fn ceil(&self, key: u64) -> Option<u64> {
+        match self.lower_bound(key) {
+            NIL => None,
+            n => Some(self.nodes[n].key),
+        }
+    }
+
+    pub fn insert(&mut self, key: u64, value: u64) -> Option<u64> {
+        let preds = self.predecessors(key);
+        let succ = self.nodes[preds[0]].next[0];
+        if succ != NIL && self.nodes[succ].key == key {
+            return Some(std::mem::replace(&mut self.nodes[succ].value, value));
+        }
+
+        let height = self.random_level();
+        let node = Node {
+            key,
+            value,
+            next: (0..height)
+                .map(|l| if l < self.level { self.nodes[preds[l]].next[l] } else { NIL })
+                .collect(),
+        };
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = node;
+                idx
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+
+        for l in 0..height {
+            // levels above the current height start at the head
+            let pred = if l < self.level { preds[l] } else { 0 };
+            self.nodes[pred].next[l] = idx;
+        }
+        self.level = std::cmp::max(self.level, height);
+
+        None
+    }
+
+    pub fn remove(&mut self, key: u64) -> Option<u64> {
+        let preds = self.predecessors(key);
+        let node = self.nodes[preds[0]].next[0];
+        if node == NIL || self.nodes[node].key != key {
+            return None;
+        }
+
+        for l in 0..self.nodes[node].next.len() {
+            let next = self.nodes[node].next[l];
+            self.nodes[preds[l]].next[l] = next;
+        }
+        while self.level > 1 && self.nodes[0].next[self.level - 1] == NIL {
+            self.level -= 1;
+        }
+
+        self.free.push(node);
+        Some(self.nodes[node].value)
+    }
+}
+
+impl Default for NrSkipList {
+    fn default() -> NrSkipList {
+        NrSkipList::init()
+    }
+}
+
+impl Dispatch for NrSkipList {
+    type ReadOperation = OpRd;
+    type WriteOperation = OpWr;
+    type Response = Option<u64>;
+    type View = NrSkipList;
+
+    /// Return a skiplist with every other key of the key space.
+    fn init() -> Self {
+        let mut skiplist = NrSkipList::new();
+        for i in 0..INITIAL_KEYS {
+            skiplist.insert(2 * i as u64, i as u64);
+        }
+        skiplist
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        op.clone()
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            OpRd::Get(key) => self.get(key),
+            OpRd::Ceil(key) => self.ceil(key),
+        }
+    }
+
+    /// Implements how we execute operation from the log against our local skiplist
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            OpWr::Insert(key, val) => self.insert(key, val),
+            OpWr::Remove(key) => self.remove(key),
+        }
+    }
+}
+
+/// Generate a random sequence of operations
+///
+/// Half of the writes are inserts and half are removes (of uniformly random
+/// keys), so the size of the skiplist stays roughly the same. Reads are
+/// split evenly between `Get` and `Ceil`.
+///
+/// # Arguments
+///  - `nop`: Number of operations to generate
+///  - `write_ratio`: Percentage of writes
+pub fn generate_operations(nop: usize, write_ratio: usize) -> Vec<Operation<OpRd, OpWr>> {
+    let mut ops = Vec::with_capacity(nop);
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+
+    for idx in 0..nop {
+        let key = rng.gen_range(0..KEY_SPACE as u64);
+        let op = if idx % 100 < write_ratio {
+            if rng.gen::<bool>() {
+                Operation::WriteOperation(OpWr::Insert(key, idx as u64))
+            } else {
+                Operation::WriteOperation(OpWr::Remove(key))
+            }
+        } else if rng.gen::<bool>() {
+            Operation::ReadOperation(OpRd::Get(key))
+        } else {
+            Operation::ReadOperation(OpRd::Ceil(key))
+        };
+        ops.push(op);
+    }
+
+    ops.shuffle(&mut rng);
+    ops
+}
@@ -0,0 +1,139 @@
+// Stack for the data-structure benchmarks
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! A stack that can be replicated.
+//!
+//! Unlike a Treiber stack all writes go through the log, the interesting
+//! property is that every write contends on the same end of the stack and
+//! reads (`Peek`, `Len`) are cheap.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use bench_utils::Operation;
+use verified_node_replication::Dispatch;
+
+/// Number of elements the stack is initialized with
+#[cfg(feature = "smokebench")]
+pub const INITIAL_ELEMENTS: usize = 50_000;
+#[cfg(not(feature = "smokebench"))]
+pub const INITIAL_ELEMENTS: usize = 1_000_000;
+
+/// Operations that mutate the stack.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpWr {
+    /// Push an element onto the stack.
+    Push(u64),
+    /// Pop the top element off the stack.
+    Pop,
+}
+
+/// Operations that only read the stack.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpRd {
+    /// Return the top element.
+    Peek,
+    /// Return the number of elements.
+    Len,
+}
+
+/// Single-threaded implementation of the stack
+///
+/// We just use a vector.
+#[derive(Debug, Clone)]
+pub struct NrStack {
+    storage: Vec<u64>,
+}
+
+impl NrStack {
+    pub fn push(&mut self, val: u64) -> Option<u64> {
+        self.storage.push(val);
+        None
+    }
+
+    pub fn pop(&mut self) -> Option<u64> {
+        self.storage.pop()
+    }
+
+    pub fn peek(&self) -> Option<u64> {
+        self.storage.last().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.storage.len()
+    }
+}
+
+impl Default for NrStack {
+    fn default() -> NrStack {
+        NrStack::init()
+    }
+}
+
+impl Dispatch for NrStack {
+    type ReadOperation = OpRd;
+    type WriteOperation = OpWr;
+    type Response = Option<u64>;
+    type View = NrStack;
+
+    /// Return a stack with `INITIAL_ELEMENTS` elements.
+    fn init() -> Self {
+        let mut storage = Vec::with_capacity(2 * INITIAL_ELEMENTS);
+        storage.extend(0..INITIAL_ELEMENTS as u64);
+        NrStack { storage }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        op.clone()
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            OpRd::Peek => self.peek(),
+            OpRd::Len => Some(self.len() as u64),
+        }
+    }
+
+    /// Implements how we execute operation from the log against our local stack
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            OpWr::Push(val) => self.push(val),
+            OpWr::Pop => self.pop(),
+        }
+    }
+}
+
+/// Generate a random sequence of operations
+///
+/// Writes alternate between `Push` and `Pop` so the stack neither runs empty
+/// nor grows without bound. Reads are split evenly between `Peek` and `Len`.
+///
+/// # Arguments
+///  - `nop`: Number of operations to generate
+///  - `write_ratio`: Percentage of writes
+pub fn generate_operations(nop: usize, write_ratio: usize) -> Vec<Operation<OpRd, OpWr>> {
+    let mut ops = Vec::with_capacity(nop);
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+
+    for idx in 0..nop {
+        let op = if idx % 100 < write_ratio {
+            if idx % 2 == 0 {
+                Operation::WriteOperation(OpWr::Push(idx as u64))
+            } else {
+                Operation::WriteOperation(OpWr::Pop)
+            }
+        } else if rng.gen::<bool>() {
+            Operation::ReadOperation(OpRd::Peek)
+        } else {
+            Operation::ReadOperation(OpRd::Len)
+        };
+        ops.push(op);
+    }
+
+    ops.shuffle(&mut rng);
+    ops
+}
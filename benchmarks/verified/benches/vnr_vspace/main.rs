@@ -244,6 +244,7 @@ struct VNRWrapper {
 /// `ScaleBench`.
 impl DsInterface for VNRWrapper {
     type D = VSpace; //: Dispatch + Default + Sync;
+    type TT = ThreadToken<Self::D>;
 
     /// Allocate a new data-structure.
     ///
@@ -1017,8 +1018,9 @@ where
 
 fn main() {
     let _r = env_logger::try_init();
-    if cfg!(feature = "smokebench") {
-        warn!("Running with feature 'smokebench' may not get the desired results");
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
     }
 
     bench_utils::disable_dvfs();
@@ -1027,7 +1029,7 @@ fn main() {
 
     let write_ratios = if cfg!(feature = "exhaustive") {
         vec![0, 10, 20, 40, 60, 80, 100]
-    } else if cfg!(feature = "smokebench") {
+    } else if bench_utils::smoke_enabled() {
         vec![10]
     } else {
         vec![0, 10, 100]
@@ -0,0 +1,286 @@
+// Writer-contention benchmark for verified NR
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Varies the number of updating threads per replica at a fixed total number
+//! of threads, from a single writer per replica to all threads writing. The
+//! other threads of a replica only read.
+//!
+//! Reports the throughput and the combiner batch sizes (operations per
+//! combine) of every run. The batch sizes are only recorded with the
+//! `metrics` feature:
+//!
+//! ```
+//! cargo bench --bench vnr_writers --features metrics [-- --baselines]
+//! ```
+//!
+//! With `--baselines` the lock-based baselines run the same mixes, to see at
+//! which number of writers flat combining pays off.
+#![allow(dead_code)]
+use std::fmt::Debug;
+use std::marker::Sync;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use logging::warn;
+use rand::seq::SliceRandom;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use bench_utils::baseline::{self, MutexBaseline, ParkingLotRwLockBaseline, StdRwLockBaseline};
+use bench_utils::benchmark::*;
+use bench_utils::mkbench::{self, DsInterface, ThreadMix};
+use bench_utils::results::{self, CombinerSample, RunResult};
+use bench_utils::topology::{ThreadMapping, MACHINE_TOPOLOGY};
+use bench_utils::Operation;
+use verified_node_replication::{
+    AffinityFn, Dispatch, NodeReplicated, NodeReplicatedT, ReplicaId, ThreadToken,
+};
+
+use builtin::Tracked;
+
+// Number of operation for test-harness.
+#[cfg(feature = "smokebench")]
+pub const NOP: usize = 2_500_000;
+#[cfg(not(feature = "smokebench"))]
+pub const NOP: usize = 25_000_000;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpWr {
+    /// Increment the Counter
+    Inc,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpRd {
+    /// Get the counter value
+    Get,
+}
+
+/// Single-threaded implementation of the counter
+#[derive(Debug, Clone)]
+pub struct NrCounter {
+    counter: u64,
+}
+
+impl Default for NrCounter {
+    fn default() -> NrCounter {
+        NrCounter::init()
+    }
+}
+
+impl Dispatch for NrCounter {
+    type ReadOperation = OpRd;
+    type WriteOperation = OpWr;
+    type Response = Result<u64, ()>;
+    type View = NrCounter;
+
+    fn init() -> Self {
+        NrCounter { counter: 0 }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        op.clone()
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            OpRd::Get => Ok(self.counter),
+        }
+    }
+
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            OpWr::Inc => {
+                self.counter += 1;
+                Ok(self.counter)
+            }
+        }
+    }
+}
+
+/// The verified implementation, reporting the statistics of its combiners.
+struct VNRWrapper {
+    val: NodeReplicated<NrCounter>,
+    replicas: usize,
+}
+
+impl DsInterface for VNRWrapper {
+    type D = NrCounter;
+    type TT = ThreadToken<Self::D>;
+
+    fn new(replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Self {
+        let val = NodeReplicated::new(replicas.into(), AffinityFn::new(mkbench::chg_affinity));
+        VNRWrapper { val, replicas: replicas.into() }
+    }
+
+    fn register(&mut self, rid: ReplicaId) -> Option<ThreadToken<Self::D>> {
+        NodeReplicatedT::<NrCounter>::register(&mut self.val, rid)
+    }
+
+    fn execute_mut(
+        &self,
+        op: <Self::D as Dispatch>::WriteOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute_mut(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+
+    fn execute(
+        &self,
+        op: <Self::D as Dispatch>::ReadOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+
+    fn combiner_stats(&self) -> Option<CombinerSample> {
+        if cfg!(not(feature = "metrics")) {
+            return None;
+        }
+        let mut sample = CombinerSample::default();
+        for rid in 0..self.replicas {
+            if let Some(stats) = self.val.combiner_stats(rid) {
+                sample.combines += stats.combines;
+                sample.ops += stats.ops;
+                results::add_histogram(&mut sample.ops_per_combine, &stats.batch_sizes);
+            }
+        }
+        let log = self.val.log_stats();
+        sample.appends = log.appends;
+        sample.entries = log.entries;
+        sample.cas_failures = log.cas_failures;
+        results::add_histogram(&mut sample.entries_per_cas, &log.entries_per_append);
+        Some(sample)
+    }
+
+    fn reset_combiner_stats(&self) {
+        self.val.reset_stats();
+    }
+}
+
+/// Generate a random sequence of operations with the given write ratio
+pub fn generate_operations(nop: usize, write_ratio: usize) -> Vec<Operation<OpRd, OpWr>> {
+    let mut ops = Vec::with_capacity(nop);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+
+    for idx in 0..nop {
+        if idx % 100 < write_ratio {
+            ops.push(Operation::WriteOperation(OpWr::Inc));
+        } else {
+            ops.push(Operation::ReadOperation(OpRd::Get));
+        }
+    }
+
+    ops.shuffle(&mut rng);
+    ops
+}
+
+/// Runs the workload on all allowed cores with `writers` updating threads per
+/// replica (one replica per socket).
+fn writers_scale_out<R>(c: &mut TestHarness, name: &str, writers: usize) -> Vec<RunResult>
+where
+    R: DsInterface + Send + Sync + 'static,
+    R::D: Send,
+    R::D: Dispatch<ReadOperation = OpRd>,
+    R::D: Dispatch<WriteOperation = OpWr>,
+    <R::D as Dispatch>::WriteOperation: Send + Sync,
+    <R::D as Dispatch>::ReadOperation: Send + Sync,
+    <R::D as Dispatch>::Response: Sync + Send + Debug,
+{
+    // the mix needs both reads and writes, the threads pick their share
+    let ops = generate_operations(NOP, 50);
+    let bench_name = format!("{}-writers{}", name, writers);
+
+    mkbench::ScaleBenchBuilder::<R>::new(ops)
+        .threads(MACHINE_TOPOLOGY.allowed().len())
+        .update_batch(32)
+        .replica_strategy(mkbench::ReplicaStrategy::Socket)
+        .thread_mapping(ThreadMapping::Interleave)
+        .thread_mix(ThreadMix::WritersPerReplica(writers))
+        .log_strategy(mkbench::LogStrategy::One)
+        .configure(
+            c,
+            &bench_name,
+            |_cid, tkn, replica, op, _batch_size| match op {
+                Operation::ReadOperation(op) => match replica.execute(*op, tkn) {
+                    Ok(r) => r.1,
+                    Err(r) => r,
+                },
+                Operation::WriteOperation(op) => match replica.execute_mut(*op, tkn) {
+                    Ok(r) => r.1,
+                    Err(r) => r,
+                },
+            },
+        )
+}
+
+/// The numbers of writers per replica: 1, 2, 4, ... up to all threads of a
+/// replica.
+fn writer_counts() -> Vec<usize> {
+    let threads = MACHINE_TOPOLOGY.allowed().len();
+    let threads_per_replica = std::cmp::max(threads / MACHINE_TOPOLOGY.sockets().len(), 1);
+    if bench_utils::smoke_enabled() {
+        return vec![1, threads_per_replica];
+    }
+
+    let mut writers = Vec::new();
+    let mut w = 1;
+    while w < threads_per_replica {
+        writers.push(w);
+        w *= 2;
+    }
+    writers.push(threads_per_replica);
+    writers
+}
+
+fn main() {
+    let _r = env_logger::try_init();
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
+    }
+    if cfg!(not(feature = "metrics")) {
+        warn!("Running without feature 'metrics', the combiner batch sizes are not recorded");
+    }
+
+    bench_utils::disable_dvfs();
+
+    let mut harness = TestHarness::new(Duration::from_secs(10));
+
+    let mut results = Vec::new();
+    for writers in writer_counts() {
+        results.extend(writers_scale_out::<VNRWrapper>(&mut harness, "vnr-counter", writers));
+        if baseline::baselines_enabled() {
+            results.extend(writers_scale_out::<StdRwLockBaseline<NrCounter>>(
+                &mut harness,
+                StdRwLockBaseline::<NrCounter>::name(),
+                writers,
+            ));
+            results.extend(writers_scale_out::<ParkingLotRwLockBaseline<NrCounter>>(
+                &mut harness,
+                ParkingLotRwLockBaseline::<NrCounter>::name(),
+                writers,
+            ));
+            results.extend(writers_scale_out::<MutexBaseline<NrCounter>>(
+                &mut harness,
+                MutexBaseline::<NrCounter>::name(),
+                writers,
+            ));
+        }
+    }
+    results::print_combiner_stats(&results);
+    if cfg!(feature = "energy") {
+        results::print_energy(&results);
+    }
+}
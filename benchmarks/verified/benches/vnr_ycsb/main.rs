@@ -0,0 +1,326 @@
+// YCSB Benchmark for verified NR
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Runs the YCSB core workloads A-F against a replicated hash-map.
+//!
+//! By default all workloads are executed with their YCSB defaults, individual
+//! parameters can be overridden on the command line, e.g.:
+//!
+//! `cargo bench --bench vnr_ycsb -- --workload a --read 90 --update 10 --distribution uniform`
+//!
+//! Pass `--baselines` to also run the lock-based baselines and print a
+//! comparison table.
+//!
+//! Instead of the YCSB workloads, an operation trace can be replayed (see
+//! `bench_utils::trace` for the format), as fast as possible or at the
+//! timestamps of the trace:
+//!
+//! `cargo bench --bench vnr_ycsb -- --trace ops.csv --replay timed`
+#![allow(dead_code)]
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::marker::Sync;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use logging::warn;
+
+use bench_utils::baseline::{self, MutexBaseline, ParkingLotRwLockBaseline, StdRwLockBaseline};
+use bench_utils::benchmark::*;
+use bench_utils::mkbench::{self, DsInterface};
+use bench_utils::topology::ThreadMapping;
+use bench_utils::trace::{Replay, ReplayMode, Trace};
+use bench_utils::ycsb::{WorkloadSpec, YcsbOp, DEFAULT_RECORD_COUNT};
+use bench_utils::results::{self, RunResult};
+use bench_utils::Operation;
+use verified_node_replication::{Dispatch, AffinityFn, NodeReplicated, ReplicaId, ThreadToken, NodeReplicatedT};
+
+use builtin::Tracked;
+
+// Number of operation for test-harness.
+#[cfg(feature = "smokebench")]
+pub const NOP: usize = 2_500_000;
+#[cfg(not(feature = "smokebench"))]
+pub const NOP: usize = 25_000_000;
+
+/// Operations that mutate the hash-map.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpWr {
+    /// Insert or update an item in the hash-map.
+    Put(u64, u64),
+    /// Read an item and replace its value, returns the old value.
+    ReadModifyWrite(u64, u64),
+}
+
+/// Operations that only read the hash-map.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OpRd {
+    /// Get item from the hash-map.
+    Get(u64),
+    /// Read up to `len` consecutive keys, returns the number of found items.
+    Scan(u64, usize),
+}
+
+/// Single-threaded implementation of the hash-map
+#[derive(Debug, Clone)]
+pub struct NrHashMap {
+    storage: HashMap<u64, u64>,
+}
+
+impl NrHashMap {
+    pub fn put(&mut self, key: u64, val: u64) -> Option<u64> {
+        self.storage.insert(key, val)
+    }
+
+    pub fn get(&self, key: u64) -> Option<u64> {
+        self.storage.get(&key).map(|v| *v)
+    }
+
+    pub fn scan(&self, key: u64, len: usize) -> u64 {
+        (key..key.saturating_add(len as u64))
+            .filter(|k| self.storage.contains_key(k))
+            .count() as u64
+    }
+}
+
+impl Default for NrHashMap {
+    fn default() -> NrHashMap {
+        NrHashMap::init()
+    }
+}
+
+impl Dispatch for NrHashMap {
+    type ReadOperation = OpRd;
+    type WriteOperation = OpWr;
+    type Response = Option<u64>;
+    type View = NrHashMap;
+
+    /// Return a hash-map loaded with `DEFAULT_RECORD_COUNT` records.
+    fn init() -> Self {
+        let mut storage = HashMap::with_capacity(2 * DEFAULT_RECORD_COUNT);
+        for i in 0..DEFAULT_RECORD_COUNT {
+            storage.insert(i as u64, (i + 1) as u64);
+        }
+        NrHashMap { storage }
+    }
+
+    // partial eq also add an exec operation
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        op.clone()
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            OpRd::Get(key) => self.get(key),
+            OpRd::Scan(key, len) => Some(self.scan(key, len)),
+        }
+    }
+
+    /// Implements how we execute operation from the log against our local hash-map
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            OpWr::Put(key, val) => self.put(key, val),
+            OpWr::ReadModifyWrite(key, val) => {
+                let old = self.get(key);
+                self.put(key, old.map_or(val, |o| o ^ val));
+                old
+            }
+        }
+    }
+}
+
+struct VNRWrapper {
+    val: NodeReplicated<NrHashMap>,
+}
+
+/// The interface a data-structure must implement to be benchmarked by
+/// `ScaleBench`.
+impl DsInterface for VNRWrapper {
+    type D = NrHashMap;
+    type TT = ThreadToken<Self::D>;
+
+    /// Allocate a new data-structure.
+    ///
+    /// - `replicas`: How many replicas the data-structure should maintain.
+    /// - `logs`: How many logs the data-structure should be partitioned over.
+    fn new(replicas: NonZeroUsize, _logs: NonZeroUsize, _log_size: usize) -> Self {
+        VNRWrapper {
+            val: NodeReplicatedT::<Self::D>::new(replicas.into(), AffinityFn::new(mkbench::chg_affinity)),
+        }
+    }
+
+    /// Register a thread with a data-structure.
+    ///
+    /// - `rid` indicates which replica the thread should use.
+    fn register(&mut self, rid: ReplicaId) -> Option<ThreadToken<Self::D>> {
+        NodeReplicatedT::<Self::D>::register(&mut self.val, rid)
+    }
+
+    /// Apply a mutable operation to the data-structure.
+    fn execute_mut(
+        &self,
+        op: <Self::D as Dispatch>::WriteOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute_mut(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+
+    /// Apply a immutable operation to the data-structure.
+    fn execute(
+        &self,
+        op: <Self::D as Dispatch>::ReadOperation,
+        idx: ThreadToken<Self::D>,
+    ) -> Result<(<Self::D as Dispatch>::Response, ThreadToken<Self::D>), ThreadToken<Self::D>> {
+        match NodeReplicatedT::execute(&self.val, op, idx, Tracked::assume_new()) {
+            Ok((res, tkn, _)) => Ok((res, tkn)),
+            Err((tkn, _)) => Err(tkn),
+        }
+    }
+}
+
+/// The operations a run executes.
+enum Input<'a> {
+    /// A generated YCSB workload
+    Ycsb(&'a WorkloadSpec),
+    /// A replayed operation trace
+    Trace(&'a Trace, ReplayMode),
+}
+
+/// Generate the operations of a YCSB workload
+///
+/// # Arguments
+///  - `nop`: Number of operations to generate
+///  - `spec`: The workload specification (mix and key distribution)
+pub fn generate_operations(nop: usize, spec: &WorkloadSpec) -> Vec<Operation<OpRd, OpWr>> {
+    assert!(
+        spec.record_count <= DEFAULT_RECORD_COUNT,
+        "the hash-map is loaded with {} records only",
+        DEFAULT_RECORD_COUNT
+    );
+
+    to_operations(spec.generate(nop))
+}
+
+/// Map YCSB operations to the operations of the hash-map.
+fn to_operations(ops: Vec<YcsbOp>) -> Vec<Operation<OpRd, OpWr>> {
+    ops.into_iter()
+        .map(|op| match op {
+            YcsbOp::Read(key) => Operation::ReadOperation(OpRd::Get(key)),
+            YcsbOp::Scan(key, len) => Operation::ReadOperation(OpRd::Scan(key, len)),
+            YcsbOp::Update(key, val) | YcsbOp::Insert(key, val) => {
+                Operation::WriteOperation(OpWr::Put(key, val))
+            }
+            YcsbOp::ReadModifyWrite(key, val) => {
+                Operation::WriteOperation(OpWr::ReadModifyWrite(key, val))
+            }
+        })
+        .collect()
+}
+
+/// Compare scale-out behaviour of the hash-map for the given YCSB workload or trace.
+fn scale_out<R>(c: &mut TestHarness, name: &str, input: &Input) -> Vec<RunResult>
+where
+    R: DsInterface + Send + Sync + 'static,
+    R::D: Send,
+    R::D: Dispatch<ReadOperation = OpRd>,
+    R::D: Dispatch<WriteOperation = OpWr>,
+    <R::D as Dispatch>::WriteOperation: Send + Sync,
+    <R::D as Dispatch>::ReadOperation: Send + Sync,
+    <R::D as Dispatch>::Response: Sync + Send + Debug,
+{
+    let (ops, bench_name, reads_pct, replay) = match input {
+        Input::Ycsb(spec) => (
+            generate_operations(NOP, spec),
+            format!("{}-{}", name, spec.name()),
+            spec.reads_pct(),
+            None,
+        ),
+        Input::Trace(trace, mode) => (
+            to_operations(trace.ycsb_ops()),
+            format!("{}-trace-{}-{}", name, trace.name, mode),
+            trace.reads_pct(),
+            Some(Replay::new(trace, *mode)),
+        ),
+    };
+
+    let mut builder = mkbench::ScaleBenchBuilder::<R>::new(ops);
+    builder
+        .thread_defaults()
+        .update_batch(32)
+        .log_size(32 * 1024 * 1024)
+        // the hash-map is allocated with room for twice the records
+        .replica_footprint(2 * DEFAULT_RECORD_COUNT * 2 * std::mem::size_of::<u64>())
+        .replica_strategy(mkbench::ReplicaStrategy::One)
+        .replica_strategy(mkbench::ReplicaStrategy::Socket)
+        .thread_mapping(ThreadMapping::Interleave)
+        .sweep_from_args()
+        .cpus_from_args()
+        .read_pct(reads_pct)
+        .log_strategy(mkbench::LogStrategy::One);
+    if let Some(replay) = replay {
+        builder.replay(replay);
+    }
+    builder.configure(
+        c,
+        &bench_name,
+        |_cid, tkn, replica, op, _batch_size| match op {
+            Operation::ReadOperation(op) => match replica.execute(*op, tkn) {
+                Ok(r) => r.1,
+                Err(r) => r,
+            },
+            Operation::WriteOperation(op) => match replica.execute_mut(*op, tkn) {
+                Ok(r) => r.1,
+                Err(r) => r,
+            },
+        },
+    )
+}
+
+fn main() {
+    let _r = env_logger::try_init();
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
+    }
+
+    bench_utils::disable_dvfs();
+
+    let mut harness = TestHarness::new(Duration::from_secs(10));
+
+    // a trace replaces the YCSB workloads
+    let trace = Trace::from_args(std::env::args().skip(1));
+    let workloads = WorkloadSpec::from_args(std::env::args().skip(1));
+    let inputs: Vec<Input> = match &trace {
+        Some((trace, mode)) => vec![Input::Trace(trace, *mode)],
+        None => workloads.iter().map(Input::Ycsb).collect(),
+    };
+
+    for input in inputs.iter() {
+        let mut results = scale_out::<VNRWrapper>(&mut harness, "vnr-hashmap", input);
+        if baseline::baselines_enabled() {
+            results.extend(scale_out::<StdRwLockBaseline<NrHashMap>>(
+                &mut harness,
+                StdRwLockBaseline::<NrHashMap>::name(),
+                input,
+            ));
+            results.extend(scale_out::<ParkingLotRwLockBaseline<NrHashMap>>(
+                &mut harness,
+                ParkingLotRwLockBaseline::<NrHashMap>::name(),
+                input,
+            ));
+            results.extend(scale_out::<MutexBaseline<NrHashMap>>(
+                &mut harness,
+                MutexBaseline::<NrHashMap>::name(),
+                input,
+            ));
+            results::print_comparison(&results);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+// Copyright © 2019-2022 VMware, Inc. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Exports the enabled features of the benchmarks as `BENCH_FEATURES`, the
+//! benchmarks record them in the metadata of their results with
+//! `bench_utils::metadata::set_features`.
+
+use std::env;
+
+fn main() {
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(k, _)| {
+            k.strip_prefix("CARGO_FEATURE_")
+                .map(|f| f.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=BENCH_FEATURES={}", features.join(","));
+    println!("cargo:rerun-if-changed=build.rs");
+}
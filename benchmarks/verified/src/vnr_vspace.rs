@@ -242,6 +242,7 @@ struct VNRWrapper {
 /// `ScaleBench`.
 impl DsInterface for VNRWrapper {
     type D = VSpace; //: Dispatch + Default + Sync;
+    type TT = ThreadToken<Self::D>;
 
     /// Allocate a new data-structure.
     ///
@@ -783,8 +784,9 @@ pub fn generate_operations(
 
 fn main() {
     let _r = env_logger::try_init();
-    if cfg!(feature = "smokebench") {
-        warn!("Running with feature 'smokebench' may not get the desired results");
+    bench_utils::metadata::set_features(env!("BENCH_FEATURES"));
+    if bench_utils::smoke_enabled() {
+        warn!("Running a smoke test (feature 'smokebench' or --smoke) may not get the desired results");
     }
 
     bench_utils::disable_dvfs();
@@ -798,11 +800,8 @@ fn main() {
     let reads_pct = args[2].parse::<usize>().unwrap();
     let write_ratio = 100 - reads_pct;
     let runtime = args[3].parse::<u64>().unwrap();
-    let numa_policy = match args[4].as_str() {
-        "fill" => ThreadMapping::NUMAFill,
-        "interleave" => ThreadMapping::Interleave,
-        _ => panic!("supply fill or interleave as numa mapping")
-    };
+    let numa_policy = ThreadMapping::parse(&args[4])
+        .expect("supply fill, interleave, smt or roundrobin as numa mapping");
     let run_id_num = &args[5];
 
     let mut harness = TestHarness::new(Duration::from_secs(runtime));
@@ -10,6 +10,7 @@ set -eu
 
 REPOSITORY_ROOT=$(git rev-parse --show-toplevel)
 VERUS_ROOT="${REPOSITORY_ROOT}/verus"
+NR_TOOLCHAIN="${REPOSITORY_ROOT}/verified-node-replication/rust-toolchain.toml"
 
 echo "Repository root: ${REPOSITORY_ROOT}"
 echo "Verus root:      ${VERUS_ROOT}"
@@ -30,4 +31,15 @@ echo "Verus version:   $(git rev-parse HEAD)"
 
 rustc --version
 
+# the crate must be built with the same toolchain as the pinned Verus version
+VERUS_CHANNEL=$(grep '^channel' ../rust-toolchain.toml | cut -d '"' -f 2)
+NR_CHANNEL=$(grep '^channel' ${NR_TOOLCHAIN} | cut -d '"' -f 2)
+echo "Toolchain:       ${VERUS_CHANNEL}"
+
+if [ "${VERUS_CHANNEL}" != "${NR_CHANNEL}" ]; then
+    echo "Toolchain mismatch: Verus uses '${VERUS_CHANNEL}', the crate pins '${NR_CHANNEL}'."
+    echo "Update ${NR_TOOLCHAIN} to match the Verus submodule."
+    exit 1
+fi
+
 popd > /dev/null
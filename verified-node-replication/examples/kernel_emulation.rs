@@ -0,0 +1,291 @@
+// Emulated Kernel Integration Example with Verified NR
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// trustedness: ignore this file
+
+//! Shows how a kernel uses the verified NR core, modeled after its use in NrOS.
+//!
+//! A kernel has no threads to spawn and no blocking primitives: every core runs a loop, executes
+//! its own operations, and takes interrupts in between. This example emulates that on a single
+//! thread. The cores are scheduled round-robin, and each core executes operations on the replica
+//! of its node. Nothing here uses `std::thread` or `std::sync`, the per-core state lives in
+//! `core::sync::atomic` statics like it would in a kernel, `std` is only used to print.
+//!
+//! This is an emulation, not a `no_std` build: the example is a `std` binary, and the crate itself
+//! still depends on `std` (e.g., `Vec`, `Box` and the `StdWait` strategy). `no_std` is not
+//! supported.
+//!
+//!  - registration: every core and the timer interrupt of every core register a thread token
+//!    with the replica of their node before the cores start.
+//!  - combining: the cores on a node take turns as the combiner of its replica. The replica
+//!    disables interrupts with the `IrqGuard` while the combiner lock is held. A timer interrupt
+//!    that arrives in the meantime is deferred and delivered once interrupts are enabled again.
+//!  - interrupts: the timer handler only reads with `try_execute`, which never waits for the
+//!    combiner, and drops the read if the replica is not up to date.
+//!  - garbage collection: the cores execute more updates than the log has entries, so the log
+//!    wraps around several times. The combiner waits until all replicas have consumed the entries
+//!    before it reuses them. Waiting means spinning on the log, which only makes progress here
+//!    because the round-robin schedule keeps all replicas close to the tail. The waits use the
+//!    `SpinWait` strategy, which never yields to an operating system.
+//!
+//! Build it with `cargo run --release --example kernel_emulation --features kernel-emulation`.
+
+// core dependencies
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// the verus dependencies
+use builtin::Tracked;
+
+// the traits and types we need from the verified-node-replicaton crate
+use verified_node_replication::constants::LOG_SIZE;
+use verified_node_replication::{
+    AffinityFn, Dispatch, LogMemFn, NodeReplicated, NodeReplicatedT, PreemptGuard, SpinWait,
+    ThreadToken,
+};
+
+/// the number of NUMA nodes, one replica per node
+const NUM_NODES: usize = 2;
+
+/// the number of cores per node
+const CORES_PER_NODE: usize = 2;
+
+/// the total number of cores
+const NUM_CORES: usize = NUM_NODES * CORES_PER_NODE;
+
+/// the number of updates every core executes, together enough to wrap the log twice
+const NUM_OPS_PER_CORE: usize = 2 * LOG_SIZE / NUM_CORES + 1;
+
+/// a timer interrupt fires after this many updates have been applied to a replica
+const TIMER_PERIOD: usize = 1024;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Per-Core State
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// the core that is currently running (the scheduler sets it before running a core)
+static CURRENT_CORE: AtomicUsize = AtomicUsize::new(0);
+
+/// the interrupt flag of every core
+static IRQ_ENABLED: [AtomicBool; NUM_CORES] = [const { AtomicBool::new(true) }; NUM_CORES];
+
+/// whether a timer interrupt is pending on the core
+static IRQ_PENDING: [AtomicBool; NUM_CORES] = [const { AtomicBool::new(false) }; NUM_CORES];
+
+/// the number of interrupts that arrived while the core was combining
+static IRQ_DEFERRED: AtomicUsize = AtomicUsize::new(0);
+
+/// the number of reads of the interrupt handlers that completed and that were dropped
+static IRQ_READS: AtomicUsize = AtomicUsize::new(0);
+static IRQ_READS_DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+fn current_core() -> usize {
+    CURRENT_CORE.load(Ordering::Relaxed)
+}
+
+/// Disables interrupts on the current core, like `cli`/`sti` would in a kernel.
+pub struct IrqGuard;
+
+impl PreemptGuard for IrqGuard {
+    fn disable() -> usize {
+        IRQ_ENABLED[current_core()].swap(false, Ordering::Relaxed) as usize
+    }
+
+    fn restore(state: usize) {
+        IRQ_ENABLED[current_core()].store(state != 0, Ordering::Relaxed);
+    }
+}
+
+/// Raises the timer interrupt on the current core, it is delivered at the next instruction
+/// boundary with enabled interrupts.
+fn raise_timer_interrupt() {
+    let core = current_core();
+    if !IRQ_ENABLED[core].load(Ordering::Relaxed) {
+        IRQ_DEFERRED.fetch_add(1, Ordering::Relaxed);
+    }
+    IRQ_PENDING[core].store(true, Ordering::Relaxed);
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Data Structure Definition with the Operations
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// represents a update operation on the data structure
+#[derive(Clone, Copy)]
+pub enum UpdateOp {
+    /// maps the given frame into the address space
+    Map(u64),
+}
+
+/// represents a read-only operation on the data structure
+pub enum ReadonlyOp {
+    /// get the number of mapped frames
+    Mapped,
+}
+
+/// the address space of a process, reduced to the number of mapped frames
+pub struct AddressSpace {
+    pub mapped: u64,
+    pub last: u64,
+}
+
+impl Dispatch for AddressSpace {
+    type ReadOperation = ReadonlyOp;
+
+    type WriteOperation = UpdateOp;
+
+    type Response = u64;
+
+    type View = AddressSpace;
+
+    fn init() -> Self {
+        AddressSpace { mapped: 0, last: 0 }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        op.clone()
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            ReadonlyOp::Mapped => self.mapped,
+        }
+    }
+
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            UpdateOp::Map(frame) => {
+                // the combiner runs with interrupts disabled
+                assert!(!IRQ_ENABLED[current_core()].load(Ordering::Relaxed));
+                self.mapped = self.mapped.wrapping_add(1);
+                self.last = frame;
+                // the timer fires while the core is combining
+                if self.mapped % TIMER_PERIOD as u64 == 0 {
+                    raise_timer_interrupt();
+                }
+                self.mapped
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// The Kernel
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// the state of a core
+struct Core {
+    /// the thread token of the core
+    tkn: Option<ThreadToken<AddressSpace>>,
+    /// the thread token of the timer interrupt handler of the core
+    irq_tkn: Option<ThreadToken<AddressSpace>>,
+    /// the number of executed operations
+    ops: usize,
+}
+
+/// The timer interrupt handler, reads the replica of the node without waiting.
+fn timer_handler(nr: &NodeReplicated<AddressSpace>, core: &mut Core) {
+    let tkn = core.irq_tkn.take().unwrap();
+    let tkn = match nr.try_execute(ReadonlyOp::Mapped, tkn, Tracked::assume_new()) {
+        Result::Ok((_mapped, tkn, _)) => {
+            IRQ_READS.fetch_add(1, Ordering::Relaxed);
+            tkn
+        }
+        Result::Err((tkn, _)) => {
+            IRQ_READS_DROPPED.fetch_add(1, Ordering::Relaxed);
+            tkn
+        }
+    };
+    core.irq_tkn = Some(tkn);
+}
+
+/// Delivers the pending interrupt of the current core if interrupts are enabled.
+fn deliver_interrupts(nr: &NodeReplicated<AddressSpace>, core: &mut Core) {
+    let id = current_core();
+    if IRQ_ENABLED[id].load(Ordering::Relaxed) && IRQ_PENDING[id].swap(false, Ordering::Relaxed) {
+        timer_handler(nr, core);
+    }
+}
+
+/// Runs one step of the core: a single update operation.
+fn core_step(nr: &NodeReplicated<AddressSpace>, core: &mut Core) {
+    let tkn = core.tkn.take().unwrap();
+    let frame = (current_core() * NUM_OPS_PER_CORE + core.ops) as u64;
+    let tkn = match nr.execute_mut(UpdateOp::Map(frame), tkn, Tracked::assume_new()) {
+        Result::Ok((_mapped, tkn, _)) => tkn,
+        Result::Err((tkn, _)) => tkn,
+    };
+    core.tkn = Some(tkn);
+    core.ops += 1;
+    deliver_interrupts(nr, core);
+}
+
+pub fn main() {
+    println!("Booting {NUM_CORES} cores on {NUM_NODES} nodes...");
+
+    // the cores spin with `core::hint::spin_loop` only, there is no OS to yield to
+    let mut nr = NodeReplicated::<AddressSpace>::new_with_hooks::<IrqGuard, SpinWait>(
+        NUM_NODES,
+        AffinityFn::new(|_node| {}),
+        LogMemFn::none(),
+    );
+
+    // registration happens on the boot core, before the other cores are started
+    let mut cores = Vec::with_capacity(NUM_CORES);
+    for id in 0..NUM_CORES {
+        let node = id / CORES_PER_NODE;
+        let tkn = nr.register(node).expect("could not register the core");
+        let irq_tkn = nr
+            .register(node)
+            .expect("could not register the interrupt handler");
+        println!(
+            " - core {id}: thread {}.{}",
+            tkn.replica_id(),
+            tkn.thread_id()
+        );
+        cores.push(Core {
+            tkn: Some(tkn),
+            irq_tkn: Some(irq_tkn),
+            ops: 0,
+        });
+    }
+
+    println!("Running {NUM_OPS_PER_CORE} updates per core (log size {LOG_SIZE})...");
+
+    // the round-robin scheduler of the cores
+    for _ in 0..NUM_OPS_PER_CORE {
+        for (id, core) in cores.iter_mut().enumerate() {
+            CURRENT_CORE.store(id, Ordering::Relaxed);
+            core_step(&nr, core);
+        }
+    }
+
+    let total = (NUM_CORES * NUM_OPS_PER_CORE) as u64;
+    for (id, core) in cores.iter_mut().enumerate() {
+        CURRENT_CORE.store(id, Ordering::Relaxed);
+        let tkn = core.tkn.take().unwrap();
+        nr.sync(&tkn);
+        match nr.execute(ReadonlyOp::Mapped, tkn, Tracked::assume_new()) {
+            Result::Ok((mapped, _, _)) => {
+                println!("Core {id} - mapped {mapped} frames, expected {total}");
+                assert_eq!(mapped, total);
+            }
+            Result::Err(_) => panic!("read failed on core {id}"),
+        }
+    }
+
+    println!(
+        "Log wrapped {} times, {} interrupts deferred while combining",
+        total as usize / LOG_SIZE,
+        IRQ_DEFERRED.load(Ordering::Relaxed)
+    );
+    println!(
+        "Interrupt handlers: {} reads, {} dropped because they would block",
+        IRQ_READS.load(Ordering::Relaxed),
+        IRQ_READS_DROPPED.load(Ordering::Relaxed)
+    );
+    println!("Done!");
+}
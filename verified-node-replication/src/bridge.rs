@@ -0,0 +1,239 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! Primary-Backup Bridge
+//!
+//! Ships the update operations of a primary [`NodeReplicated`] instance to followers on other
+//! machines, which apply them to their own [`NodeReplicated`] instance in the same order.
+//!
+//! The bridge is unverified. It only moves operations between instances: the primary executes
+//! an update with `execute_mut` and then writes it to the stream, the follower reads it from the
+//! stream and executes it with `execute_mut`, so the updates are applied through the verified
+//! path on both sides. What the proofs don't cover is that the follower receives the updates in
+//! the order of the primary's log. The primary takes a sequencer lock around executing and
+//! shipping an update, so updates that go through the bridge are linearized and shipped in the
+//! same order. This serializes the updates of the primary; reads are not affected.
+//!
+//! Updates must only reach the primary through [`Primary::execute_mut`], otherwise the
+//! follower misses them.
+//!
+//! The stream is a sequence of frames: the sequence number of the update (`u64`), the length of
+//! the encoded operation (`u32`, at most [`MAX_FRAME_SIZE`]) and the encoded operation, all
+//! little endian. Any [`std::io::Write`] and [`std::io::Read`] can carry it, e.g., a `TcpStream`.
+
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+
+use builtin::Tracked;
+
+use crate::{Dispatch, NodeReplicated, NodeReplicatedT, ThreadToken};
+
+/// A data structure whose update operations can be shipped to another machine.
+pub trait WireDispatch: Dispatch {
+    /// appends the encoding of the update operation to `buf`
+    fn encode_op(op: &Self::WriteOperation, buf: &mut Vec<u8>);
+
+    /// decodes an update operation, `None` if `buf` isn't a valid encoding
+    fn decode_op(buf: &[u8]) -> Option<Self::WriteOperation>;
+}
+
+/// the maximum size of an encoded operation in a frame
+pub const MAX_FRAME_SIZE: usize = 1 << 20;
+
+/// writes one frame to the stream
+fn write_frame<W: Write>(sink: &mut W, seq: u64, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "operation too large"));
+    }
+    let len = payload.len() as u32;
+    sink.write_all(&seq.to_le_bytes())?;
+    sink.write_all(&len.to_le_bytes())?;
+    sink.write_all(payload)?;
+    sink.flush()
+}
+
+/// reads one frame from the stream, `None` if the stream ended between two frames
+///
+/// A stream that ends within a frame is an `UnexpectedEof` error.
+fn read_frame<R: Read>(source: &mut R, payload: &mut Vec<u8>) -> io::Result<Option<u64>> {
+    let mut seq = [0u8; 8];
+    let mut read = 0;
+    while read == 0 {
+        match source.read(&mut seq) {
+            Ok(0) => return Ok(None),
+            Ok(n) => read = n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    source.read_exact(&mut seq[read..])?;
+    let mut len = [0u8; 4];
+    source.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes exceeds the maximum of {MAX_FRAME_SIZE} bytes"),
+        ));
+    }
+    payload.clear();
+    payload.resize(len, 0);
+    source.read_exact(payload)?;
+    Ok(Some(u64::from_le_bytes(seq)))
+}
+
+/// the sequencer of the primary: the stream and the number of shipped updates
+struct Sequencer<W> {
+    sink: W,
+    shipped: u64,
+    /// whether writing to the stream failed, the follower has missed an update
+    failed: bool,
+    buf: Vec<u8>,
+}
+
+/// The primary: executes updates and ships them to a follower.
+///
+/// The updates of the primary are serialized: [`Primary::execute_mut`] holds the sequencer lock
+/// while the update executes, so at most one update is in flight and the combiners of the
+/// replicas never batch updates of different threads. Reads through
+/// [`Primary::replicated`] are not affected.
+pub struct Primary<DT: WireDispatch + Sync, W: Write> {
+    nr: NodeReplicated<DT>,
+    sequencer: Mutex<Sequencer<W>>,
+}
+
+impl<DT: WireDispatch + Sync, W: Write> Primary<DT, W> {
+    /// ships the updates of `nr` to `sink`
+    pub fn new(nr: NodeReplicated<DT>, sink: W) -> Self {
+        Primary {
+            nr,
+            sequencer: Mutex::new(Sequencer {
+                sink,
+                shipped: 0,
+                failed: false,
+                buf: Vec::new(),
+            }),
+        }
+    }
+
+    /// the replicated data structure, for reads and registering threads
+    pub fn replicated(&self) -> &NodeReplicated<DT> {
+        &self.nr
+    }
+
+    /// the replicated data structure, for reads and registering threads
+    pub fn replicated_mut(&mut self) -> &mut NodeReplicated<DT> {
+        &mut self.nr
+    }
+
+    /// the number of updates shipped so far
+    pub fn shipped(&self) -> u64 {
+        self.sequencer.lock().unwrap().shipped
+    }
+
+    /// whether writing to the stream failed, the primary then refuses further updates
+    pub fn failed(&self) -> bool {
+        self.sequencer.lock().unwrap().failed
+    }
+
+    /// Executes the update on the primary and ships it to the follower.
+    ///
+    /// Returns the thread token if it doesn't belong to a replica of the primary. An error of
+    /// the stream is returned after the update was executed on the primary; the follower then
+    /// misses the update and must be resynchronized. From then on the primary is failed and
+    /// refuses all updates without executing them, so no later update reaches the follower in
+    /// place of the missing one.
+    pub fn execute_mut(
+        &self,
+        op: DT::WriteOperation,
+        tkn: ThreadToken<DT>,
+    ) -> Result<(DT::Response, ThreadToken<DT>), (ThreadToken<DT>, Option<io::Error>)> {
+        let mut sequencer = self.sequencer.lock().unwrap();
+        let Sequencer { sink, shipped, failed, buf } = &mut *sequencer;
+        if *failed {
+            let e = io::Error::new(io::ErrorKind::BrokenPipe, "the follower missed an update");
+            return Err((tkn, Some(e)));
+        }
+        buf.clear();
+        DT::encode_op(&op, buf);
+        match self.nr.execute_mut(op, tkn, Tracked::assume_new()) {
+            Ok((resp, tkn, _)) => match write_frame(sink, *shipped, buf) {
+                Ok(()) => {
+                    *shipped += 1;
+                    Ok((resp, tkn))
+                }
+                Err(e) => {
+                    *failed = true;
+                    Err((tkn, Some(e)))
+                }
+            },
+            Err((tkn, _)) => Err((tkn, None)),
+        }
+    }
+}
+
+/// The follower: applies the updates shipped by a primary.
+pub struct Follower<DT: WireDispatch + Sync> {
+    nr: NodeReplicated<DT>,
+    applied: u64,
+    buf: Vec<u8>,
+}
+
+impl<DT: WireDispatch + Sync> Follower<DT> {
+    /// applies shipped updates to `nr`, which must be in the initial state of the primary
+    pub fn new(nr: NodeReplicated<DT>) -> Self {
+        Follower {
+            nr,
+            applied: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    /// the replicated data structure, for reads and registering threads
+    pub fn replicated(&self) -> &NodeReplicated<DT> {
+        &self.nr
+    }
+
+    /// the replicated data structure, for reads and registering threads
+    pub fn replicated_mut(&mut self) -> &mut NodeReplicated<DT> {
+        &mut self.nr
+    }
+
+    /// the number of updates applied so far
+    pub fn applied(&self) -> u64 {
+        self.applied
+    }
+
+    /// Applies the updates from `source` until the stream ends, with the thread token `tkn`.
+    ///
+    /// Returns the thread token. Fails if an update is missing or can't be decoded, or if the
+    /// thread token doesn't belong to a replica of the follower.
+    pub fn apply_from<R: Read>(
+        &mut self,
+        source: &mut R,
+        mut tkn: ThreadToken<DT>,
+    ) -> io::Result<ThreadToken<DT>> {
+        while let Some(seq) = read_frame(source, &mut self.buf)? {
+            if seq != self.applied {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected update {}, received update {seq}", self.applied),
+                ));
+            }
+            let op = DT::decode_op(&self.buf).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("invalid update {seq}"))
+            })?;
+            tkn = match self.nr.execute_mut(op, tkn, Tracked::assume_new()) {
+                Ok((_, tkn, _)) => tkn,
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "thread token of another data structure",
+                    ))
+                }
+            };
+            self.applied += 1;
+        }
+        Ok(tkn)
+    }
+}
@@ -34,6 +34,33 @@ pub open const MAX_REQUESTS: usize = MAX_THREADS_PER_REPLICA * MAX_PENDING_OPS;
 /// interval when we do a try_combine when checking for responses
 pub open const RESPONSE_CHECK_INTERVAL: usize = 0x2000_0000;
 
+/// interval when we do a try_combine when checking for responses of eager updates
+pub open const EAGER_CHECK_INTERVAL: usize = 0x100;
+
+/// Interval when we do a try_combine when checking for responses on a contended replica.
+///
+/// The update is left to the current combiner, the interval only bounds the wait if that
+/// combiner already collected its batch. It is short compared to `RESPONSE_CHECK_INTERVAL`, and
+/// long enough for a combiner to append and execute a batch of `MAX_REQUESTS` updates.
+pub open const CONTENDED_CHECK_INTERVAL: usize = 0x1000;
+
+/// The contention score above which updates are left to the current combiner.
+///
+/// The score is raised by every failed compare-and-swap of the combiner lock or the tail, and
+/// lowered by every successful acquire of the combiner lock. A replica is contended once the
+/// failures outnumber the uncontended rounds by more than this, a single failure from a
+/// combiner that finished just before is not enough.
+pub open const CONTENTION_THRESHOLD: u64 = 8;
+
+/// The maximum contention score of a replica.
+///
+/// Bounds how long a replica stays contended after the contention is gone: at most
+/// `MAX_CONTENTION - CONTENTION_THRESHOLD` uncontended rounds of the combiner.
+pub open const MAX_CONTENTION: u64 = 64;
+
+/// the number of attempts to acquire the combiner lock before a non-blocking update gives up
+pub open const TRY_COMBINE_ATTEMPTS: usize = 4;
+
 /// Constant required for garbage collection. When the tail and the head are these many
 /// entries apart on the circular buffer, garbage collection will be performed by one of
 /// the replicas registered with the log.
@@ -44,6 +71,10 @@ pub open const RESPONSE_CHECK_INTERVAL: usize = 0x2000_0000;
 /// log.
 pub open const GC_FROM_HEAD: usize = MAX_PENDING_OPS * MAX_THREADS_PER_REPLICA;
 
+/// the number of rounds an appender waits for a lagging replica before reporting it to the
+/// `LogPressureFn`
+pub open const LAGGARD_THRESHOLD: usize = 0x1000;
+
 /// Threshold after how many iterations we abort and report the replica we're waiting for
 /// as stuck for busy spinning loops.
 ///
@@ -51,6 +82,18 @@ pub open const GC_FROM_HEAD: usize = MAX_PENDING_OPS * MAX_THREADS_PER_REPLICA;
 pub open const WARN_THRESHOLD: usize = 0x10000000;
 
 /// the maximum number of identifiers that can be used
+///
+/// The tail, the head and the versions of the log stay at or below it, appends beyond it stop the
+/// program (see `assume_operation_count_bounded`).
 pub open const MAX_IDX: u64 = 0xffff_ffff_f000_0000;
 
+/// The counters of the log don't wrap around: below `MAX_IDX` there is room for the log size and
+/// a full batch of every replica, the largest values added to a counter or the head.
+pub proof fn counters_do_not_wrap()
+    ensures
+        MAX_IDX as int + LOG_SIZE as int + MAX_REPLICAS as int * MAX_REQUESTS as int
+            <= u64::MAX as int,
+{
+}
+
 } // verus!
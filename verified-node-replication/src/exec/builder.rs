@@ -0,0 +1,263 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+// Builder for Node Replicated Data Structures
+#[allow(unused_imports)]
+use builtin::*;
+use builtin_macros::*;
+
+use vstd::prelude::*;
+
+use crate::constants::{MAX_REPLICAS, MAX_THREADS_PER_REPLICA};
+use crate::exec::context::ResponseDelivery;
+use crate::exec::NodeReplicated;
+use crate::{
+    AffinityFn, Dispatch, LogMemFn, LogPressureFn, NodeReplicatedT, PreemptFn, PreemptGuard,
+    WaitFn, WaitStrategy,
+};
+
+verus! {
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Replica Selection
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How [`NodeReplicated::register_next`] picks the replica of a thread.
+///
+/// If the picked replica is full, the next replica with a free thread token is used.
+pub enum ReplicaSelection {
+    /// spread the threads over the replicas, one replica after the other
+    RoundRobin,
+    /// fill the first replica before using the next one
+    Fill,
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Builder
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// An invalid configuration of a [`NodeReplicatedBuilder`].
+#[verus::trusted]
+#[verifier::external_body]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    /// the number of replicas is zero or larger than `MAX_REPLICAS`
+    Replicas(usize),
+    /// the number of threads per replica is zero or larger than `MAX_THREADS_PER_REPLICA`
+    ThreadsPerReplica(usize),
+    /// the number of NUMA nodes of the replicas differs from the number of replicas
+    ReplicaNodes(usize),
+}
+
+#[verus::trusted]
+impl std::fmt::Display for BuildError {
+    #[verifier::external_body]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::Replicas(n) => {
+                write!(f, "{n} replicas, supported are 1 to {MAX_REPLICAS}")
+            },
+            BuildError::ThreadsPerReplica(n) => {
+                write!(f, "{n} threads per replica, supported are 1 to {MAX_THREADS_PER_REPLICA}")
+            },
+            BuildError::ReplicaNodes(n) => {
+                write!(f, "NUMA nodes of {n} replicas, needs the node of every replica")
+            },
+        }
+    }
+}
+
+/// the number of NUMA nodes of the machine, 1 if unknown.
+#[verus::trusted]
+#[verifier::external_body]
+fn numa_nodes() -> usize {
+    let nodes = std::fs::read_dir("/sys/devices/system/node").map(
+        |entries| {
+            entries.flatten().filter(
+                |e| {
+                    let name = e.file_name().to_string_lossy().into_owned();
+                    name.starts_with("node") && name[4..].parse::<usize>().is_ok()
+                },
+            ).count()
+        },
+    );
+    nodes.unwrap_or(1).max(1)
+}
+
+/// spreads the replicas evenly over the NUMA nodes of the machine, in order.
+#[verus::trusted]
+#[verifier::external_body]
+fn default_replica_nodes(replicas: usize) -> Vec<usize> {
+    let nodes = numa_nodes();
+    (0..replicas).map(|replica| replica * nodes / replicas).collect()
+}
+
+/// Configures and creates a [`NodeReplicated`] data structure.
+///
+/// Created with [`NodeReplicated::builder`]. The defaults are one replica per NUMA node (one
+/// replica if the nodes can't be read), `MAX_THREADS_PER_REPLICA` threads per replica, the
+/// [`crate::StdWait`] strategy, spinning for responses, no preemption guard, round-robin replica
+/// selection, no stealing of combiners, the replicas spread evenly over the NUMA nodes, and no
+/// affinity, log memory or log pressure hooks.
+/// [`NodeReplicatedBuilder::build`] validates the configuration.
+///
+/// The log always has `LOG_SIZE` entries, the only size it is verified for.
+#[verifier::reject_recursive_types(DT)]
+pub struct NodeReplicatedBuilder<DT: Dispatch> {
+    replicas: usize,
+    threads_per_replica: usize,
+    preempt: PreemptFn,
+    wait: WaitFn,
+    delivery: ResponseDelivery,
+    selection: ReplicaSelection,
+    steal_lag: u64,
+    replica_nodes: Option<Vec<usize>>,
+    affinity: AffinityFn,
+    log_mem: LogMemFn,
+    pressure: LogPressureFn,
+    _dt: std::marker::PhantomData<DT>,
+}
+
+impl<DT: Dispatch + Sync> NodeReplicatedBuilder<DT> {
+    /// the number of replicas the data structure is built with
+    pub closed spec fn replicas_spec(&self) -> usize {
+        self.replicas
+    }
+
+    /// whether [`NodeReplicatedBuilder::build`] accepts the configuration
+    pub closed spec fn valid(&self) -> bool {
+        &&& 0 < self.replicas <= MAX_REPLICAS
+        &&& 0 < self.threads_per_replica <= MAX_THREADS_PER_REPLICA
+        &&& self.replica_nodes.is_Some() ==> self.replica_nodes.get_Some_0().len()
+            == self.replicas
+    }
+
+    /// creates a builder with the default configuration.
+    #[verifier::external_body]
+    pub fn new() -> Self {
+        NodeReplicatedBuilder {
+            replicas: numa_nodes().min(MAX_REPLICAS),
+            threads_per_replica: MAX_THREADS_PER_REPLICA,
+            preempt: PreemptFn::none(),
+            wait: WaitFn::std(),
+            delivery: ResponseDelivery::Spin,
+            selection: ReplicaSelection::RoundRobin,
+            steal_lag: 0,
+            replica_nodes: None,
+            affinity: AffinityFn::new(|_replica| {}),
+            log_mem: LogMemFn::none(),
+            pressure: LogPressureFn::none(),
+            _dt: std::marker::PhantomData,
+        }
+    }
+
+    /// sets the number of replicas.
+    pub fn replicas(mut self, replicas: usize) -> Self {
+        self.replicas = replicas;
+        self
+    }
+
+    /// sets the maximum number of threads that can register with a replica.
+    pub fn threads_per_replica(mut self, threads: usize) -> Self {
+        self.threads_per_replica = threads;
+        self
+    }
+
+    /// sets the guard that disables preemption while a combiner lock is held.
+    pub fn preempt_guard<G: PreemptGuard>(mut self) -> Self {
+        self.preempt = PreemptFn::new::<G>();
+        self
+    }
+
+    /// sets the strategy of all busy waiting of the replicas and the log.
+    pub fn wait_strategy<W: WaitStrategy>(mut self) -> Self {
+        self.wait = WaitFn::new::<W>();
+        self
+    }
+
+    /// sets how threads wait for the responses of their updates, parked threads park with the
+    /// wait strategy.
+    pub fn response_delivery(mut self, delivery: ResponseDelivery) -> Self {
+        self.delivery = delivery;
+        self
+    }
+
+    /// sets how [`NodeReplicated::register_next`] picks the replica of a thread.
+    pub fn replica_selection(mut self, selection: ReplicaSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// lets idle threads run the combiner of replicas that lag at least `min_lag` entries behind
+    /// the tail of the log, see [`NodeReplicated::help_lagging`]. 0 disables it.
+    pub fn steal_lagging(mut self, min_lag: u64) -> Self {
+        self.steal_lag = min_lag;
+        self
+    }
+
+    /// sets the NUMA node of each replica, combiners are only stolen within a node.
+    pub fn replica_nodes(mut self, nodes: Vec<usize>) -> Self {
+        self.replica_nodes = Some(nodes);
+        self
+    }
+
+    /// sets the function that changes the memory affinity to a replica before it is allocated.
+    pub fn affinity(mut self, affinity: AffinityFn) -> Self {
+        self.affinity = affinity;
+        self
+    }
+
+    /// sets the function that prepares the memory of the log.
+    pub fn log_mem(mut self, log_mem: LogMemFn) -> Self {
+        self.log_mem = log_mem;
+        self
+    }
+
+    /// sets the function that is notified about the occupancy of the log and lagging replicas.
+    pub fn log_pressure(mut self, pressure: LogPressureFn) -> Self {
+        self.pressure = pressure;
+        self
+    }
+
+    /// validates the configuration and creates the data structure.
+    pub fn build(self) -> (res: Result<NodeReplicated<DT>, BuildError>)
+        ensures
+            res.is_Ok() <==> self.valid(),
+            res.is_Ok() ==> res.get_Ok_0().wf() && res.get_Ok_0().replicas().len()
+                == self.replicas_spec(),
+    {
+        if self.replicas == 0 || self.replicas > MAX_REPLICAS {
+            return Err(BuildError::Replicas(self.replicas));
+        }
+        if self.threads_per_replica == 0 || self.threads_per_replica > MAX_THREADS_PER_REPLICA {
+            return Err(BuildError::ThreadsPerReplica(self.threads_per_replica));
+        }
+        let replica_nodes = match self.replica_nodes {
+            Some(nodes) => {
+                if nodes.len() != self.replicas {
+                    return Err(BuildError::ReplicaNodes(nodes.len()));
+                }
+                nodes
+            },
+            None => default_replica_nodes(self.replicas),
+        };
+        let park_waiters = match self.delivery {
+            ResponseDelivery::Spin => false,
+            ResponseDelivery::Park => true,
+        };
+        let mut nr = NodeReplicated::new_with_config(
+            self.replicas,
+            self.threads_per_replica,
+            park_waiters,
+            self.affinity,
+            self.log_mem,
+            self.preempt,
+            self.wait,
+        );
+        nr.selection = self.selection;
+        nr.steal_lag = self.steal_lag;
+        nr.replica_nodes = replica_nodes;
+        nr.set_log_pressure(self.pressure);
+        Ok(nr)
+    }
+}
+
+} // verus!
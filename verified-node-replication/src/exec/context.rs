@@ -12,17 +12,19 @@ use vstd::{
     prelude::*,
 };
 
-use crate::Dispatch;
+use crate::{Dispatch, ParkWord, WaitFn};
 
 // constants
 use crate::constants::MAX_THREADS_PER_REPLICA;
 
 // spec import
 use crate::spec::flat_combiner::FlatCombiner;
+use crate::spec::types::ReqId;
 use crate::spec::unbounded_log::UnboundedLog;
 
 // exec imports
 use crate::exec::replica::{ReplicaId, ReplicaToken};
+use crate::exec::utils::{debug_check_slot, debug_check_slot_transition, debug_invariants_enabled};
 use crate::exec::CachePadded;
 use crate::exec::Replica;
 
@@ -37,6 +39,10 @@ pub type ThreadId = u32;
 
 /// the thread token identifies a thread of a given replica
 ///
+/// There is no `Drop` for the token: it doesn't reference its replica, so it can't hand itself
+/// back. A token that is dropped instead of passed to [`NodeReplicated::unregister`] retires its
+/// thread slot of the replica for good, which is safe but limits the threads that can register.
+///
 ///  - Dafny: linear datatype ThreadOwnedContext
 pub struct ThreadToken<DT: Dispatch> {
     /// the replica id this thread uses
@@ -98,6 +104,211 @@ impl<DT: Dispatch> ThreadToken<DT> {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Operation Handle
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// the handle of an update that was submitted but whose response hasn't been collected yet
+///
+/// The handle holds the thread token of the submitting thread in its pending state: the batch
+/// slot of the thread is owned by the combiner until the response is delivered. A thread has
+/// `MAX_PENDING_OPS` slots, so a thread token has at most one outstanding handle; threads
+/// pipeline updates by submitting with several thread tokens before collecting the responses.
+/// Until the combiner collects the update, it can be withdrawn with `NodeReplicated::cancel`.
+pub struct OpHandle<DT: Dispatch> {
+    /// the replica id the thread uses
+    pub(crate) rid: ReplicaToken,
+    /// identifies the thread within the replica
+    pub(crate) tid: ThreadId,
+    /// the request id of the submitted update
+    pub(crate) req_id: Ghost<ReqId>,
+    /// the response, once it has been collected from the context
+    pub(crate) response: Option<DT::Response>,
+    /// the flat combiner client, the batch permission and the update ticket of the thread
+    pub(crate) context_ghost: Tracked<FCClientRequestResponseGhost<DT>>,
+}
+
+impl<DT: Dispatch> OpHandle<DT> {
+    pub open spec fn wf(&self, replica: &Replica<DT>) -> bool {
+        &&& self.rid.wf(replica.spec_id() + 1)
+        &&& self.rid@ == replica.spec_id()
+        &&& (self.tid as nat) < MAX_THREADS_PER_REPLICA
+        &&& (self.tid as nat) < replica.contexts.len()
+        &&& self.response.is_None() ==> {
+            &&& self.context_ghost@.dequeue_resp_pre(
+                replica.contexts[self.tid as int].batch.0.id(),
+                self.tid as nat,
+                replica.flat_combiner_instance@,
+            )
+            &&& self.context_ghost@.fc_clients@.value.get_Waiting_0() == self.req_id@
+        }
+        &&& self.response.is_Some() ==> {
+            &&& self.context_ghost@.cell_id == replica.contexts[self.tid as int].batch.0.id()
+            &&& self.context_ghost@.batch_perms.is_Some()
+            &&& self.context_ghost@.batch_perms.get_Some_0()@.value.is_None()
+            &&& self.context_ghost@.batch_perms.get_Some_0()@.pcell
+                == self.context_ghost@.cell_id
+            &&& self.context_ghost@.local_updates.is_Some()
+            &&& self.context_ghost@.local_updates.get_Some_0()@.instance
+                == replica.unbounded_log_instance@
+            &&& self.context_ghost@.local_updates.get_Some_0()@.key == self.req_id@
+            &&& self.context_ghost@.local_updates.get_Some_0()@.value.is_Done()
+            &&& self.context_ghost@.local_updates.get_Some_0()@.value.get_Done_ret()
+                == self.response.get_Some_0()
+            &&& self.context_ghost@.fc_clients@.instance == replica.flat_combiner_instance@
+            &&& self.context_ghost@.fc_clients@.key == self.tid as nat
+            &&& self.context_ghost@.fc_clients@.value.is_Idle()
+        }
+    }
+
+    /// the request id of the submitted update
+    pub open spec fn req_id(&self) -> ReqId {
+        self.req_id@
+    }
+
+    pub open spec fn replica_id_spec(&self) -> nat {
+        self.rid.id_spec()
+    }
+
+    pub const fn replica_id(&self) -> (result: ReplicaId)
+        ensures
+            result as nat == self.replica_id_spec(),
+    {
+        self.rid.id()
+    }
+
+    /// returns whether the response of the update has been collected
+    ///
+    /// The response is only collected when the handle is polled, so this doesn't change
+    /// without a call to `NodeReplicated::poll`.
+    pub fn is_done(&self) -> (result: bool)
+        ensures
+            result == self.response.is_Some(),
+    {
+        self.response.is_some()
+    }
+
+    /// returns the response and the thread token if the response has been collected,
+    /// otherwise returns the handle unchanged.
+    pub fn take_response(self) -> (result: Result<
+        (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>),
+        OpHandle<DT>,
+    >)
+        ensures
+            self.response.is_Some() ==> {
+                &&& result.is_Ok()
+                &&& result.get_Ok_0().0 == self.response.get_Some_0()
+                &&& result.get_Ok_0().1.rid == self.rid
+                &&& result.get_Ok_0().1.tid == self.tid
+                &&& result.get_Ok_0().1.fc_client@ == self.context_ghost@.fc_clients
+                &&& result.get_Ok_0().1.batch_perm@
+                    == self.context_ghost@.batch_perms.get_Some_0()
+                &&& result.get_Ok_0().2@ == self.context_ghost@.local_updates.get_Some_0()
+            },
+            self.response.is_None() ==> result.is_Err() && result.get_Err_0() == self,
+    {
+        let OpHandle { rid, tid, req_id, response, context_ghost } = self;
+        match response {
+            Some(response) => {
+                let tracked FCClientRequestResponseGhost {
+                    batch_perms,
+                    cell_id,
+                    local_updates,
+                    fc_clients,
+                } = context_ghost.get();
+                let tracked ticket = local_updates.tracked_unwrap();
+                let tracked batch_perm = batch_perms.tracked_unwrap();
+                Ok(
+                    (
+                        response,
+                        ThreadToken {
+                            rid,
+                            tid,
+                            fc_client: Tracked(fc_clients),
+                            batch_perm: Tracked(batch_perm),
+                        },
+                        Tracked(ticket),
+                    ),
+                )
+            },
+            None => Err(OpHandle { rid, tid, req_id, response: None, context_ghost }),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Response Signal
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Wakes a thread that parked while waiting for its response.
+///
+/// Only used if the replica parks waiting threads (see [`ResponseDelivery::Park`]). A waiting
+/// thread first spins, then announces that it parks with `prepare_park`, checks its context for
+/// the response once more, and only then parks with the wait strategy of the replica. The
+/// combiner publishes the response before it notifies the thread, so either the thread sees the
+/// response in its last check, or the notification finds the thread announced and unparks it.
+#[verus::trusted]
+#[verifier::external_body]
+pub struct ResponseSignal {
+    state: ParkWord,
+}
+
+#[verus::trusted]
+impl ResponseSignal {
+    /// the number of rounds a thread spins before it parks
+    pub const SPIN_ITERATIONS: usize = 1 << 10;
+
+    /// the thread is not parked
+    const IDLE: u32 = 0;
+
+    /// the thread is about to park or parked
+    const PARKED: u32 = 1;
+
+    #[verifier::external_body]
+    pub fn new() -> Self {
+        ResponseSignal { state: ParkWord::new(Self::IDLE) }
+    }
+
+    /// announces that the thread parks, it must check for its response before calling `park`.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn prepare_park(&self) {
+        self.state.store(Self::PARKED);
+    }
+
+    /// withdraws the announcement, the thread found its response.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn cancel_park(&self) {
+        self.state.store(Self::IDLE);
+    }
+
+    /// parks the calling thread until it is notified, unless it was notified since
+    /// `prepare_park` already. The wait strategy bounds how long the thread stays parked.
+    #[verifier::external_body]
+    pub fn park(&self, wait: &WaitFn) {
+        wait.park(&self.state, Self::PARKED);
+        self.state.store(Self::IDLE);
+    }
+
+    /// wakes the thread if it announced that it parks, called after its response was published.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn notify(&self, wait: &WaitFn) {
+        if self.state.swap(Self::IDLE) == Self::PARKED {
+            wait.unpark(&self.state);
+        }
+    }
+}
+
+/// How the threads of a replica wait for the responses of their updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseDelivery {
+    /// spin on the context with the wait strategy until the response is there
+    Spin,
+    /// spin for a while, then park until the combiner delivers the response, for deployments
+    /// with more threads than cores where spinning threads take cycles from the combiner
+    Park,
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Pending Operation
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -154,7 +365,8 @@ pub struct Context<DT: Dispatch> {
     ///  - Rust:  pub(crate) batch: [CachePadded<PendingOperation<T, R, M>>; MAX_PENDING_OPS],
     pub/*REVIEW: (crate)*/ batch: CachePadded<PCell<PendingOperation<DT>>>,
 
-    /// The number of operations in this context, (just 0 or 1)
+    /// The state of the operation in this context: 0 if there is none or it has its response,
+    /// 1 if it is pending, and 2 if the combiner has collected it
     ///
     ///  - Dafny: linear atomic: CachePadded<Atomic<uint64, ContextGhost>>,
     ///  - Rust:  N/A
@@ -163,6 +375,9 @@ pub struct Context<DT: Dispatch> {
     /// ghost: identifier of the thread
     pub thread_id_g: Ghost<nat>,
 
+    /// wakes the thread once its response was delivered, if the replica parks waiting threads
+    pub signal: ResponseSignal,
+
     pub flat_combiner_instance: Tracked<FlatCombiner::Instance>,
     pub unbounded_log_instance: Tracked<UnboundedLog::Instance<DT>>,
 }
@@ -221,6 +436,7 @@ impl<DT: Dispatch> Context<DT> {
                 batch,
                 atomic,
                 thread_id_g: Ghost(thread_id_g),
+                signal: ResponseSignal::new(),
                 flat_combiner_instance,
                 unbounded_log_instance,
             },
@@ -262,6 +478,14 @@ impl<DT: Dispatch> Context<DT> {
         let tracked local_updates = local_updates.tracked_unwrap();
         // put the operation there, updates the permissions so we can store them in the GhostContext
         self.batch.0.put(Tracked(&mut batch_perms), PendingOperation::new(op));
+        if debug_invariants_enabled() {
+            let prev = atomic_with_ghost!(
+                &self.atomic.0 => load();
+                returning prev;
+                ghost g => { }
+            );
+            debug_check_slot_transition(prev, 1);
+        }
         let tracked send_request_result;
         let res =
             atomic_with_ghost!(
@@ -337,6 +561,9 @@ impl<DT: Dispatch> Context<DT> {
                 }
             }
         );
+        if debug_invariants_enabled() {
+            debug_check_slot(res);
+        }
         if res == 0 {
             let tracked mut batch_perms = batch_perms.tracked_unwrap();
             let op = self.batch.0.take(Tracked(&mut batch_perms));
@@ -359,6 +586,75 @@ impl<DT: Dispatch> Context<DT> {
         }
     }
 
+    /// Withdraws the pending operation if the combiner hasn't collected it yet. Returns whether
+    /// the operation was withdrawn.
+    ///
+    /// this is invoked by the thread that has enqueued the operation before
+    pub fn withdraw_op(
+        &self,
+        context_ghost: Tracked<FCClientRequestResponseGhost<DT>>,
+    ) -> (res: (bool, Tracked<FCClientRequestResponseGhost<DT>>))
+        requires
+            context_ghost@.dequeue_resp_pre(
+                self.batch.0.id(),
+                self.thread_id_g@,
+                self.flat_combiner_instance@,
+            ),
+            self.wf(self.thread_id_g@),
+        ensures
+            res.1@.withdraw_op_post(context_ghost@, res.0, self.unbounded_log_instance@),
+            self.wf(self.thread_id_g@),
+    {
+        let tracked FCClientRequestResponseGhost {
+            batch_perms: mut batch_perms,
+            cell_id,
+            local_updates: mut local_updates,
+            fc_clients: mut fc_clients,
+        } = context_ghost.get();
+        let tracked withdraw_request_result;
+        let res =
+            atomic_with_ghost!(
+            &self.atomic.0 => compare_exchange(1, 0);
+            update prev->next;
+            ghost g => {
+                if prev == 1 {
+                    batch_perms = g.batch_perms;
+                    local_updates = g.update;
+
+                    let tid = fc_clients.view().key;
+                    let rid = fc_clients.view().value.get_Waiting_0();
+                    self.flat_combiner_instance.borrow().pre_recv_response(tid, &fc_clients, &g.slots);
+                    withdraw_request_result = self.flat_combiner_instance.borrow().withdraw_request(tid, rid, fc_clients, g.slots);
+                    fc_clients = withdraw_request_result.0.get();
+
+                    g.slots = withdraw_request_result.1.get();
+                    g.batch_perms = None;
+                    g.update = None;
+                }
+            }
+        );
+        if let Result::Ok(_) = res {
+            // take the operation out of the batch, the slot is empty again
+            let tracked mut batch_perms = batch_perms.tracked_unwrap();
+            let _op = self.batch.0.take(Tracked(&mut batch_perms));
+            let tracked new_context_ghost = FCClientRequestResponseGhost {
+                batch_perms: Some(batch_perms),
+                cell_id,
+                local_updates,
+                fc_clients,
+            };
+            (true, Tracked(new_context_ghost))
+        } else {
+            let tracked new_context_ghost = FCClientRequestResponseGhost {
+                batch_perms,
+                cell_id,
+                local_updates,
+                fc_clients,
+            };
+            (false, Tracked(new_context_ghost))
+        }
+    }
+
     // /// Enqueues a response onto this context. This is invoked by the combiner
     // /// after it has executed operations (obtained through a call to ops()) against the
     // /// replica this thread is registered against.
@@ -439,9 +735,10 @@ pub open spec fn inv(&self, v: u64, tid: nat, cell: PCell<PendingOperation<DT>>,
         &&& self.slots@.key == tid
         &&& self.slots@.instance == fc
 
-        &&& ((v == 0) || (v == 1))
+        &&& ((v == 0) || (v == 1) || (v == 2))
         &&& (v == 0 ==> self.slots@.value.is_Empty() || self.slots@.value.is_Response())
-        &&& (v == 1 ==> self.slots@.value.is_Request() || self.slots@.value.is_InProgress())
+        &&& (v == 1 ==> self.slots@.value.is_Request())
+        &&& (v == 2 ==> self.slots@.value.is_InProgress())
 
         &&& (self.slots@.value.is_Empty() ==> {
             &&& self.update.is_None()
@@ -539,6 +836,30 @@ impl<DT: Dispatch> FCClientRequestResponseGhost<DT> {
         &&& self.cell_id == batch_cell
     }
 
+    pub open spec fn withdraw_op_post(
+        &self,
+        pre: FCClientRequestResponseGhost<DT>,
+        withdrawn: bool,
+        inst: UnboundedLog::Instance<DT>,
+    ) -> bool {
+        &&& withdrawn ==> {
+            &&& self.cell_id == pre.cell_id
+            &&& self.batch_perms.is_Some()
+            &&& self.batch_perms.get_Some_0()@.value.is_None()
+            &&& self.batch_perms.get_Some_0()@.pcell == self.cell_id
+            &&& self.local_updates.is_Some()
+            &&& self.local_updates.get_Some_0()@.instance == inst
+            &&& self.local_updates.get_Some_0()@.value.is_Init()
+            &&& self.local_updates.get_Some_0()@.key == pre.fc_clients@.value.get_Waiting_0()
+            &&& self.fc_clients@.instance == pre.fc_clients@.instance
+            &&& self.fc_clients@.key == pre.fc_clients@.key
+            &&& self.fc_clients@.value.is_Idle()
+        }
+        &&& !withdrawn ==> {
+            &&& self == pre
+        }
+    }
+
     pub open spec fn dequeue_resp_post(
         &self,
         pre: FCClientRequestResponseGhost<DT>,
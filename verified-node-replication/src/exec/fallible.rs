@@ -0,0 +1,80 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+// Fallible Operations
+#[allow(unused_imports)]
+use builtin::*;
+use builtin_macros::*;
+
+use vstd::prelude::*;
+
+use crate::{Dispatch, FallibleDispatch};
+
+verus! {
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Fallible Adapter
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Adapter that replicates a [`FallibleDispatch`] data structure.
+///
+/// Write operations respond with `Ok(response)` or `Err(error)`, read-only operations always
+/// respond with `Ok(response)`. The error is part of the response, so the specs of the log and
+/// the linearization treat it as the outcome of the operation.
+pub struct Fallible<DT: FallibleDispatch> {
+    /// the wrapped data structure
+    pub inner: DT,
+}
+
+impl<DT: FallibleDispatch> Dispatch for Fallible<DT> {
+    type ReadOperation = DT::ReadOperation;
+
+    type WriteOperation = DT::WriteOperation;
+
+    type Response = Result<DT::Response, DT::Error>;
+
+    type View = DT::View;
+
+    open spec fn view(&self) -> Self::View {
+        self.inner@
+    }
+
+    fn init() -> (res: Self) {
+        Fallible { inner: DT::init() }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> (res: Self::WriteOperation) {
+        DT::clone_write_op(op)
+    }
+
+    fn clone_response(op: &Self::Response) -> (res: Self::Response) {
+        match op {
+            Ok(resp) => Ok(DT::clone_response(resp)),
+            Err(err) => Err(DT::clone_error(err)),
+        }
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> (result: Self::Response) {
+        Ok(self.inner.dispatch(op))
+    }
+
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> (result: Self::Response) {
+        self.inner.try_dispatch_mut(op)
+    }
+
+    open spec fn init_spec() -> Self::View {
+        DT::init_spec()
+    }
+
+    open spec fn dispatch_spec(ds: Self::View, op: Self::ReadOperation) -> Self::Response {
+        Ok(DT::dispatch_spec(ds, op))
+    }
+
+    open spec fn dispatch_mut_spec(ds: Self::View, op: Self::WriteOperation) -> (
+        Self::View,
+        Self::Response,
+    ) {
+        DT::try_dispatch_mut_spec(ds, op)
+    }
+}
+
+} // verus!
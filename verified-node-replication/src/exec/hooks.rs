@@ -0,0 +1,388 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! Runtime hooks of the replicated data structure: the wrappers the replicas and the log call
+//! the hooks of the application through, and the default implementations of the
+//! [`PreemptGuard`] and [`WaitStrategy`] traits. They are policy, not specification: nothing in
+//! here is visible to the proofs, the hooks only decide how threads wait and what is reported.
+#[allow(unused_imports)]
+use builtin::*;
+use builtin_macros::*;
+
+use vstd::prelude::*;
+
+use crate::{PreemptGuard, ReplicaId, WaitStrategy};
+
+verus! {
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Log Memory and Log Pressure
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Log Memory Function
+///
+/// This structure is a wrapper around a function that is called with the memory region
+/// (start address and length in bytes) of the cyclic buffer after it has been allocated and
+/// before it is initialized, e.g., to back the log with huge pages. Failures must be handled by
+/// the function itself, the log works with any backing memory.
+///
+#[verifier::external_body]
+#[verus::trusted]
+pub struct LogMemFn {
+    f: Option<Box<dyn Fn(*const u8, usize)>>,
+}
+
+#[verus::trusted]
+impl LogMemFn {
+    /// creates a new LogMemFn object that points to the given function.
+    #[verifier::external_body]
+    pub fn new(f: impl Fn(*const u8, usize) + 'static) -> Self {
+        Self { f: Some(Box::new(f)) }
+    }
+
+    /// creates a new LogMemFn object that leaves the memory of the log untouched.
+    #[verifier::external_body]
+    pub fn none() -> Self {
+        Self { f: None }
+    }
+
+    /// calls the function with the allocated memory region of the given buffer.
+    #[verifier::external_body]
+    pub fn call<T>(&self, buf: &Vec<T>) {
+        if let Some(f) = &self.f {
+            f(buf.as_ptr() as *const u8, buf.capacity() * core::mem::size_of::<T>())
+        }
+    }
+}
+
+/// Log Pressure Event
+///
+/// Reported to the [`LogPressureFn`] of a replicated data structure.
+#[verus::trusted]
+#[verifier::external_body]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogPressure {
+    /// the occupancy of the log crossed the watermark (in percent of the log entries), upwards
+    /// if `rising` is set, downwards otherwise
+    Watermark { percent: usize, rising: bool },
+    /// the replica is the laggard that keeps the log from being reused, appenders are waiting
+    /// for it to apply the entries from `head` onwards
+    Laggard { replica: ReplicaId, local_version: u64, head: u64 },
+}
+
+/// Log Pressure Function
+///
+/// This structure is a wrapper around a function that is called when the occupancy of the log
+/// (the entries between the head and the tail) crosses one of the watermarks, and when an
+/// appender has waited for a lagging replica for `LAGGARD_THRESHOLD` rounds. The application can
+/// throttle writers or sync the lagging replica before the appenders stall.
+///
+/// The function is called by the thread that appends, it must not execute operations on the
+/// data structure. Every crossing is reported once, concurrent appenders may report crossings
+/// out of order.
+///
+#[verifier::external_body]
+#[verus::trusted]
+pub struct LogPressureFn {
+    f: Option<Box<dyn Fn(LogPressure) + Send + Sync>>,
+    watermarks: Vec<usize>,
+    level: std::sync::atomic::AtomicUsize,
+}
+
+#[verus::trusted]
+impl LogPressureFn {
+    /// creates a new LogPressureFn object that calls `f` at the watermarks, given in percent of
+    /// the log entries.
+    #[verifier::external_body]
+    pub fn new(watermarks: &[usize], f: impl Fn(LogPressure) + Send + Sync + 'static) -> Self {
+        let mut watermarks: Vec<usize> = watermarks.iter().map(|w| (*w).clamp(1, 100)).collect();
+        watermarks.sort_unstable();
+        watermarks.dedup();
+        Self { f: Some(Box::new(f)), watermarks, level: std::sync::atomic::AtomicUsize::new(0) }
+    }
+
+    /// creates a new LogPressureFn object that reports nothing.
+    #[verifier::external_body]
+    pub fn none() -> Self {
+        Self { f: None, watermarks: Vec::new(), level: std::sync::atomic::AtomicUsize::new(0) }
+    }
+
+    /// reports the watermarks crossed since the last call, given the tail and the head of a log
+    /// with `log_size` entries.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn occupancy(&self, tail: u64, head: u64, log_size: usize) {
+        let f = match &self.f {
+            Some(f) if !self.watermarks.is_empty() => f,
+            _ => return,
+        };
+        let percent = (tail.saturating_sub(head) * 100 / log_size as u64) as usize;
+        let level = self.watermarks.iter().take_while(|w| **w <= percent).count();
+        let prev = self.level.swap(level, std::sync::atomic::Ordering::Relaxed);
+        if prev < level {
+            for w in &self.watermarks[prev..level] {
+                f(LogPressure::Watermark { percent: *w, rising: true });
+            }
+        } else if prev > level {
+            for w in self.watermarks[level..prev].iter().rev() {
+                f(LogPressure::Watermark { percent: *w, rising: false });
+            }
+        }
+    }
+
+    /// reports the replica that keeps the head of the log at `head`.
+    #[verifier::external_body]
+    pub fn laggard(&self, replica: ReplicaId, local_version: u64, head: u64) {
+        if let Some(f) = &self.f {
+            f(LogPressure::Laggard { replica, local_version, head })
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Preemption
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The default guard for user-space, does not disable preemption.
+#[verus::trusted]
+pub struct NoPreemptGuard;
+
+#[verus::trusted]
+impl PreemptGuard for NoPreemptGuard {
+    #[verifier::external_body]
+    fn disable() -> usize {
+        0
+    }
+
+    #[verifier::external_body]
+    fn restore(_state: usize) {
+    }
+}
+
+/// Preemption Function
+///
+/// This structure is a wrapper around the [`PreemptGuard`] of a replicated data structure,
+/// called by the replicas when acquiring and releasing the combiner lock.
+///
+#[verifier::external_body]
+#[verus::trusted]
+pub struct PreemptFn {
+    disable: fn() -> usize,
+    restore: fn(usize),
+}
+
+#[verus::trusted]
+impl PreemptFn {
+    /// creates a new PreemptFn object that points to the functions of the given guard.
+    #[verifier::external_body]
+    pub fn new<G: PreemptGuard>() -> Self {
+        Self { disable: G::disable, restore: G::restore }
+    }
+
+    /// creates a new PreemptFn object that does not disable preemption.
+    #[verifier::external_body]
+    pub fn none() -> Self {
+        Self::new::<NoPreemptGuard>()
+    }
+
+    /// creates a copy that calls the functions of the same guard.
+    #[verifier::external_body]
+    pub fn clone(&self) -> Self {
+        Self { disable: self.disable, restore: self.restore }
+    }
+
+    /// disables preemption, returns the previous state.
+    #[verifier::external_body]
+    pub fn disable(&self) -> usize {
+        (self.disable)()
+    }
+
+    /// restores the preemption state.
+    #[verifier::external_body]
+    pub fn restore(&self, state: usize) {
+        (self.restore)(state)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Waiting
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A word threads park on until another thread changes it, see [`WaitStrategy::park`].
+#[verus::trusted]
+#[verifier::external_body]
+pub struct ParkWord {
+    word: core::sync::atomic::AtomicU32,
+}
+
+#[verus::trusted]
+impl ParkWord {
+    #[verifier::external_body]
+    pub const fn new(val: u32) -> Self {
+        ParkWord { word: core::sync::atomic::AtomicU32::new(val) }
+    }
+
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn load(&self) -> u32 {
+        self.word.load(core::sync::atomic::Ordering::SeqCst)
+    }
+
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn store(&self, val: u32) {
+        self.word.store(val, core::sync::atomic::Ordering::SeqCst)
+    }
+
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn swap(&self, val: u32) -> u32 {
+        self.word.swap(val, core::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// the address of the word, e.g., to find the queue of the threads parked on it
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn addr(&self) -> usize {
+        &self.word as *const core::sync::atomic::AtomicU32 as usize
+    }
+}
+
+/// The default strategy, spins for a while and then yields to the operating system.
+///
+/// Yielding lets a preempted combiner run again if there are more threads than cores.
+#[verus::trusted]
+pub struct StdWait;
+
+#[verus::trusted]
+impl StdWait {
+    /// the number of rounds that spin before the thread starts to yield
+    pub const SPIN_ITERATIONS: usize = 1 << 12;
+
+    /// the longest time a thread stays parked without being unparked
+    pub const PARK_TIMEOUT: std::time::Duration = std::time::Duration::from_micros(100);
+}
+
+/// The queues of the threads parked by [`StdWait`], a word is hashed to one of them by its address.
+#[verus::trusted]
+#[verifier::external]
+static STD_PARK_QUEUES: [(std::sync::Mutex<()>, std::sync::Condvar); 64] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const QUEUE: (std::sync::Mutex<()>, std::sync::Condvar) =
+        (std::sync::Mutex::new(()), std::sync::Condvar::new());
+    [QUEUE; 64]
+};
+
+#[verus::trusted]
+#[verifier::external]
+fn std_park_queue(word: &ParkWord) -> &'static (std::sync::Mutex<()>, std::sync::Condvar) {
+    &STD_PARK_QUEUES[(word.addr() >> 6) % STD_PARK_QUEUES.len()]
+}
+
+#[verus::trusted]
+impl WaitStrategy for StdWait {
+    #[verifier::external_body]
+    #[inline(always)]
+    fn wait(iteration: usize) {
+        if iteration < Self::SPIN_ITERATIONS {
+            core::hint::spin_loop();
+        } else {
+            std::thread::yield_now();
+        }
+    }
+
+    /// Checks the word while holding the lock of its queue, an `unpark` after the word changed
+    /// takes the same lock and therefore either comes before the check or finds the thread
+    /// waiting on the queue.
+    #[verifier::external_body]
+    fn park(word: &ParkWord, expected: u32) {
+        let (lock, queue) = std_park_queue(word);
+        let guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        if word.load() == expected {
+            let _ = queue.wait_timeout(guard, Self::PARK_TIMEOUT);
+        }
+    }
+
+    #[verifier::external_body]
+    fn unpark(word: &ParkWord) {
+        let (lock, queue) = std_park_queue(word);
+        drop(lock.lock().unwrap_or_else(|e| e.into_inner()));
+        queue.notify_all();
+    }
+}
+
+/// A strategy that only spins and uses nothing but `core`, e.g., for kernels.
+#[verus::trusted]
+pub struct SpinWait;
+
+#[verus::trusted]
+impl WaitStrategy for SpinWait {
+    #[verifier::external_body]
+    #[inline(always)]
+    fn wait(_iteration: usize) {
+        core::hint::spin_loop();
+    }
+
+    /// never parks, the thread keeps spinning for its response
+    #[verifier::external_body]
+    #[inline(always)]
+    fn park(_word: &ParkWord, _expected: u32) {
+        core::hint::spin_loop();
+    }
+
+    #[verifier::external_body]
+    #[inline(always)]
+    fn unpark(_word: &ParkWord) {}
+}
+
+/// Wait Function
+///
+/// This structure is a wrapper around the [`WaitStrategy`] of a replicated data structure,
+/// called by the replicas and the log in every round of a busy loop.
+///
+#[verifier::external_body]
+#[verus::trusted]
+pub struct WaitFn {
+    wait: fn(usize),
+    park: fn(&ParkWord, u32),
+    unpark: fn(&ParkWord),
+}
+
+#[verus::trusted]
+impl WaitFn {
+    /// creates a new WaitFn object that points to the function of the given strategy.
+    #[verifier::external_body]
+    pub fn new<W: WaitStrategy>() -> Self {
+        Self { wait: W::wait, park: W::park, unpark: W::unpark }
+    }
+
+    /// creates a new WaitFn object with the default strategy.
+    #[verifier::external_body]
+    pub fn std() -> Self {
+        Self::new::<StdWait>()
+    }
+
+    /// creates a copy that calls the same strategy.
+    #[verifier::external_body]
+    pub fn clone(&self) -> Self {
+        Self { wait: self.wait, park: self.park, unpark: self.unpark }
+    }
+
+    /// waits for one round, `iteration` rounds have been waited so far.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn call(&self, iteration: usize) {
+        (self.wait)(iteration)
+    }
+
+    /// parks the calling thread while `word` holds `expected`, see [`WaitStrategy::park`].
+    #[verifier::external_body]
+    pub fn park(&self, word: &ParkWord, expected: u32) {
+        (self.park)(word, expected)
+    }
+
+    /// wakes the threads parked on `word`, see [`WaitStrategy::unpark`].
+    #[verifier::external_body]
+    pub fn unpark(&self, word: &ParkWord) {
+        (self.unpark)(word)
+    }
+}
+
+} // verus!
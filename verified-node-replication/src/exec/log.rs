@@ -15,13 +15,21 @@ use crate::spec::cyclicbuffer::{log_entry_alive_value, log_entry_idx, stored_typ
 use crate::spec::cyclicbuffer::{CyclicBuffer, LogicalLogIdx, StoredType};
 use crate::spec::types::{ConcreteLogEntry, LogIdx, NodeId, ReqId};
 use crate::spec::unbounded_log::UnboundedLog;
-use crate::Dispatch;
+use crate::{Dispatch, LogMemFn, LogPressureFn, WaitFn};
 
 use crate::constants::{
-    GC_FROM_HEAD, LOG_SIZE, MAX_IDX, MAX_REPLICAS, MAX_REQUESTS, WARN_THRESHOLD,
+    GC_FROM_HEAD, LAGGARD_THRESHOLD, LOG_SIZE, MAX_CONTENTION, MAX_IDX, MAX_REPLICAS,
+    MAX_REQUESTS, WARN_THRESHOLD,
 };
+use crate::exec::metrics::LogMetrics;
 use crate::exec::replica::{ReplicaId, ReplicaToken};
+use crate::exec::trace::{trace_advance_head, trace_append, trace_apply};
+use crate::exec::utils::{
+    debug_check_alive_bit_flip, debug_check_log_entry, debug_check_versions,
+    debug_invariants_enabled, DebugAppliedCounters,
+};
 use crate::exec::CachePadded;
+use crate::trusted::assume_operation_count_bounded;
 
 verus! {
 
@@ -30,17 +38,11 @@ verus! {
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 #[verus::line_count::ignore]
 #[verus::trusted]
-#[verifier(external_body)]  /* vattr */
+#[verifier::external_body]
 pub fn print_starvation_warning(line: u32) {
     eprintln!("WARNING({line}): has been looping for `WARN_THRESHOLD` iterations. Are we starving?");
 }
 
-#[verus::trusted]
-#[verifier(external_body)]  /* vattr */
-pub fn warn_with_tail_too_big() {
-    eprintln!("WARNING: Tail value exceeds the maximum value of u64.");
-}
-
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Log Entries
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -90,6 +92,19 @@ pub open spec fn wf(&self, idx: nat, cb_inst: CyclicBuffer::Instance<DT>) -> boo
     }
 }
 }  // struct_with_invariants
+
+/// Drops the operation stored in the entry when the log is deallocated. The cell is initialized
+/// when the log is created and its content is only ever replaced, so it can always be taken.
+#[verifier::external]
+impl<DT: Dispatch> Drop for BufferEntry<DT> {
+    fn drop(&mut self) {
+        // The permission to the cell lives in the ghost contents of the cyclic buffer, or with a
+        // reader or appender that holds it temporarily. Any of them borrows the log, and
+        // `&mut self` means no such borrow is alive and none can be created anymore: the
+        // permission is dead, and the one conjured here is the only one that is ever used again.
+        let _ = self.log_entry.take(Tracked::assume_new());
+    }
+}
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // NR Log
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -146,6 +161,18 @@ pub struct NrLog<DT: Dispatch>
     pub num_replicas: Ghost<nat>,
     pub unbounded_log_instance: Tracked<UnboundedLog::Instance<DT>>,
     pub cyclic_buffer_instance: Tracked<CyclicBuffer::Instance<DT>>,
+
+    /// Statistics of the appends, only recorded with the `metrics` feature.
+    pub metrics: LogMetrics,
+
+    /// The applied counters of the replicas, only checked with the `debug-invariants` feature.
+    pub debug_applied: DebugAppliedCounters,
+
+    /// How to wait for lagging replicas and for entries to become alive.
+    pub wait: WaitFn,
+
+    /// Notified about the occupancy of the log and about replicas that block its reuse.
+    pub pressure: LogPressureFn,
 }
 
 pub open spec fn wf(&self) -> bool {
@@ -207,8 +234,8 @@ pub open spec fn wf(&self) -> bool {
 
 
 impl<DT: Dispatch> NrLog<DT> {
-    /// initializes the NrLOg
-    pub fn new(num_replicas: usize, log_size: usize) -> (res: (
+    /// initializes the NrLOg, `log_mem` is called with the memory of the cyclic buffer
+    pub fn new(num_replicas: usize, log_size: usize, log_mem: &LogMemFn, wait: WaitFn) -> (res: (
         Self,
         Vec<ReplicaToken>,
         Tracked<NrLogTokens<DT>>,
@@ -234,6 +261,7 @@ impl<DT: Dispatch> NrLog<DT> {
         let tracked mut ul_local_versions: Map<NodeId, UnboundedLog::local_versions<DT>>;
         let tracked ul_version_upper_bound: UnboundedLog::version_upper_bound<DT>;
         let tracked ul_combiner: Map<NodeId, UnboundedLog::combiner<DT>>;
+        let tracked ul_applied: Map<NodeId, UnboundedLog::applied<DT>>;
         proof {
             let tracked (
                 Tracked(unbounded_log_instance0),  // Tracked<Instance>,
@@ -245,6 +273,7 @@ impl<DT: Dispatch> NrLog<DT> {
                 _,  //Tracked(ul_local_reads0), //Tracked<Map<ReqId,local_reads>>,
                 _,  //Tracked(ul_local_updates0), //Tracked<Map<ReqId,local_updates>>,
                 Tracked(ul_combiner0),  //Tracked<Map<NodeId,combiner>>
+                Tracked(ul_applied0),  //Tracked<Map<NodeId,applied>>
             ) = UnboundedLog::Instance::initialize(num_replicas as nat);
             unbounded_log_instance = unbounded_log_instance0;
             ul_log = ul_log0;
@@ -253,6 +282,7 @@ impl<DT: Dispatch> NrLog<DT> {
             ul_local_versions = ul_local_versions0;
             ul_version_upper_bound = ul_version_upper_bound0;
             ul_combiner = ul_combiner0;
+            ul_applied = ul_applied0;
         }
         //
         // initialize the log cells
@@ -347,6 +377,7 @@ impl<DT: Dispatch> NrLog<DT> {
         // build up the actual log
         //
         let mut slog: Vec<BufferEntry<DT>> = Vec::with_capacity(log_size);
+        log_mem.call(&slog);
         let mut log_idx = 0;
         while log_idx < log_size
             invariant
@@ -494,6 +525,7 @@ impl<DT: Dispatch> NrLog<DT> {
             num_replicas: num_replicas_ghost,
             replicas: ul_replicas,
             combiners: ul_combiner,
+            applied: ul_applied,
             cb_combiners,
             unbounded_log_instance: unbounded_log_instance.clone(),
             cyclic_buffer_instance: cyclic_buffer_instance.clone(),
@@ -507,6 +539,10 @@ impl<DT: Dispatch> NrLog<DT> {
             num_replicas: Ghost(num_replicas as nat),
             unbounded_log_instance: Tracked(unbounded_log_instance),
             cyclic_buffer_instance: Tracked(cyclic_buffer_instance),
+            metrics: LogMetrics::new(),
+            debug_applied: DebugAppliedCounters::new(),
+            wait,
+            pressure: LogPressureFn::none(),
         };
         (log, replica_tokens, Tracked(config))
     }
@@ -564,6 +600,7 @@ impl<DT: Dispatch> NrLog<DT> {
             local_reads@@.instance == self.unbounded_log_instance@,
             local_reads@@.value.is_Init(),
         ensures
+            ret.0 <= MAX_IDX,
             ret.1@@.value.is_VersionUpperBound(),
             ret.1@@.value.get_VersionUpperBound_version_upper_bound() == ret.0 as nat,
             ret.1@@.value.get_VersionUpperBound_op() == local_reads@@.value.get_Init_op(),
@@ -585,6 +622,110 @@ impl<DT: Dispatch> NrLog<DT> {
         (res, Tracked(new_local_reads_g))
     }
 
+    /// This method returns the current version upper bound value for the log, without
+    /// advancing a read request.
+    pub(crate) fn get_version_upper_bound_value(&self) -> (ret: u64)
+        requires
+            self.wf(),
+        ensures
+            ret <= MAX_IDX,
+    {
+        atomic_with_ghost!(
+            &self.version_upper_bound.0 => load();
+            returning res;
+            ghost g => { }
+        )
+    }
+
+    /// This method returns the current tail of the log.
+    pub(crate) fn get_tail(&self) -> (ret: u64)
+        requires
+            self.wf(),
+        ensures
+            ret <= MAX_IDX,
+    {
+        atomic_with_ghost!(
+            &self.tail.0 => load();
+            returning res;
+            ghost g => { }
+        )
+    }
+
+    /// Whether an appender would have to wait for the garbage collection of the log.
+    ///
+    /// This is the condition on which `append` waits for the head to advance. The tail and the
+    /// head are loaded one after the other, so the answer may already be outdated.
+    pub(crate) fn is_full(&self) -> (result: bool)
+        requires
+            self.wf(),
+    {
+        let tail = self.get_tail();
+        let head =
+            atomic_with_ghost!(
+            &self.head.0 => load();
+            returning head;
+            ghost g => { }
+        );
+        // the head may have moved past the loaded tail in the mean time
+        tail > head && tail - head > (self.slog.len() as u64 - GC_FROM_HEAD as u64)
+    }
+
+    /// This method returns the version of the given replica.
+    pub(crate) fn get_local_version(&self, node_id: ReplicaId) -> (ret: u64)
+        requires
+            self.wf(),
+            node_id < self.local_versions.len(),
+        ensures
+            ret <= MAX_IDX,
+    {
+        atomic_with_ghost!(
+            &self.local_versions[node_id as usize].0 => load();
+            returning res;
+            ghost g => { }
+        )
+    }
+
+    /// Returns the replica with the smallest version and its version.
+    pub(crate) fn find_laggard(&self) -> (result: (ReplicaId, u64))
+        requires
+            self.wf(),
+    {
+        let num_replicas = self.local_versions.len();
+        let mut laggard = 0;
+        let mut min_local_version = self.get_local_version(0);
+        let mut idx = 1;
+        while idx < num_replicas
+            invariant
+                self.wf(),
+                num_replicas == self.local_versions.len(),
+                1 <= idx <= num_replicas,
+        {
+            let local_version = self.get_local_version(idx);
+            if local_version < min_local_version {
+                laggard = idx;
+                min_local_version = local_version;
+            }
+            idx = idx + 1;
+        }
+        (laggard, min_local_version)
+    }
+
+    /// Checks `local_version <= version_upper_bound <= tail` for the given replica at runtime,
+    /// with the `debug-invariants` feature.
+    pub(crate) fn debug_check_versions(&self, node_id: ReplicaId)
+        requires
+            self.wf(),
+            node_id < self.local_versions.len(),
+    {
+        if debug_invariants_enabled() {
+            // load in this order, all three only increase
+            let local_version = self.get_local_version(node_id);
+            let version_upper_bound = self.get_version_upper_bound_value();
+            let tail = self.get_tail();
+            debug_check_versions(local_version, version_upper_bound, tail);
+        }
+    }
+
     /// checks whether the version of the local replica has advanced enough to perform read operations
     ///
     /// This basically corresponds to the transition `readonly_read_to_read` in the unbounded log.
@@ -692,7 +833,8 @@ impl<DT: Dispatch> NrLog<DT> {
         res
     }
 
-    /// Inserts a slice of operations into the log.
+    /// Inserts a slice of operations into the log. Returns the number of failed attempts to
+    /// reserve the entries with a compare-and-swap of the tail, up to `MAX_CONTENTION`.
     #[inline(always)]
     pub fn append(
         &self,
@@ -703,7 +845,7 @@ impl<DT: Dispatch> NrLog<DT> {
         actual_replica: &mut DT,
         // here we also need to pass the mut replica
         ghost_data: Tracked<NrLogAppendExecDataGhost<DT>>,
-    ) -> (result: Tracked<NrLogAppendExecDataGhost<DT>>)
+    ) -> (result: (u64, Tracked<NrLogAppendExecDataGhost<DT>>))
         requires
             self.wf(),
             replica_token@ < self.local_versions.len(),
@@ -717,7 +859,8 @@ impl<DT: Dispatch> NrLog<DT> {
             ),
             operations.len() <= MAX_REQUESTS,
         ensures
-            result@.append_post(
+            result.0 <= MAX_CONTENTION,
+            result.1@.append_post(
                 ghost_data@,
                 replica_token@,
                 actual_replica.view(),
@@ -732,9 +875,12 @@ impl<DT: Dispatch> NrLog<DT> {
         let nops = operations.len();
         let mut iteration = 1;
         let mut waitgc = 1;
+        // the failed compare-and-swaps of the tail, a measure of the contention on the log
+        let mut cas_failures: u64 = 0;
         loop
             invariant
                 self.wf(),
+                cas_failures <= MAX_CONTENTION,
                 0 <= waitgc <= WARN_THRESHOLD,
                 0 <= iteration <= WARN_THRESHOLD,
                 responses.len() == 0,
@@ -756,6 +902,7 @@ impl<DT: Dispatch> NrLog<DT> {
             let tracked NrLogAppendExecDataGhost {
                 local_updates,
                 ghost_replica,
+                applied,
                 combiner,
                 cb_combiner,
                 request_ids,
@@ -785,6 +932,8 @@ impl<DT: Dispatch> NrLog<DT> {
                         .advance_tail_start(nid as nat, &g, cb_combiner);
                 }
             );
+            // report the watermarks of the log occupancy the tail has crossed
+            self.pressure.occupancy(tail, head, self.slog.len());
             // If there are fewer than `GC_FROM_HEAD` entries on the log, then just
             // try again. The replica that reserved entry (h + self.slog.len() - GC_FROM_HEAD)
             // is currently trying to advance the head of the log. Keep refreshing the
@@ -807,6 +956,7 @@ impl<DT: Dispatch> NrLog<DT> {
                 let tracked ghost_data0 = NrLogAppendExecDataGhost {
                     local_updates: Tracked(local_updates),
                     ghost_replica,
+                    applied,
                     combiner: Tracked(combiner),
                     cb_combiner: Tracked(cb_combiner),
                     request_ids: Ghost(Seq::empty()),
@@ -823,6 +973,7 @@ impl<DT: Dispatch> NrLog<DT> {
                     NrLogAppendExecDataGhost {
                         local_updates: ghost_data0.local_updates,
                         ghost_replica: ghost_data0.ghost_replica,
+                        applied: ghost_data0.applied,
                         combiner: ghost_data0.combiner,
                         cb_combiner: ghost_data0.cb_combiner,
                         request_ids,
@@ -830,20 +981,12 @@ impl<DT: Dispatch> NrLog<DT> {
                 }
                 // upstream has an advance_head here, but dafny doesn't
                 // let ghost_data0 = self.advance_head(replica_token, responses, actual_replica, ghost_data0);
+                self.wait.call(waitgc);
                 continue ;
             }
             let new_tail = tail + (nops as u64);
-            // capture the warning here
-            if new_tail >= MAX_IDX {
-                warn_with_tail_too_big();
-                ////////////////////////////////////////////////////////////////////////////////////
-                // !!! THIS IS A PANIC CASE! WE DO NOT RETURN FROM HERE !!!
-                ////////////////////////////////////////////////////////////////////////////////////
-                #[allow(while_true)]
-                while true {
-                }  // TODO: is that fine?
-
-            }
+            // the tail stays below MAX_IDX, this panics otherwise
+            assume_operation_count_bounded(new_tail);
             // If on adding in the above entries there would be fewer than `GC_FROM_HEAD`
             // entries left on the log, then we need to advance the head of the log.
 
@@ -905,13 +1048,21 @@ impl<DT: Dispatch> NrLog<DT> {
                     }
                 }
             );
+            self.metrics.record_tail_cas(matches!(result, Result::Ok(tail)), nops);
+            if matches!(result, Result::Ok(tail)) {
+                trace_append(nid, tail, nops);
+            }
             if !matches!(result, Result::Ok(tail)) {
+                if cas_failures < MAX_CONTENTION {
+                    cas_failures = cas_failures + 1;
+                }
                 // assemble the struct again
                 proof {
                     ghost_data_new =
                     NrLogAppendExecDataGhost {
                         local_updates: Tracked(local_updates),  // Tracked::<Map<ReqId, UnboundedLog::local_updates>>,
                         ghost_replica,  // Tracked<UnboundedLog::replicas>,
+                        applied,  // Tracked<UnboundedLog::applied>,
                         combiner: Tracked(combiner),  // Tracked<UnboundedLog::combiner>,
                         cb_combiner: Tracked(cb_combiner),  // Tracked<CyclicBuffer::combiner>,
                         request_ids,  // Ghost<Seq<ReqId>>,
@@ -993,6 +1144,15 @@ impl<DT: Dispatch> NrLog<DT> {
                 );
                 // unsafe { (*e).alivef.store(m, Ordering::Release) };
                 let m = self.is_alive_value(logical_log_idx as u64);
+                if debug_invariants_enabled() {
+                    let old_alive =
+                        atomic_with_ghost!(
+                        &self.slog[log_idx].alive => load();
+                        returning old_alive;
+                        ghost g => { }
+                    );
+                    debug_check_alive_bit_flip(logical_log_idx, old_alive, m);
+                }
                 atomic_with_ghost!(
                     &self.slog[log_idx].alive => store(m);
                     ghost g => {
@@ -1038,6 +1198,7 @@ impl<DT: Dispatch> NrLog<DT> {
                 NrLogAppendExecDataGhost {
                     local_updates: Tracked(local_updates),  // Tracked::<Map<ReqId, UnboundedLog::local_updates>>,
                     ghost_replica,  // Tracked<UnboundedLog::replicas>,
+                    applied,  // Tracked<UnboundedLog::applied>,
                     combiner: Tracked(combiner),  // Tracked<UnboundedLog::combiner>,
                     cb_combiner: Tracked(cb_combiner),  // Tracked<CyclicBuffer::combiner>,
                     request_ids,  // Ghost<Seq<ReqId>>,
@@ -1050,9 +1211,9 @@ impl<DT: Dispatch> NrLog<DT> {
                     actual_replica,
                     Tracked(ghost_data_new),
                 );
-                return ghost_data_new;
+                return (cas_failures, ghost_data_new);
             } else {
-                return Tracked(ghost_data_new);
+                return (cas_failures, Tracked(ghost_data_new));
             }
         }
     }
@@ -1114,6 +1275,7 @@ impl<DT: Dispatch> NrLog<DT> {
             let tracked NrLogAppendExecDataGhost {
                 local_updates,
                 ghost_replica,
+                applied,
                 combiner,
                 cb_combiner,
                 request_ids,
@@ -1159,12 +1321,19 @@ impl<DT: Dispatch> NrLog<DT> {
                 let tracked ghost_data0 = NrLogAppendExecDataGhost {
                     local_updates,
                     ghost_replica,
+                    applied,
                     combiner,
                     cb_combiner,
                     request_ids,
                 };
                 ghost_data_new =
                 self.execute(replica_token, responses, actual_replica, Tracked(ghost_data0));
+                if iteration == LAGGARD_THRESHOLD {
+                    let (laggard, local_version) = self.find_laggard();
+                    self.pressure.laggard(laggard, local_version, global_head);
+                }
+                self.wait.call(iteration);
+                iteration = iteration + 1;
                 continue ;
             }
             // There are entries that can be freed up; update the head offset.
@@ -1176,11 +1345,13 @@ impl<DT: Dispatch> NrLog<DT> {
                 ghost g => {
                     cb_combiner = self.cyclic_buffer_instance.borrow().advance_head_finish(replica_token.id_spec(), &mut g, cb_combiner);
             });
+            trace_advance_head(replica_token.id(), min_local_version);
             if global_tail < min_local_version + self.slog.len() as u64 - GC_FROM_HEAD as u64 {
                 let cb_combiner = Tracked(cb_combiner);
                 let tracked ghost_data_new = NrLogAppendExecDataGhost {
                     local_updates,
                     ghost_replica,
+                    applied,
                     combiner,
                     cb_combiner,
                     request_ids,
@@ -1191,6 +1362,7 @@ impl<DT: Dispatch> NrLog<DT> {
             let tracked ghost_data0 = NrLogAppendExecDataGhost {
                 local_updates,
                 ghost_replica,
+                applied,
                 combiner,
                 cb_combiner,
                 request_ids,
@@ -1285,11 +1457,13 @@ impl<DT: Dispatch> NrLog<DT> {
             ),
     {
         let nid = replica_token.id() as usize;
+        self.debug_check_versions(replica_token.id());
         // somehow can't really do this as a destructor
         let tracked ghost_data = ghost_data.get();
         // let tracked Tracked(ghost_data) = ghost_data;  // XXX: that one here doesn't work?
         let tracked mut local_updates = ghost_data.local_updates.get();  // Tracked::<Map<ReqId, UnboundedLog::local_updates>>,
         let tracked mut ghost_replica = ghost_data.ghost_replica.get();  // Tracked<UnboundedLog::replicas>,
+        let tracked mut applied = ghost_data.applied.get();  // Tracked<UnboundedLog::applied>,
         let tracked mut combiner = ghost_data.combiner.get();  // Tracked<UnboundedLog::combiner>,
         let tracked mut cb_combiner = ghost_data.cb_combiner.get();  // Tracked<CyclicBuffer::combiner>,
         let ghost request_ids = ghost_data.request_ids@;  // Ghost<Seq<ReqId>>,
@@ -1335,6 +1509,7 @@ impl<DT: Dispatch> NrLog<DT> {
             let tracked ghost_data_ret = NrLogAppendExecDataGhost {
                 local_updates: Tracked(local_updates),  // Tracked::<Map<ReqId, UnboundedLog::local_updates>>,
                 ghost_replica: Tracked(ghost_replica),  // Tracked<UnboundedLog::replicas>,
+                applied: Tracked(applied),  // Tracked<UnboundedLog::applied>,
                 combiner: Tracked(combiner),  // Tracked<UnboundedLog::combiner>,
                 cb_combiner: Tracked(cb_combiner),  // Tracked<CyclicBuffer::combiner>,
                 request_ids: Ghost(request_ids),  // Ghost<Seq<ReqId>>,
@@ -1364,9 +1539,13 @@ impl<DT: Dispatch> NrLog<DT> {
         // entries, but not filled them into the log yet.
         // for i in ltail..gtail {
 
+        trace_apply(nid, local_version, global_tail);
         let ghost local_updates_old = local_updates;
         let ghost responses_old = responses@;
         let mut responses_idx: usize = 0;
+        if debug_invariants_enabled() {
+            self.debug_applied.start_round(nid);
+        }
         while local_version < global_tail
             invariant
                 self.wf(),
@@ -1386,6 +1565,8 @@ impl<DT: Dispatch> NrLog<DT> {
                 ghost_replica@.instance == self.unbounded_log_instance@,
                 ghost_replica@.key == nid as nat,
                 ghost_replica@.value == actual_replica.view(),
+                applied@.instance == self.unbounded_log_instance@,
+                applied@.key == nid as nat,
                 cb_combiner@.key == nid as nat,
                 cb_combiner@.instance == self.cyclic_buffer_instance@,
                 cb_combiner@.value.is_Reading(),
@@ -1465,6 +1646,9 @@ impl<DT: Dispatch> NrLog<DT> {
                         }
                     });
                 is_alive = alive_bit == is_alive_value;
+                if !is_alive {
+                    self.wait.call(iteration);
+                }
                 iteration = iteration + 1;
             }
             // dispatch the operation to apply the update to the replica
@@ -1479,10 +1663,24 @@ impl<DT: Dispatch> NrLog<DT> {
             let log_entry = self.slog[phys_log_idx].log_entry.borrow(
                 Tracked(&stored_entry.cell_perms),
             );
+            if debug_invariants_enabled() {
+                debug_check_log_entry(
+                    local_version,
+                    log_entry.as_ref().unwrap().node_id,
+                    self.local_versions.len(),
+                );
+            }
             // perform the update
             let res = actual_replica.dispatch_mut(
                 DT::clone_write_op(&log_entry.as_ref().unwrap().op),
             );
+            self.metrics.record_apply::<DT::WriteOperation>(
+                nid,
+                log_entry.as_ref().unwrap().node_id == nid as u64,
+            );
+            if debug_invariants_enabled() {
+                self.debug_applied.record(nid, log_entry.as_ref().unwrap().node_id == nid as u64);
+            }
             if log_entry.as_ref().unwrap().node_id == nid as u64 {
                 // case: local dispatch, store the result in the response vector
                 proof {
@@ -1501,14 +1699,17 @@ impl<DT: Dispatch> NrLog<DT> {
                             Tracked(ghost_replica0),
                             Tracked(local_update),
                             Tracked(combiner0),
+                            Tracked(applied0),
                         ) = self.unbounded_log_instance.borrow().exec_dispatch_local(
                             nid as nat,
                             e,
                             ghost_replica,
                             local_update,
                             combiner,
+                            applied,
                         );
                         ghost_replica = ghost_replica0;
+                        applied = applied0;
                         local_updates.tracked_insert(responses_idx as nat, local_update);
                         combiner = combiner0;
                     } else {
@@ -1531,9 +1732,11 @@ impl<DT: Dispatch> NrLog<DT> {
                             e,
                             ghost_replica,
                             combiner,
+                            applied,
                         );
                         ghost_replica = exec_dispatch_remote_result.0.get();
                         combiner = exec_dispatch_remote_result.1.get();
+                        applied = exec_dispatch_remote_result.2.get();
                     } else {
                         assert(false)  // should not happen
 
@@ -1543,8 +1746,18 @@ impl<DT: Dispatch> NrLog<DT> {
             proof {
                 cb_combiner =
                 self.cyclic_buffer_instance.borrow().reader_unguard(nid as nat, cb_combiner);
+                // the replica has applied exactly the updates up to the new local version
+                self.unbounded_log_instance.borrow().pre_exec_applied_counters(
+                    nid as nat,
+                    &combiner,
+                    &applied,
+                );
+                assert(applied@.value.total() == local_version + 1);
             }
             local_version = local_version + 1;
+            if debug_invariants_enabled() {
+                self.debug_applied.check(nid, local_version, responses_idx);
+            }
         }
         proof {
             self.unbounded_log_instance.borrow().pre_exec_update_version_upper_bound(
@@ -1579,6 +1792,7 @@ impl<DT: Dispatch> NrLog<DT> {
         let tracked ghost_data_ret = NrLogAppendExecDataGhost {
             local_updates: Tracked(local_updates),  // Tracked::<Map<ReqId, UnboundedLog::local_updates>>,
             ghost_replica: Tracked(ghost_replica),  // Tracked<UnboundedLog::replicas>,
+            applied: Tracked(applied),  // Tracked<UnboundedLog::applied>,
             combiner: Tracked(combiner),  // Tracked<UnboundedLog::combiner>,
             cb_combiner: Tracked(cb_combiner),  // Tracked<CyclicBuffer::combiner>,
             request_ids: Ghost(request_ids),  // Ghost<Seq<ReqId>>,
@@ -1677,6 +1891,8 @@ pub tracked struct NrLogAppendExecDataGhost<DT: Dispatch> {
      local_updates: Tracked::<Map<ReqId, UnboundedLog::local_updates<DT>>>,
     pub  /* REVIEW (crate) */
      ghost_replica: Tracked<UnboundedLog::replicas<DT>>,
+    pub  /* REVIEW (crate) */
+     applied: Tracked<UnboundedLog::applied<DT>>,
     pub  /* REVIEW (crate) */
      combiner: Tracked<UnboundedLog::combiner<DT>>,
     pub  /* REVIEW (crate) */
@@ -1702,6 +1918,8 @@ impl<DT: Dispatch> NrLogAppendExecDataGhost<DT> {
         &&& self.ghost_replica@@.key == nid
         &&& self.ghost_replica@@.instance == inst
         &&& self.ghost_replica@@.value == data
+        &&& self.applied@@.key == nid
+        &&& self.applied@@.instance == inst
         &&& self.combiner@@.key == nid
         &&& self.combiner@@.instance == inst
         &&& self.cb_combiner@@.key == nid
@@ -1928,6 +2146,7 @@ pub tracked struct NrLogTokens<DT: Dispatch> {
     pub ghost num_replicas: nat,
     pub tracked replicas                : Map<NodeId,UnboundedLog::replicas<DT>>,
     pub tracked combiners               : Map<NodeId,UnboundedLog::combiner<DT>>,
+    pub tracked applied                 : Map<NodeId,UnboundedLog::applied<DT>>,
     pub tracked cb_combiners            : Map<NodeId, CyclicBuffer::combiner<DT>>,
     pub tracked unbounded_log_instance  : UnboundedLog::Instance<DT>,
     pub tracked cyclic_buffer_instance  : CyclicBuffer::Instance<DT>,
@@ -1952,6 +2171,13 @@ pub open spec fn wf(&self, num_replicas: nat) -> bool {
             &&& self.combiners[i]@.value.is_Ready()
         })
 
+        &&& (forall |i| #![trigger self.applied[i]]0 <= i < self.num_replicas ==> {
+            &&& #[trigger]  self.applied.contains_key(i)
+            &&& self.applied[i]@.instance == self.unbounded_log_instance
+            &&& self.applied[i]@.key == i
+            &&& self.applied[i]@.value.total() == 0
+        })
+
         &&& (forall |i| #![trigger self.cb_combiners[i]]0 <= i < self.num_replicas ==> {
             &&& #[trigger] self.cb_combiners.contains_key(i)
             &&& self.cb_combiners[i]@.instance == self.cyclic_buffer_instance
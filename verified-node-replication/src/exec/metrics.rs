@@ -0,0 +1,355 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! Runtime statistics of the replicas and the log, for benchmarks and tuning.
+//!
+//! The counters are only updated with the `metrics` feature, otherwise recording is a no-op and
+//! the statistics stay at zero. They are plain relaxed counters outside of the ghost state: they
+//! don't take part in the proofs, and the values of concurrent updates are only approximately
+//! consistent with each other.
+//!
+//! Distributions are recorded in histograms with power-of-two buckets: bucket 0 counts the
+//! value 0, bucket `i > 0` counts the values in `[2^(i-1), 2^i)`, and the last bucket also counts
+//! all larger values.
+#[allow(unused_imports)]
+use builtin::*;
+use builtin_macros::*;
+
+use vstd::prelude::*;
+
+use crate::constants::MAX_REPLICAS;
+
+verus! {
+
+/// the number of buckets of a histogram
+pub const HISTOGRAM_BUCKETS: usize = 16;
+
+/// A histogram with power-of-two buckets.
+#[verus::trusted]
+#[verifier::external_body]
+pub struct Histogram {
+    buckets: [std::sync::atomic::AtomicU64; HISTOGRAM_BUCKETS],
+}
+
+#[verus::trusted]
+impl Histogram {
+    #[verifier::external_body]
+    pub fn new() -> Self {
+        Histogram { buckets: std::array::from_fn(|_| std::sync::atomic::AtomicU64::new(0)) }
+    }
+
+    /// the bucket of `value`.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn bucket(value: u64) -> usize {
+        let bits = (u64::BITS - value.leading_zeros()) as usize;
+        if bits < HISTOGRAM_BUCKETS {
+            bits
+        } else {
+            HISTOGRAM_BUCKETS - 1
+        }
+    }
+
+    /// records `value`.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn record(&self, value: u64) {
+        self.buckets[Self::bucket(value)].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// the current counts of the buckets.
+    #[verifier::external_body]
+    pub fn counts(&self) -> [u64; HISTOGRAM_BUCKETS] {
+        std::array::from_fn(|i| self.buckets[i].load(std::sync::atomic::Ordering::Relaxed))
+    }
+
+    /// sets all buckets back to zero.
+    #[verifier::external_body]
+    pub fn reset(&self) {
+        for b in self.buckets.iter() {
+            b.store(0, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Combiner
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A snapshot of the combiner statistics of a replica.
+#[verus::trusted]
+#[verifier::external_body]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CombinerStats {
+    /// the number of times a thread acquired the combiner lock and combined
+    pub combines: u64,
+    /// the number of update operations collected by the combiner over all combines
+    pub ops: u64,
+    /// the distribution of the number of update operations per combine
+    pub batch_sizes: [u64; HISTOGRAM_BUCKETS],
+}
+
+#[verus::trusted]
+impl CombinerStats {
+    /// the average number of update operations per combine (the batch size).
+    #[verifier::external_body]
+    pub fn ops_per_combine(&self) -> f64 {
+        if self.combines == 0 {
+            0.0
+        } else {
+            self.ops as f64 / self.combines as f64
+        }
+    }
+}
+
+/// The combiner counters of a replica.
+#[verus::trusted]
+#[verifier::external_body]
+pub struct CombinerMetrics {
+    combines: std::sync::atomic::AtomicU64,
+    ops: std::sync::atomic::AtomicU64,
+    batch_sizes: Histogram,
+}
+
+#[verus::trusted]
+impl CombinerMetrics {
+    #[verifier::external_body]
+    pub fn new() -> Self {
+        CombinerMetrics {
+            combines: std::sync::atomic::AtomicU64::new(0),
+            ops: std::sync::atomic::AtomicU64::new(0),
+            batch_sizes: Histogram::new(),
+        }
+    }
+
+    /// records a combine that collected `ops` update operations.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn record_combine(&self, ops: usize) {
+        if cfg!(feature = "metrics") {
+            use std::sync::atomic::Ordering::Relaxed;
+            self.combines.fetch_add(1, Relaxed);
+            self.ops.fetch_add(ops as u64, Relaxed);
+            self.batch_sizes.record(ops as u64);
+        }
+    }
+
+    /// the current values of the counters.
+    #[verifier::external_body]
+    pub fn stats(&self) -> CombinerStats {
+        use std::sync::atomic::Ordering::Relaxed;
+        CombinerStats {
+            combines: self.combines.load(Relaxed),
+            ops: self.ops.load(Relaxed),
+            batch_sizes: self.batch_sizes.counts(),
+        }
+    }
+
+    /// sets all counters back to zero.
+    #[verifier::external_body]
+    pub fn reset(&self) {
+        use std::sync::atomic::Ordering::Relaxed;
+        self.combines.store(0, Relaxed);
+        self.ops.store(0, Relaxed);
+        self.batch_sizes.reset();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Apply
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A snapshot of the log entries a replica applied.
+///
+/// Local entries were appended by the combiner of the replica itself, remote entries by the
+/// combiners of the other replicas. Applying a remote entry copies it from the log, which is
+/// the traffic between the NUMA nodes the replication keeps the reads from doing.
+#[verus::trusted]
+#[verifier::external_body]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ApplyStats {
+    /// the number of applied entries that were appended by this replica
+    pub local: u64,
+    /// the number of applied entries that were appended by other replicas
+    pub remote: u64,
+    /// the number of bytes of the operations copied from the log over all applied entries
+    pub bytes: u64,
+}
+
+#[verus::trusted]
+impl ApplyStats {
+    /// the fraction of the applied entries that were appended by other replicas.
+    #[verifier::external_body]
+    pub fn remote_fraction(&self) -> f64 {
+        let total = self.local + self.remote;
+        if total == 0 {
+            0.0
+        } else {
+            self.remote as f64 / total as f64
+        }
+    }
+}
+
+/// The counters of the log entries a replica applied.
+#[verus::trusted]
+#[verifier::external_body]
+pub struct ApplyMetrics {
+    local: std::sync::atomic::AtomicU64,
+    remote: std::sync::atomic::AtomicU64,
+    bytes: std::sync::atomic::AtomicU64,
+}
+
+#[verus::trusted]
+impl ApplyMetrics {
+    #[verifier::external_body]
+    pub fn new() -> Self {
+        ApplyMetrics {
+            local: std::sync::atomic::AtomicU64::new(0),
+            remote: std::sync::atomic::AtomicU64::new(0),
+            bytes: std::sync::atomic::AtomicU64::new(0),
+        }
+    }
+
+    /// the current values of the counters.
+    #[verifier::external_body]
+    pub fn stats(&self) -> ApplyStats {
+        use std::sync::atomic::Ordering::Relaxed;
+        ApplyStats {
+            local: self.local.load(Relaxed),
+            remote: self.remote.load(Relaxed),
+            bytes: self.bytes.load(Relaxed),
+        }
+    }
+
+    /// sets all counters back to zero.
+    #[verifier::external_body]
+    pub fn reset(&self) {
+        use std::sync::atomic::Ordering::Relaxed;
+        self.local.store(0, Relaxed);
+        self.remote.store(0, Relaxed);
+        self.bytes.store(0, Relaxed);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Log
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A snapshot of the statistics of the log.
+#[verus::trusted]
+#[verifier::external_body]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LogStats {
+    /// the number of successful compare-and-swaps of the tail, one per append
+    pub appends: u64,
+    /// the number of entries reserved over all appends
+    pub entries: u64,
+    /// the number of failed compare-and-swaps of the tail
+    pub cas_failures: u64,
+    /// the distribution of the number of entries per successful compare-and-swap
+    pub entries_per_append: [u64; HISTOGRAM_BUCKETS],
+}
+
+#[verus::trusted]
+impl LogStats {
+    /// the average number of entries per successful compare-and-swap of the tail.
+    #[verifier::external_body]
+    pub fn entries_per_cas(&self) -> f64 {
+        if self.appends == 0 {
+            0.0
+        } else {
+            self.entries as f64 / self.appends as f64
+        }
+    }
+}
+
+/// The counters of the log.
+#[verus::trusted]
+#[verifier::external_body]
+pub struct LogMetrics {
+    appends: std::sync::atomic::AtomicU64,
+    entries: std::sync::atomic::AtomicU64,
+    cas_failures: std::sync::atomic::AtomicU64,
+    entries_per_append: Histogram,
+    applied: [ApplyMetrics; MAX_REPLICAS],
+}
+
+#[verus::trusted]
+impl LogMetrics {
+    #[verifier::external_body]
+    pub fn new() -> Self {
+        LogMetrics {
+            appends: std::sync::atomic::AtomicU64::new(0),
+            entries: std::sync::atomic::AtomicU64::new(0),
+            cas_failures: std::sync::atomic::AtomicU64::new(0),
+            entries_per_append: Histogram::new(),
+            applied: std::array::from_fn(|_| ApplyMetrics::new()),
+        }
+    }
+
+    /// records a compare-and-swap of the tail that reserved `entries` entries if it succeeded.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn record_tail_cas(&self, success: bool, entries: usize) {
+        if cfg!(feature = "metrics") {
+            use std::sync::atomic::Ordering::Relaxed;
+            if success {
+                self.appends.fetch_add(1, Relaxed);
+                self.entries.fetch_add(entries as u64, Relaxed);
+                self.entries_per_append.record(entries as u64);
+            } else {
+                self.cas_failures.fetch_add(1, Relaxed);
+            }
+        }
+    }
+
+    /// records that `replica` applied an entry with an operation of type `T`, which was appended
+    /// by the replica itself if `local`.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn record_apply<T>(&self, replica: usize, local: bool) {
+        if cfg!(feature = "metrics") {
+            use std::sync::atomic::Ordering::Relaxed;
+            let applied = &self.applied[replica];
+            if local {
+                applied.local.fetch_add(1, Relaxed);
+            } else {
+                applied.remote.fetch_add(1, Relaxed);
+            }
+            applied.bytes.fetch_add(std::mem::size_of::<T>() as u64, Relaxed);
+        }
+    }
+
+    /// the current values of the apply counters of `replica`.
+    #[verifier::external_body]
+    pub fn apply_stats(&self, replica: usize) -> ApplyStats {
+        self.applied[replica].stats()
+    }
+
+    /// the current values of the counters.
+    #[verifier::external_body]
+    pub fn stats(&self) -> LogStats {
+        use std::sync::atomic::Ordering::Relaxed;
+        LogStats {
+            appends: self.appends.load(Relaxed),
+            entries: self.entries.load(Relaxed),
+            cas_failures: self.cas_failures.load(Relaxed),
+            entries_per_append: self.entries_per_append.counts(),
+        }
+    }
+
+    /// sets all counters back to zero.
+    #[verifier::external_body]
+    pub fn reset(&self) {
+        use std::sync::atomic::Ordering::Relaxed;
+        self.appends.store(0, Relaxed);
+        self.entries.store(0, Relaxed);
+        self.cas_failures.store(0, Relaxed);
+        self.entries_per_append.reset();
+        for a in self.applied.iter() {
+            a.reset();
+        }
+    }
+}
+
+} // verus!
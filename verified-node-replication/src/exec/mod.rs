@@ -13,17 +13,29 @@ use crate::Dispatch;
 use crate::spec::{cyclicbuffer::CyclicBuffer, unbounded_log::UnboundedLog};
 
 // exec imports
-use crate::exec::context::ThreadToken;
+use crate::exec::context::{OpHandle, ThreadToken};
 use crate::exec::log::{NrLog, NrLogTokens};
+use crate::exec::builder::{NodeReplicatedBuilder, ReplicaSelection};
+use crate::exec::metrics::{ApplyStats, CombinerStats, LogStats};
 use crate::exec::replica::{Replica, ReplicaConfig, ReplicaId};
+use crate::exec::utils::Deadline;
 
 use crate::constants::{LOG_SIZE, MAX_REPLICAS, MAX_THREADS_PER_REPLICA};
-use crate::AffinityFn;
+use crate::{
+    is_readonly_stub, is_readonly_ticket, is_update_stub, is_update_ticket, AffinityFn, LogMemFn, LogPressureFn, NoPreemptGuard,
+    NodeReplicatedT, PreemptFn, PreemptGuard, SnapshotDispatch, StdWait, WaitFn, WaitStrategy,
+};
 
+pub mod builder;
 pub mod context;
+pub mod fallible;
+pub mod hooks;
 pub mod log;
+pub mod metrics;
 pub mod replica;
 pub mod rwlock;
+pub mod sharded;
+pub mod trace;
 pub mod utils;
 
 verus! {
@@ -54,6 +66,16 @@ pub struct NodeReplicated<DT: Dispatch> {
     // replicas: Vec<Box<Replica<DataStructureType, UpdateOp, ReturnType>>>,
     pub  /* REVIEW (crate) */
      replicas: Vec<Box<Replica<DT>>>,
+    /// how [`NodeReplicated::register_next`] picks the replica of a thread
+    pub selection: ReplicaSelection,
+    /// the replica [`NodeReplicated::register_next`] tries first
+    pub next_replica: usize,
+    /// the lag behind the tail from which [`NodeReplicated::help_lagging`] runs the combiner of
+    /// another replica, 0 disables it
+    pub steal_lag: u64,
+    /// the NUMA node of each replica, [`NodeReplicated::help_lagging`] only runs the combiners of
+    /// replicas on the node of the helping thread's replica
+    pub replica_nodes: Vec<usize>,
     // pub /* REVIEW: (crate) */ thread_tokens: Vec<Vec<ThreadToken<DT>>>,
     /// XXX: should that be here, or go into the NrLog / replicas?
     pub unbounded_log_instance: Tracked<UnboundedLog::Instance<DT>>,
@@ -70,63 +92,126 @@ impl<DT: Dispatch> crate::ThreadTokenT<DT, Replica<DT>> for ThreadToken<DT> {
     }
 }
 
-impl<DT: Dispatch + Sync> crate::NodeReplicatedT<DT> for NodeReplicated<DT> {
-    type Replica = Replica<DT>;
-
-    type ReplicaId = ReplicaId;
-
-    type TT = ThreadToken<DT>;
-
-    /// Wellformedness of the NodeReplicated data structure
-    open spec fn wf(&self) -> bool {
-        // the log shall be well-formed and the instances match
-        &&& self.log.wf()
-        &&& self.unbounded_log_instance@ == self.log.unbounded_log_instance@
-        &&& self.cyclic_buffer_instance@
-            == self.log.cyclic_buffer_instance@
-        // the number of replicas should be the as configured
-
-        &&& self.replicas.len()
-            <= MAX_REPLICAS
-        // the replicas should be well-formed and the instances match
+impl<DT: Dispatch + Sync> NodeReplicated<DT> {
+    /// Creates a new, replicated data-structure with a preemption guard from a single-threaded
+    /// data-structure that implements [`Dispatch`]. It uses the [`Default`]
+    /// constructor to create a initial data-structure for `D` on all replicas.
+    ///
+    /// The replicas disable preemption through the guard `G` while their combiner lock is
+    /// held, this is required if operations are executed from interrupt contexts.
+    ///
+    ///  - Dafny: n/a ?
+    ///  - Rust:  pub fn new(num_replicas: NonZeroUsize) -> Result<Self, NodeReplicatedError>
+    pub fn new_with_preempt_guard<G: PreemptGuard>(
+        num_replicas: usize,
+        chg_mem_affinity: AffinityFn,
+    ) -> (res: Self)
+        requires
+            0 < num_replicas && num_replicas <= MAX_REPLICAS,
+        ensures
+            res.wf() && res.replicas().len() == num_replicas,
+    {
+        Self::new_with_hooks::<G, StdWait>(num_replicas, chg_mem_affinity, LogMemFn::none())
+    }
 
-        &&& (forall|i|
-            0 <= i < self.replicas.len() ==> {
-                &&& (#[trigger] self.replicas[i]).wf()
-                &&& self.replicas[i].spec_id() == i
-                &&& self.replicas[i].replica_token@ == i
-                &&& self.replicas[i].unbounded_log_instance@ == self.unbounded_log_instance@
-                &&& self.replicas[i].cyclic_buffer_instance@ == self.cyclic_buffer_instance@
-            })
+    /// Creates a new, replicated data-structure like [`NodeReplicatedT::new`], but checks the
+    /// number of replicas at runtime: returns `None` if it is zero or exceeds `MAX_REPLICAS`.
+    ///
+    /// For callers that can't establish the bounds statically, e.g., from a command line.
+    pub fn try_new(num_replicas: usize, chg_mem_affinity: AffinityFn) -> (res: Option<Self>)
+        ensures
+            res.is_Some() <==> (0 < num_replicas && num_replicas <= MAX_REPLICAS),
+            res.is_Some() ==> res.get_Some_0().wf() && res.get_Some_0().replicas().len()
+                == num_replicas,
+    {
+        if num_replicas == 0 || num_replicas > MAX_REPLICAS {
+            return None;
+        }
+        Some(Self::new_with_preempt_guard::<NoPreemptGuard>(num_replicas, chg_mem_affinity))
     }
 
-    open spec fn replicas(&self) -> Vec<Box<Self::Replica>> {
-        self.replicas
+    /// Creates a new, replicated data-structure with a preemption guard, a wait strategy and a
+    /// function that prepares the memory of the log, e.g., to back the log with huge pages.
+    ///
+    /// `log_mem` is called once with the memory region of the cyclic buffer, after it has been
+    /// allocated with the affinity of the first replica and before any entry is written. All
+    /// busy waiting of the replicas and the log goes through `W`, use [`crate::SpinWait`] to
+    /// keep `std` out of the waits.
+    pub fn new_with_hooks<G: PreemptGuard, W: WaitStrategy>(
+        num_replicas: usize,
+        chg_mem_affinity: AffinityFn,
+        log_mem: LogMemFn,
+    ) -> (res: Self)
+        requires
+            0 < num_replicas && num_replicas <= MAX_REPLICAS,
+        ensures
+            res.wf() && res.replicas().len() == num_replicas,
+    {
+        Self::new_with_fns(
+            num_replicas,
+            chg_mem_affinity,
+            log_mem,
+            PreemptFn::new::<G>(),
+            WaitFn::new::<W>(),
+        )
     }
 
-    open spec fn unbounded_log_instance(&self) -> UnboundedLog::Instance<DT> {
-        self.log.unbounded_log_instance@
+    /// Creates a new, replicated data-structure with the hooks of [`Self::new_with_hooks`], with
+    /// the preemption guard and the wait strategy passed as function objects.
+    pub fn new_with_fns(
+        num_replicas: usize,
+        chg_mem_affinity: AffinityFn,
+        log_mem: LogMemFn,
+        preempt: PreemptFn,
+        wait: WaitFn,
+    ) -> (res: Self)
+        requires
+            0 < num_replicas && num_replicas <= MAX_REPLICAS,
+        ensures
+            res.wf() && res.replicas().len() == num_replicas,
+    {
+        Self::new_with_config(
+            num_replicas,
+            MAX_THREADS_PER_REPLICA,
+            false,
+            chg_mem_affinity,
+            log_mem,
+            preempt,
+            wait,
+        )
     }
 
-    /// Creates a new, replicated data-structure from a single-threaded
-    /// data-structure that implements [`Dispatch`]. It uses the [`Default`]
-    /// constructor to create a initial data-structure for `D` on all replicas.
-    ///
-    ///  - Dafny: n/a ?
-    ///  - Rust:  pub fn new(num_replicas: NonZeroUsize) -> Result<Self, NodeReplicatedError>
-    fn new(num_replicas: usize, chg_mem_affinity: AffinityFn) -> (res:
-        Self)
-    // requires
-    //     num_replicas <= MAX_REPLICAS
-    // ensures res.wf()
+    /// Creates a new, replicated data-structure like [`Self::new_with_fns`], where at most
+    /// `threads_per_replica` threads can register with each replica, and threads park while
+    /// waiting for their responses if `park_waiters` is set. Used by [`NodeReplicatedBuilder`].
+    pub fn new_with_config(
+        num_replicas: usize,
+        threads_per_replica: usize,
+        park_waiters: bool,
+        chg_mem_affinity: AffinityFn,
+        log_mem: LogMemFn,
+        preempt: PreemptFn,
+        wait: WaitFn,
+    ) -> (res: Self)
+        requires
+            0 < num_replicas && num_replicas <= MAX_REPLICAS,
+            0 < threads_per_replica && threads_per_replica <= MAX_THREADS_PER_REPLICA,
+        ensures
+            res.wf() && res.replicas().len() == num_replicas,
     {
         // switch affinity to the first replica
         chg_mem_affinity.call(0);
-        let (log, replica_tokens, nr_log_tokens) = NrLog::new(num_replicas, LOG_SIZE);
+        let (log, replica_tokens, nr_log_tokens) = NrLog::new(
+            num_replicas,
+            LOG_SIZE,
+            &log_mem,
+            wait.clone(),
+        );
         let tracked NrLogTokens {
             num_replicas: _,
             replicas: mut replicas,
             combiners: mut combiners,
+            applied: mut applied,
             cb_combiners: mut cb_combiners,
             unbounded_log_instance: unbounded_log_instance,
             cyclic_buffer_instance: cyclic_buffer_instance,
@@ -170,6 +255,13 @@ impl<DT: Dispatch + Sync> crate::NodeReplicatedT<DT> for NodeReplicated<DT> {
                         &&& combiners[i]@.key == i
                         &&& combiners[i]@.value.is_Ready()
                     }),
+                (forall|i|
+                    #![trigger applied[i]]
+                    idx <= i < num_replicas ==> {
+                        &&& #[trigger] applied.contains_key(i)
+                        &&& applied[i]@.instance == unbounded_log_instance
+                        &&& applied[i]@.key == i
+                    }),
                 (forall|i|
                     #![trigger cb_combiners[i]]
                     idx <= i < num_replicas ==> {
@@ -186,13 +278,22 @@ impl<DT: Dispatch + Sync> crate::NodeReplicatedT<DT> for NodeReplicated<DT> {
             let tracked config = ReplicaConfig {
                 replica: replicas.tracked_remove(idx_ghost),
                 combiner: combiners.tracked_remove(idx_ghost),
+                applied: applied.tracked_remove(idx_ghost),
                 cb_combiner: cb_combiners.tracked_remove(idx_ghost),
                 unbounded_log_instance: unbounded_log_instance.clone(),
                 cyclic_buffer_instance: cyclic_buffer_instance.clone(),
             };
             // switch the affinity of the replica before we do the allocation
             chg_mem_affinity.call(replica_token.id());
-            let replica = Replica::new(replica_token, MAX_THREADS_PER_REPLICA, Tracked(config));
+            let mut replica = Replica::new(
+                replica_token,
+                MAX_THREADS_PER_REPLICA,
+                Tracked(config),
+                preempt.clone(),
+                wait.clone(),
+            );
+            replica.limit_threads(threads_per_replica);
+            replica.park_waiters = park_waiters;
             actual_replicas.push(Box::new(replica));
             idx = idx + 1;
         }
@@ -204,11 +305,334 @@ impl<DT: Dispatch + Sync> crate::NodeReplicatedT<DT> for NodeReplicated<DT> {
         NodeReplicated {
             log,
             replicas: actual_replicas,
+            selection: ReplicaSelection::RoundRobin,
+            next_replica: 0,
+            steal_lag: 0,
+            replica_nodes: Vec::new(),
             unbounded_log_instance,
             cyclic_buffer_instance,
         }
     }
 
+    /// Returns a builder to configure and create a replicated data structure.
+    pub fn builder() -> (res: NodeReplicatedBuilder<DT>) {
+        NodeReplicatedBuilder::new()
+    }
+
+    /// Sets the function that is notified when the occupancy of the log crosses its watermarks
+    /// and when a lagging replica keeps the log from being reused.
+    pub fn set_log_pressure(&mut self, pressure: LogPressureFn)
+        requires
+            old(self).wf(),
+        ensures
+            self.wf(),
+            self.replicas() == old(self).replicas(),
+    {
+        self.log.pressure = pressure;
+    }
+
+    /// Registers a thread with the replica picked by the replica-selection policy, see
+    /// [`ReplicaSelection`]. Returns `None` if all replicas are full.
+    pub fn register_next(&mut self) -> (result: Option<ThreadToken<DT>>)
+        requires
+            old(self).wf(),
+        ensures
+            self.wf(),
+            self.replicas().len() == old(self).replicas().len(),
+    {
+        let num_replicas = self.replicas.len();
+        if num_replicas == 0 {
+            return None;
+        }
+        let start = match self.selection {
+            ReplicaSelection::RoundRobin => self.next_replica % num_replicas,
+            ReplicaSelection::Fill => 0,
+        };
+        let mut idx = 0;
+        while idx < num_replicas
+            invariant
+                self.wf(),
+                self.replicas().len() == num_replicas,
+                0 < num_replicas,
+                start < num_replicas,
+        {
+            let replica_id = (start + idx) % num_replicas;
+            if replica_id < self.replicas.len() {
+                let mut replica: Box<Replica<DT>> = self.replicas.remove(replica_id);
+                let res: Option<ThreadToken<DT>> = (*replica).register();
+                self.replicas.insert(replica_id, replica);
+                if res.is_some() {
+                    self.next_replica = (replica_id + 1) % num_replicas;
+                    return res;
+                }
+            }
+            idx = idx + 1;
+        }
+        None
+    }
+
+    /// Runs the combiner of the replica that lags the most behind the tail of the log, if it lags
+    /// by at least `steal_lag` entries, on behalf of an idle thread of another replica on the
+    /// same NUMA node.
+    ///
+    /// Threads whose replica has no pending work can call this to keep the other replicas of
+    /// their node fresh while their own threads are idle, so reads on them don't have to catch up
+    /// first. Replicas on other nodes are left alone, running their combiner would apply the log
+    /// to remote memory. Returns the replica that was helped, or `None` if no replica of the node
+    /// lags enough, its combiner was busy, or stealing is disabled (see
+    /// [`NodeReplicatedBuilder::steal_lagging`] and [`NodeReplicatedBuilder::replica_nodes`]).
+    pub fn help_lagging(&self, tkn: &ThreadToken<DT>) -> (result: Option<ReplicaId>)
+        requires
+            self.wf(),
+    {
+        if self.steal_lag == 0 {
+            return None;
+        }
+        let own = tkn.replica_id() as usize;
+        let tail = self.log.get_tail();
+        // Step 1: find the replica with the lowest local version
+        let mut lagging: Option<ReplicaId> = None;
+        let mut lagging_version = tail;
+        let mut idx: usize = 0;
+        while idx < self.replicas.len()
+            invariant
+                self.wf(),
+                lagging.is_Some() ==> lagging.get_Some_0() < self.replicas.len(),
+        {
+            if idx != own && self.same_node(idx, own) {
+                assert(self.replicas[idx as int].wf());
+                let version = self.log.get_local_version(idx);
+                if version < lagging_version && tail - version >= self.steal_lag {
+                    lagging = Some(idx);
+                    lagging_version = version;
+                }
+            }
+            idx = idx + 1;
+        }
+        // Step 2: try to run its combiner
+        match lagging {
+            Some(replica_id) => {
+                if (&self.replicas[replica_id]).try_help(&self.log) {
+                    Some(replica_id)
+                } else {
+                    None
+                }
+            },
+            None => None,
+        }
+    }
+
+    /// Whether the two replicas are on the same NUMA node, false if the node of one is unknown.
+    fn same_node(&self, a: ReplicaId, b: ReplicaId) -> bool {
+        a < self.replica_nodes.len() && b < self.replica_nodes.len()
+            && self.replica_nodes[a] == self.replica_nodes[b]
+    }
+
+    /// Returns the combiner statistics of the given replica, or `None` if there is no such
+    /// replica. The statistics are only recorded with the `metrics` feature.
+    pub fn combiner_stats(&self, replica_id: ReplicaId) -> (result: Option<CombinerStats>) {
+        if replica_id < self.replicas.len() {
+            Some(self.replicas[replica_id].metrics.stats())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the statistics of the appends to the log. The statistics are only recorded with
+    /// the `metrics` feature.
+    pub fn log_stats(&self) -> (result: LogStats) {
+        self.log.metrics.stats()
+    }
+
+    /// Returns the numbers of local and remote log entries the given replica applied and the bytes
+    /// it copied from the log, or `None` if there is no such replica. The statistics are only
+    /// recorded with the `metrics` feature.
+    pub fn apply_stats(&self, replica_id: ReplicaId) -> (result: Option<ApplyStats>) {
+        if replica_id < self.replicas.len() {
+            Some(self.log.metrics.apply_stats(replica_id))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the combiner statistics of all replicas and the statistics of the log back to zero.
+    pub fn reset_stats(&self) {
+        let mut idx = 0;
+        while idx < self.replicas.len() {
+            self.replicas[idx].metrics.reset();
+            idx = idx + 1;
+        }
+        self.log.metrics.reset();
+    }
+
+    /// Unregisters a thread from its replica. The thread token is handed out again to the next
+    /// thread that registers with the replica.
+    ///
+    /// Threads that are done with the data structure should unregister, a dropped thread token
+    /// is lost for its replica. Dropping the data structure itself needs no unregistering: it
+    /// is owned, so no operation can be in progress, and the remaining thread tokens can't be
+    /// used with any other instance.
+    pub fn unregister(&mut self, tkn: ThreadToken<DT>)
+        requires
+            old(self).wf(),
+            tkn.wf(&old(self).replicas()[tkn.replica_id_spec() as int]),
+        ensures
+            self.wf(),
+            self.replicas().len() == old(self).replicas().len(),
+    {
+        let replica_id = tkn.replica_id() as usize;
+        if replica_id < self.replicas.len() {
+            let mut replica: Box<Replica<DT>> = self.replicas.remove(replica_id);
+            (*replica).unregister(tkn);
+            self.replicas.insert(replica_id, replica);
+        }
+    }
+
+    /// Submits a mutable operation without waiting for its response.
+    ///
+    /// Returns a handle that holds the thread token until the response is collected, either by
+    /// polling it with [`NodeReplicated::poll`] and [`OpHandle::take_response`], or by blocking
+    /// in [`NodeReplicated::wait`]. A thread token has a single operation slot, so a thread
+    /// pipelines updates by submitting with several thread tokens before collecting them.
+    pub fn submit_mut(
+        &self,
+        op: DT::WriteOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+    ) -> (result: Result<
+        OpHandle<DT>,
+        (ThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>),
+    >)
+        requires
+            self.wf(),
+            tkn.wf(&self.replicas()[tkn.replica_id_spec() as int]),
+            is_update_ticket(ticket@, op, self.unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> {
+                &&& result.get_Ok_0().wf(&self.replicas()[tkn.replica_id_spec() as int])
+                &&& result.get_Ok_0().replica_id_spec() == tkn.replica_id_spec()
+                &&& result.get_Ok_0().replica_id_spec() < self.replicas().len()
+                &&& result.get_Ok_0().req_id() == ticket@@.key
+            },
+            result.is_Err() ==> result.get_Err_0().1 == ticket && result.get_Err_0().0 == tkn,
+    {
+        let replica_id = tkn.replica_id() as usize;
+        if replica_id < self.replicas.len() {
+            Ok((&self.replicas[replica_id]).submit_mut(&self.log, op, tkn, ticket))
+        } else {
+            Err((tkn, ticket))
+        }
+    }
+
+    /// Collects the response of a submitted operation if it is available, see
+    /// [`OpHandle::is_done`]. Tries at most once to combine and never waits otherwise.
+    pub fn poll(&self, handle: OpHandle<DT>) -> (result: OpHandle<DT>)
+        requires
+            self.wf(),
+            handle.replica_id_spec() < self.replicas().len(),
+            handle.wf(&self.replicas()[handle.replica_id_spec() as int]),
+        ensures
+            result.wf(&self.replicas()[handle.replica_id_spec() as int]),
+            result.replica_id_spec() == handle.replica_id_spec(),
+            result.req_id() == handle.req_id(),
+    {
+        let replica_id = handle.replica_id() as usize;
+        (&self.replicas[replica_id]).poll_response(&self.log, handle)
+    }
+
+    /// Withdraws a submitted operation unless the combiner has already collected it.
+    ///
+    /// Returns the thread token if the operation was withdrawn: it was never placed into the log
+    /// and will never be applied, its ticket is consumed by the `update_cancel` transition of
+    /// the unbounded log. Otherwise the handle is returned unchanged: the operation has been
+    /// collected and will be applied exactly once, [`NodeReplicated::wait`] returns its response.
+    pub fn cancel(&self, handle: OpHandle<DT>) -> (result: Result<ThreadToken<DT>, OpHandle<DT>>)
+        requires
+            self.wf(),
+            handle.replica_id_spec() < self.replicas().len(),
+            handle.wf(&self.replicas()[handle.replica_id_spec() as int]),
+        ensures
+            result.is_Ok() ==> result.get_Ok_0().wf(
+                &self.replicas()[handle.replica_id_spec() as int],
+            ),
+            result.is_Err() ==> result.get_Err_0() == handle,
+    {
+        let replica_id = handle.replica_id() as usize;
+        (&self.replicas[replica_id]).cancel_op(handle)
+    }
+
+    /// Waits for the response of a submitted operation, returns it with the thread token.
+    pub fn wait(&self, handle: OpHandle<DT>) -> (result: (
+        DT::Response,
+        ThreadToken<DT>,
+        Tracked<UnboundedLog::local_updates<DT>>,
+    ))
+        requires
+            self.wf(),
+            handle.replica_id_spec() < self.replicas().len(),
+            handle.wf(&self.replicas()[handle.replica_id_spec() as int]),
+        ensures
+            result.1.wf(&self.replicas()[handle.replica_id_spec() as int]),
+            is_update_stub(result.2@, handle.req_id(), result.0, self.unbounded_log_instance()),
+    {
+        let replica_id = handle.replica_id() as usize;
+        (&self.replicas[replica_id]).wait_response(&self.log, handle)
+    }
+}
+
+impl<DT: Dispatch + Sync> crate::NodeReplicatedT<DT> for NodeReplicated<DT> {
+    type Replica = Replica<DT>;
+
+    type ReplicaId = ReplicaId;
+
+    type TT = ThreadToken<DT>;
+
+    /// Wellformedness of the NodeReplicated data structure
+    open spec fn wf(&self) -> bool {
+        // the log shall be well-formed and the instances match
+        &&& self.log.wf()
+        &&& self.unbounded_log_instance@ == self.log.unbounded_log_instance@
+        &&& self.cyclic_buffer_instance@
+            == self.log.cyclic_buffer_instance@
+        // the number of replicas should be the as configured
+
+        &&& self.replicas.len()
+            <= MAX_REPLICAS
+        // the replicas should be well-formed and the instances match
+
+        &&& (forall|i|
+            0 <= i < self.replicas.len() ==> {
+                &&& (#[trigger] self.replicas[i]).wf()
+                &&& self.replicas[i].spec_id() == i
+                &&& self.replicas[i].replica_token@ == i
+                &&& self.replicas[i].unbounded_log_instance@ == self.unbounded_log_instance@
+                &&& self.replicas[i].cyclic_buffer_instance@ == self.cyclic_buffer_instance@
+            })
+    }
+
+    open spec fn replicas(&self) -> Vec<Box<Self::Replica>> {
+        self.replicas
+    }
+
+    open spec fn unbounded_log_instance(&self) -> UnboundedLog::Instance<DT> {
+        self.log.unbounded_log_instance@
+    }
+
+    /// Creates a new, replicated data-structure from a single-threaded
+    /// data-structure that implements [`Dispatch`]. It uses the [`Default`]
+    /// constructor to create a initial data-structure for `D` on all replicas.
+    ///
+    ///  - Dafny: n/a ?
+    ///  - Rust:  pub fn new(num_replicas: NonZeroUsize) -> Result<Self, NodeReplicatedError>
+    fn new(num_replicas: usize, chg_mem_affinity: AffinityFn) -> (res:
+        Self)
+    // requires
+    //     num_replicas <= MAX_REPLICAS
+    // ensures res.wf()
+    {
+        Self::new_with_preempt_guard::<NoPreemptGuard>(num_replicas, chg_mem_affinity)
+    }
+
     /// Registers a thread with a given replica in the [`NodeReplicated`]
     /// data-structure. Returns an Option containing a [`ThreadToken`] if the
     /// registration was successful. None if the registration failed.
@@ -263,7 +687,26 @@ impl<DT: Dispatch + Sync> crate::NodeReplicatedT<DT> for NodeReplicated<DT> {
         let replica_id = tkn.replica_id() as usize;
         if replica_id < self.replicas.len() {
             // get the replica/node, execute it with the log and provide the thread id.
-            Ok((&self.replicas[replica_id]).execute_mut(&self.log, op, tkn, ticket))
+            Ok((&self.replicas[replica_id]).execute_mut(&self.log, op, tkn, ticket, false))
+        } else {
+            Err((tkn, ticket))
+        }
+    }
+
+    /// Executes a mutable operation against the data-structure, combining eagerly.
+    fn execute_mut_eager(
+        &self,
+        op: DT::WriteOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+    ) -> (result: Result<
+        (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>),
+        (ThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>),
+    >)
+    {
+        let replica_id = tkn.replica_id() as usize;
+        if replica_id < self.replicas.len() {
+            Ok((&self.replicas[replica_id]).execute_mut(&self.log, op, tkn, ticket, true))
         } else {
             Err((tkn, ticket))
         }
@@ -301,6 +744,194 @@ impl<DT: Dispatch + Sync> crate::NodeReplicatedT<DT> for NodeReplicated<DT> {
             Err((tkn, ticket))
         }
     }
+
+    /// Executes a mutable operation against the data-structure, unless the timeout expires
+    /// before the combiner collects the operation.
+    fn execute_mut_timeout(
+        &self,
+        op: DT::WriteOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+        timeout: std::time::Duration,
+    ) -> (result: Result<
+        (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>),
+        (ThreadToken<DT>, Tracked<Option<UnboundedLog::local_updates<DT>>>),
+    >)
+    {
+        let replica_id = tkn.replica_id() as usize;
+        if replica_id < self.replicas.len() {
+            let deadline = Deadline::after(timeout);
+            match (&self.replicas[replica_id]).execute_mut_with_deadline(
+                &self.log,
+                op,
+                tkn,
+                ticket,
+                &deadline,
+            ) {
+                Ok(res) => Ok(res),
+                Err(tkn) => Err((tkn, Tracked(None))),
+            }
+        } else {
+            let tracked ticket = ticket.get();
+            Err((tkn, Tracked(Some(ticket))))
+        }
+    }
+
+    /// Executes a mutable operation against the data-structure, unless the log is full or the
+    /// thread can't become the combiner of its replica right away.
+    fn try_execute_mut(
+        &self,
+        op: DT::WriteOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+    ) -> (result: Result<
+        (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>),
+        (ThreadToken<DT>, Tracked<Option<UnboundedLog::local_updates<DT>>>),
+    >)
+    {
+        let replica_id = tkn.replica_id() as usize;
+        if replica_id < self.replicas.len() {
+            match (&self.replicas[replica_id]).try_execute_mut(&self.log, op, tkn, ticket) {
+                Ok(res) => Ok(res),
+                Err(tkn) => Err((tkn, Tracked(None))),
+            }
+        } else {
+            let tracked ticket = ticket.get();
+            Err((tkn, Tracked(Some(ticket))))
+        }
+    }
+
+    /// Executes a immutable operation against the data-structure, unless the timeout expires
+    /// while the replica is catching up with the log.
+    fn execute_timeout(
+        &self,
+        op: DT::ReadOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+        timeout: std::time::Duration,
+    ) -> (result: Result<
+        (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_reads<DT>>),
+        (ThreadToken<DT>, Tracked<Option<UnboundedLog::local_reads<DT>>>),
+    >)
+    {
+        let replica_id = tkn.replica_id() as usize;
+        if replica_id < self.replicas.len() {
+            let deadline = Some(Deadline::after(timeout));
+            match (&self.replicas[replica_id]).execute_with_deadline(
+                &self.log,
+                op,
+                tkn,
+                ticket,
+                &deadline,
+            ) {
+                Ok(res) => Ok(res),
+                Err(tkn) => Err((tkn, Tracked(None))),
+            }
+        } else {
+            let tracked ticket = ticket.get();
+            Err((tkn, Tracked(Some(ticket))))
+        }
+    }
+
+    /// Executes a immutable operation against the data-structure, unless the replica would have
+    /// to catch up with the log or wait for the combiner first.
+    fn try_execute(
+        &self,
+        op: DT::ReadOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+    ) -> (result: Result<
+        (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_reads<DT>>),
+        (ThreadToken<DT>, Tracked<Option<UnboundedLog::local_reads<DT>>>),
+    >)
+    {
+        let replica_id = tkn.replica_id() as usize;
+        if replica_id < self.replicas.len() {
+            match (&self.replicas[replica_id]).try_execute(&self.log, op, tkn, ticket) {
+                Ok(res) => Ok(res),
+                Err(tkn) => Err((tkn, Tracked(None))),
+            }
+        } else {
+            let tracked ticket = ticket.get();
+            Err((tkn, Tracked(Some(ticket))))
+        }
+    }
+
+    /// Brings the replica of the thread up to date with the log, returns the reached version and
+    /// the tail it had to reach.
+    fn sync(&self, tkn: &ThreadToken<DT>) -> (result: Option<(u64, u64)>) {
+        let replica_id = tkn.replica_id() as usize;
+        if replica_id < self.replicas.len() {
+            Some((&self.replicas[replica_id]).sync(&self.log))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the current version of the data structure, the version upper bound of the log.
+    fn current_version(&self, ticket: Tracked<UnboundedLog::local_reads<DT>>) -> (result: (
+        u64,
+        Tracked<UnboundedLog::local_reads<DT>>,
+    )) {
+        self.log.get_version_upper_bound(ticket)
+    }
+
+    /// Returns the version of the given replica.
+    fn replica_version(&self, replica_id: ReplicaId) -> (result: Option<u64>) {
+        if replica_id < self.replicas.len() {
+            assert(self.replicas[replica_id as int].wf());
+            Some(self.log.get_local_version(replica_id))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the tail of the log.
+    fn log_tail(&self) -> (result: u64) {
+        self.log.get_tail()
+    }
+}
+
+impl<DT: SnapshotDispatch + Sync> NodeReplicated<DT> {
+    /// Captures a consistent snapshot of the whole data structure on the replica of the thread.
+    ///
+    /// The snapshot is taken together with the read `op` and linearized like a read: the
+    /// response of `op` is returned with its stub, and it is the response of `op` on the
+    /// snapshot. Returns the ticket if the thread token does not belong to a replica of this data
+    /// structure.
+    pub fn snapshot(
+        &self,
+        op: DT::ReadOperation,
+        tkn: &ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+    ) -> (result: Result<
+        (DT::Snapshot, DT::Response, Tracked<UnboundedLog::local_reads<DT>>),
+        Tracked<UnboundedLog::local_reads<DT>>,
+    >)
+        requires
+            self.wf(),
+            tkn.wf(&self.replicas()[tkn.replica_id_spec() as int]),
+            is_readonly_ticket(ticket@, op, self.unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> {
+                &&& is_readonly_stub(
+                    result.get_Ok_0().2@,
+                    ticket@@.key,
+                    result.get_Ok_0().1,
+                    self.unbounded_log_instance(),
+                )
+                &&& DT::dispatch_spec(DT::snapshot_view(&result.get_Ok_0().0), op)
+                    == result.get_Ok_0().1
+            },
+            result.is_Err() ==> result.get_Err_0() == ticket,
+    {
+        let replica_id = tkn.replica_id() as usize;
+        if replica_id < self.replicas.len() {
+            Ok((&self.replicas[replica_id]).snapshot(&self.log, op, tkn, ticket))
+        } else {
+            Err(ticket)
+        }
+    }
 }
 
 } // verus!
@@ -14,10 +14,12 @@ use vstd::{
 };
 
 use crate::constants::{
-    MAX_REPLICAS, MAX_REQUESTS, MAX_THREADS_PER_REPLICA, RESPONSE_CHECK_INTERVAL,
+    CONTENDED_CHECK_INTERVAL, CONTENTION_THRESHOLD, MAX_CONTENTION, MAX_IDX, MAX_REPLICAS,
+    MAX_REQUESTS, MAX_THREADS_PER_REPLICA, EAGER_CHECK_INTERVAL, RESPONSE_CHECK_INTERVAL,
+    TRY_COMBINE_ATTEMPTS,
 };
 
-use crate::Dispatch;
+use crate::{Dispatch, PreemptFn, SnapshotDispatch, WaitFn};
 
 // spec import
 use crate::spec::cyclicbuffer::CyclicBuffer;
@@ -29,24 +31,25 @@ use crate::{is_readonly_stub, is_readonly_ticket, is_update_stub, is_update_tick
 
 // exec imports
 use crate::exec::context::{
-    Context, FCClientRequestResponseGhost, PendingOperation, ThreadId, ThreadToken,
+    Context, FCClientRequestResponseGhost, OpHandle, PendingOperation, ResponseSignal, ThreadId,
+    ThreadToken,
 };
 use crate::exec::log::{NrLog, NrLogAppendExecDataGhost};
+use crate::exec::metrics::CombinerMetrics;
 use crate::exec::rwlock::RwLock;
+use crate::exec::trace::{trace_combiner_busy, TraceSpan};
 #[cfg(verus_keep_ghost)]
 use crate::exec::utils::{rids_match, rids_match_add_none, rids_match_add_rid, rids_match_pop};
+use crate::exec::utils::{
+    debug_check_slot, debug_check_slot_transition, debug_invariants_enabled, next_iteration,
+    Deadline,
+};
 use crate::exec::CachePadded;
 
 // use crate::exec::rwlock_unverified::RwLock as RwLockUnverified;
 
 verus! {
 
-#[verus::trusted]
-#[verifier(external_body)]  /* vattr */
-fn spin_loop_hint() {
-    core::hint::spin_loop();
-}
-
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Replica Types
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -111,6 +114,8 @@ pub struct ReplicatedDataStructure<DT: Dispatch> {
     pub replica: Tracked<UnboundedLog::replicas<DT>>,
     ///  - Dafny: glinear combiner: CombinerToken,
     pub combiner: Tracked<UnboundedLog::combiner<DT>>,
+    ///  - Dafny: n/a, the counters of the updates applied to the replica
+    pub applied: Tracked<UnboundedLog::applied<DT>>,
     ///  - Dafny: glinear cb: CBCombinerToken
     pub cb_combiner: Tracked<CyclicBuffer::combiner<DT>>
 }
@@ -123,6 +128,8 @@ pub open spec fn wf(&self, nid: NodeId, inst: UnboundedLog::Instance<DT>, cb: Cy
 
         &&& self.replica@@.value == self.data.view()
         &&& self.replica@@.key == nid
+        &&& self.applied@@.instance == inst
+        &&& self.applied@@.key == nid
         &&& self.combiner@@.value.is_Ready()
         &&& self.combiner@@.key == nid
         &&& self.cb_combiner@@.key == nid
@@ -204,6 +211,25 @@ pub struct Replica<DT: Dispatch> {
     // with the replica when calling [`Replica::register()`].
     pub num_threads: u64, //CachePadded<AtomicU64<_, Tracked<u64>, _>>,
 
+    /// The contention score: raised by failed compare-and-swaps of the combiner lock and of the
+    /// tail of the log, lowered by a successful acquire of the combiner lock. Used to pick the
+    /// combining strategy of updates, see `CONTENTION_THRESHOLD`.
+    pub contention: CachePadded<AtomicU64<_, (), _>>,
+
+    /// Disables preemption while the combiner lock is held, so the combiner can't be
+    /// interrupted by a thread that then waits for its responses.
+    pub preempt: PreemptFn,
+
+    /// How threads wait for their responses, for the replica to catch up, and for its lock.
+    pub wait: WaitFn,
+
+    /// Whether threads park while waiting for their responses, instead of only spinning with
+    /// the wait strategy. Set by [`crate::exec::builder::NodeReplicatedBuilder::response_delivery`].
+    pub park_waiters: bool,
+
+    /// Statistics of the combiner, only recorded with the `metrics` feature.
+    pub metrics: CombinerMetrics,
+
     /// thread token that is handed out to the threads that register
     pub /* REVIEW: (crate) */ thread_tokens: Vec<ThreadToken<DT>>,
 
@@ -240,6 +266,10 @@ pub open spec fn wf(&self) -> bool {
         &&& (g.is_some() ==> g.get_Some_0().inv(flat_combiner_instance@, responses.id(), collected_operations.id(), collected_operations_per_thread.id()))
     }
 
+    invariant on contention specifically (self.contention.0) is (v: u64, g: ()) {
+        v <= MAX_CONTENTION
+    }
+
     // invariant on num_threads with (flat_combiner_instance) specifically (self.num_threads.0)  is (v: u64, g: Tracked<u64>) {
     //     v == g@
     // }
@@ -253,6 +283,8 @@ impl<DT: Dispatch> Replica<DT> {
         replica_token: ReplicaToken,
         num_threads: usize,
         config: Tracked<ReplicaConfig<DT>>,
+        preempt: PreemptFn,
+        wait: WaitFn,
     ) -> (res: Self)
         requires
             num_threads == MAX_THREADS_PER_REPLICA,
@@ -267,6 +299,7 @@ impl<DT: Dispatch> Replica<DT> {
         let tracked ReplicaConfig {
             replica: replica,
             combiner: combiner,
+            applied: applied,
             cb_combiner: cb_combiner,
             unbounded_log_instance: unbounded_log_instance,
             cyclic_buffer_instance: cyclic_buffer_instance,
@@ -307,6 +340,7 @@ impl<DT: Dispatch> Replica<DT> {
             data: DT::init(),
             replica: Tracked(replica),
             combiner: Tracked(combiner),
+            applied: Tracked(applied),
             cb_combiner: Tracked(cb_combiner),
         };
         assert(replicated_data_structure.wf(
@@ -322,6 +356,7 @@ impl<DT: Dispatch> Replica<DT> {
                 MAX_THREADS_PER_REPLICA,
                 replicated_data_structure,
                 Ghost(data_structure_inv),
+                wait.clone(),
             ),
         );
         // let _replicated_data_structure = ReplicatedDataStructure {
@@ -420,6 +455,7 @@ impl<DT: Dispatch> Replica<DT> {
             ),
         );
         let num_threads = 0;  //AtomicU64::new(Ghost(()), 0, Tracked(0));
+        let contention = CachePadded(AtomicU64::new(Ghost(()), 0, Tracked(())));
         //
         // Assemble the data struture
         //
@@ -434,6 +470,11 @@ impl<DT: Dispatch> Replica<DT> {
             // _data,
             thread_tokens,
             num_threads,
+            contention,
+            preempt,
+            wait,
+            park_waiters: false,
+            metrics: CombinerMetrics::new(),
             unbounded_log_instance: Tracked(unbounded_log_instance),
             cyclic_buffer_instance: Tracked(cyclic_buffer_instance),
             flat_combiner_instance: Tracked(fc_instance),
@@ -452,6 +493,19 @@ impl<DT: Dispatch> Replica<DT> {
         self.replica_token.id_spec()
     }
 
+    /// returns whether a thread holds the combiner lock
+    pub fn is_combiner_locked(&self) -> (result: bool)
+        requires
+            self.wf(),
+    {
+        let res =
+            atomic_with_ghost!(
+            &self.combiner.0 => load();
+            ghost g => { }
+        );
+        res != 0
+    }
+
     /// Try to become acquire the combiner lock here. If this fails, then return None.
     ///
     ///  - Dafny: part of method try_combine
@@ -461,6 +515,7 @@ impl<DT: Dispatch> Replica<DT> {
             self.wf(),
         ensures
             result.0 ==> result.1@.is_some(),
+            !result.0 ==> result.1@.is_none(),
             result.0 ==> result.1@.get_Some_0().inv(
                 self.flat_combiner_instance@,
                 self.responses.id(),
@@ -535,26 +590,106 @@ impl<DT: Dispatch> Replica<DT> {
 
     /// Appends an operation to the log and attempts to perform flat combining.
     /// Accepts a thread `tid` as an argument. Required to acquire the combiner lock.
-    fn try_combine(&self, slog: &NrLog<DT>)
+    /// Returns whether the combiner lock was acquired and a round of combining ran.
+    ///
+    /// The combiner is never entered re-entrantly: `combine` consumes the combiner lock
+    /// token, and a failed acquire yields no token. Preemption is disabled while the lock
+    /// is held, so an interrupt handler on this core can't wait for the preempted combiner.
+    /// Why this can't deadlock is argued next to `combiner_step_progress` in the flat combiner.
+    fn try_combine(&self, slog: &NrLog<DT>) -> (acquired: bool)
         requires
             self.wf(),
             slog.wf(),
             self.unbounded_log_instance@ == slog.unbounded_log_instance@,
             slog.cyclic_buffer_instance@ == self.cyclic_buffer_instance@,
     {
+        // Step 0: disable preemption before becoming the combiner
+        let preempt_state = self.preempt.disable();
         // Step 1: try to take the combiner lock to become combiner
         let (acquired, combiner_lock) = self.acquire_combiner_lock();
         // Step 2: if we are the combiner then perform flat combining, else return
         if acquired {
             assert(combiner_lock@.is_some());
             let combiner_lock = Tracked(combiner_lock.get().tracked_unwrap());
+            let span = TraceSpan::combine(self.id());
             let combiner_lock = self.combine(slog, combiner_lock);
             self.release_combiner_lock(combiner_lock);
+            span.exit();
         } else {
-            // nothing to be done here.
+            // nothing to be done here, the lock token is held by the current combiner.
+            assert(combiner_lock@.is_none());
+            trace_combiner_busy(self.id());
+        }
+        self.record_contention(!acquired);
+        // Step 3: restore the preemption state
+        self.preempt.restore(preempt_state);
+        acquired
+    }
+
+    /// Updates the contention score after an attempt to acquire the combiner lock: a failed
+    /// attempt raises it by one, a successful one lowers it by one.
+    fn record_contention(&self, failed: bool)
+        requires
+            self.wf(),
+    {
+        self.update_contention(failed, 1);
+    }
+
+    /// Raises the contention score by the number of compare-and-swaps of the tail of the log
+    /// that failed while the combiner appended its batch.
+    fn record_tail_contention(&self, cas_failures: u64)
+        requires
+            self.wf(),
+    {
+        if cas_failures > 0 {
+            self.update_contention(true, cas_failures);
         }
     }
 
+    /// Adds `delta` to the contention score if `raise`, subtracts it otherwise, saturating at 0
+    /// and `MAX_CONTENTION`.
+    ///
+    /// This is a saturating `fetch_add`/`fetch_sub`: it retries the compare-and-swap until no
+    /// other thread updated the score in between, so concurrent updates are never lost.
+    fn update_contention(&self, raise: bool, delta: u64)
+        requires
+            self.wf(),
+    {
+        let mut done = false;
+        while !done
+            invariant
+                self.wf(),
+        {
+            let score = atomic_with_ghost!(&self.contention.0 => load(); ghost g => { });
+            let next = if raise {
+                if score >= MAX_CONTENTION || delta >= MAX_CONTENTION - score {
+                    MAX_CONTENTION
+                } else {
+                    score + delta
+                }
+            } else if score > delta {
+                score - delta
+            } else {
+                0
+            };
+            let res = atomic_with_ghost!(
+                &self.contention.0 => compare_exchange(score, next);
+                returning res;
+                ghost g => { }
+            );
+            done = res.is_ok();
+        }
+    }
+
+    /// Whether the combiner lock of this replica is currently contended.
+    fn is_contended(&self) -> bool
+        requires
+            self.wf(),
+    {
+        let score = atomic_with_ghost!(&self.contention.0 => load(); ghost g => { });
+        score > CONTENTION_THRESHOLD
+    }
+
     /// Performs one round of flat combining. Collects, appends and executes operations.
     fn combine(
         &self,
@@ -605,10 +740,12 @@ impl<DT: Dispatch> Replica<DT> {
         );
         let tracked ThreadOpsData { flat_combiner, local_updates, request_ids, cell_permissions } =
             collect_res;
+        self.metrics.record_combine(operations.len());
         // Step 2: Take the R/W lock on the data structure
         let (replicated_data_structure, write_handle) = self.data.0.acquire_write();
         let mut data = replicated_data_structure.data;
         let ghost_replica = replicated_data_structure.replica;
+        let applied = replicated_data_structure.applied;
         let combiner = replicated_data_structure.combiner;
         let cb_combiner = replicated_data_structure.cb_combiner;
         // let mut replicated_data_structure = self._data.0.write(MAX_THREADS_PER_REPLICA);
@@ -617,17 +754,19 @@ impl<DT: Dispatch> Replica<DT> {
         let tracked append_exec_ghost_data = NrLogAppendExecDataGhost {
             local_updates,
             ghost_replica,
+            applied,
             combiner,
             cb_combiner,
             request_ids,
         };
-        let append_exec_ghost_data = slog.append(
+        let (cas_failures, append_exec_ghost_data) = slog.append(
             &self.replica_token,
             &operations,
             &mut responses,
             &mut data,
             Tracked(append_exec_ghost_data),
         );
+        self.record_tail_contention(cas_failures);
         // TODO: release lock here! upstream does release the lock here and the reacquire it!
         // drop(replicated_data_structure);
         // Step 3: Execute all operations
@@ -641,17 +780,20 @@ impl<DT: Dispatch> Replica<DT> {
         let tracked NrLogAppendExecDataGhost {
             local_updates,
             ghost_replica,
+            applied,
             combiner,
             cb_combiner,
             request_ids,
         } = append_exec_ghost_data;
         let tracked ghost_replica = ghost_replica.get();
+        let tracked applied = applied.get();
         let tracked combiner = combiner.get();
         let tracked cb_combiner = cb_combiner.get();
         // Step 4: release the R/W lock on the data structure
         let replicated_data_structure = ReplicatedDataStructure {
             data,
             replica: Tracked(ghost_replica),
+            applied: Tracked(applied),
             combiner: Tracked(combiner),
             cb_combiner: Tracked(cb_combiner),
         };
@@ -771,14 +913,15 @@ impl<DT: Dispatch> Replica<DT> {
         {
             let tracked update_req: std::option::Option<UnboundedLog::local_updates<DT>>;
             let tracked batch_perms: std::option::Option<PointsTo<PendingOperation<DT>>>;
-            let num_ops =
+            // mark the operation as collected, so the client can no longer withdraw it
+            let res =
                 atomic_with_ghost!(
-                &self.contexts[thread_idx].atomic.0 => load();
-                returning num_ops;
+                &self.contexts[thread_idx].atomic.0 => compare_exchange(1, 2);
+                update prev->next;
                 ghost g // g : ContextGhost
             => {
-                if num_ops == 1 {
-                    self.flat_combiner_instance.borrow().pre_combiner_collect_request(&g.slots, flat_combiner.borrow());
+                self.flat_combiner_instance.borrow().pre_combiner_collect_request(&g.slots, flat_combiner.borrow());
+                if prev == 1 {
 
                     rids_match_add_rid(flat_combiner.view().view().value.get_Collecting_0(), request_ids,
                         0, flat_combiner.view().view().value.get_Collecting_0().len(), 0, request_ids.len(),g.update.get_Some_0().view().key);
@@ -798,6 +941,15 @@ impl<DT: Dispatch> Replica<DT> {
                     batch_perms = None;
                 }
             });
+            let num_ops: u64 = match res {
+                Result::Ok(_) => 1,
+                Result::Err(prev) => {
+                    if debug_invariants_enabled() {
+                        debug_check_slot(prev);
+                    }
+                    0
+                },
+            };
             if num_ops == 1 {
                 let tracked batch_token_value = batch_perms.tracked_unwrap();
                 let op = DT::clone_write_op(
@@ -958,6 +1110,15 @@ impl<DT: Dispatch> Replica<DT> {
                 // place the element back into the batch
                 self.contexts[thread_idx].batch.0.put(Tracked(&mut permission), op_resp);
                 //     operations[i - 1] = 0;
+                if debug_invariants_enabled() {
+                    let prev =
+                        atomic_with_ghost!(
+                        &self.contexts[thread_idx].atomic.0 => load();
+                        returning prev;
+                        ghost g => { }
+                    );
+                    debug_check_slot_transition(prev, 0);
+                }
                 atomic_with_ghost!(
                     &self.contexts[thread_idx].atomic.0 => store(0);
                     update prev -> next;
@@ -969,6 +1130,9 @@ impl<DT: Dispatch> Replica<DT> {
                         g.update = Some(updates.tracked_remove(resp_idx as nat));
                     }
                 );
+                if self.park_waiters {
+                    self.contexts[thread_idx].signal.notify(&self.wait);
+                }
                 resp_idx = resp_idx + 1;
             }
             thread_idx = thread_idx + 1;
@@ -1000,7 +1164,47 @@ impl<DT: Dispatch> Replica<DT> {
         self.thread_tokens.pop()
     }
 
-    #[verifier(external_body)]  /* vattr */
+    /// Returns the token of a thread that no longer uses this replica, so it can be handed out
+    /// to the next thread that registers.
+    pub fn unregister(&mut self, tkn: ThreadToken<DT>)
+        requires
+            old(self).wf(),
+            tkn.wf(old(self)),
+        ensures
+            self.wf(),
+            old(self).replica_token@ == self.replica_token@,
+            old(self).unbounded_log_instance@ == self.unbounded_log_instance@,
+            old(self).cyclic_buffer_instance@ == self.cyclic_buffer_instance@,
+    {
+        self.thread_tokens.push(tkn);
+    }
+
+    /// Limits the number of threads that can register with this replica to `threads`. The tokens
+    /// are handed out from the back, so the ones of the threads with the lowest ids are kept.
+    pub fn limit_threads(&mut self, threads: usize)
+        requires
+            old(self).wf(),
+        ensures
+            self.wf(),
+            self.thread_tokens.len() <= threads,
+            old(self).spec_id() == self.spec_id(),
+            old(self).replica_token@ == self.replica_token@,
+            old(self).unbounded_log_instance@ == self.unbounded_log_instance@,
+            old(self).cyclic_buffer_instance@ == self.cyclic_buffer_instance@,
+    {
+        while self.thread_tokens.len() > threads
+            invariant
+                self.wf(),
+                old(self).spec_id() == self.spec_id(),
+                old(self).replica_token@ == self.replica_token@,
+                old(self).unbounded_log_instance@ == self.unbounded_log_instance@,
+                old(self).cyclic_buffer_instance@ == self.cyclic_buffer_instance@,
+        {
+            self.thread_tokens.pop();
+        }
+    }
+
+    #[verifier::external_body]
     pub fn progress(line: u32) {
         println!("Replica:: progress {line}");
     }
@@ -1031,19 +1235,64 @@ impl<DT: Dispatch> Replica<DT> {
                 == self.contexts[result.1.thread_id_spec() as int].batch.0.id(),
             is_readonly_stub(result.2@, ticket@@.key, result.0, slog.unbounded_log_instance@),
     {
-        // let tracked local_reads : UnboundedLog::local_reads<DT>;
-        // proof {
-        //     let tracked ticket = self.unbounded_log_instance.borrow().readonly_start(op);
-        //     local_reads = ticket.1.get();
-        // }
-        let ghost rid: nat = ticket@@.key;
-        proof {
-            rid = ticket@@.key;
+        match self.execute_with_deadline(slog, op, tkn, ticket, &None) {
+            Ok(res) => res,
+            Err(_) => unreached(),
         }
-        let ghost nid = tkn.replica_id_spec();
-        // Step 1: Read the local tail value
-        // let ctail = slog.get_ctail();
-        let (version_upper_bound, ticket) = slog.get_version_upper_bound(ticket);
+    }
+
+    /// Executes an immutable operation against this replica, giving up once the deadline
+    /// has passed.
+    ///
+    /// The deadline is only checked while waiting for the replica to catch up with the log. If
+    /// it has passed, the read is withdrawn (its ticket is consumed) and the thread token is
+    /// returned in the error. Without a deadline the read always completes.
+    pub fn execute_with_deadline(
+        &self,
+        slog: &NrLog<DT>,
+        op: DT::ReadOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+        deadline: &Option<Deadline>,
+    ) -> (result: Result<
+        (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_reads<DT>>),
+        ThreadToken<DT>,
+    >)
+        requires
+            self.wf(),
+            slog.wf(),
+            tkn.wf(self),
+            tkn.batch_perm@@.pcell == self.contexts[tkn.thread_id_spec() as int].batch.0.id(),
+            self.replica_token@ == tkn.replica_token()@,
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+            is_readonly_ticket(ticket@, op, slog.unbounded_log_instance@),
+        ensures
+            deadline.is_None() ==> result.is_Ok(),
+            result.is_Ok() ==> {
+                &&& result.get_Ok_0().1.wf(&self)
+                &&& result.get_Ok_0().1.batch_perm@@.pcell
+                    == self.contexts[result.get_Ok_0().1.thread_id_spec() as int].batch.0.id()
+                &&& is_readonly_stub(
+                    result.get_Ok_0().2@,
+                    ticket@@.key,
+                    result.get_Ok_0().0,
+                    slog.unbounded_log_instance@,
+                )
+            },
+            result.is_Err() ==> result.get_Err_0() == tkn,
+    {
+        let ghost rid: nat = ticket@@.key;
+        // Step 1: read right away if the replica has already caught up with the log
+        let (op, version_upper_bound, mut ticket) = match self.read_fast_path(
+            slog,
+            op,
+            &tkn,
+            ticket,
+        ) {
+            Ok((result, ticket)) => return Ok((result, tkn, ticket)),
+            Err(slow_path) => slow_path,
+        };
         // Step 2: wait until the replica is synced for reads, try to combine in mean time
         // while !slog.is_replica_synced_for_reads(&self.log_tkn, ctail) {
         //     if let Err(e) = self.try_combine(slog) {
@@ -1051,11 +1300,9 @@ impl<DT: Dispatch> Replica<DT> {
         //     }
         //     spin_loop();
         // }
-        let (mut is_synced, mut ticket) = slog.is_replica_synced_for_reads(
-            self.id(),
-            version_upper_bound,
-            ticket,
-        );
+        let wait_span = TraceSpan::read_wait(self.id(), version_upper_bound);
+        let mut is_synced = false;
+        let mut iteration: usize = 0;
         while !is_synced
             invariant
                 self.wf(),
@@ -1072,23 +1319,113 @@ impl<DT: Dispatch> Replica<DT> {
                 slog.unbounded_log_instance@ == self.unbounded_log_instance@,
                 slog.cyclic_buffer_instance@ == self.cyclic_buffer_instance@,
         {
+            if let Some(deadline) = deadline {
+                if deadline.expired() {
+                    // the read hasn't picked a replica yet, so it can still be withdrawn
+                    proof {
+                        self.unbounded_log_instance.borrow().readonly_cancel(rid, ticket.get());
+                    }
+                    return Err(tkn);
+                }
+            }
             self.try_combine(slog);
-            spin_loop_hint();
+            self.wait.call(iteration);
+            iteration = next_iteration(iteration);
             let res = slog.is_replica_synced_for_reads(self.id(), version_upper_bound, ticket);
             is_synced = res.0;
             ticket = res.1;
         }
-        let tracked ticket = ticket.get();
+        wait_span.exit();
+        // Step 3: Take the read-only lock, and read the value
+        let (result, ticket) = self.read_synced(op, &tkn, ticket);
+        Ok((result, tkn, ticket))
+    }
+
+    /// The fast path of a read: the replica has already caught up with the version upper bound of
+    /// the log, so the read takes the read lock and dispatches right away.
+    ///
+    /// The path never combines or waits for the log. It only loads the version upper bound and
+    /// the local version of the replica and takes the read lock of the thread; its transitions
+    /// only change the `local_reads` token of the read (`lemma_reads_do_not_modify_shared_state`).
+    /// If the replica is behind, the operation, the version upper bound and the ticket holding it
+    /// are returned for the slow path.
+    #[inline(always)]
+    fn read_fast_path(
+        &self,
+        slog: &NrLog<DT>,
+        op: DT::ReadOperation,
+        tkn: &ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+    ) -> (result: Result<
+        (DT::Response, Tracked<UnboundedLog::local_reads<DT>>),
+        (DT::ReadOperation, u64, Tracked<UnboundedLog::local_reads<DT>>),
+    >)
+        requires
+            self.wf(),
+            slog.wf(),
+            tkn.wf(self),
+            self.replica_token@ == tkn.replica_token()@,
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+            is_readonly_ticket(ticket@, op, slog.unbounded_log_instance@),
+        ensures
+            result.is_Ok() ==> is_readonly_stub(
+                result.get_Ok_0().1@,
+                ticket@@.key,
+                result.get_Ok_0().0,
+                slog.unbounded_log_instance@,
+            ),
+            result.is_Err() ==> {
+                &&& result.get_Err_0().0 == op
+                &&& result.get_Err_0().2@@.value.is_VersionUpperBound()
+                &&& result.get_Err_0().2@@.value.get_VersionUpperBound_version_upper_bound()
+                    == result.get_Err_0().1
+                &&& result.get_Err_0().2@@.value.get_VersionUpperBound_op() == op
+                &&& result.get_Err_0().2@@.instance == slog.unbounded_log_instance@
+                &&& result.get_Err_0().2@@.key == ticket@@.key
+            },
+    {
+        // Step 1: Read the version upper bound of the log
+        let (version_upper_bound, ticket) = slog.get_version_upper_bound(ticket);
+        // Step 2: Check once whether the replica has reached it
+        let (is_synced, ticket) = slog.is_replica_synced_for_reads(
+            self.id(),
+            version_upper_bound,
+            ticket,
+        );
+        if !is_synced {
+            return Err((op, version_upper_bound, ticket));
+        }
         // Step 3: Take the read-only lock, and read the value
+        Ok(self.read_synced(op, tkn, ticket))
+    }
+
+    /// Dispatches a read on the replica once it has caught up with the version upper bound of
+    /// the read.
+    #[inline(always)]
+    fn read_synced(
+        &self,
+        op: DT::ReadOperation,
+        tkn: &ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+    ) -> (result: (DT::Response, Tracked<UnboundedLog::local_reads<DT>>))
+        requires
+            self.wf(),
+            tkn.wf(self),
+            ticket@@.instance == self.unbounded_log_instance@,
+            ticket@@.value.is_ReadyToRead(),
+            ticket@@.value.get_ReadyToRead_node_id() == self.spec_id(),
+            ticket@@.value.get_ReadyToRead_op() == op,
+        ensures
+            is_readonly_stub(result.1@, ticket@@.key, result.0, self.unbounded_log_instance@),
+    {
+        let ghost rid = ticket@@.key;
+        let tracked ticket = ticket.get();
         // let res = self.data.read(idx.tid() - 1).dispatch(op)
         assert(tkn.thread_id_spec() < self.data.0.max_threads());
-        // let result = self._data.0.read(tkn.thread_id() as usize).data.dispatch(op);
         let read_handle = self.data.0.acquire_read(tkn.thread_id() as usize);
         let replica = self.data.0.borrow(Tracked(&read_handle));
         let result = replica.data.dispatch(op);
-        // assert(replica.wf(self.spec_id(), self.unbounded_log_instance@, self.cyclic_buffer_instance@));
-        // assert(replica.replica@.view().instance == self.unbounded_log_instance);
-        // assert(replica.replica@.view().key == rid);
         let tracked ticket = self.unbounded_log_instance.borrow().readonly_apply(
             rid,
             replica.replica.borrow(),
@@ -1096,18 +1433,155 @@ impl<DT: Dispatch> Replica<DT> {
             replica.combiner.borrow(),
         );
         self.data.0.release_read(read_handle);
-        // // Step 4: Finish the read-only transaction, return result
-        // let tracked local_reads = local_reads;
-        // proof {
-        //     self.unbounded_log_instance.borrow().readonly_finish(rid, op, result, local_reads);
-        // }
-        // assert(false);
-        (result, tkn, Tracked(ticket))
+        (result, Tracked(ticket))
+    }
+
+    /// Executes an immutable operation against this replica only if it can be done without
+    /// waiting.
+    ///
+    /// The read succeeds if the replica has already caught up with the version upper bound of the
+    /// log and its data is not locked by the combiner. It never combines, spins, or waits for a
+    /// lock. Otherwise the read is withdrawn (its ticket is consumed) and the thread token is
+    /// returned in the error.
+    pub fn try_execute(
+        &self,
+        slog: &NrLog<DT>,
+        op: DT::ReadOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+    ) -> (result: Result<
+        (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_reads<DT>>),
+        ThreadToken<DT>,
+    >)
+        requires
+            self.wf(),
+            slog.wf(),
+            tkn.wf(self),
+            tkn.batch_perm@@.pcell == self.contexts[tkn.thread_id_spec() as int].batch.0.id(),
+            self.replica_token@ == tkn.replica_token()@,
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+            is_readonly_ticket(ticket@, op, slog.unbounded_log_instance@),
+        ensures
+            result.is_Ok() ==> {
+                &&& result.get_Ok_0().1.wf(&self)
+                &&& result.get_Ok_0().1.batch_perm@@.pcell
+                    == self.contexts[result.get_Ok_0().1.thread_id_spec() as int].batch.0.id()
+                &&& is_readonly_stub(
+                    result.get_Ok_0().2@,
+                    ticket@@.key,
+                    result.get_Ok_0().0,
+                    slog.unbounded_log_instance@,
+                )
+            },
+            result.is_Err() ==> result.get_Err_0() == tkn,
+    {
+        let ghost rid: nat = ticket@@.key;
+        // Step 1: Read the version upper bound and check once whether the replica has reached it
+        let (version_upper_bound, ticket) = slog.get_version_upper_bound(ticket);
+        let (is_synced, ticket) = slog.is_replica_synced_for_reads(
+            self.id(),
+            version_upper_bound,
+            ticket,
+        );
+        if !is_synced {
+            // the read hasn't picked a replica yet, so it can still be withdrawn
+            proof {
+                self.unbounded_log_instance.borrow().readonly_cancel(rid, ticket.get());
+            }
+            return Err(tkn);
+        }
+        let tracked ticket = ticket.get();
+        // Step 2: Take the read-only lock if the combiner doesn't hold it, and read the value
+        assert(tkn.thread_id_spec() < self.data.0.max_threads());
+        let read_handle = match self.data.0.try_acquire_read(tkn.thread_id() as usize) {
+            Some(read_handle) => read_handle,
+            None => {
+                // the replica was picked but not read from yet
+                proof {
+                    self.unbounded_log_instance.borrow().readonly_cancel(rid, ticket);
+                }
+                return Err(tkn);
+            },
+        };
+        let replica = self.data.0.borrow(Tracked(&read_handle));
+        let result = replica.data.dispatch(op);
+        let tracked ticket = self.unbounded_log_instance.borrow().readonly_apply(
+            rid,
+            replica.replica.borrow(),
+            ticket,
+            replica.combiner.borrow(),
+        );
+        self.data.0.release_read(read_handle);
+        Ok((result, tkn, Tracked(ticket)))
+    }
+
+    /// Runs one round of the combiner of this replica on behalf of an idle thread, which may be
+    /// registered with another replica. Returns whether the replica was behind the tail of the
+    /// log and the thread acquired its combiner lock and ran the combiner.
+    ///
+    /// The combiner lock doesn't belong to a thread of the replica: whoever holds it collects
+    /// the operations of the replica's threads, appends them, executes the log on the replica and
+    /// delivers the responses. A thread of another replica is a combiner like any other.
+    pub fn try_help(&self, slog: &NrLog<DT>) -> (result: bool)
+        requires
+            self.wf(),
+            slog.wf(),
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+    {
+        if self.is_combiner_locked() {
+            return false;
+        }
+        let tail = slog.get_tail();
+        let version = slog.get_local_version(self.id());
+        if version >= tail {
+            return false;
+        }
+        self.try_combine(slog)
+    }
+
+    /// Runs the combiner until this replica has applied all updates up to the current tail of
+    /// the log. Returns the version the replica has reached and the tail.
+    pub fn sync(&self, slog: &NrLog<DT>) -> (result: (u64, u64))
+        requires
+            self.wf(),
+            slog.wf(),
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+        ensures
+            result.1 <= result.0 <= MAX_IDX,
+    {
+        // Step 1: read the tail, all updates up to here must be applied to the replica
+        let tail = slog.get_tail();
+        // Step 2: combine until the local version has reached the tail
+        let mut version = slog.get_local_version(self.id());
+        let mut iteration: usize = 0;
+        while version < tail
+            invariant
+                self.wf(),
+                slog.wf(),
+                self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+                self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+                tail <= MAX_IDX,
+                version <= MAX_IDX,
+        {
+            self.try_combine(slog);
+            self.wait.call(iteration);
+            iteration = next_iteration(iteration);
+            version = slog.get_local_version(self.id());
+        }
+        (version, tail)
     }
 
     /// Executes a mutable operation against this replica and returns a
     /// response.
     ///
+    /// An `eager` update doesn't leave the combining to the current combiner on a contended
+    /// replica, tries to become the combiner more often while it waits, and never parks. It is
+    /// not ordered before the other updates: the combiner collects the pending updates in the
+    /// order of the thread contexts and delivers the responses in the same order.
+    ///
     /// In Dafny this refers to do_operation
     pub fn execute_mut(
         &self,
@@ -1115,6 +1589,7 @@ impl<DT: Dispatch> Replica<DT> {
         op: DT::WriteOperation,
         tkn: ThreadToken<DT>,
         ticket: Tracked<UnboundedLog::local_updates<DT>>,
+        eager: bool,
     ) -> (result: (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>))
         requires
             slog.wf(),
@@ -1145,10 +1620,28 @@ impl<DT: Dispatch> Replica<DT> {
         };
         let mk_pending_res = self.make_pending(op, tid, Tracked(context_ghost));
         let context_ghost = mk_pending_res.1;
-        // Step 2: Try to do flat combining to appy the update to the data structure
-        self.try_combine(slog);
+        // Step 2: Try to do flat combining to appy the update to the data structure. If the
+        // replica is contended, another thread is most likely combining already: leave the
+        // update to it and only try to combine after a short back-off. Both strategies go
+        // through the same combiner, they only differ in when we attempt to become combiner.
+        let check_interval = if eager {
+            self.try_combine(slog);
+            EAGER_CHECK_INTERVAL
+        } else if self.is_contended() {
+            CONTENDED_CHECK_INTERVAL
+        } else {
+            self.try_combine(slog);
+            RESPONSE_CHECK_INTERVAL
+        };
         // Step 3: Obtain the result form the responses
-        let response = self.get_response(slog, tid, Ghost(req_id), context_ghost);
+        let response = self.get_response(
+            slog,
+            tid,
+            Ghost(req_id),
+            context_ghost,
+            check_interval,
+            self.park_waiters && !eager,
+        );
         let context_ghost = response.1;
         let tracked FCClientRequestResponseGhost {
             batch_perms: batch_perms,
@@ -1170,6 +1663,397 @@ impl<DT: Dispatch> Replica<DT> {
         )
     }
 
+    /// Executes a mutable operation against the replica only if it doesn't have to wait.
+    ///
+    /// The update is submitted only if the log has room for it and the thread obtains the
+    /// combiner lock within `TRY_COMBINE_ATTEMPTS` attempts. The thread then combines its own
+    /// update and never waits for another combiner. Otherwise the update is withdrawn before it
+    /// is enqueued and the ticket is consumed. The log may still fill up between the check and
+    /// the append, the combiner then waits for the garbage collection as usual.
+    pub fn try_execute_mut(
+        &self,
+        slog: &NrLog<DT>,
+        op: DT::WriteOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+    ) -> (result: Result<
+        (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>),
+        ThreadToken<DT>,
+    >)
+        requires
+            slog.wf(),
+            self.wf(),
+            tkn.wf(self),
+            tkn.batch_perm@@.pcell == self.contexts[tkn.thread_id_spec() as int].batch.0.id(),
+            self.replica_token == tkn.replica_token(),
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+            is_update_ticket(ticket@, op, slog.unbounded_log_instance@),
+        ensures
+            result.is_Ok() ==> {
+                &&& result.get_Ok_0().1.wf(self)
+                &&& result.get_Ok_0().1.batch_perm@@.pcell
+                    == self.contexts[result.get_Ok_0().1.thread_id_spec() as int].batch.0.id()
+                &&& is_update_stub(
+                    result.get_Ok_0().2@,
+                    ticket@@.key,
+                    result.get_Ok_0().0,
+                    slog.unbounded_log_instance@,
+                )
+            },
+            result.is_Err() ==> result.get_Err_0() == tkn,
+    {
+        let tracked ticket = ticket.get();
+        let ghost req_id: nat = ticket@.key;
+        // Step 1: the combiner would wait for the garbage collection of a full log
+        if slog.is_full() {
+            // the update is still in `Init`, withdraw it before it reaches the combiner
+            proof {
+                self.unbounded_log_instance.borrow().update_cancel(req_id, ticket);
+            }
+            return Err(tkn);
+        }
+        // Step 2: try to become the combiner, giving up after a bounded number of attempts
+        let preempt_state = self.preempt.disable();
+        let res = self.acquire_combiner_lock();
+        let mut acquired = res.0;
+        let mut combiner_lock = res.1;
+        let mut attempts: usize = 1;
+        while !acquired && attempts < TRY_COMBINE_ATTEMPTS
+            invariant
+                self.wf(),
+                1 <= attempts <= TRY_COMBINE_ATTEMPTS,
+                acquired ==> combiner_lock@.is_some(),
+                acquired ==> combiner_lock@.get_Some_0().inv(
+                    self.flat_combiner_instance@,
+                    self.responses.id(),
+                    self.collected_operations.id(),
+                    self.collected_operations_per_thread.id(),
+                ),
+        {
+            self.wait.call(attempts);
+            let res = self.acquire_combiner_lock();
+            acquired = res.0;
+            combiner_lock = res.1;
+            attempts = attempts + 1;
+        }
+        self.record_contention(!acquired);
+        if !acquired {
+            self.preempt.restore(preempt_state);
+            trace_combiner_busy(self.id());
+            // the update hasn't been enqueued, withdraw it
+            proof {
+                self.unbounded_log_instance.borrow().update_cancel(req_id, ticket);
+            }
+            return Err(tkn);
+        }
+        let combiner_lock = Tracked(combiner_lock.get().tracked_unwrap());
+        // Step 3: enqueue the operation, we hold the combiner lock so our round collects it
+        let ThreadToken { rid, tid, fc_client, batch_perm } = tkn;
+        let tracked context_ghost = FCClientRequestResponseGhost {
+            batch_perms: Some(batch_perm.get()),
+            cell_id: Ghost(self.contexts[tkn.thread_id_spec() as int].batch.0.id()),
+            local_updates: Some(ticket),
+            fc_clients: fc_client.get(),
+        };
+        let mk_pending_res = self.make_pending(op, tid, Tracked(context_ghost));
+        let context_ghost = mk_pending_res.1;
+        // Step 4: combine, this appends and applies the update
+        let span = TraceSpan::combine(self.id());
+        let combiner_lock = self.combine(slog, combiner_lock);
+        self.release_combiner_lock(combiner_lock);
+        span.exit();
+        self.preempt.restore(preempt_state);
+        // Step 5: the response is already in the context
+        let response = self.get_response(
+            slog,
+            tid,
+            Ghost(req_id),
+            context_ghost,
+            RESPONSE_CHECK_INTERVAL,
+            false,
+        );
+        let context_ghost = response.1;
+        let tracked FCClientRequestResponseGhost {
+            batch_perms: batch_perms,
+            cell_id,
+            local_updates: ticket,
+            fc_clients: fc_clients,
+        } = context_ghost.get();
+        let tracked ticket = ticket.tracked_unwrap();
+        let tracked batch_perm = batch_perms.tracked_unwrap();
+        Ok(
+            (
+                response.0,
+                ThreadToken {
+                    rid,
+                    tid,
+                    fc_client: Tracked(fc_clients),
+                    batch_perm: Tracked(batch_perm),
+                },
+                Tracked(ticket),
+            ),
+        )
+    }
+
+    /// Submits a mutable operation to the replica without waiting for its response.
+    ///
+    /// The operation is enqueued and, unless the replica is contended, the thread tries once to
+    /// become the combiner. The returned handle holds the thread token until the response is
+    /// collected with `poll_response` or `wait_response`.
+    pub fn submit_mut(
+        &self,
+        slog: &NrLog<DT>,
+        op: DT::WriteOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+    ) -> (result: OpHandle<DT>)
+        requires
+            slog.wf(),
+            self.wf(),
+            tkn.wf(self),
+            tkn.batch_perm@@.pcell == self.contexts[tkn.thread_id_spec() as int].batch.0.id(),
+            self.replica_token == tkn.replica_token(),
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+            is_update_ticket(ticket@, op, slog.unbounded_log_instance@),
+        ensures
+            result.wf(self),
+            result.req_id() == ticket@@.key,
+            result.rid == tkn.rid,
+            result.tid == tkn.tid,
+    {
+        let tracked ticket = ticket.get();
+        let ghost req_id: nat = ticket@.key;
+        let ThreadToken { rid, tid, fc_client, batch_perm } = tkn;
+        // Step 1: Enqueue the operation onto the thread local batch
+        let tracked context_ghost = FCClientRequestResponseGhost {
+            batch_perms: Some(batch_perm.get()),
+            cell_id: Ghost(self.contexts[tkn.thread_id_spec() as int].batch.0.id()),
+            local_updates: Some(ticket),
+            fc_clients: fc_client.get(),
+        };
+        let mk_pending_res = self.make_pending(op, tid, Tracked(context_ghost));
+        // Step 2: Try to combine once, on a contended replica leave it to the current combiner
+        if !self.is_contended() {
+            self.try_combine(slog);
+        }
+        OpHandle {
+            rid,
+            tid,
+            req_id: Ghost(req_id),
+            response: None,
+            context_ghost: mk_pending_res.1,
+        }
+    }
+
+    /// Collects the response of a submitted operation if it is available.
+    ///
+    /// If the response isn't there yet, the thread tries once to become the combiner and checks
+    /// again, so polling makes progress even if no other thread combines. Never waits otherwise.
+    pub fn poll_response(&self, slog: &NrLog<DT>, handle: OpHandle<DT>) -> (result: OpHandle<DT>)
+        requires
+            slog.wf(),
+            self.wf(),
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+            handle.wf(self),
+        ensures
+            result.wf(self),
+            result.req_id() == handle.req_id(),
+            result.rid == handle.rid,
+            result.tid == handle.tid,
+            handle.response.is_Some() ==> result == handle,
+    {
+        if handle.response.is_some() {
+            return handle;
+        }
+        let OpHandle { rid, tid, req_id, response, context_ghost } = handle;
+        let context = &self.contexts[tid as usize];
+        // Step 1: check for the response, combine once if it isn't there yet
+        let deq_resp_result = context.dequeue_response(context_ghost);
+        let deq_resp_result = if deq_resp_result.0.is_none() {
+            self.try_combine(slog);
+            context.dequeue_response(deq_resp_result.1)
+        } else {
+            deq_resp_result
+        };
+        OpHandle { rid, tid, req_id, response: deq_resp_result.0, context_ghost: deq_resp_result.1 }
+    }
+
+    /// Withdraws a submitted operation if the combiner hasn't collected it yet.
+    ///
+    /// Returns the thread token if the operation was withdrawn: it was never placed into the log
+    /// and its ticket is consumed by the cancellation. Otherwise the operation has been
+    /// collected and will be applied, the handle is returned unchanged.
+    pub fn cancel_op(&self, handle: OpHandle<DT>) -> (result: Result<
+        ThreadToken<DT>,
+        OpHandle<DT>,
+    >)
+        requires
+            self.wf(),
+            handle.wf(self),
+        ensures
+            result.is_Ok() ==> {
+                &&& result.get_Ok_0().wf(self)
+                &&& result.get_Ok_0().batch_perm@@.pcell
+                    == self.contexts[result.get_Ok_0().thread_id_spec() as int].batch.0.id()
+                &&& result.get_Ok_0().rid == handle.rid
+                &&& result.get_Ok_0().tid == handle.tid
+            },
+            result.is_Err() ==> result.get_Err_0() == handle,
+    {
+        if handle.response.is_some() {
+            return Err(handle);
+        }
+        let OpHandle { rid, tid, req_id, response, context_ghost } = handle;
+        let context = &self.contexts[tid as usize];
+        let withdraw_result = context.withdraw_op(context_ghost);
+        let context_ghost = withdraw_result.1;
+        if !withdraw_result.0 {
+            return Err(OpHandle { rid, tid, req_id, response, context_ghost });
+        }
+        let tracked FCClientRequestResponseGhost {
+            batch_perms: batch_perms,
+            cell_id,
+            local_updates: ticket,
+            fc_clients: fc_clients,
+        } = context_ghost.get();
+        let tracked ticket = ticket.tracked_unwrap();
+        // the update is still in `Init`, withdraw it from the log as well
+        proof {
+            self.unbounded_log_instance.borrow().update_cancel(req_id@, ticket);
+        }
+        let tracked batch_perm = batch_perms.tracked_unwrap();
+        Ok(
+            ThreadToken {
+                rid,
+                tid,
+                fc_client: Tracked(fc_clients),
+                batch_perm: Tracked(batch_perm),
+            },
+        )
+    }
+
+    /// Waits for the response of a submitted operation and returns the thread token.
+    ///
+    /// Waits the same way as `execute_mut` does after enqueuing the operation.
+    pub fn wait_response(&self, slog: &NrLog<DT>, handle: OpHandle<DT>) -> (result: (
+        DT::Response,
+        ThreadToken<DT>,
+        Tracked<UnboundedLog::local_updates<DT>>,
+    ))
+        requires
+            slog.wf(),
+            self.wf(),
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+            handle.wf(self),
+        ensures
+            result.1.wf(self),
+            result.1.batch_perm@@.pcell
+                == self.contexts[result.1.thread_id_spec() as int].batch.0.id(),
+            is_update_stub(result.2@, handle.req_id(), result.0, slog.unbounded_log_instance@),
+    {
+        let handle = if handle.response.is_none() {
+            let OpHandle { rid, tid, req_id, response, context_ghost } = handle;
+            let check_interval = if self.is_contended() {
+                CONTENDED_CHECK_INTERVAL
+            } else {
+                RESPONSE_CHECK_INTERVAL
+            };
+            let response = self.get_response(
+                slog,
+                tid,
+                req_id,
+                context_ghost,
+                check_interval,
+                self.park_waiters,
+            );
+            OpHandle { rid, tid, req_id, response: Some(response.0), context_ghost: response.1 }
+        } else {
+            handle
+        };
+        match handle.take_response() {
+            Ok(res) => res,
+            Err(_) => unreached(),
+        }
+    }
+
+    /// Executes a mutable operation against the replica, unless the deadline passes before the
+    /// combiner collects it.
+    ///
+    /// The update is submitted and polled until its response is there. Once the deadline has
+    /// passed, the thread tries to withdraw the update: if the combiner hasn't collected it, it
+    /// is cancelled and never applied. Otherwise it has been placed into the log and will be
+    /// applied exactly once, so the thread waits for its response as `execute_mut` does.
+    pub fn execute_mut_with_deadline(
+        &self,
+        slog: &NrLog<DT>,
+        op: DT::WriteOperation,
+        tkn: ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+        deadline: &Deadline,
+    ) -> (result: Result<
+        (DT::Response, ThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>),
+        ThreadToken<DT>,
+    >)
+        requires
+            slog.wf(),
+            self.wf(),
+            tkn.wf(self),
+            tkn.batch_perm@@.pcell == self.contexts[tkn.thread_id_spec() as int].batch.0.id(),
+            self.replica_token == tkn.replica_token(),
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+            is_update_ticket(ticket@, op, slog.unbounded_log_instance@),
+        ensures
+            result.is_Ok() ==> {
+                &&& result.get_Ok_0().1.wf(self)
+                &&& result.get_Ok_0().1.batch_perm@@.pcell
+                    == self.contexts[result.get_Ok_0().1.thread_id_spec() as int].batch.0.id()
+                &&& is_update_stub(
+                    result.get_Ok_0().2@,
+                    ticket@@.key,
+                    result.get_Ok_0().0,
+                    slog.unbounded_log_instance@,
+                )
+            },
+            result.is_Err() ==> {
+                &&& result.get_Err_0().wf(self)
+                &&& result.get_Err_0().batch_perm@@.pcell
+                    == self.contexts[result.get_Err_0().thread_id_spec() as int].batch.0.id()
+            },
+    {
+        let ghost req_id: nat = ticket@@.key;
+        // Step 1: submit the update, this tries once to combine
+        let mut handle = self.submit_mut(slog, op, tkn, ticket);
+        // Step 2: poll for the response until the deadline passes
+        let mut iteration: usize = 0;
+        while !handle.is_done() && !deadline.expired()
+            invariant
+                slog.wf(),
+                self.wf(),
+                self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+                self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+                handle.wf(self),
+                handle.req_id() == req_id,
+        {
+            self.wait.call(iteration);
+            iteration = next_iteration(iteration);
+            handle = self.poll_response(slog, handle);
+        }
+        // Step 3: withdraw the update if the combiner hasn't collected it yet
+        if !handle.is_done() {
+            match self.cancel_op(handle) {
+                Ok(tkn) => return Err(tkn),
+                Err(collected) => handle = collected,
+            }
+        }
+        // Step 4: the update was collected and is applied exactly once, wait for its response
+        Ok(self.wait_response(slog, handle))
+    }
+
     /// Enqueues an operation inside a thread local context. Returns a boolean
     /// indicating whether the operation was enqueued (true) or not (false).
     #[inline(always)]
@@ -1196,16 +2080,21 @@ impl<DT: Dispatch> Replica<DT> {
         context.enqueue_op(op, context_ghost)
     }
 
-    /// Busy waits until a response is available within the thread's context.
+    /// Busy waits until a response is available within the thread's context. Tries to become
+    /// the combiner every `check_interval` iterations. With `park`, the thread parks after
+    /// spinning for a while until the combiner delivers its response.
     fn get_response(
         &self,
         slog: &NrLog<DT>,
         tid: ThreadId,
         req_id: Ghost<ReqId>,
         context_ghost: Tracked<FCClientRequestResponseGhost<DT>>,
+        check_interval: usize,
+        park: bool,
     ) -> (res: (DT::Response, Tracked<FCClientRequestResponseGhost<DT>>))
         requires
             self.wf(),
+            0 < check_interval,
             slog.wf(),
             slog.unbounded_log_instance@ == self.unbounded_log_instance@,
             slog.cyclic_buffer_instance@ == self.cyclic_buffer_instance@,
@@ -1221,6 +2110,7 @@ impl<DT: Dispatch> Replica<DT> {
         let mut context_ghost_new = context_ghost;
         let context = &self.contexts[tid as usize];
         let mut iter: usize = 0;
+        let mut iteration: usize = 0;
         let mut r = None;
         while r.is_none()
             invariant
@@ -1231,7 +2121,8 @@ impl<DT: Dispatch> Replica<DT> {
                 context.wf(tid as nat),
                 context.flat_combiner_instance@ == self.flat_combiner_instance@,
                 context.unbounded_log_instance@ == self.unbounded_log_instance@,
-                0 <= iter <= RESPONSE_CHECK_INTERVAL,
+                0 < check_interval,
+                0 <= iter <= check_interval,
                 r.is_None() ==> context_ghost_new@.dequeue_resp_pre(
                     context.batch.0.id(),
                     tid as nat,
@@ -1243,13 +2134,29 @@ impl<DT: Dispatch> Replica<DT> {
                     self.unbounded_log_instance@,
                 ),
         {
-            if iter == RESPONSE_CHECK_INTERVAL {
+            if iter == check_interval {
                 self.try_combine(slog);
                 iter = 0;
             }
+            // announce the parking before the last check for the response, so that a response
+            // published after the check notifies the thread
+            let parking = park && iteration >= ResponseSignal::SPIN_ITERATIONS;
+            if parking {
+                context.signal.prepare_park();
+            }
             let deq_resp_result = context.dequeue_response(context_ghost_new);
             r = deq_resp_result.0;
             context_ghost_new = deq_resp_result.1;
+            if r.is_none() {
+                if parking {
+                    context.signal.park(&self.wait);
+                } else {
+                    self.wait.call(iteration);
+                }
+                iteration = next_iteration(iteration);
+            } else if parking {
+                context.signal.cancel_park();
+            }
             iter = iter + 1;
         }
         let r = r.unwrap();
@@ -1257,6 +2164,81 @@ impl<DT: Dispatch> Replica<DT> {
     }
 }
 
+impl<DT: SnapshotDispatch> Replica<DT> {
+    /// Captures a snapshot of the state of this replica together with the read `op`.
+    ///
+    /// The snapshot is taken like a read: it pins the version upper bound of the log, waits
+    /// until the replica has applied all updates below it, and then captures the state while
+    /// holding the reader lock of the replica. The read `op` is dispatched on the same state, so
+    /// the snapshot is the state the read is linearized against. Writers only wait for the
+    /// replica while [`SnapshotDispatch::snapshot`] runs.
+    pub fn snapshot(
+        &self,
+        slog: &NrLog<DT>,
+        op: DT::ReadOperation,
+        tkn: &ThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+    ) -> (result: (DT::Snapshot, DT::Response, Tracked<UnboundedLog::local_reads<DT>>))
+        requires
+            self.wf(),
+            slog.wf(),
+            tkn.wf(self),
+            self.unbounded_log_instance@ == slog.unbounded_log_instance@,
+            self.cyclic_buffer_instance@ == slog.cyclic_buffer_instance@,
+            is_readonly_ticket(ticket@, op, slog.unbounded_log_instance@),
+        ensures
+            is_readonly_stub(result.2@, ticket@@.key, result.1, slog.unbounded_log_instance@),
+            DT::dispatch_spec(DT::snapshot_view(&result.0), op) == result.1,
+    {
+        let ghost rid: nat = ticket@@.key;
+        // Step 1: pin the version, all updates below it must be part of the snapshot
+        let (version_upper_bound, ticket) = slog.get_version_upper_bound(ticket);
+        // Step 2: wait until the replica has reached the version, combine in the mean time
+        let res = slog.is_replica_synced_for_reads(self.id(), version_upper_bound, ticket);
+        let mut is_synced = res.0;
+        let mut ticket = res.1;
+        let mut iteration: usize = 0;
+        while !is_synced
+            invariant
+                self.wf(),
+                slog.wf(),
+                !is_synced ==> ticket@@.value.is_VersionUpperBound(),
+                !is_synced ==> ticket@@.value.get_VersionUpperBound_version_upper_bound()
+                    == version_upper_bound,
+                !is_synced ==> ticket@@.value.get_VersionUpperBound_op() == op,
+                is_synced ==> ticket@@.value.is_ReadyToRead(),
+                is_synced ==> ticket@@.value.get_ReadyToRead_node_id() == self.spec_id(),
+                is_synced ==> ticket@@.value.get_ReadyToRead_op() == op,
+                ticket@@.instance == self.unbounded_log_instance@,
+                ticket@@.key == rid,
+                slog.unbounded_log_instance@ == self.unbounded_log_instance@,
+                slog.cyclic_buffer_instance@ == self.cyclic_buffer_instance@,
+        {
+            self.try_combine(slog);
+            self.wait.call(iteration);
+            iteration = next_iteration(iteration);
+            let res = slog.is_replica_synced_for_reads(self.id(), version_upper_bound, ticket);
+            is_synced = res.0;
+            ticket = res.1;
+        }
+        // Step 3: take the reader lock, capture the state and read from it
+        let tracked ticket = ticket.get();
+        assert(tkn.thread_id_spec() < self.data.0.max_threads());
+        let read_handle = self.data.0.acquire_read(tkn.thread_id() as usize);
+        let replica = self.data.0.borrow(Tracked(&read_handle));
+        let snapshot = replica.data.snapshot();
+        let response = replica.data.dispatch(op);
+        let tracked ticket = self.unbounded_log_instance.borrow().readonly_apply(
+            rid,
+            replica.replica.borrow(),
+            ticket,
+            replica.combiner.borrow(),
+        );
+        self.data.0.release_read(read_handle);
+        (snapshot, response, Tracked(ticket))
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Ghost Structures
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -1264,6 +2246,7 @@ impl<DT: Dispatch> Replica<DT> {
 pub tracked struct ReplicaConfig<DT: Dispatch> {
     pub tracked replica: UnboundedLog::replicas<DT>,
     pub tracked combiner: UnboundedLog::combiner<DT>,
+    pub tracked applied: UnboundedLog::applied<DT>,
     pub tracked cb_combiner: CyclicBuffer::combiner<DT>,
     pub tracked unbounded_log_instance: UnboundedLog::Instance<DT>,
     pub tracked cyclic_buffer_instance: CyclicBuffer::Instance<DT>,
@@ -1281,6 +2264,8 @@ impl<DT: Dispatch> ReplicaConfig<DT> {
         &&& self.replica@.instance == self.unbounded_log_instance
         &&& self.combiner@.value.is_Ready()
         &&& self.combiner@.key == nid
+        &&& self.applied@.instance == self.unbounded_log_instance
+        &&& self.applied@.key == nid
         &&& self.cb_combiner@.key == nid
         &&& self.cb_combiner@.value.is_Idle()
         &&& self.cb_combiner@.instance == self.cyclic_buffer_instance
@@ -1438,4 +2423,24 @@ impl<DT: Dispatch> ThreadOpsData<DT> {
     }
 }
 
+/// Drops the buffers of the combiner when the replica is deallocated. The combiner takes them
+/// out of their cells while it holds the combiner lock, so if the lock is still held, e.g., by a
+/// combiner that panicked, the cells are empty and there is nothing to drop.
+///
+/// The replicated data structure itself is dropped by its [`RwLock`].
+#[verifier::external]
+impl<DT: Dispatch> Drop for Replica<DT> {
+    fn drop(&mut self) {
+        // The permissions to the cells live in the ghost state of the combiner lock while it is
+        // free, and with the combiner while it is held. A combiner borrows the replica, and
+        // `&mut self` means no such borrow is alive: the permissions stored in the lock are dead,
+        // and the ones conjured here are the only ones that are ever used again.
+        if !self.is_combiner_locked() {
+            let _ = self.collected_operations.take(Tracked::assume_new());
+            let _ = self.collected_operations_per_thread.take(Tracked::assume_new());
+            let _ = self.responses.take(Tracked::assume_new());
+        }
+    }
+}
+
 } // verus!
@@ -11,8 +11,10 @@ use vstd::{
     prelude::*,
 };
 
+use crate::exec::utils::next_iteration;
 use crate::exec::CachePadded;
 use crate::spec::rwlock::RwLockSpec;
+use crate::WaitFn;
 
 verus! {
 
@@ -53,7 +55,7 @@ impl<T> RwLockReadGuard<T> {
 pub const MAX_RC: u64 = 0xffff_ffff_ffff_fff0;
 
 #[verus::trusted]
-#[verifier(external_body)]  /* vattr */
+#[verifier::external_body]
 pub fn warn_with_ref_count_too_big() {
     panic!("WARNING: Refcount value exceeds the maximum value of u64.");
 }
@@ -70,6 +72,8 @@ struct_with_invariants!{
         /// the spec instance
         inst: Tracked<RwLockSpec::Instance<PointsTo<T>>>,
         user_inv: Ghost<Set<T>>,
+        /// how to wait for the writer or the readers
+        wait: WaitFn,
     }
 
     pub closed spec fn wf(&self) -> bool {
@@ -134,8 +138,8 @@ impl<T> RwLock<T> {
         &&& write_handle.cell_perms@@.value.is_None()
     }
 
-    #[verifier(spinoff_prover)]
-    pub fn new(rc_width: usize, t: T, inv: Ghost<spec_fn(T) -> bool>) -> (s: Self)
+    #[verifier::spinoff_prover]
+    pub fn new(rc_width: usize, t: T, inv: Ghost<spec_fn(T) -> bool>, wait: WaitFn) -> (s: Self)
         requires
             0 < rc_width && inv@(t),
         ensures
@@ -268,6 +272,7 @@ impl<T> RwLock<T> {
             inst: Tracked(inst),
             exc_locked: CachePadded(exc_locked_atomic),
             ref_counts: v,
+            wait,
         };
         assert(s.inst@.rc_width() == s.ref_counts@.len());
         s
@@ -284,6 +289,7 @@ impl<T> RwLock<T> {
         // -----------------------------------------------------------------------------------------
         let tracked mut token: Option<RwLockSpec::exc_pending<PointsTo<T>>> = None;
         let mut acquired = false;
+        let mut iteration: usize = 0;
         while !acquired
             invariant
                 self.wf(),
@@ -301,6 +307,10 @@ impl<T> RwLock<T> {
                 }
             });
             acquired = result.is_ok();
+            if !acquired {
+                self.wait.call(iteration);
+                iteration = next_iteration(iteration);
+            }
         }
         let tracked mut token = token.tracked_unwrap();
         // -----------------------------------------------------------------------------------------
@@ -316,6 +326,7 @@ impl<T> RwLock<T> {
         {
             // wait until the reader hasn't taken the reader lock yet
             let mut taken = true;
+            let mut iteration: usize = 0;
             while taken
                 invariant
                     self.wf(),
@@ -334,6 +345,10 @@ impl<T> RwLock<T> {
                         }
                 });
                 taken = result != 0;
+                if taken {
+                    self.wait.call(iteration);
+                    iteration = next_iteration(iteration);
+                }
             }
             idx = idx + 1;
         }
@@ -352,6 +367,7 @@ impl<T> RwLock<T> {
         ensures
             self.wf() && self.wf_read_handle(&res) && self.inv(res@),
     {
+        let mut iteration: usize = 0;
         loop
             invariant
                 self.wf() && tid < self.ref_counts.len(),
@@ -407,6 +423,8 @@ impl<T> RwLock<T> {
                 // assert(g@.key == tid as nat);
             });
             if res.is_err() {
+                self.wait.call(iteration);
+                iteration = next_iteration(iteration);
                 continue ;
             }
             // exc_locked: CachePadded<AtomicBool<_, RwLockSpec::exc_locked<PointsTo<T>>, _>>,
@@ -438,6 +456,8 @@ impl<T> RwLock<T> {
                     self.inst.borrow().rc_not_zero_guard(tid as int, &g, &shared_pending);
                     g = self.inst.borrow().shared_abandon(tid as int, g, shared_pending);
                 });
+                self.wait.call(iteration);
+                iteration = next_iteration(iteration);
             } else {
                 // create the read guard lock
                 return RwLockReadGuard {
@@ -449,6 +469,78 @@ impl<T> RwLock<T> {
         }
     }
 
+    /// Tries to acquire the lock for reading without waiting.
+    ///
+    /// Returns `None` if the lock is currently held for writing, or the reference count of the
+    /// thread could not be incremented at the first attempt.
+    pub fn try_acquire_read<'a>(&'a self, tid: usize) -> (res: Option<RwLockReadGuard<T>>)
+        requires
+            self.wf() && self.thread_id_valid(tid as nat),
+        ensures
+            self.wf(),
+            res.is_Some() ==> self.wf_read_handle(&res.get_Some_0()) && self.inv(res.get_Some_0()@),
+    {
+        let rc =
+            atomic_with_ghost!(
+            &self.ref_counts[tid].0 => load();
+            returning rc;
+            ghost g => { }
+        );
+        if rc == MAX_RC {
+            return None;
+        }
+        let tracked mut shared_pending: Option<RwLockSpec::shared_pending<PointsTo<T>>>;
+        let res =
+            atomic_with_ghost!(
+            &self.ref_counts[tid].0 => compare_exchange(rc, rc+1);
+            update prev->next;
+            ghost g =>
+        {
+            if prev == rc {
+                assert(rc < MAX_RC);
+                let tracked (_ref_counts, _shared_pending) = self.inst.borrow().shared_start(tid as int, g);
+                shared_pending = Some(_shared_pending.get());
+                g = _ref_counts.get();
+            } else {
+                shared_pending = None;
+            }
+        });
+        if res.is_err() {
+            return None;
+        }
+        let ghost mut perms: PointsTo<T>;
+        let tracked shared_guard: Option<RwLockSpec::shared_guard<PointsTo<T>>>;
+        let is_exc_locked =
+            atomic_with_ghost!(
+            &self.exc_locked.0 => load();
+            returning res;
+            ghost g => {
+                if !res {
+                    let tracked (_perms, _shared_guard) = self.inst.borrow().shared_finish(tid as int, &g, shared_pending.tracked_unwrap());
+                    perms = _perms@.unwrap();
+                    shared_guard = Some(_shared_guard.get());
+                    shared_pending = None;
+                } else {
+                    shared_guard = None;
+                }
+        });
+        let perms = Ghost(perms);
+        if is_exc_locked {
+            // the writer holds the lock, undo the increment and give up
+            let res =
+                atomic_with_ghost!(
+                &self.ref_counts[tid].0 => fetch_sub(1);
+                ghost g => {
+                let tracked shared_pending = shared_pending.tracked_unwrap();
+                self.inst.borrow().rc_not_zero_guard(tid as int, &g, &shared_pending);
+                g = self.inst.borrow().shared_abandon(tid as int, g, shared_pending);
+            });
+            None
+        } else {
+            Some(RwLockReadGuard { tid, perms, handle: Tracked(shared_guard.tracked_unwrap()) })
+        }
+    }
+
     pub fn borrow<'a>(&'a self, read_handle: Tracked<&'a RwLockReadGuard<T>>) -> (res: &'a T)
         requires
             self.wf() && self.wf_read_handle(&read_handle@),
@@ -479,6 +571,18 @@ impl<T> RwLock<T> {
         });
     }
 
+    /// returns whether the writer lock is held or being acquired
+    pub fn is_write_locked(&self) -> (res: bool)
+        requires
+            self.wf(),
+    {
+        atomic_with_ghost!(
+            &self.exc_locked.0 => load();
+            returning res;
+            ghost g => { }
+        )
+    }
+
     pub fn release_read(&self, read_handle: RwLockReadGuard<T>)
         requires
             self.wf() && self.wf_read_handle(&read_handle),
@@ -496,4 +600,20 @@ impl<T> RwLock<T> {
     }
 }
 
+/// Drops the data when the lock is deallocated. A writer takes the data out of the cell while it
+/// holds the lock, so if the lock is still held, e.g., by a writer that panicked, the cell is
+/// empty and there is nothing to drop.
+#[verifier::external]
+impl<T> Drop for RwLock<T> {
+    fn drop(&mut self) {
+        // The permission to the cell lives in the ghost state of the lock while there is no
+        // writer, readers only get a shared borrow of it. All of them borrow the lock, and
+        // `&mut self` means no such borrow is alive: the permission stored in the lock is dead,
+        // and the one conjured here is the only one that is ever used again.
+        if !self.is_write_locked() {
+            let _ = self.data.take(Tracked::assume_new());
+        }
+    }
+}
+
 } // verus!
@@ -0,0 +1,277 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+// Sharded Node Replication
+#[allow(unused_imports)]
+use builtin::*;
+use builtin_macros::*;
+
+use vstd::prelude::*;
+
+use crate::{Dispatch, NodeReplicatedT};
+
+// spec imports
+use crate::spec::unbounded_log::UnboundedLog;
+use crate::{is_readonly_ticket, is_readonly_stub, is_update_ticket, is_update_stub};
+
+// exec imports
+use crate::exec::context::ThreadToken;
+use crate::exec::replica::ReplicaId;
+use crate::exec::NodeReplicated;
+
+verus! {
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Sharded Thread Token
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A thread token that is valid for every shard of a [`ShardedNodeReplicated`].
+///
+/// Holds one thread token per shard, all registered with the same replica id. Every shard keeps
+/// the per-thread operation slots of its replicas, so a thread still occupies one slot in each
+/// shard; the tokens are obtained with a single registration and used as one.
+pub struct ShardedThreadToken<DT: Dispatch> {
+    /// the thread tokens, indexed by the shard. A slot is only empty while its token is in use.
+    pub  /* REVIEW: (crate) */
+     tokens: Vec<Option<ThreadToken<DT>>>,
+}
+
+impl<DT: Dispatch> ShardedThreadToken<DT> {
+    /// the token is well-formed with respect to all shards
+    pub open spec fn wf(&self, shards: Seq<NodeReplicated<DT>>) -> bool {
+        &&& self.tokens.len() == shards.len()
+        &&& forall|i|
+            #![trigger self.tokens[i]]
+            0 <= i < shards.len() ==> {
+                &&& self.tokens[i].is_Some()
+                &&& self.tokens[i].get_Some_0().wf(
+                    &shards[i].replicas()[self.tokens[i].get_Some_0().replica_id_spec() as int],
+                )
+            }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Sharded Node Replicated
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A collection of independent [`NodeReplicated`] instances, one per shard of the application
+/// state, that share the thread registration.
+///
+/// A thread registers once and obtains a [`ShardedThreadToken`] it can use with every shard.
+/// The shards are fully independent, each has its own log and replicas.
+pub struct ShardedNodeReplicated<DT: Dispatch + Sync> {
+    /// the shards
+    pub  /* REVIEW: (crate) */
+     shards: Vec<NodeReplicated<DT>>,
+}
+
+impl<DT: Dispatch + Sync> ShardedNodeReplicated<DT> {
+    /// all shards are well-formed
+    pub open spec fn wf(&self) -> bool {
+        forall|i| 0 <= i < self.shards.len() ==> (#[trigger] self.shards[i]).wf()
+    }
+
+    /// Creates a new sharded data structure from the given shards.
+    pub fn new(shards: Vec<NodeReplicated<DT>>) -> (res: Self)
+        requires
+            forall|i| 0 <= i < shards.len() ==> (#[trigger] shards[i]).wf(),
+        ensures
+            res.wf(),
+            res.shards@ == shards@,
+    {
+        ShardedNodeReplicated { shards }
+    }
+
+    /// Returns the number of shards.
+    pub fn num_shards(&self) -> (res: usize)
+        ensures
+            res == self.shards.len(),
+    {
+        self.shards.len()
+    }
+
+    /// Registers a thread with the given replica of every shard. Returns None if the
+    /// registration failed with any of the shards, the thread is then unregistered again from
+    /// the shards it was already registered with.
+    pub fn register(&mut self, replica_id: ReplicaId) -> (result: Option<ShardedThreadToken<DT>>)
+        requires
+            old(self).wf(),
+        ensures
+            self.wf(),
+            self.shards.len() == old(self).shards.len(),
+            result.is_Some() ==> result.get_Some_0().wf(self.shards@),
+    {
+        let mut tokens: Vec<Option<ThreadToken<DT>>> = Vec::with_capacity(self.shards.len());
+        let mut idx = 0;
+        while idx < self.shards.len()
+            invariant
+                self.wf(),
+                self.shards.len() == old(self).shards.len(),
+                0 <= idx <= self.shards.len(),
+                tokens.len() == idx,
+                forall|i|
+                    #![trigger tokens[i]]
+                    0 <= i < idx ==> {
+                        &&& tokens[i].is_Some()
+                        &&& tokens[i].get_Some_0().wf(
+                            &self.shards[i].replicas()[tokens[i].get_Some_0().replica_id_spec() as int],
+                        )
+                    },
+        {
+            let mut shard = self.shards.remove(idx);
+            let res = shard.register(replica_id);
+            self.shards.insert(idx, shard);
+            match res {
+                Some(tkn) => {
+                    tokens.push(Some(tkn));
+                },
+                None => {
+                    // don't leak the registrations with the shards before this one
+                    self.unregister_partial(tokens);
+                    return None;
+                },
+            }
+            idx = idx + 1;
+        }
+        Some(ShardedThreadToken { tokens })
+    }
+
+    /// Unregisters the tokens of a registration that failed, `tokens[i]` belongs to shard `i`.
+    fn unregister_partial(&mut self, tokens: Vec<Option<ThreadToken<DT>>>)
+        requires
+            old(self).wf(),
+            tokens.len() <= old(self).shards.len(),
+            forall|i|
+                #![trigger tokens[i]]
+                0 <= i < tokens.len() ==> {
+                    &&& tokens[i].is_Some()
+                    &&& tokens[i].get_Some_0().wf(
+                        &old(self).shards[i].replicas()[tokens[i].get_Some_0().replica_id_spec() as int],
+                    )
+                },
+        ensures
+            self.wf(),
+            self.shards.len() == old(self).shards.len(),
+    {
+        let mut tokens = tokens;
+        while tokens.len() > 0
+            invariant
+                self.wf(),
+                self.shards.len() == old(self).shards.len(),
+                tokens.len() <= self.shards.len(),
+                forall|i|
+                    #![trigger tokens[i]]
+                    0 <= i < tokens.len() ==> {
+                        &&& tokens[i].is_Some()
+                        &&& tokens[i].get_Some_0().wf(
+                            &self.shards[i].replicas()[tokens[i].get_Some_0().replica_id_spec() as int],
+                        )
+                    },
+        {
+            let tkn = tokens.pop().unwrap().unwrap();
+            let idx = tokens.len();
+            let mut shard = self.shards.remove(idx);
+            shard.unregister(tkn);
+            self.shards.insert(idx, shard);
+        }
+    }
+
+    /// Executes a mutable operation against the given shard.
+    pub fn execute_mut(
+        &self,
+        shard: usize,
+        op: DT::WriteOperation,
+        tkn: ShardedThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+    ) -> (result: Result<
+        (DT::Response, ShardedThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>),
+        (ShardedThreadToken<DT>, Tracked<UnboundedLog::local_updates<DT>>),
+    >)
+        requires
+            self.wf(),
+            shard < self.shards.len(),
+            tkn.wf(self.shards@),
+            is_update_ticket(ticket@, op, self.shards[shard as int].unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> is_update_stub(
+                result.get_Ok_0().2@,
+                ticket@@.key,
+                result.get_Ok_0().0,
+                self.shards[shard as int].unbounded_log_instance(),
+            ) && result.get_Ok_0().1.wf(self.shards@),
+            result.is_Err() ==> result.get_Err_0().1 == ticket && result.get_Err_0().0.tokens@
+                == tkn.tokens@,
+    {
+        let ghost tokens = tkn.tokens@;
+        let mut tkn = tkn;
+        // take the token of the shard out of its slot, and put it back afterwards
+        let mut slot = None;
+        tkn.tokens.set_and_swap(shard, &mut slot);
+        assert(slot == tokens[shard as int]);
+        let shard_tkn = slot.unwrap();
+        match self.shards[shard].execute_mut(op, shard_tkn, ticket) {
+            Ok((resp, shard_tkn, stub)) => {
+                let mut slot = Some(shard_tkn);
+                tkn.tokens.set_and_swap(shard, &mut slot);
+                assert(tkn.wf(self.shards@));
+                Ok((resp, tkn, stub))
+            },
+            Err((shard_tkn, ticket)) => {
+                let mut slot = Some(shard_tkn);
+                tkn.tokens.set_and_swap(shard, &mut slot);
+                assert(tkn.tokens@ =~= tokens);
+                Err((tkn, ticket))
+            },
+        }
+    }
+
+    /// Executes an immutable operation against the given shard.
+    pub fn execute(
+        &self,
+        shard: usize,
+        op: DT::ReadOperation,
+        tkn: ShardedThreadToken<DT>,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+    ) -> (result: Result<
+        (DT::Response, ShardedThreadToken<DT>, Tracked<UnboundedLog::local_reads<DT>>),
+        (ShardedThreadToken<DT>, Tracked<UnboundedLog::local_reads<DT>>),
+    >)
+        requires
+            self.wf(),
+            shard < self.shards.len(),
+            tkn.wf(self.shards@),
+            is_readonly_ticket(ticket@, op, self.shards[shard as int].unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> is_readonly_stub(
+                result.get_Ok_0().2@,
+                ticket@@.key,
+                result.get_Ok_0().0,
+                self.shards[shard as int].unbounded_log_instance(),
+            ) && result.get_Ok_0().1.wf(self.shards@),
+            result.is_Err() ==> result.get_Err_0().1 == ticket && result.get_Err_0().0.tokens@
+                == tkn.tokens@,
+    {
+        let ghost tokens = tkn.tokens@;
+        let mut tkn = tkn;
+        // take the token of the shard out of its slot, and put it back afterwards
+        let mut slot = None;
+        tkn.tokens.set_and_swap(shard, &mut slot);
+        assert(slot == tokens[shard as int]);
+        let shard_tkn = slot.unwrap();
+        match self.shards[shard].execute(op, shard_tkn, ticket) {
+            Ok((resp, shard_tkn, stub)) => {
+                let mut slot = Some(shard_tkn);
+                tkn.tokens.set_and_swap(shard, &mut slot);
+                assert(tkn.wf(self.shards@));
+                Ok((resp, tkn, stub))
+            },
+            Err((shard_tkn, ticket)) => {
+                let mut slot = Some(shard_tkn);
+                tkn.tokens.set_and_swap(shard, &mut slot);
+                assert(tkn.tokens@ =~= tokens);
+                Err((tkn, ticket))
+            },
+        }
+    }
+}
+
+} // verus!
@@ -0,0 +1,116 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! Tracing of the runtime phases of the replicas and the log.
+//!
+//! With the `tracing` feature, the combiner, the application of log entries, the advancement of
+//! the tail and the head, and readers waiting for their replica emit spans and events with the
+//! [`tracing`](https://docs.rs/tracing) crate, under the `verified_node_replication` target.
+//! Without it, the functions are empty and compile to nothing. Like the metrics, the tracing
+//! is outside of the ghost state and doesn't take part in the proofs.
+//!
+//! Spans:
+//!  - `combine` (`replica`): a thread holds the combiner lock of the replica.
+//!  - `read_wait` (`replica`, `version`): a reader waits until its replica has reached the
+//!    version upper bound of the log.
+//!
+//! Events (`TRACE` level):
+//!  - `combiner_busy` (`replica`): the combiner lock of the replica is held by another thread.
+//!  - `apply` (`replica`, `from`, `to`): the replica applied the log entries `[from, to)`.
+//!  - `append` (`replica`, `tail`, `entries`): the replica reserved entries at the tail.
+//!  - `advance_head` (`replica`, `head`): the replica moved the head of the log for reuse.
+#[allow(unused_imports)]
+use builtin::*;
+use builtin_macros::*;
+
+use vstd::prelude::*;
+
+use crate::exec::replica::ReplicaId;
+
+verus! {
+
+/// An entered span, ends with [`TraceSpan::exit`].
+#[verus::trusted]
+#[verifier::external_body]
+pub struct TraceSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+}
+
+#[verus::trusted]
+impl TraceSpan {
+    /// enters the span of a combine on the replica.
+    #[verifier::external_body]
+    #[inline(always)]
+    #[allow(unused_variables)]
+    pub fn combine(replica: ReplicaId) -> Self {
+        TraceSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::trace_span!(target: "verified_node_replication", "combine", replica)
+                .entered(),
+        }
+    }
+
+    /// enters the span of a reader waiting for the replica to reach `version`.
+    #[verifier::external_body]
+    #[inline(always)]
+    #[allow(unused_variables)]
+    pub fn read_wait(replica: ReplicaId, version: u64) -> Self {
+        TraceSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::trace_span!(
+                target: "verified_node_replication", "read_wait", replica, version
+            ).entered(),
+        }
+    }
+
+    /// ends the span.
+    #[verifier::external_body]
+    #[inline(always)]
+    pub fn exit(self) {
+        #[cfg(feature = "tracing")]
+        self.span.exit();
+    }
+}
+
+/// the combiner lock of the replica is held by another thread.
+#[verus::trusted]
+#[verifier::external_body]
+#[inline(always)]
+#[allow(unused_variables)]
+pub fn trace_combiner_busy(replica: ReplicaId) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(target: "verified_node_replication", replica, "combiner_busy");
+}
+
+/// the replica applied the log entries `[from, to)`.
+#[verus::trusted]
+#[verifier::external_body]
+#[inline(always)]
+#[allow(unused_variables)]
+pub fn trace_apply(replica: ReplicaId, from: u64, to: u64) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(target: "verified_node_replication", replica, from, to, "apply");
+}
+
+/// the replica reserved `entries` entries at `tail`.
+#[verus::trusted]
+#[verifier::external_body]
+#[inline(always)]
+#[allow(unused_variables)]
+pub fn trace_append(replica: ReplicaId, tail: u64, entries: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(target: "verified_node_replication", replica, tail, entries, "append");
+}
+
+/// the replica moved the head of the log to `head`.
+#[verus::trusted]
+#[verifier::external_body]
+#[inline(always)]
+#[allow(unused_variables)]
+pub fn trace_advance_head(replica: ReplicaId, head: u64) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(target: "verified_node_replication", replica, head, "advance_head");
+}
+
+} // verus!
@@ -7,10 +7,230 @@ use builtin_macros::*;
 
 use vstd::{prelude::*, seq::Seq};
 
+use crate::constants::MAX_REPLICAS;
 use crate::spec::types::ReqId;
+use crate::spec::utils::IdAllocator;
 
 verus! {
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Deadlines
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A point in time after which a request should give up.
+///
+/// Deadlines are only checked at points where the request can still be withdrawn, so they are
+/// not a hard bound on the time a request takes.
+#[verus::trusted]
+#[verifier::external_body]
+pub struct Deadline {
+    at: std::time::Instant,
+}
+
+#[verus::trusted]
+impl Deadline {
+    /// creates a deadline that expires `dur` from now.
+    #[verifier::external_body]
+    pub fn after(dur: std::time::Duration) -> Self {
+        Deadline { at: std::time::Instant::now() + dur }
+    }
+
+    /// whether the deadline has passed.
+    #[verifier::external_body]
+    pub fn expired(&self) -> bool {
+        std::time::Instant::now() >= self.at
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Waiting
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// the round of a busy loop after `iteration`, saturates at `usize::MAX`.
+pub fn next_iteration(iteration: usize) -> usize {
+    if iteration < usize::MAX {
+        iteration + 1
+    } else {
+        iteration
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Fresh Identifiers
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A counter that hands out fresh identifiers, implements the [`IdAllocator`].
+pub struct IdCounter {
+    next: u64,
+    allocated: Ghost<Set<nat>>,
+}
+
+impl IdCounter {
+    /// the allocator the counter implements
+    pub open spec fn view(&self) -> IdAllocator {
+        IdAllocator { next: self.next as nat, allocated: self.allocated@ }
+    }
+
+    /// creates a counter that hasn't handed out any identifier.
+    pub fn new() -> (res: Self)
+        ensures
+            res@ == IdAllocator::new(),
+            res@.wf(),
+    {
+        IdCounter { next: 0, allocated: Ghost(Set::empty()) }
+    }
+
+    /// hands out a fresh identifier, `None` once the identifiers are exhausted.
+    pub fn alloc(&mut self) -> (res: Option<u64>)
+        requires
+            old(self)@.wf(),
+        ensures
+            self@.wf(),
+            res.is_Some() ==> res.get_Some_0() as nat == old(self)@.fresh() && self@ == old(
+                self,
+            )@.alloc(),
+            res.is_None() ==> self@ == old(self)@,
+    {
+        if self.next == u64::MAX {
+            return None;
+        }
+        let id = self.next;
+        proof {
+            self.allocated = Ghost(self.allocated@.insert(id as nat));
+        }
+        self.next = self.next + 1;
+        Some(id)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Runtime Invariant Checks
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//
+// With the `debug-invariants` feature, the executable code re-checks some of the invariants of
+// the state machines on the values it loads. The proofs establish these already; the checks
+// catch bugs in the unverified code around the verified one (trusted helpers, the `Dispatch`
+// implementation, unsafe callers) in tests and fuzzing.
+/// whether the runtime invariant checks are compiled in.
+#[verus::trusted]
+#[verifier::external_body]
+#[inline(always)]
+pub fn debug_invariants_enabled() -> bool {
+    cfg!(feature = "debug-invariants")
+}
+
+/// checks `local_version <= version_upper_bound <= tail`.
+///
+/// The values must be loaded in this order, all of them only ever increase.
+#[verus::trusted]
+#[verifier::external_body]
+pub fn debug_check_versions(local_version: u64, version_upper_bound: u64, tail: u64) {
+    debug_assert!(
+        local_version <= version_upper_bound,
+        "local version {local_version} exceeds the version upper bound {version_upper_bound}"
+    );
+    debug_assert!(
+        version_upper_bound <= tail,
+        "version upper bound {version_upper_bound} exceeds the tail {tail}"
+    );
+}
+
+/// checks that appending an entry flips the alive bit of its slot.
+#[verus::trusted]
+#[verifier::external_body]
+pub fn debug_check_alive_bit_flip(logical: u64, old_alive: bool, new_alive: bool) {
+    debug_assert!(
+        old_alive != new_alive,
+        "alive bit of log entry {logical} is already {new_alive} before the append"
+    );
+}
+
+/// checks that a log entry was appended by one of the replicas.
+#[verus::trusted]
+#[verifier::external_body]
+pub fn debug_check_log_entry(logical: u64, node_id: u64, num_replicas: usize) {
+    debug_assert!(
+        (node_id as usize) < num_replicas,
+        "log entry {logical} has node id {node_id}, but there are {num_replicas} replicas"
+    );
+}
+
+/// checks the value of a request slot: 0 (empty or response ready), 1 (request pending) or 2
+/// (request collected by the combiner).
+#[verus::trusted]
+#[verifier::external_body]
+pub fn debug_check_slot(value: u64) {
+    debug_assert!(value <= 2, "request slot has invalid value {value}");
+}
+
+/// checks that a request slot changes from `prev` to `next`, clients only enqueue into empty
+/// slots and withdraw pending requests, and the combiner only responds to collected requests.
+#[verus::trusted]
+#[verifier::external_body]
+pub fn debug_check_slot_transition(prev: u64, next: u64) {
+    debug_assert!(prev <= 2 && next <= 2, "request slot transition {prev} -> {next}");
+    debug_assert!(prev != next, "request slot is already {next}");
+    debug_assert!(
+        matches!((prev, next), (0, 1) | (1, 0) | (1, 2) | (2, 0)),
+        "request slot transition {prev} -> {next}"
+    );
+}
+
+/// Runtime mirror of the ghost `applied` counters of the unbounded log: the number of log entries
+/// each replica applied, split by whether the replica appended them itself. Only the combiner of
+/// a replica updates its counters, so relaxed accesses suffice.
+#[verus::trusted]
+#[verifier::external_body]
+pub struct DebugAppliedCounters {
+    local: [std::sync::atomic::AtomicU64; MAX_REPLICAS],
+    remote: [std::sync::atomic::AtomicU64; MAX_REPLICAS],
+    /// the local counter at the start of the current round of the combiner
+    round_start: [std::sync::atomic::AtomicU64; MAX_REPLICAS],
+}
+
+#[verus::trusted]
+impl DebugAppliedCounters {
+    #[verifier::external_body]
+    pub fn new() -> Self {
+        DebugAppliedCounters {
+            local: std::array::from_fn(|_| std::sync::atomic::AtomicU64::new(0)),
+            remote: std::array::from_fn(|_| std::sync::atomic::AtomicU64::new(0)),
+            round_start: std::array::from_fn(|_| std::sync::atomic::AtomicU64::new(0)),
+        }
+    }
+
+    /// starts a round of the combiner of the replica.
+    #[verifier::external_body]
+    pub fn start_round(&self, replica: usize) {
+        use std::sync::atomic::Ordering::Relaxed;
+        self.round_start[replica].store(self.local[replica].load(Relaxed), Relaxed);
+    }
+
+    /// counts an entry the replica applied.
+    #[verifier::external_body]
+    pub fn record(&self, replica: usize, local: bool) {
+        let counter = if local { &self.local[replica] } else { &self.remote[replica] };
+        counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// checks that the replica applied exactly the entries below `local_version`, and that the
+    /// current round applied one local entry per response it produced.
+    #[verifier::external_body]
+    pub fn check(&self, replica: usize, local_version: u64, responses: usize) {
+        use std::sync::atomic::Ordering::Relaxed;
+        let local = self.local[replica].load(Relaxed);
+        let remote = self.remote[replica].load(Relaxed);
+        debug_assert!(
+            local + remote == local_version,
+            "replica {replica} applied {local} local and {remote} remote entries, but is at \
+             version {local_version}"
+        );
+        let round = local - self.round_start[replica].load(Relaxed);
+        debug_assert!(
+            round == responses as u64,
+            "replica {replica} applied {round} local entries this round, but produced \
+             {responses} responses"
+        );
+    }
+}
+
 pub open spec fn rids_match(
     bools: Seq<Option<ReqId>>,
     rids: Seq<ReqId>,
@@ -0,0 +1,398 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! Reference interpreter for the `CyclicBuffer` state machine (`spec/cyclicbuffer.rs`).
+//!
+//! The stored entries carry the log entry only, the cell permissions and the tokens of the
+//! unbounded log are not represented. An initial entry (negative logical index) is `None`.
+
+use std::collections::BTreeMap;
+
+use crate::Dispatch;
+
+use super::{require, LogEntry, LogIdx, LogicalLogIdx, NodeId, NotEnabled};
+
+/// an entry stored in the buffer, see `spec::cyclicbuffer::StoredType`
+pub type StoredType<DT> = Option<LogEntry<DT>>;
+
+/// the state of a reader, see `spec::cyclicbuffer::ReaderState`
+pub enum ReaderState<DT: Dispatch> {
+    Starting { start: LogIdx },
+    Range { start: LogIdx, end: LogIdx, cur: LogIdx },
+    Guard { start: LogIdx, end: LogIdx, cur: LogIdx, val: StoredType<DT> },
+}
+
+/// the state of the combiner of a node, see `spec::cyclicbuffer::CombinerState`
+pub enum CombinerState<DT: Dispatch> {
+    Idle,
+    Reading(ReaderState<DT>),
+    AdvancingHead { idx: LogIdx, min_local_version: LogIdx },
+    AdvancingTail { observed_head: LogIdx },
+    Appending { cur_idx: LogIdx, tail: LogIdx },
+}
+
+/// The transitions of the state machine, one for each `transition!` in the spec.
+pub enum Transition<DT: Dispatch> {
+    ReaderStart { node_id: NodeId },
+    ReaderEnter { node_id: NodeId },
+    ReaderGuard { node_id: NodeId },
+    ReaderUnguard { node_id: NodeId },
+    ReaderFinish { node_id: NodeId },
+    ReaderAbort { node_id: NodeId },
+    AdvanceHeadStart { node_id: NodeId },
+    AdvanceHeadNext { node_id: NodeId },
+    AdvanceHeadFinish { node_id: NodeId },
+    AdvanceHeadAbort { node_id: NodeId },
+    AdvanceTailStart { node_id: NodeId },
+    AdvanceTailFinish { node_id: NodeId, new_tail: LogIdx },
+    AdvanceTailAbort { node_id: NodeId },
+    AppendFlipBit { node_id: NodeId, deposited: LogEntry<DT> },
+    AppendFinish { node_id: NodeId },
+}
+
+/// converts the logical to the physical log index, see `log_entry_idx`
+pub fn log_entry_idx(logical: LogicalLogIdx, buffer_size: LogIdx) -> LogIdx {
+    logical.rem_euclid(buffer_size as LogicalLogIdx) as LogIdx
+}
+
+/// the value of the alive bit of a live entry, see `log_entry_alive_value`
+pub fn log_entry_alive_value(logical: LogicalLogIdx, buffer_size: LogIdx) -> bool {
+    logical.div_euclid(buffer_size as LogicalLogIdx) % 2 == 0
+}
+
+/// The state of the `CyclicBuffer` state machine.
+pub struct CyclicBuffer<DT: Dispatch> {
+    /// the size of the buffer
+    pub buffer_size: LogIdx,
+    /// the number of replicas
+    pub num_replicas: usize,
+    pub head: LogIdx,
+    pub tail: LogIdx,
+    pub local_versions: BTreeMap<NodeId, LogIdx>,
+    pub contents: BTreeMap<LogicalLogIdx, StoredType<DT>>,
+    pub alive_bits: BTreeMap<LogIdx, bool>,
+    pub combiner: BTreeMap<NodeId, CombinerState<DT>>,
+}
+
+impl<DT: Dispatch> CyclicBuffer<DT> {
+    /// The `initialize` transition.
+    ///
+    /// In contrast to the spec, the buffer size is not fixed to `LOG_SIZE`, so small buffers
+    /// can be used to explore the wrap-around behavior.
+    pub fn initialize(buffer_size: LogIdx, num_replicas: usize) -> Result<Self, NotEnabled> {
+        require(num_replicas > 0, "num_replicas > 0")?;
+        require(buffer_size > 0, "buffer_size > 0")?;
+        let bs = buffer_size as LogicalLogIdx;
+        Ok(CyclicBuffer {
+            buffer_size,
+            num_replicas,
+            head: 0,
+            tail: 0,
+            local_versions: (0..num_replicas).map(|n| (n, 0)).collect(),
+            contents: (-bs..0).map(|i| (i, None)).collect(),
+            alive_bits: (0..buffer_size)
+                .map(|i| (i, !log_entry_alive_value(i as LogicalLogIdx, buffer_size)))
+                .collect(),
+            combiner: (0..num_replicas).map(|n| (n, CombinerState::Idle)).collect(),
+        })
+    }
+
+    /// whether the entry at the logical index is alive, see `log_entry_is_alive`
+    pub fn log_entry_is_alive(&self, logical: LogicalLogIdx) -> bool {
+        let bit = self.alive_bits.get(&log_entry_idx(logical, self.buffer_size));
+        bit == Some(&log_entry_alive_value(logical, self.buffer_size))
+    }
+
+    /// the smallest local version of all replicas, see `map_min_value`
+    pub fn min_local_version(&self) -> LogIdx {
+        self.local_versions.values().copied().min().unwrap_or(0)
+    }
+
+    /// All the transitions that are enabled in the current state.
+    ///
+    /// `AppendFlipBit` deposits an entry supplied by the caller and is not included.
+    pub fn enabled_transitions(&self) -> Vec<Transition<DT>> {
+        let mut candidates = Vec::new();
+        for &node_id in self.combiner.keys() {
+            candidates.push(Transition::ReaderStart { node_id });
+            candidates.push(Transition::ReaderEnter { node_id });
+            candidates.push(Transition::ReaderGuard { node_id });
+            candidates.push(Transition::ReaderUnguard { node_id });
+            candidates.push(Transition::ReaderFinish { node_id });
+            candidates.push(Transition::ReaderAbort { node_id });
+            candidates.push(Transition::AdvanceHeadStart { node_id });
+            candidates.push(Transition::AdvanceHeadNext { node_id });
+            candidates.push(Transition::AdvanceHeadFinish { node_id });
+            candidates.push(Transition::AdvanceHeadAbort { node_id });
+            candidates.push(Transition::AdvanceTailStart { node_id });
+            candidates.push(Transition::AdvanceTailAbort { node_id });
+            candidates.push(Transition::AppendFinish { node_id });
+            if let Some(CombinerState::AdvancingTail { observed_head }) = self.combiner.get(&node_id)
+            {
+                for new_tail in self.tail..=observed_head + self.buffer_size {
+                    candidates.push(Transition::AdvanceTailFinish { node_id, new_tail });
+                }
+            }
+        }
+        candidates.into_iter().filter(|t| self.check_enabled(t).is_ok()).collect()
+    }
+
+    /// checks the preconditions of the transition, the `remove`, `have` and `require` clauses
+    pub fn check_enabled(&self, t: &Transition<DT>) -> Result<(), NotEnabled> {
+        let bs = self.buffer_size;
+        match t {
+            Transition::ReaderStart { node_id } => {
+                require(self.local_versions.contains_key(node_id), "local version of the node")?;
+                let c = self.combiner.get(node_id);
+                require(matches!(c, Some(CombinerState::Idle)), "combiner is Idle")
+            }
+            Transition::ReaderEnter { node_id } => {
+                let c = self.combiner.get(node_id);
+                require(
+                    matches!(c, Some(CombinerState::Reading(ReaderState::Starting { .. }))),
+                    "reader is Starting",
+                )
+            }
+            Transition::ReaderGuard { node_id } => match self.combiner.get(node_id) {
+                Some(CombinerState::Reading(ReaderState::Range { end, cur, .. })) => {
+                    require(cur < end, "cur < end")?;
+                    let cur = *cur as LogicalLogIdx;
+                    require(self.log_entry_is_alive(cur), "entry at cur is alive")?;
+                    require(self.contents.contains_key(&cur), "contents at cur")
+                }
+                _ => Err(NotEnabled("reader is Range")),
+            },
+            Transition::ReaderUnguard { node_id } => {
+                let c = self.combiner.get(node_id);
+                require(
+                    matches!(c, Some(CombinerState::Reading(ReaderState::Guard { .. }))),
+                    "reader is Guard",
+                )
+            }
+            Transition::ReaderFinish { node_id } => match self.combiner.get(node_id) {
+                Some(CombinerState::Reading(ReaderState::Range { end, cur, .. })) => {
+                    require(self.local_versions.contains_key(node_id), "local version of the node")?;
+                    require(cur == end, "cur == end")
+                }
+                _ => Err(NotEnabled("reader is Range")),
+            },
+            Transition::ReaderAbort { node_id } => {
+                let c = self.combiner.get(node_id);
+                require(
+                    matches!(
+                        c,
+                        Some(CombinerState::Reading(ReaderState::Starting { .. }))
+                            | Some(CombinerState::Reading(ReaderState::Range { .. }))
+                    ),
+                    "reader is Starting or Range",
+                )
+            }
+            Transition::AdvanceHeadStart { node_id } => {
+                require(self.local_versions.contains_key(&0), "local version of node 0")?;
+                let c = self.combiner.get(node_id);
+                require(matches!(c, Some(CombinerState::Idle)), "combiner is Idle")
+            }
+            Transition::AdvanceHeadNext { node_id } => match self.combiner.get(node_id) {
+                Some(CombinerState::AdvancingHead { idx, .. }) => {
+                    let idx = *idx as NodeId;
+                    require(self.local_versions.contains_key(&idx), "local version at idx")?;
+                    require(idx < self.num_replicas, "idx < num_replicas")
+                }
+                _ => Err(NotEnabled("combiner is AdvancingHead")),
+            },
+            Transition::AdvanceHeadFinish { node_id } => match self.combiner.get(node_id) {
+                Some(CombinerState::AdvancingHead { idx, .. }) => {
+                    require(*idx as NodeId == self.num_replicas, "idx == num_replicas")
+                }
+                _ => Err(NotEnabled("combiner is AdvancingHead")),
+            },
+            Transition::AdvanceHeadAbort { node_id } => {
+                let c = self.combiner.get(node_id);
+                require(
+                    matches!(c, Some(CombinerState::AdvancingHead { .. })),
+                    "combiner is AdvancingHead",
+                )
+            }
+            Transition::AdvanceTailStart { node_id } => {
+                let c = self.combiner.get(node_id);
+                require(matches!(c, Some(CombinerState::Idle)), "combiner is Idle")
+            }
+            Transition::AdvanceTailFinish { node_id, new_tail } => match self.combiner.get(node_id)
+            {
+                Some(CombinerState::AdvancingTail { observed_head }) => {
+                    require(self.tail <= *new_tail, "tail <= new_tail")?;
+                    require(*new_tail <= observed_head + bs, "new_tail <= observed_head + size")?;
+                    // the withdrawn entries must be present, this is proven in the spec
+                    let lo = self.tail as LogicalLogIdx - bs as LogicalLogIdx;
+                    let hi = *new_tail as LogicalLogIdx - bs as LogicalLogIdx;
+                    require(
+                        (lo..hi).all(|i| self.contents.contains_key(&i)),
+                        "withdrawn entries are in the buffer",
+                    )
+                }
+                _ => Err(NotEnabled("combiner is AdvancingTail")),
+            },
+            Transition::AdvanceTailAbort { node_id } => {
+                let c = self.combiner.get(node_id);
+                require(
+                    matches!(c, Some(CombinerState::AdvancingTail { .. })),
+                    "combiner is AdvancingTail",
+                )
+            }
+            Transition::AppendFlipBit { node_id, .. } => match self.combiner.get(node_id) {
+                Some(CombinerState::Appending { cur_idx, tail }) => {
+                    require(cur_idx < tail, "cur_idx < tail")?;
+                    // the deposited entry must not be present, this is proven in the spec
+                    let cur = *cur_idx as LogicalLogIdx;
+                    require(!self.contents.contains_key(&cur), "no entry at cur_idx")
+                }
+                _ => Err(NotEnabled("combiner is Appending")),
+            },
+            Transition::AppendFinish { node_id } => match self.combiner.get(node_id) {
+                Some(CombinerState::Appending { cur_idx, tail }) => {
+                    require(cur_idx == tail, "cur_idx == tail")
+                }
+                _ => Err(NotEnabled("combiner is Appending")),
+            },
+        }
+    }
+
+    /// applies the transition to the state, if it is enabled
+    pub fn apply_transition(&mut self, t: Transition<DT>) -> Result<(), NotEnabled> {
+        self.check_enabled(&t)?;
+        let bs = self.buffer_size;
+        match t {
+            Transition::ReaderStart { node_id } => {
+                let start = self.local_versions[&node_id];
+                let c = CombinerState::Reading(ReaderState::Starting { start });
+                self.combiner.insert(node_id, c);
+            }
+            Transition::ReaderEnter { node_id } => {
+                if let Some(CombinerState::Reading(ReaderState::Starting { start })) =
+                    self.combiner.remove(&node_id)
+                {
+                    let r = ReaderState::Range { start, end: self.tail, cur: start };
+                    self.combiner.insert(node_id, CombinerState::Reading(r));
+                }
+            }
+            Transition::ReaderGuard { node_id } => {
+                if let Some(CombinerState::Reading(ReaderState::Range { start, end, cur })) =
+                    self.combiner.remove(&node_id)
+                {
+                    let val = self.contents[&(cur as LogicalLogIdx)].clone();
+                    let r = ReaderState::Guard { start, end, cur, val };
+                    self.combiner.insert(node_id, CombinerState::Reading(r));
+                }
+            }
+            Transition::ReaderUnguard { node_id } => {
+                if let Some(CombinerState::Reading(ReaderState::Guard { start, end, cur, .. })) =
+                    self.combiner.remove(&node_id)
+                {
+                    let r = ReaderState::Range { start, end, cur: cur + 1 };
+                    self.combiner.insert(node_id, CombinerState::Reading(r));
+                }
+            }
+            Transition::ReaderFinish { node_id } => {
+                if let Some(CombinerState::Reading(ReaderState::Range { end, .. })) =
+                    self.combiner.remove(&node_id)
+                {
+                    self.local_versions.insert(node_id, end);
+                    self.combiner.insert(node_id, CombinerState::Idle);
+                }
+            }
+            Transition::ReaderAbort { node_id }
+            | Transition::AdvanceHeadAbort { node_id }
+            | Transition::AdvanceTailAbort { node_id } => {
+                self.combiner.insert(node_id, CombinerState::Idle);
+            }
+            Transition::AdvanceHeadStart { node_id } => {
+                let min_local_version = self.local_versions[&0];
+                let c = CombinerState::AdvancingHead { idx: 1, min_local_version };
+                self.combiner.insert(node_id, c);
+            }
+            Transition::AdvanceHeadNext { node_id } => {
+                if let Some(CombinerState::AdvancingHead { idx, min_local_version }) =
+                    self.combiner.remove(&node_id)
+                {
+                    let local_head_at_idx = self.local_versions[&(idx as NodeId)];
+                    let min_local_version = min_local_version.min(local_head_at_idx);
+                    let c = CombinerState::AdvancingHead { idx: idx + 1, min_local_version };
+                    self.combiner.insert(node_id, c);
+                }
+            }
+            Transition::AdvanceHeadFinish { node_id } => {
+                if let Some(CombinerState::AdvancingHead { min_local_version, .. }) =
+                    self.combiner.remove(&node_id)
+                {
+                    self.head = min_local_version;
+                    self.combiner.insert(node_id, CombinerState::Idle);
+                }
+            }
+            Transition::AdvanceTailStart { node_id } => {
+                let c = CombinerState::AdvancingTail { observed_head: self.head };
+                self.combiner.insert(node_id, c);
+            }
+            Transition::AdvanceTailFinish { node_id, new_tail } => {
+                let lo = self.tail as LogicalLogIdx - bs as LogicalLogIdx;
+                let hi = new_tail as LogicalLogIdx - bs as LogicalLogIdx;
+                for i in lo..hi {
+                    self.contents.remove(&i);
+                }
+                let c = CombinerState::Appending { cur_idx: self.tail, tail: new_tail };
+                self.combiner.insert(node_id, c);
+                self.tail = new_tail;
+            }
+            Transition::AppendFlipBit { node_id, deposited } => {
+                if let Some(CombinerState::Appending { cur_idx, tail }) =
+                    self.combiner.remove(&node_id)
+                {
+                    let cur = cur_idx as LogicalLogIdx;
+                    self.alive_bits.insert(log_entry_idx(cur, bs), log_entry_alive_value(cur, bs));
+                    self.contents.insert(cur, Some(deposited));
+                    let c = CombinerState::Appending { cur_idx: cur_idx + 1, tail };
+                    self.combiner.insert(node_id, c);
+                }
+            }
+            Transition::AppendFinish { node_id } => {
+                self.combiner.insert(node_id, CombinerState::Idle);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a subset of the invariants of the state machine.
+    ///
+    /// Covers the pointer ordering and the invariants about the contents and the alive bits of
+    /// the buffer.
+    pub fn check_invariant(&self) -> Result<(), &'static str> {
+        let bs = self.buffer_size;
+        if self.head > self.tail {
+            return Err("pointer_ordering: head <= tail");
+        }
+        for &v in self.local_versions.values() {
+            if v < self.head || v > self.tail || self.tail > v + bs {
+                return Err("pointer_ordering: local version");
+            }
+        }
+        let min_local_head = self.min_local_version() as LogicalLogIdx;
+        let tail = self.tail as LogicalLogIdx;
+        for i in tail..min_local_head + bs as LogicalLogIdx {
+            if self.log_entry_is_alive(i) {
+                return Err("upcoming_bits_are_not_alive");
+            }
+        }
+        for i in tail - bs as LogicalLogIdx..tail {
+            let expected = self.log_entry_is_alive(i) || i < min_local_head;
+            if expected != self.contents.contains_key(&i) {
+                return Err("inv_buffer_contents");
+            }
+        }
+        if self.contents.keys().any(|&i| i >= tail) {
+            return Err("inv_buffer_contents: entry at or above the tail");
+        }
+        if self.contents.iter().any(|(&i, st)| i >= 0 && st.is_none()) {
+            return Err("contents_meet_inv");
+        }
+        Ok(())
+    }
+}
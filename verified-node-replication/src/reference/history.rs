@@ -0,0 +1,274 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! Compact binary format of recorded histories.
+//!
+//! Histories recorded by the tests and the benchmarks are written in this format, the
+//! linearizability checker reads them back to re-check them and they can be inspected offline
+//! when a run looks suspicious.
+//!
+//! The format starts with the magic bytes `VNRH` and a version byte, followed by the number of
+//! entries and the entries. An entry is the thread id, the kind of the operation (0 = read,
+//! 1 = write), the invocation time, the time between invocation and return, the operation and
+//! the response. Integers are encoded as LEB128 varints, operations and responses with their
+//! [`Encode`] implementation.
+
+use std::fmt::Debug;
+use std::path::Path;
+
+use crate::reference::linearizability::{HistoryEntry, Operation};
+use crate::Dispatch;
+
+/// the magic bytes at the start of a history
+pub const MAGIC: &[u8; 4] = b"VNRH";
+
+/// the version of the format
+pub const VERSION: u8 = 1;
+
+/// Binary encoding of operations and responses in a history.
+pub trait Encode: Sized {
+    /// appends the encoding of `self` to `out`
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// decodes a value from the front of `input` and advances it past the value
+    fn decode(input: &mut &[u8]) -> Option<Self>;
+}
+
+/// appends `v` as LEB128 varint
+fn put_varint(mut v: u64, out: &mut Vec<u8>) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+/// decodes a LEB128 varint from the front of `input`
+fn get_varint(input: &mut &[u8]) -> Option<u64> {
+    let mut v = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (b, rest) = input.split_first()?;
+        *input = rest;
+        v |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Some(v);
+        }
+    }
+    None
+}
+
+impl Encode for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let (b, rest) = input.split_first()?;
+        *input = rest;
+        Some(*b)
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! encode_varint {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    put_varint(*self as u64, out);
+                }
+
+                fn decode(input: &mut &[u8]) -> Option<Self> {
+                    <$t>::try_from(get_varint(input)?).ok()
+                }
+            }
+        )*
+    };
+}
+
+encode_varint!(u16, u32, u64, usize);
+
+impl Encode for () {
+    fn encode(&self, _out: &mut Vec<u8>) {}
+
+    fn decode(_input: &mut &[u8]) -> Option<Self> {
+        Some(())
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(v) => {
+                out.push(1);
+                v.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(None),
+            1 => Some(Some(T::decode(input)?)),
+            _ => None,
+        }
+    }
+}
+
+/// The history could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// the input does not start with [`MAGIC`]
+    BadMagic,
+    /// the history was written with another version of the format
+    UnsupportedVersion(u8),
+    /// the entry with the index is truncated or malformed
+    BadEntry(usize),
+    /// there are bytes after the last entry
+    TrailingBytes,
+}
+
+/// Encodes the history in the binary format.
+pub fn encode_history<DT>(history: &[HistoryEntry<DT>]) -> Vec<u8>
+where
+    DT: Dispatch,
+    DT::ReadOperation: Encode,
+    DT::WriteOperation: Encode,
+    DT::Response: Encode,
+{
+    let mut out = Vec::with_capacity(8 + history.len() * 8);
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    put_varint(history.len() as u64, &mut out);
+    for entry in history {
+        put_varint(entry.thread as u64, &mut out);
+        out.push(match &entry.op {
+            Operation::Read(_) => 0,
+            Operation::Write(_) => 1,
+        });
+        put_varint(entry.invoked, &mut out);
+        put_varint(entry.returned - entry.invoked, &mut out);
+        match &entry.op {
+            Operation::Read(op) => op.encode(&mut out),
+            Operation::Write(op) => op.encode(&mut out),
+        }
+        entry.response.encode(&mut out);
+    }
+    out
+}
+
+/// decodes a single entry from the front of `input`
+fn decode_entry<DT>(input: &mut &[u8]) -> Option<HistoryEntry<DT>>
+where
+    DT: Dispatch,
+    DT::ReadOperation: Encode,
+    DT::WriteOperation: Encode,
+    DT::Response: Encode,
+{
+    let thread = usize::try_from(get_varint(input)?).ok()?;
+    let kind = u8::decode(input)?;
+    let invoked = get_varint(input)?;
+    let returned = invoked.checked_add(get_varint(input)?)?;
+    let op = match kind {
+        0 => Operation::Read(DT::ReadOperation::decode(input)?),
+        1 => Operation::Write(DT::WriteOperation::decode(input)?),
+        _ => return None,
+    };
+    let response = DT::Response::decode(input)?;
+    Some(HistoryEntry { thread, op, response, invoked, returned })
+}
+
+/// Decodes a history from the binary format.
+pub fn decode_history<DT>(mut input: &[u8]) -> Result<Vec<HistoryEntry<DT>>, DecodeError>
+where
+    DT: Dispatch,
+    DT::ReadOperation: Encode,
+    DT::WriteOperation: Encode,
+    DT::Response: Encode,
+{
+    if !input.starts_with(MAGIC) {
+        return Err(DecodeError::BadMagic);
+    }
+    input = &input[MAGIC.len()..];
+    match u8::decode(&mut input) {
+        Some(VERSION) => {}
+        Some(v) => return Err(DecodeError::UnsupportedVersion(v)),
+        None => return Err(DecodeError::BadEntry(0)),
+    }
+    let len = get_varint(&mut input).ok_or(DecodeError::BadEntry(0))? as usize;
+
+    // the length is not trusted, every entry takes at least four bytes
+    let mut history = Vec::with_capacity(len.min(input.len() / 4));
+    for idx in 0..len {
+        history.push(decode_entry(&mut input).ok_or(DecodeError::BadEntry(idx))?);
+    }
+    if !input.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(history)
+}
+
+/// Writes the history to the file at `path`.
+pub fn save_history<DT>(history: &[HistoryEntry<DT>], path: &Path) -> std::io::Result<()>
+where
+    DT: Dispatch,
+    DT::ReadOperation: Encode,
+    DT::WriteOperation: Encode,
+    DT::Response: Encode,
+{
+    std::fs::write(path, encode_history(history))
+}
+
+/// Reads a history from the file at `path`.
+pub fn load_history<DT>(path: &Path) -> std::io::Result<Vec<HistoryEntry<DT>>>
+where
+    DT: Dispatch,
+    DT::ReadOperation: Encode,
+    DT::WriteOperation: Encode,
+    DT::Response: Encode,
+{
+    let bytes = std::fs::read(path)?;
+    decode_history(&bytes).map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {:?}", path.display(), e))
+    })
+}
+
+/// Formats the history one entry per line, ordered by invocation time.
+pub fn format_history<DT>(history: &[HistoryEntry<DT>]) -> String
+where
+    DT: Dispatch,
+    DT::ReadOperation: Debug,
+    DT::WriteOperation: Debug,
+    DT::Response: Debug,
+{
+    let mut order: Vec<usize> = (0..history.len()).collect();
+    order.sort_by_key(|i| history[*i].invoked);
+
+    let mut out = String::new();
+    for i in order {
+        let entry = &history[i];
+        let op = match &entry.op {
+            Operation::Read(op) => format!("read  {:?}", op),
+            Operation::Write(op) => format!("write {:?}", op),
+        };
+        out.push_str(&format!(
+            "[{:>4}, {:>4}] thread {:>2}: {} -> {:?}\n",
+            entry.invoked, entry.returned, entry.thread, op, entry.response
+        ));
+    }
+    out
+}
@@ -0,0 +1,135 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! Linearizability checker for recorded histories (Wing & Gong).
+//!
+//! A history is a set of completed operations with the logical times of their invocation and
+//! their return. The history is linearizable if there is a sequential order of the operations
+//! that respects the real-time order (an operation that returned before another one was
+//! invoked comes first) and in which every operation returns the recorded response when the
+//! operations are applied one by one to `DT::init()`. This is the behavior of the `SimpleLog`:
+//! updates are applied in log order and reads observe a version between their invocation and
+//! their return.
+//!
+//! The search is exponential in the number of concurrent operations, histories are limited to
+//! [`MAX_HISTORY_LEN`] operations.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::Dispatch;
+
+/// the maximum number of operations in a history
+pub const MAX_HISTORY_LEN: usize = 64;
+
+/// an operation of the history
+pub enum Operation<DT: Dispatch> {
+    Read(DT::ReadOperation),
+    Write(DT::WriteOperation),
+}
+
+/// a completed operation with the times of its invocation and return
+pub struct HistoryEntry<DT: Dispatch> {
+    /// the thread that executed the operation
+    pub thread: usize,
+    pub op: Operation<DT>,
+    pub response: DT::Response,
+    /// the logical time before the operation was invoked
+    pub invoked: u64,
+    /// the logical time after the operation returned
+    pub returned: u64,
+}
+
+/// Logical clock shared by the threads recording a history.
+pub struct Clock(AtomicU64);
+
+impl Clock {
+    pub fn new() -> Self {
+        Clock(AtomicU64::new(0))
+    }
+
+    /// the next logical time, unique across all threads
+    pub fn tick(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::SeqCst)
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new()
+    }
+}
+
+/// The history is not linearizable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotLinearizable {
+    /// the history has more than [`MAX_HISTORY_LEN`] operations
+    TooLong(usize),
+    /// there is no valid linearization
+    NoLinearization,
+}
+
+/// Checks whether the history is linearizable, returns the linearization order as indices into
+/// the history.
+pub fn check_linearizable<DT>(history: &[HistoryEntry<DT>]) -> Result<Vec<usize>, NotLinearizable>
+where
+    DT: Dispatch + Clone,
+    DT::ReadOperation: Clone,
+    DT::Response: PartialEq,
+{
+    if history.len() > MAX_HISTORY_LEN {
+        return Err(NotLinearizable::TooLong(history.len()));
+    }
+    let mut order = Vec::with_capacity(history.len());
+    if search(history, 0, &DT::init(), &mut order) {
+        Ok(order)
+    } else {
+        Err(NotLinearizable::NoLinearization)
+    }
+}
+
+/// tries to extend the linearization `order` of the operations in `done` to the full history
+fn search<DT>(history: &[HistoryEntry<DT>], done: u64, state: &DT, order: &mut Vec<usize>) -> bool
+where
+    DT: Dispatch + Clone,
+    DT::ReadOperation: Clone,
+    DT::Response: PartialEq,
+{
+    if order.len() == history.len() {
+        return true;
+    }
+
+    let pending = |i: &usize| done & (1 << *i) == 0;
+
+    // an operation can go next if it was invoked before any pending operation returned
+    let min_returned = (0..history.len()).filter(pending).map(|i| history[i].returned).min();
+    let min_returned = min_returned.unwrap_or(u64::MAX);
+
+    for i in (0..history.len()).filter(pending) {
+        let entry = &history[i];
+        if entry.invoked > min_returned {
+            continue;
+        }
+        match &entry.op {
+            Operation::Read(op) => {
+                if state.dispatch(op.clone()) == entry.response {
+                    order.push(i);
+                    if search(history, done | (1 << i), state, order) {
+                        return true;
+                    }
+                    order.pop();
+                }
+            }
+            Operation::Write(op) => {
+                let mut next = state.clone();
+                if next.dispatch_mut(DT::clone_write_op(op)) == entry.response {
+                    order.push(i);
+                    if search(history, done | (1 << i), &next, order) {
+                        return true;
+                    }
+                    order.pop();
+                }
+            }
+        }
+    }
+    false
+}
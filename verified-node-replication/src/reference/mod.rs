@@ -0,0 +1,69 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! Reference Interpreter for the State Machines
+//!
+//! Executable, unverified versions of the `UnboundedLog` and `CyclicBuffer` state machines.
+//! The state is represented with plain Rust data structures and every transition of the spec
+//! is a variant of a `Transition` enum that can be applied to the state with
+//! `apply_transition`. A transition that is not enabled in the current state is rejected with
+//! [`NotEnabled`] and leaves the state unchanged.
+//!
+//! This is used by randomized tests to explore the behaviors the specs allow, and to
+//! differential-test the executable implementation against them. The interpreter mirrors the
+//! `transition!` definitions one by one; when a transition in the spec changes, the
+//! corresponding transition here needs to be updated as well.
+//!
+//! The data structure state is the concrete `Dispatch` implementation instead of its view,
+//! the spec functions `dispatch_spec` and `dispatch_mut_spec` are replaced by their
+//! executable counterparts.
+//!
+//! The `linearizability` module checks recorded histories of the executable implementation
+//! against the sequential semantics of the data structure, the `history` module stores them in
+//! a compact binary format to re-check and inspect them offline.
+
+pub mod cyclicbuffer;
+pub mod history;
+pub mod linearizability;
+pub mod unbounded_log;
+
+use crate::Dispatch;
+
+/// the node id of a replica
+pub type NodeId = usize;
+
+/// an index into the unbounded log
+pub type LogIdx = u64;
+
+/// a request id
+pub type ReqId = u64;
+
+/// a logical index into the cyclic buffer, can be negative for the initial entries
+pub type LogicalLogIdx = i64;
+
+/// An entry in the log
+pub struct LogEntry<DT: Dispatch> {
+    pub op: DT::WriteOperation,
+    pub node_id: NodeId,
+}
+
+impl<DT: Dispatch> Clone for LogEntry<DT> {
+    fn clone(&self) -> Self {
+        LogEntry { op: DT::clone_write_op(&self.op), node_id: self.node_id }
+    }
+}
+
+/// The transition is not enabled in the current state.
+///
+/// Contains a description of the precondition that doesn't hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotEnabled(pub &'static str);
+
+/// checks the precondition of a transition
+fn require(cond: bool, what: &'static str) -> Result<(), NotEnabled> {
+    if cond {
+        Ok(())
+    } else {
+        Err(NotEnabled(what))
+    }
+}
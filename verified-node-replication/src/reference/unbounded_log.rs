@@ -0,0 +1,574 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! Reference interpreter for the `UnboundedLog` state machine (`spec/unbounded_log.rs`).
+
+use std::collections::BTreeMap;
+
+use crate::Dispatch;
+
+use super::{require, LogEntry, LogIdx, NodeId, NotEnabled, ReqId};
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Request and Combiner States
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// the state of a read-only request, see `spec::unbounded_log::ReadonlyState`
+pub enum ReadonlyState<DT: Dispatch> {
+    Init { op: DT::ReadOperation },
+    VersionUpperBound { op: DT::ReadOperation, version_upper_bound: LogIdx },
+    ReadyToRead { op: DT::ReadOperation, version_upper_bound: LogIdx, node_id: NodeId },
+    Done { op: DT::ReadOperation, version_upper_bound: LogIdx, node_id: NodeId, ret: DT::Response },
+}
+
+/// the state of an update request, see `spec::unbounded_log::UpdateState`
+pub enum UpdateState<DT: Dispatch> {
+    Init { op: DT::WriteOperation },
+    Placed { op: DT::WriteOperation, idx: LogIdx },
+    Applied { ret: DT::Response, idx: LogIdx },
+    Done { ret: DT::Response, idx: LogIdx },
+}
+
+/// the state of the combiner of a node, see `spec::unbounded_log::CombinerState`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CombinerState {
+    Ready,
+    Placed { queued_ops: Vec<ReqId> },
+    LoadedLocalVersion { queued_ops: Vec<ReqId>, lversion: LogIdx },
+    Loop { queued_ops: Vec<ReqId>, lversion: LogIdx, idx: usize, tail: LogIdx },
+    UpdatedVersion { queued_ops: Vec<ReqId>, tail: LogIdx },
+}
+
+impl CombinerState {
+    pub fn queued_ops(&self) -> &[ReqId] {
+        match self {
+            CombinerState::Ready => &[],
+            CombinerState::Placed { queued_ops } => queued_ops,
+            CombinerState::LoadedLocalVersion { queued_ops, .. } => queued_ops,
+            CombinerState::Loop { queued_ops, .. } => queued_ops,
+            CombinerState::UpdatedVersion { queued_ops, .. } => queued_ops,
+        }
+    }
+}
+
+/// the number of updates a replica has applied, see `spec::unbounded_log::AppliedCounters`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AppliedCounters {
+    pub local: u64,
+    pub remote: u64,
+}
+
+/// an operation entering the system, see `trusted::InputOperation`
+pub enum InputOperation<DT: Dispatch> {
+    Read(DT::ReadOperation),
+    Write(DT::WriteOperation),
+}
+
+/// the response of a completed request, see `trusted::OutputOperation`
+pub enum OutputOperation<DT: Dispatch> {
+    Read(DT::Response),
+    Write(DT::Response),
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Transitions
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// The transitions of the state machine, one for each `transition!` in the spec.
+///
+/// The tickets and stubs of the requests are handled by [`UnboundedLog::add_ticket`] and
+/// [`UnboundedLog::consume_stub`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    ReadonlyVersionUpperBound { rid: ReqId },
+    ReadonlyReadyToRead { rid: ReqId, node_id: NodeId },
+    ReadonlyApply { rid: ReqId },
+    ReadonlyCancel { rid: ReqId },
+    UpdatePlaceOpsInLogOne { node_id: NodeId, rid: ReqId },
+    UpdateDone { rid: ReqId },
+    UpdateCancel { rid: ReqId },
+    ExecTrivialStart { node_id: NodeId },
+    ExecLoadLocalVersion { node_id: NodeId },
+    ExecLoadGlobalHead { node_id: NodeId },
+    ExecDispatchLocal { node_id: NodeId },
+    ExecDispatchRemote { node_id: NodeId },
+    ExecUpdateVersionUpperBound { node_id: NodeId },
+    ExecFinish { node_id: NodeId },
+    ExecFinishNoChange { node_id: NodeId },
+    ReplicaRebuild { node_id: NodeId, donor: NodeId },
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// State
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// The state of the `UnboundedLog` state machine.
+pub struct UnboundedLog<DT: Dispatch> {
+    /// the number of replicas
+    pub num_replicas: usize,
+    pub log: BTreeMap<LogIdx, LogEntry<DT>>,
+    pub tail: LogIdx,
+    pub replicas: BTreeMap<NodeId, DT>,
+    pub local_versions: BTreeMap<NodeId, LogIdx>,
+    pub version_upper_bound: LogIdx,
+    pub local_reads: BTreeMap<ReqId, ReadonlyState<DT>>,
+    pub local_updates: BTreeMap<ReqId, UpdateState<DT>>,
+    pub combiner: BTreeMap<NodeId, CombinerState>,
+    pub applied: BTreeMap<NodeId, AppliedCounters>,
+}
+
+impl<DT: Dispatch + Clone> UnboundedLog<DT>
+where
+    DT::ReadOperation: Clone,
+{
+    /// the `initialize` transition
+    pub fn initialize(number_of_nodes: usize) -> Result<Self, NotEnabled> {
+        require(number_of_nodes > 0, "number_of_nodes > 0")?;
+        let nodes = 0..number_of_nodes;
+        Ok(UnboundedLog {
+            num_replicas: number_of_nodes,
+            log: BTreeMap::new(),
+            tail: 0,
+            replicas: nodes.clone().map(|n| (n, DT::init())).collect(),
+            local_versions: nodes.clone().map(|n| (n, 0)).collect(),
+            version_upper_bound: 0,
+            local_reads: BTreeMap::new(),
+            local_updates: BTreeMap::new(),
+            combiner: nodes.clone().map(|n| (n, CombinerState::Ready)).collect(),
+            applied: nodes.map(|n| (n, AppliedCounters::default())).collect(),
+        })
+    }
+
+    /// a request id that is not used by any request or combiner, see `get_fresh_nat`
+    pub fn fresh_rid(&self) -> ReqId {
+        let reads = self.local_reads.keys();
+        let updates = self.local_updates.keys();
+        let queued = self.combiner.values().flat_map(|c| c.queued_ops().iter());
+        reads.chain(updates).chain(queued).max().map_or(0, |rid| rid + 1)
+    }
+
+    /// adds a new request to the system, see `trusted::add_ticket`
+    pub fn add_ticket(&mut self, input: InputOperation<DT>) -> ReqId {
+        let rid = self.fresh_rid();
+        match input {
+            InputOperation::Read(op) => {
+                self.local_reads.insert(rid, ReadonlyState::Init { op });
+            }
+            InputOperation::Write(op) => {
+                self.local_updates.insert(rid, UpdateState::Init { op });
+            }
+        }
+        rid
+    }
+
+    /// removes a completed request from the system, see `trusted::consume_stub`
+    pub fn consume_stub(&mut self, rid: ReqId) -> Result<OutputOperation<DT>, NotEnabled> {
+        if let Some(ReadonlyState::Done { .. }) = self.local_reads.get(&rid) {
+            match self.local_reads.remove(&rid) {
+                Some(ReadonlyState::Done { ret, .. }) => return Ok(OutputOperation::Read(ret)),
+                _ => unreachable!(),
+            }
+        }
+        if let Some(UpdateState::Done { .. }) = self.local_updates.get(&rid) {
+            match self.local_updates.remove(&rid) {
+                Some(UpdateState::Done { ret, .. }) => return Ok(OutputOperation::Write(ret)),
+                _ => unreachable!(),
+            }
+        }
+        Err(NotEnabled("request is done"))
+    }
+
+    /// the version of the replica of the node, see `current_local_version`
+    pub fn current_local_version(&self, node_id: NodeId) -> LogIdx {
+        match &self.combiner[&node_id] {
+            CombinerState::Ready => self.local_versions[&node_id],
+            CombinerState::Placed { .. } => self.local_versions[&node_id],
+            CombinerState::LoadedLocalVersion { lversion, .. } => *lversion,
+            CombinerState::Loop { lversion, .. } => *lversion,
+            CombinerState::UpdatedVersion { tail, .. } => *tail,
+        }
+    }
+
+    /// the state of the data structure at the given version, see `compute_nrstate_at_version`
+    pub fn compute_nrstate_at_version(&self, version: LogIdx) -> DT {
+        let mut state = DT::init();
+        for idx in 0..version {
+            state.dispatch_mut(DT::clone_write_op(&self.log[&idx].op));
+        }
+        state
+    }
+
+    /// the applied counters of the node at the given version, see `compute_applied_at_version`
+    pub fn compute_applied_at_version(&self, node_id: NodeId, version: LogIdx) -> AppliedCounters {
+        let mut counters = AppliedCounters::default();
+        for idx in 0..version {
+            if self.log[&idx].node_id == node_id {
+                counters.local += 1;
+            } else {
+                counters.remote += 1;
+            }
+        }
+        counters
+    }
+
+    /// all the transitions that are enabled in the current state
+    pub fn enabled_transitions(&self) -> Vec<Transition> {
+        let mut candidates = Vec::new();
+        for &rid in self.local_reads.keys() {
+            candidates.push(Transition::ReadonlyVersionUpperBound { rid });
+            candidates.push(Transition::ReadonlyApply { rid });
+            candidates.push(Transition::ReadonlyCancel { rid });
+            for &node_id in self.combiner.keys() {
+                candidates.push(Transition::ReadonlyReadyToRead { rid, node_id });
+            }
+        }
+        for &rid in self.local_updates.keys() {
+            candidates.push(Transition::UpdateDone { rid });
+            candidates.push(Transition::UpdateCancel { rid });
+            for &node_id in self.combiner.keys() {
+                candidates.push(Transition::UpdatePlaceOpsInLogOne { node_id, rid });
+            }
+        }
+        for &node_id in self.combiner.keys() {
+            candidates.push(Transition::ExecTrivialStart { node_id });
+            candidates.push(Transition::ExecLoadLocalVersion { node_id });
+            candidates.push(Transition::ExecLoadGlobalHead { node_id });
+            candidates.push(Transition::ExecDispatchLocal { node_id });
+            candidates.push(Transition::ExecDispatchRemote { node_id });
+            candidates.push(Transition::ExecUpdateVersionUpperBound { node_id });
+            candidates.push(Transition::ExecFinish { node_id });
+            candidates.push(Transition::ExecFinishNoChange { node_id });
+            for &donor in self.combiner.keys() {
+                candidates.push(Transition::ReplicaRebuild { node_id, donor });
+            }
+        }
+        candidates.into_iter().filter(|t| self.is_enabled(*t)).collect()
+    }
+
+    /// whether the transition is enabled in the current state
+    pub fn is_enabled(&self, t: Transition) -> bool {
+        self.check_enabled(t).is_ok()
+    }
+
+    /// checks the preconditions of the transition, the `remove`, `have` and `require` clauses
+    pub fn check_enabled(&self, t: Transition) -> Result<(), NotEnabled> {
+        match t {
+            Transition::ReadonlyVersionUpperBound { rid } => {
+                let r = self.local_reads.get(&rid);
+                require(matches!(r, Some(ReadonlyState::Init { .. })), "read is Init")
+            }
+            Transition::ReadonlyReadyToRead { rid, node_id } => {
+                let vub = match self.local_reads.get(&rid) {
+                    Some(ReadonlyState::VersionUpperBound { version_upper_bound, .. }) => {
+                        *version_upper_bound
+                    }
+                    _ => return Err(NotEnabled("read is VersionUpperBound")),
+                };
+                let local_head = self.local_versions.get(&node_id);
+                require(local_head.is_some(), "local version of the node")?;
+                require(*local_head.unwrap() >= vub, "local_head >= version_upper_bound")
+            }
+            Transition::ReadonlyApply { rid } => {
+                let node_id = match self.local_reads.get(&rid) {
+                    Some(ReadonlyState::ReadyToRead { node_id, .. }) => *node_id,
+                    _ => return Err(NotEnabled("read is ReadyToRead")),
+                };
+                let c = self.combiner.get(&node_id);
+                require(c == Some(&CombinerState::Ready), "combiner is Ready")?;
+                require(self.replicas.contains_key(&node_id), "replica of the node")
+            }
+            Transition::ReadonlyCancel { rid } => {
+                let r = self.local_reads.get(&rid);
+                require(
+                    matches!(
+                        r,
+                        Some(ReadonlyState::Init { .. })
+                            | Some(ReadonlyState::VersionUpperBound { .. })
+                            | Some(ReadonlyState::ReadyToRead { .. })
+                    ),
+                    "read is Init, VersionUpperBound or ReadyToRead",
+                )
+            }
+            Transition::UpdatePlaceOpsInLogOne { node_id, rid } => {
+                let c = self.combiner.get(&node_id);
+                require(matches!(c, Some(CombinerState::Placed { .. })), "combiner is Placed")?;
+                let u = self.local_updates.get(&rid);
+                require(matches!(u, Some(UpdateState::Init { .. })), "update is Init")
+            }
+            Transition::UpdateDone { rid } => match self.local_updates.get(&rid) {
+                Some(UpdateState::Applied { idx, .. }) => {
+                    require(self.version_upper_bound > *idx, "version_upper_bound > idx")
+                }
+                _ => Err(NotEnabled("update is Applied")),
+            },
+            Transition::UpdateCancel { rid } => {
+                let u = self.local_updates.get(&rid);
+                require(matches!(u, Some(UpdateState::Init { .. })), "update is Init")
+            }
+            Transition::ExecTrivialStart { node_id } => {
+                let c = self.combiner.get(&node_id);
+                require(c == Some(&CombinerState::Ready), "combiner is Ready")
+            }
+            Transition::ExecLoadLocalVersion { node_id } => {
+                let c = self.combiner.get(&node_id);
+                require(matches!(c, Some(CombinerState::Placed { .. })), "combiner is Placed")?;
+                require(self.local_versions.contains_key(&node_id), "local version of the node")
+            }
+            Transition::ExecLoadGlobalHead { node_id } => {
+                let c = self.combiner.get(&node_id);
+                require(
+                    matches!(c, Some(CombinerState::LoadedLocalVersion { .. })),
+                    "combiner is LoadedLocalVersion",
+                )
+            }
+            Transition::ExecDispatchLocal { node_id } => {
+                let (queued_ops, lversion, idx, tail) = match self.combiner.get(&node_id) {
+                    Some(CombinerState::Loop { queued_ops, lversion, idx, tail }) => {
+                        (queued_ops, *lversion, *idx, *tail)
+                    }
+                    _ => return Err(NotEnabled("combiner is Loop")),
+                };
+                require(self.replicas.contains_key(&node_id), "replica of the node")?;
+                require(self.applied.contains_key(&node_id), "applied counters of the node")?;
+                require(idx < queued_ops.len(), "idx < queued_ops.len()")?;
+                let rid = queued_ops[idx];
+                require(self.local_updates.contains_key(&rid), "update of the queued op")?;
+                let log_entry = self.log.get(&lversion);
+                require(log_entry.is_some(), "log entry at lversion")?;
+                require(lversion < tail, "lversion < tail")?;
+                require(log_entry.unwrap().node_id == node_id, "log_entry.node_id == node_id")
+            }
+            Transition::ExecDispatchRemote { node_id } => {
+                let (lversion, tail) = match self.combiner.get(&node_id) {
+                    Some(CombinerState::Loop { lversion, tail, .. }) => (*lversion, *tail),
+                    _ => return Err(NotEnabled("combiner is Loop")),
+                };
+                require(self.replicas.contains_key(&node_id), "replica of the node")?;
+                require(self.applied.contains_key(&node_id), "applied counters of the node")?;
+                let log_entry = self.log.get(&lversion);
+                require(log_entry.is_some(), "log entry at lversion")?;
+                require(lversion < tail, "lversion < tail")?;
+                require(log_entry.unwrap().node_id != node_id, "log_entry.node_id != node_id")
+            }
+            Transition::ExecUpdateVersionUpperBound { node_id } => {
+                match self.combiner.get(&node_id) {
+                    Some(CombinerState::Loop { lversion, tail, .. }) => {
+                        require(lversion == tail, "lversion == tail")
+                    }
+                    _ => Err(NotEnabled("combiner is Loop")),
+                }
+            }
+            Transition::ExecFinish { node_id } => {
+                let c = self.combiner.get(&node_id);
+                require(
+                    matches!(c, Some(CombinerState::UpdatedVersion { .. })),
+                    "combiner is UpdatedVersion",
+                )?;
+                require(self.local_versions.contains_key(&node_id), "local version of the node")
+            }
+            Transition::ExecFinishNoChange { node_id } => match self.combiner.get(&node_id) {
+                Some(CombinerState::LoadedLocalVersion { lversion, .. }) => {
+                    require(*lversion == self.tail, "lversion == tail")
+                }
+                _ => Err(NotEnabled("combiner is LoadedLocalVersion")),
+            },
+            Transition::ReplicaRebuild { node_id, donor } => {
+                require(node_id != donor, "node_id != donor")?;
+                let ready = Some(&CombinerState::Ready);
+                require(self.combiner.get(&node_id) == ready, "combiner of the node is Ready")?;
+                require(self.combiner.get(&donor) == ready, "combiner of the donor is Ready")?;
+                require(self.replicas.contains_key(&donor), "replica of the donor")?;
+                require(self.replicas.contains_key(&node_id), "replica of the node")?;
+                require(self.applied.contains_key(&node_id), "applied counters of the node")?;
+                match (self.local_versions.get(&node_id), self.local_versions.get(&donor)) {
+                    (Some(lversion), Some(version)) => {
+                        require(lversion <= version, "lversion <= version")
+                    }
+                    _ => Err(NotEnabled("local versions of the node and the donor")),
+                }
+            }
+        }
+    }
+
+    /// applies the transition to the state, if it is enabled
+    pub fn apply_transition(&mut self, t: Transition) -> Result<(), NotEnabled> {
+        self.check_enabled(t)?;
+        match t {
+            Transition::ReadonlyVersionUpperBound { rid } => {
+                if let Some(ReadonlyState::Init { op }) = self.local_reads.remove(&rid) {
+                    let version_upper_bound = self.version_upper_bound;
+                    let r = ReadonlyState::VersionUpperBound { op, version_upper_bound };
+                    self.local_reads.insert(rid, r);
+                }
+            }
+            Transition::ReadonlyReadyToRead { rid, node_id } => {
+                if let Some(ReadonlyState::VersionUpperBound { op, version_upper_bound }) =
+                    self.local_reads.remove(&rid)
+                {
+                    let r = ReadonlyState::ReadyToRead { op, version_upper_bound, node_id };
+                    self.local_reads.insert(rid, r);
+                }
+            }
+            Transition::ReadonlyApply { rid } => {
+                if let Some(ReadonlyState::ReadyToRead { op, version_upper_bound, node_id }) =
+                    self.local_reads.remove(&rid)
+                {
+                    let ret = self.replicas[&node_id].dispatch(op.clone());
+                    let r = ReadonlyState::Done { op, version_upper_bound, node_id, ret };
+                    self.local_reads.insert(rid, r);
+                }
+            }
+            Transition::ReadonlyCancel { rid } => {
+                self.local_reads.remove(&rid);
+            }
+            Transition::UpdatePlaceOpsInLogOne { node_id, rid } => {
+                let idx = self.tail;
+                if let Some(UpdateState::Init { op }) = self.local_updates.remove(&rid) {
+                    let entry = LogEntry { op: DT::clone_write_op(&op), node_id };
+                    self.log.insert(idx, entry);
+                    self.local_updates.insert(rid, UpdateState::Placed { op, idx });
+                }
+                if let Some(CombinerState::Placed { queued_ops }) = self.combiner.get_mut(&node_id) {
+                    queued_ops.push(rid);
+                }
+                self.tail = idx + 1;
+            }
+            Transition::UpdateDone { rid } => {
+                if let Some(UpdateState::Applied { ret, idx }) = self.local_updates.remove(&rid) {
+                    self.local_updates.insert(rid, UpdateState::Done { ret, idx });
+                }
+            }
+            Transition::UpdateCancel { rid } => {
+                self.local_updates.remove(&rid);
+            }
+            Transition::ExecTrivialStart { node_id } => {
+                self.combiner.insert(node_id, CombinerState::Placed { queued_ops: Vec::new() });
+            }
+            Transition::ExecLoadLocalVersion { node_id } => {
+                let lversion = self.local_versions[&node_id];
+                if let Some(CombinerState::Placed { queued_ops }) = self.combiner.remove(&node_id) {
+                    let c = CombinerState::LoadedLocalVersion { queued_ops, lversion };
+                    self.combiner.insert(node_id, c);
+                }
+            }
+            Transition::ExecLoadGlobalHead { node_id } => {
+                if let Some(CombinerState::LoadedLocalVersion { queued_ops, lversion }) =
+                    self.combiner.remove(&node_id)
+                {
+                    let c = CombinerState::Loop { queued_ops, lversion, idx: 0, tail: self.tail };
+                    self.combiner.insert(node_id, c);
+                }
+            }
+            Transition::ExecDispatchLocal { node_id } => {
+                if let Some(CombinerState::Loop { queued_ops, lversion, idx, tail }) =
+                    self.combiner.remove(&node_id)
+                {
+                    let rid = queued_ops[idx];
+                    let op = DT::clone_write_op(&self.log[&lversion].op);
+                    let ret = self.replicas.get_mut(&node_id).unwrap().dispatch_mut(op);
+                    self.local_updates.insert(rid, UpdateState::Applied { ret, idx: lversion });
+                    self.applied.get_mut(&node_id).unwrap().local += 1;
+                    let lversion = lversion + 1;
+                    let c = CombinerState::Loop { queued_ops, lversion, idx: idx + 1, tail };
+                    self.combiner.insert(node_id, c);
+                }
+            }
+            Transition::ExecDispatchRemote { node_id } => {
+                if let Some(CombinerState::Loop { queued_ops, lversion, idx, tail }) =
+                    self.combiner.remove(&node_id)
+                {
+                    let op = DT::clone_write_op(&self.log[&lversion].op);
+                    self.replicas.get_mut(&node_id).unwrap().dispatch_mut(op);
+                    self.applied.get_mut(&node_id).unwrap().remote += 1;
+                    let lversion = lversion + 1;
+                    let c = CombinerState::Loop { queued_ops, lversion, idx, tail };
+                    self.combiner.insert(node_id, c);
+                }
+            }
+            Transition::ExecUpdateVersionUpperBound { node_id } => {
+                if let Some(CombinerState::Loop { queued_ops, tail, .. }) =
+                    self.combiner.remove(&node_id)
+                {
+                    self.version_upper_bound = self.version_upper_bound.max(tail);
+                    self.combiner.insert(node_id, CombinerState::UpdatedVersion { queued_ops, tail });
+                }
+            }
+            Transition::ExecFinish { node_id } => {
+                if let Some(CombinerState::UpdatedVersion { tail, .. }) =
+                    self.combiner.remove(&node_id)
+                {
+                    self.local_versions.insert(node_id, tail);
+                    self.combiner.insert(node_id, CombinerState::Ready);
+                }
+            }
+            Transition::ExecFinishNoChange { node_id } => {
+                self.combiner.insert(node_id, CombinerState::Ready);
+            }
+            Transition::ReplicaRebuild { node_id, donor } => {
+                let version = self.local_versions[&donor];
+                let state = self.replicas[&donor].clone();
+                let applied = self.compute_applied_at_version(node_id, version);
+                self.replicas.insert(node_id, state);
+                self.local_versions.insert(node_id, version);
+                self.applied.insert(node_id, applied);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<DT: Dispatch + Clone + PartialEq> UnboundedLog<DT>
+where
+    DT::ReadOperation: Clone,
+    DT::Response: PartialEq,
+{
+    /// Checks a subset of the invariants of the state machine.
+    ///
+    /// Covers the invariants relating the replicas, the applied counters and the responses of
+    /// the requests to the log, these are the ones a modeling error would most likely break.
+    pub fn check_invariant(&self) -> Result<(), &'static str> {
+        for (&idx, _) in self.log.iter() {
+            if idx >= self.tail {
+                return Err("log entry at or above the tail");
+            }
+        }
+        if (0..self.tail).any(|idx| !self.log.contains_key(&idx)) {
+            return Err("log has a hole below the tail");
+        }
+        if self.version_upper_bound > self.tail {
+            return Err("version_upper_bound above the tail");
+        }
+        for (&node_id, replica) in self.replicas.iter() {
+            let version = self.current_local_version(node_id);
+            if *replica != self.compute_nrstate_at_version(version) {
+                return Err("replica_state");
+            }
+            if self.applied[&node_id] != self.compute_applied_at_version(node_id, version) {
+                return Err("inv_applied_counters");
+            }
+        }
+        for update in self.local_updates.values() {
+            match update {
+                UpdateState::Applied { ret, idx } | UpdateState::Done { ret, idx } => {
+                    let mut state = self.compute_nrstate_at_version(*idx);
+                    let op = DT::clone_write_op(&self.log[idx].op);
+                    if state.dispatch_mut(op) != *ret {
+                        return Err("inv_update_results");
+                    }
+                }
+                _ => {}
+            }
+        }
+        for read in self.local_reads.values() {
+            if let ReadonlyState::Done { op, version_upper_bound, ret, .. } = read {
+                let matches = (*version_upper_bound..=self.version_upper_bound).any(|v| {
+                    self.compute_nrstate_at_version(v).dispatch(op.clone()) == *ret
+                });
+                if !matches {
+                    return Err("inv_read_results");
+                }
+            }
+        }
+        Ok(())
+    }
+}
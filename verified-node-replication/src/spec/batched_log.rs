@@ -0,0 +1,218 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+use builtin::*;
+use builtin_macros::*;
+use state_machines_macros::*;
+
+use vstd::prelude::*;
+
+use crate::spec::types::*;
+use crate::Dispatch;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//
+// Batched Log
+// ===========
+//
+// An optional layer on top of the log model that keeps the batches of the combiners. The Dafny
+// version of NR had the invariant that the batch of a combiner occupies contiguous slots of the
+// log. The UnboundedLog dropped it, it appends the entries of a batch one by one and doesn't
+// need to know where a batch ends.
+//
+// The Batched Log re-introduces it: a combiner reserves the slots of its whole batch with a single
+// step (this models the compare-and-swap of the tail in `NrLog::append`), and the ends of the
+// batches are recorded. Replicas only apply the log up to a tail they observed, the
+// version is the maximum of those, and reads are answered by replicas at or past the version
+// the read recorded. All of these are ends of batches, which gives atomic batch visibility
+// (`atomic_batch_visibility`): a read observes either all updates of a batch or none of them,
+// never a proper subset.
+//
+// This is an unconnected model: there is no refinement from the UnboundedLog or from `NrLog`
+// into it, and no other module uses it. `atomic_batch_visibility` only holds for the transitions
+// of this state machine, which record the end of a batch by construction; nothing is proven
+// about the implementation.
+//
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+verus! {
+
+state_machine! {
+    BatchedLog<DT: Dispatch> {
+    fields {
+        /// the update operations of all batches, in the order of the batches
+        pub log: Seq<DT::WriteOperation>,
+        /// the indices of the log at which a batch ends (including the empty batch at 0)
+        pub batch_ends: Set<LogIdx>,
+        /// the version of the log, the version upper bound of the implementation
+        pub version: LogIdx,
+        /// the versions up to which the replicas applied the log
+        pub local_versions: Map<NodeId, LogIdx>,
+        /// the versions the in-flight reads recorded when they started
+        pub reads: Map<ReqId, LogIdx>,
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////////////////
+    // Invariant
+    ////////////////////////////////////////////////////////////////////////////////////////////
+
+    /// the batches end within the log, and the log ends with a batch
+    #[invariant]
+    pub fn inv_batch_ends(&self) -> bool {
+        &&& self.batch_ends.contains(0)
+        &&& self.batch_ends.contains(self.log.len())
+        &&& forall |v: LogIdx| #[trigger] self.batch_ends.contains(v) ==> v <= self.log.len()
+    }
+
+    /// the version is the end of a batch
+    #[invariant]
+    pub fn inv_version(&self) -> bool {
+        &&& self.batch_ends.contains(self.version)
+        &&& self.version <= self.log.len()
+    }
+
+    /// the replicas applied the log up to the end of a batch
+    #[invariant]
+    pub fn inv_local_versions(&self) -> bool {
+        forall |node_id: NodeId| #[trigger] self.local_versions.contains_key(node_id) ==> {
+            &&& self.batch_ends.contains(self.local_versions[node_id])
+            &&& self.local_versions[node_id] <= self.log.len()
+        }
+    }
+
+    /// the reads recorded the end of a batch
+    #[invariant]
+    pub fn inv_reads(&self) -> bool {
+        forall |rid: ReqId| #[trigger] self.reads.contains_key(rid) ==> {
+            &&& self.batch_ends.contains(self.reads[rid])
+            &&& self.reads[rid] <= self.version
+        }
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////////////////
+    // State Machine Initialization
+    ////////////////////////////////////////////////////////////////////////////////////////////
+
+    init!{
+        initialize(num_replicas: nat) {
+            init log = Seq::empty();
+            init batch_ends = Set::empty().insert(0);
+            init version = 0;
+            init local_versions = Map::new(|n: NodeId| n < num_replicas, |n: NodeId| 0);
+            init reads = Map::empty();
+        }
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////////////////
+    // Transitions
+    ////////////////////////////////////////////////////////////////////////////////////////////
+
+    /// a combiner appends its batch to contiguous slots at the end of the log
+    transition!{
+        append_batch(ops: Seq<DT::WriteOperation>) {
+            require ops.len() > 0;
+
+            update log = pre.log + ops;
+            update batch_ends = pre.batch_ends.insert(pre.log.len() + ops.len());
+        }
+    }
+
+    /// a replica applies the log up to the tail it observed
+    transition!{
+        exec(node_id: NodeId, tail: LogIdx) {
+            require pre.local_versions.contains_key(node_id);
+            require pre.local_versions[node_id] <= tail;
+            require pre.batch_ends.contains(tail);
+
+            update local_versions = pre.local_versions.insert(node_id, tail);
+        }
+    }
+
+    /// a replica raises the version to the tail it applied up to
+    transition!{
+        update_version(node_id: NodeId) {
+            require pre.local_versions.contains_key(node_id);
+
+            let local_version = pre.local_versions[node_id];
+            update version = if pre.version < local_version { local_version } else { pre.version };
+        }
+    }
+
+    /// a read records the version
+    transition!{
+        read_start(rid: ReqId) {
+            require !pre.reads.contains_key(rid);
+
+            update reads = pre.reads.insert(rid, pre.version);
+        }
+    }
+
+    /// a replica at or past the recorded version answers the read
+    transition!{
+        read_finish(rid: ReqId, node_id: NodeId) {
+            require pre.reads.contains_key(rid);
+            require pre.local_versions.contains_key(node_id);
+            require pre.reads[rid] <= pre.local_versions[node_id];
+
+            update reads = pre.reads.remove(rid);
+        }
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////////////////
+    // Inductiveness Proofs
+    ////////////////////////////////////////////////////////////////////////////////////////////
+
+    #[inductive(initialize)]
+    fn initialize_inductive(post: Self, num_replicas: nat) { }
+
+    #[inductive(append_batch)]
+    fn append_batch_inductive(pre: Self, post: Self, ops: Seq<DT::WriteOperation>) { }
+
+    #[inductive(exec)]
+    fn exec_inductive(pre: Self, post: Self, node_id: NodeId, tail: LogIdx) { }
+
+    #[inductive(update_version)]
+    fn update_version_inductive(pre: Self, post: Self, node_id: NodeId) { }
+
+    #[inductive(read_start)]
+    fn read_start_inductive(pre: Self, post: Self, rid: ReqId) { }
+
+    #[inductive(read_finish)]
+    fn read_finish_inductive(pre: Self, post: Self, rid: ReqId, node_id: NodeId) { }
+}}  // state_machine! BatchedLog
+
+/// the updates in `[start, end)` are the batch of a combiner
+pub open spec fn is_batch<DT: Dispatch>(s: BatchedLog::State<DT>, start: LogIdx, end: LogIdx) -> bool {
+    &&& start < end
+    &&& s.batch_ends.contains(start)
+    &&& s.batch_ends.contains(end)
+    &&& forall |v: LogIdx| start < v < end ==> !(#[trigger] s.batch_ends.contains(v))
+}
+
+/// the state at `version` contains some, but not all updates of the batch `[start, end)`
+pub open spec fn observes_part_of_batch(version: LogIdx, start: LogIdx, end: LogIdx) -> bool {
+    start < version < end
+}
+
+/// Atomic batch visibility: the version, the replicas, and with them the reads never observe a
+/// proper subset of a batch.
+pub proof fn atomic_batch_visibility<DT: Dispatch>(
+    s: BatchedLog::State<DT>,
+    start: LogIdx,
+    end: LogIdx,
+)
+    requires
+        s.inv_version(),
+        s.inv_local_versions(),
+        s.inv_reads(),
+        is_batch(s, start, end),
+    ensures
+        !observes_part_of_batch(s.version, start, end),
+        forall |node_id: NodeId| #[trigger] s.local_versions.contains_key(node_id)
+            ==> !observes_part_of_batch(s.local_versions[node_id], start, end),
+        forall |rid: ReqId| #[trigger] s.reads.contains_key(rid)
+            ==> !observes_part_of_batch(s.reads[rid], start, end),
+{
+}
+
+} // verus!
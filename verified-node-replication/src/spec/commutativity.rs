@@ -0,0 +1,162 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+// Commuting Reorderings of the Log
+//
+// Proof only: the combiner still applies the log entries in log order, nothing in the
+// executable implementation or the trusted specification depends on this module.
+#[allow(unused_imports)]
+use builtin::*;
+use builtin_macros::*;
+
+use vstd::map::Map;
+use vstd::prelude::*;
+use vstd::seq::Seq;
+
+use crate::Dispatch;
+
+use super::types::*;
+use super::unbounded_log::{
+    compute_nrstate_at_version, fold_ops, lemma_nrstate_at_version_is_fold, log_prefix,
+};
+
+verus! {
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Commutative Dispatch Trait
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The commutative dispatch trait extends [`Dispatch`] with a declaration of which pairs of write
+/// operations commute. The data structure proves in [`CommutativeDispatch::lemma_commutes`] that
+/// applying two commuting operations in either order results in the same state. The responses of
+/// the operations may still depend on the order.
+///
+/// The trait is not part of the trusted specification: the proof obligation is only used by the
+/// lemmas below. It is not exported either, as no part of the implementation makes use of it yet.
+pub trait CommutativeDispatch: Dispatch {
+    /// whether the two write operations commute
+    spec fn commutes(a: Self::WriteOperation, b: Self::WriteOperation) -> bool;
+
+    /// proof obligation: commuting operations result in the same state in either order
+    proof fn lemma_commutes(ds: Self::View, a: Self::WriteOperation, b: Self::WriteOperation)
+        requires
+            Self::commutes(a, b),
+        ensures
+            Self::dispatch_mut_spec(Self::dispatch_mut_spec(ds, a).0, b).0
+                == Self::dispatch_mut_spec(Self::dispatch_mut_spec(ds, b).0, a).0,
+    ;
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//
+// Commuting Reorderings
+// =====================
+//
+// A commuting reordering of a sequence of update operations is obtained by repeatedly swapping
+// two adjacent operations that commute. Applying the reordered sequence results in the same
+// state as applying the original sequence. This is the safety argument for applying log entries
+// out of log order on a replica.
+//
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// swaps the operations at `i` and `i + 1`
+pub open spec fn swap_adjacent<T>(ops: Seq<T>, i: nat) -> Seq<T>
+    recommends
+        i + 1 < ops.len(),
+{
+    ops.update(i as int, ops[i + 1]).update(i + 1, ops[i as int])
+}
+
+/// whether `i` is the index of two adjacent operations that commute
+pub open spec fn is_commuting_swap<DT: CommutativeDispatch>(
+    ops: Seq<DT::WriteOperation>,
+    i: nat,
+) -> bool {
+    &&& i + 1 < ops.len()
+    &&& DT::commutes(ops[i as int], ops[i + 1])
+}
+
+/// applies the swaps of adjacent operations, in order
+pub open spec fn apply_swaps<T>(ops: Seq<T>, swaps: Seq<nat>) -> Seq<T>
+    decreases swaps.len(),
+{
+    if swaps.len() == 0 {
+        ops
+    } else {
+        swap_adjacent(apply_swaps(ops, swaps.drop_last()), swaps.last())
+    }
+}
+
+/// whether every swap exchanges two adjacent operations that commute
+pub open spec fn is_commuting_reordering<DT: CommutativeDispatch>(
+    ops: Seq<DT::WriteOperation>,
+    swaps: Seq<nat>,
+) -> bool
+    decreases swaps.len(),
+{
+    swaps.len() == 0 || {
+        &&& is_commuting_reordering::<DT>(ops, swaps.drop_last())
+        &&& is_commuting_swap::<DT>(apply_swaps(ops, swaps.drop_last()), swaps.last())
+    }
+}
+
+/// Swapping two adjacent, commuting operations does not change the resulting state.
+pub proof fn lemma_swap_adjacent_preserves_state<DT: CommutativeDispatch>(
+    ops: Seq<DT::WriteOperation>,
+    i: nat,
+)
+    requires
+        is_commuting_swap::<DT>(ops, i),
+    ensures
+        fold_ops::<DT>(swap_adjacent(ops, i)) == fold_ops::<DT>(ops),
+    decreases ops.len(),
+{
+    let swapped = swap_adjacent(ops, i);
+    if i + 2 == ops.len() {
+        // the swapped operations are the last two, they are applied to the same state
+        let prefix = ops.drop_last().drop_last();
+        assert(swapped.drop_last().drop_last() =~= prefix);
+        assert(swapped.drop_last().last() == ops.last());
+        assert(swapped.last() == ops.drop_last().last());
+        DT::lemma_commutes(fold_ops::<DT>(prefix), ops[i as int], ops[i + 1]);
+    } else {
+        // the last operation is the same, the swap happens in the prefix
+        assert(swapped.drop_last() =~= swap_adjacent(ops.drop_last(), i));
+        lemma_swap_adjacent_preserves_state::<DT>(ops.drop_last(), i);
+    }
+}
+
+/// Any commuting reordering of the operations results in the same state.
+pub proof fn lemma_commuting_reordering_preserves_state<DT: CommutativeDispatch>(
+    ops: Seq<DT::WriteOperation>,
+    swaps: Seq<nat>,
+)
+    requires
+        is_commuting_reordering::<DT>(ops, swaps),
+    ensures
+        fold_ops::<DT>(apply_swaps(ops, swaps)) == fold_ops::<DT>(ops),
+    decreases swaps.len(),
+{
+    if swaps.len() > 0 {
+        lemma_commuting_reordering_preserves_state::<DT>(ops, swaps.drop_last());
+        lemma_swap_adjacent_preserves_state::<DT>(apply_swaps(ops, swaps.drop_last()), swaps.last());
+    }
+}
+
+/// The state at a version is the state after applying any commuting reordering of the log
+/// prefix up to that version.
+pub proof fn lemma_nrstate_at_version_commuting_reordering<DT: CommutativeDispatch>(
+    log: Map<LogIdx, LogEntry<DT>>,
+    version: LogIdx,
+    swaps: Seq<nat>,
+)
+    requires
+        is_commuting_reordering::<DT>(log_prefix(log, version), swaps),
+    ensures
+        compute_nrstate_at_version(log, version) == fold_ops::<DT>(
+            apply_swaps(log_prefix(log, version), swaps),
+        ),
+{
+    lemma_nrstate_at_version_is_fold(log, version);
+    lemma_commuting_reordering_preserves_state::<DT>(log_prefix(log, version), swaps);
+}
+
+} // verus!
@@ -191,6 +191,15 @@ tokenized_state_machine! { CyclicBuffer<DT: Dispatch> {
         self.buffer_size == LOG_SIZE
     }
 
+    /// there is at least one replica and one entry, and every replica fits into the buffer.
+    /// The minimum of the local versions (`map_min_value` up to `num_replicas - 1`) relies on it.
+    #[invariant]
+    pub spec fn constants_nonzero(&self) -> bool {
+        &&& self.num_replicas >= 1
+        &&& self.buffer_size >= 1
+        &&& self.buffer_size >= self.num_replicas
+    }
+
     #[invariant]
     pub spec fn cell_ids(&self) -> bool {
         self.cell_ids.len() == self.buffer_size
@@ -245,6 +254,12 @@ tokenized_state_machine! { CyclicBuffer<DT: Dispatch> {
         &&& (forall |i: int| self.tail <= i ==> ! #[trigger] self.contents.contains_key(i))
     }
 
+    /// the entries behind the buffer have been withdrawn (see `live_entries_bounded`)
+    #[invariant]
+    pub spec fn contents_in_window(&self) -> bool {
+        forall |i: int| #[trigger] self.contents.contains_key(i) ==> self.tail - self.buffer_size <= i
+    }
+
     #[invariant]
     pub spec fn contents_meet_inv(&self) -> bool {
         forall |i: int| #[trigger] self.contents.contains_key(i) ==>
@@ -341,6 +356,7 @@ tokenized_state_machine! { CyclicBuffer<DT: Dispatch> {
         initialize(buffer_size: nat, num_replicas: nat, contents: Map<int, StoredType<DT>>, cell_ids: Seq<CellId>, unbounded_log_instance: UnboundedLog::Instance<DT>, ) {
             require(num_replicas > 0);
             require(buffer_size == LOG_SIZE);
+            require(num_replicas <= buffer_size);
             require(cell_ids.len() == buffer_size);
 
             init unbounded_log_instance = unbounded_log_instance;
@@ -653,7 +669,7 @@ tokenized_state_machine! { CyclicBuffer<DT: Dispatch> {
         assert(mycur_idx == pre.tail);
 
         let min_local_versions = map_min_value(post.local_versions, (post.num_replicas - 1) as nat);
-        map_min_value_smallest(post.local_versions,  (post.num_replicas - 1) as nat);
+        map_min_value_lower_bound(post.local_versions, (post.num_replicas - 1) as nat);
         assert(mycur_idx >= min_local_versions);
     }
 
@@ -663,6 +679,8 @@ tokenized_state_machine! { CyclicBuffer<DT: Dispatch> {
 
         let myidx = pre.combiner[node_id].get_Appending_cur_idx();
         let mytail = pre.combiner[node_id].get_Appending_tail();
+        assert(pre.combiner_valid(node_id, pre.combiner[node_id]));
+        assert(post.tail - post.buffer_size <= myidx);
 
         let min_local_head = map_min_value(post.local_versions, (post.num_replicas - 1) as nat);
         map_min_value_smallest(post.local_versions, (post.num_replicas - 1) as nat);
@@ -754,24 +772,230 @@ pub open spec fn map_min_value(m: Map<NodeId, nat>, idx: nat) -> nat
     }
 }
 
+/// the minimum is a lower bound of every entry up to `idx`
+pub proof fn map_min_value_lower_bound(m: Map<NodeId, nat>, idx: nat)
+    ensures
+        forall|n| 0 <= n <= idx ==> map_min_value(m, idx) <= #[trigger] m.index(n),
+    decreases idx,
+{
+    if idx > 0 {
+        map_min_value_lower_bound(m, (idx - 1) as nat);
+    }
+}
+
+/// the minimum is the value of some entry up to `idx`
+pub proof fn map_min_value_attained(m: Map<NodeId, nat>, idx: nat)
+    ensures
+        exists|n| 0 <= n <= idx && map_min_value(m, idx) == #[trigger] m.index(n),
+    decreases idx,
+{
+    if idx == 0 {
+        assert(map_min_value(m, idx) == m.index(0));
+    } else {
+        map_min_value_attained(m, (idx - 1) as nat);
+        if m.index(idx) < map_min_value(m, (idx - 1) as nat) {
+            assert(map_min_value(m, idx) == m.index(idx));
+        } else {
+            let n = choose|n| 0 <= n <= idx - 1 && map_min_value(m, (idx - 1) as nat) == #[trigger] m.index(n);
+            assert(map_min_value(m, idx) == m.index(n));
+        }
+    }
+}
+
+/// updating an entry above `idx` doesn't change the minimum
+pub proof fn map_min_value_insert_above(m: Map<NodeId, nat>, idx: nat, k: NodeId, v: nat)
+    requires
+        idx < k,
+    ensures
+        map_min_value(m.insert(k, v), idx) == map_min_value(m, idx),
+    decreases idx,
+{
+    if idx > 0 {
+        map_min_value_insert_above(m, (idx - 1) as nat, k, v);
+    }
+}
+
+/// how the minimum changes when the entry `k` is updated to `v`
+///
+///  - the new minimum is at most the new value,
+///  - increasing an entry doesn't decrease the minimum,
+///  - if the old minimum wasn't attained by the entry, the new minimum is the smaller of the old
+///    minimum and the new value.
+pub proof fn map_min_value_insert(m: Map<NodeId, nat>, idx: nat, k: NodeId, v: nat)
+    requires
+        k <= idx,
+    ensures
+        map_min_value(m.insert(k, v), idx) <= v,
+        m.index(k) <= v ==> map_min_value(m, idx) <= map_min_value(m.insert(k, v), idx),
+        map_min_value(m, idx) < m.index(k) ==> map_min_value(m.insert(k, v), idx) == min(
+            map_min_value(m, idx),
+            v,
+        ),
+    decreases idx,
+{
+    let m2 = m.insert(k, v);
+    assert(m2.index(k) == v);
+    if idx == 0 {
+        assert(k == 0);
+    } else if k == idx {
+        map_min_value_insert_above(m, (idx - 1) as nat, k, v);
+    } else {
+        assert(m2.index(idx) == m.index(idx));
+        map_min_value_insert(m, (idx - 1) as nat, k, v);
+        map_min_value_lower_bound(m, (idx - 1) as nat);
+        if map_min_value(m, idx) < m.index(k) {
+            assert(map_min_value(m, (idx - 1) as nat) <= m.index(k));
+            if map_min_value(m, (idx - 1) as nat) == m.index(k) {
+                // the old minimum is attained by the entry `idx`, the new minimum up to `idx - 1`
+                // is either the new value or at least the old minimum up to `idx - 1`
+                assert(map_min_value(m, idx) == m.index(idx));
+                map_min_value_attained(m2, (idx - 1) as nat);
+                let n = choose|n|
+                    0 <= n <= idx - 1 && map_min_value(m2, (idx - 1) as nat) == #[trigger] m2.index(n);
+                if n != k {
+                    assert(m2.index(n) == m.index(n));
+                }
+            }
+        }
+    }
+}
+
 proof fn map_min_value_smallest(m: Map<NodeId, nat>, idx: nat)
     requires
         forall|i| 0 <= i <= idx ==> m.contains_key(i),
     ensures
         forall|n| 0 <= n <= idx as nat ==> map_min_value(m, idx) <= m.index(n),
         map_contains_value(m, map_min_value(m, idx)),
+{
+    map_min_value_lower_bound(m, idx);
+    map_min_value_attained(m, idx);
+    let n = choose|n| 0 <= n <= idx && map_min_value(m, idx) == #[trigger] m.index(n);
+    assert(m.contains_key(n));
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Progress of the Head Advancement
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//
+// The state machine only states safety: a replica that never applies the log keeps its local
+// version, and with it the head, where it is, and every appender waits for it. The lemmas below
+// are the steps of the argument that this is the only way to block the appenders. Assuming that
+// the combiner of every replica runs infinitely often:
+//
+//  1. a replica whose combiner runs applies the log up to the tail it observes
+//     (`reader_finish` sets its local version to `end`, the tail read by `reader_enter`), and
+//     the local versions never decrease;
+//  2. once every replica ran after the tail reached `version`, all local versions are at least
+//     `version`, and so is their minimum (`map_min_value_at_least`);
+//  3. an advance of the head that starts afterwards collects a minimum of at least `version`
+//     (`advance_head_next_at_least`) and sets the head to it;
+//  4. an appender that observes that head can reserve up to `buffer_size` entries from any tail
+//     up to `version` (`advance_tail_enabled`), so it proceeds.
+//
+// Fairness and "eventually" are properties of executions, which the state machines don't
+// describe, hence the steps are stated on the states and transitions they relate.
+
+/// every replica has applied the log up to `version`
+pub open spec fn all_caught_up(m: Map<NodeId, nat>, num_replicas: nat, version: nat) -> bool {
+    forall|n: NodeId| 0 <= n < num_replicas ==> version <= #[trigger] m.index(n)
+}
+
+/// the minimum is at least `version` if all entries up to `idx` are
+pub proof fn map_min_value_at_least(m: Map<NodeId, nat>, idx: nat, version: nat)
+    requires
+        forall|n: NodeId| 0 <= n <= idx ==> version <= #[trigger] m.index(n),
+    ensures
+        version <= map_min_value(m, idx),
     decreases idx,
 {
-    if idx == 0 {
-        assert(m.contains_key(0));
-    } else {
-        map_min_value_smallest(m, (idx - 1) as nat);
-        if m.index(idx) < map_min_value(m, (idx - 1) as nat) {
-            assert(m.contains_key(idx));
-        }
+    if idx > 0 {
+        map_min_value_at_least(m, (idx - 1) as nat, version);
     }
 }
 
+/// the minimum over all replicas that caught up with `version` is at least `version`, and the
+/// head may be advanced to it
+pub proof fn advance_head_progress(m: Map<NodeId, nat>, num_replicas: nat, version: nat)
+    requires
+        0 < num_replicas,
+        all_caught_up(m, num_replicas, version),
+    ensures
+        version <= map_min_value(m, (num_replicas - 1) as nat),
+{
+    assert forall|n: NodeId| 0 <= n <= (num_replicas - 1) as nat implies version <= #[trigger] m.index(n) by {
+        assert(0 <= n < num_replicas);
+    }
+    map_min_value_at_least(m, (num_replicas - 1) as nat, version);
+}
+
+/// a step of `advance_head_next` keeps the collected minimum at or above `version` if the local
+/// version it reads caught up with `version`
+pub proof fn advance_head_next_at_least(min_local_version: nat, local_head_at_idx: nat, version: nat)
+    requires
+        version <= min_local_version,
+        version <= local_head_at_idx,
+    ensures
+        version <= min(min_local_version, local_head_at_idx),
+{
+}
+
+/// once the observed head caught up with the tail an appender read, `advance_tail_finish`
+/// accepts any reservation of up to `buffer_size` entries
+pub proof fn advance_tail_enabled(observed_head: nat, tail: nat, new_tail: nat, buffer_size: nat)
+    requires
+        tail <= observed_head,
+        tail <= new_tail <= tail + buffer_size,
+    ensures
+        tail <= new_tail <= observed_head + buffer_size,
+{
+}
+
+/// the head is bounded by the minimum of the local versions, so `advance_head_finish` with the
+/// minimum never moves the head back behind a replica
+pub proof fn head_bounded_by_min_local_version(
+    m: Map<NodeId, nat>,
+    num_replicas: nat,
+    head: nat,
+)
+    requires
+        0 < num_replicas,
+        forall|n: NodeId| 0 <= n < num_replicas ==> m.contains_key(n),
+        forall|n: NodeId| #[trigger] m.contains_key(n) ==> head <= m[n],
+    ensures
+        head <= map_min_value(m, (num_replicas - 1) as nat),
+{
+    map_min_value_smallest(m, (num_replicas - 1) as nat);
+    map_min_value_attained(m, (num_replicas - 1) as nat);
+    let n = choose|n| 0 <= n <= (num_replicas - 1) as nat
+        && map_min_value(m, (num_replicas - 1) as nat) == #[trigger] m.index(n);
+    assert(m.contains_key(n));
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Bounded Memory
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//
+// The unbounded log of the refinement keeps every entry forever, but the buffer only holds the
+// entries of the last `buffer_size` indices before the tail: advancing the tail withdraws the
+// entries that fall out of the window, and appends only deposit entries inside of it. Together
+// with the batches of the replicas, which hold at most one operation per thread
+// (`combiner_batch_bounded` of the flat combiner), this bounds the memory of the whole system
+// by `buffer_size` entries plus one operation and response per registered thread.
+
+/// the buffer holds at most `buffer_size` entries
+pub proof fn live_entries_bounded<DT: Dispatch>(s: CyclicBuffer::State<DT>)
+    requires
+        s.invariant(),
+    ensures
+        s.contents.dom().finite(),
+        s.contents.dom().len() <= s.buffer_size,
+{
+    let window = int_range(s.tail - s.buffer_size, s.tail as int);
+    int_range_len(s.tail - s.buffer_size, s.tail as int);
+    assert(s.contents.dom().subset_of(window));
+    vstd::set_lib::lemma_len_subset(s.contents.dom(), window);
+}
+
 /// converts the logical to the physical log index
 pub open spec fn log_entry_idx(logical: LogicalLogIdx, buffer_size: nat) -> LogIdx
     recommends
@@ -874,7 +1098,7 @@ proof fn log_entry_alive_wrap_around_helper(
     }
 }
 
-#[verifier(nonlinear)]
+#[verifier::nonlinear]
 pub proof fn log_entry_alive_value_wrap_around(i: LogicalLogIdx, buffer_size: nat)
     requires
         buffer_size > 0,
@@ -322,6 +322,17 @@ FlatCombiner {
         }
     }
 
+    /// the client withdraws its request before the combiner has collected it
+    transition!{
+        withdraw_request(tid: ThreadId, rid: ReqId) {
+            remove clients -= [ tid => ClientState::Waiting(rid) ];
+            add    clients += [ tid => ClientState::Idle ];
+
+            remove slots -= [ tid => SlotState::Request(rid) ];
+            add    slots += [ tid => SlotState::Empty ];
+        }
+    }
+
     /// Safety Condition: the slot state is not in progress when collecting
     property!{
         pre_recv_response(tid: ThreadId) {
@@ -345,6 +356,51 @@ FlatCombiner {
     }
 
 
+    ////////////////////////////////////////////////////////////////////////////////////////////
+    // Deadlock Freedom
+    ////////////////////////////////////////////////////////////////////////////////////////////
+    //
+    // The combiner never waits for a client: in every state one of its transitions is enabled,
+    // whatever the clients do. The clients only wait for the combiner (see `client_waits_for_combiner`).
+
+    /// Progress Condition: one of the collect transitions is enabled, `combiner_collect_request`
+    /// for a request and `combiner_collect_empty` otherwise
+    property!{
+        combiner_collect_enabled() {
+            require(pre.combiner.is_Collecting());
+            let tid = pre.combiner.get_Collecting_0().len();
+            require(tid < pre.num_threads);
+            have slots >= [ tid => let slot_state ];
+
+            assert(slot_state.is_Request() || slot_state.is_Empty() || slot_state.is_Response());
+        }
+    }
+
+    /// Progress Condition: `combiner_responding_result` is enabled for a collected request
+    property!{
+        combiner_responding_enabled() {
+            require(pre.combiner.is_Responding());
+            let tid = pre.combiner.get_Responding_1();
+            require(tid < pre.num_threads);
+            require(!pre.combiner.req_is_none(tid));
+            have slots >= [ tid => let slot_state ];
+
+            assert(slot_state.is_InProgress());
+        }
+    }
+
+    /// Progress Condition: a waiting client has a request in its slot that the combiner collects,
+    /// is processing or has answered, in which case `recv_response` is enabled
+    property!{
+        client_waits_for_combiner(tid: ThreadId) {
+            have clients >= [ tid => let ClientState::Waiting(rid) ];
+            have slots   >= [ tid => let slot_state ];
+
+            assert(slot_state.is_Request() || slot_state.is_InProgress() || slot_state.is_Response());
+        }
+    }
+
+
     ////////////////////////////////////////////////////////////////////////////////////////////
     // Proofs
     ////////////////////////////////////////////////////////////////////////////////////////////
@@ -395,6 +451,13 @@ FlatCombiner {
 
     }
 
+    #[inductive(withdraw_request)]
+    fn withdraw_request_inductive(pre: Self, post: Self, tid: ThreadId, rid: ReqId) {
+        assert(Self::slot_in_progress(post.slots, tid) == Self::slot_in_progress(pre.slots, tid));
+        assert(forall |i: nat| 0 <= i < post.num_threads
+            ==> #[trigger] Self::slot_in_progress(post.slots, i) == Self::slot_in_progress(pre.slots, i));
+    }
+
     #[inductive(recv_response)]
     fn recv_response_inductive(pre: Self, post: Self, tid: ThreadId, rid: ReqId) {
         assert(Self::slot_in_progress(post.slots, tid) == Self::slot_in_progress(pre.slots, tid));
@@ -404,5 +467,68 @@ FlatCombiner {
 
 }}  // tokenized_state_machine! { FlatCombiner { ...
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Bounded Combiner Rounds
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//
+// Together with the progress conditions above, a round of the combiner ends after at most
+// `2 * num_threads + 2` steps: every step but `combiner_responding_done` decreases
+// `steps_to_round_end`, which is bounded by the invariant. A client that sends a request is
+// collected in the current or the next round, unless it withdraws it first, and answered in the
+// same round.
+//
+// The combiner lock (`Replica::combiner`) only adds a try-lock around the rounds: a thread that
+// fails to acquire it returns without waiting and retries later. The holder only takes the write
+// lock of the replica, which readers hold while they dispatch, never while they wait for the
+// combiner, so the lock order is acyclic. When the log is full, the holder executes the log on its own
+// replica while it waits for the head to advance, so two combiners waiting for each other's
+// replicas both make progress. Hence some thread holds the lock or can acquire it whenever a
+// request is pending. Fairness is a property of executions, which the state machine doesn't
+// describe, hence the argument is stated on the states and transitions it relates.
+
+impl CombinerState {
+    /// the number of steps until the combiner finishes the current round
+    pub open spec fn steps_to_round_end(self, num_threads: nat) -> int {
+        match self {
+            CombinerState::Collecting(reqs) => 2 * num_threads + 2 - reqs.len(),
+            CombinerState::Responding(_, idx) => num_threads + 1 - idx,
+        }
+    }
+}
+
+/// the steps of a round are bounded by `2 * num_threads + 2`
+pub proof fn combiner_round_bounded(s: FlatCombiner::State)
+    requires
+        s.invariant(),
+    ensures
+        0 <= s.combiner.steps_to_round_end(s.num_threads) <= 2 * s.num_threads + 2,
+{
+}
+
+/// a batch of the combiner holds at most one operation per thread
+pub proof fn combiner_batch_bounded(s: FlatCombiner::State)
+    requires
+        s.invariant(),
+    ensures
+        s.combiner.req_len() <= s.num_threads,
+{
+}
+
+/// every step of the combiner within a round brings it closer to the end of the round
+pub proof fn combiner_step_progress(pre: FlatCombiner::State, post: FlatCombiner::State)
+    requires
+        FlatCombiner::State::combiner_collect_empty(pre, post)
+            || FlatCombiner::State::combiner_collect_request(pre, post)
+            || FlatCombiner::State::combiner_responding_start(pre, post)
+            || FlatCombiner::State::combiner_responding_empty(pre, post)
+            || FlatCombiner::State::combiner_responding_result(pre, post),
+    ensures
+        post.num_threads == pre.num_threads,
+        post.combiner.steps_to_round_end(post.num_threads) < pre.combiner.steps_to_round_end(
+            pre.num_threads,
+        ),
+{
+}
+
 
 } // verus!
@@ -111,6 +111,18 @@ proof fn exists_equiv_behavior_rec<DT: Dispatch>(
                     );
                     AsynchronousSingletonBehavior::Stepped(a0, aop, Box::new(b0))
                 },
+                SimpleLog::Step::readonly_cancel(rid) => {
+                    let b0 = exists_equiv_behavior_rec(*tail, r_points);
+                    let a0 = readonly_cancel_refines(
+                        prev,
+                        post,
+                        aop,
+                        b0.get_last(),
+                        r_points,
+                        rid,
+                    );
+                    AsynchronousSingletonBehavior::Stepped(a0, aop, Box::new(b0))
+                },
                 SimpleLog::Step::update_start(rid, uop) => {
                     let b0 = exists_equiv_behavior_rec(*tail, r_points);
                     let a0 = update_start_refines(
@@ -136,6 +148,18 @@ proof fn exists_equiv_behavior_rec<DT: Dispatch>(
                     );
                     AsynchronousSingletonBehavior::Stepped(a0, aop, Box::new(b0))
                 },
+                SimpleLog::Step::update_cancel(rid) => {
+                    let b0 = exists_equiv_behavior_rec(*tail, r_points);
+                    let a0 = update_cancel_refines(
+                        prev,
+                        post,
+                        aop,
+                        b0.get_last(),
+                        r_points,
+                        rid,
+                    );
+                    AsynchronousSingletonBehavior::Stepped(a0, aop, Box::new(b0))
+                },
                 SimpleLog::Step::update_incr_version(logidx) => {
                     update_incr_version_refines(a, r_points, logidx)
                 },
@@ -240,13 +264,13 @@ spec fn readonly_response_is_valid<DT: Dispatch>(
     &&& s.readonly_reqs[rid] is Req
     &&& s.readonly_reqs[rid]->version <= s.version
     &&& t.resps.contains_key(rid)
-    &&& (r_points.contains_key(rid) ==> {
-        &&& s.readonly_reqs[rid]->version <= r_points[rid] && r_points[rid] <= s.version
-        &&& 0 <= r_points[rid] && r_points[rid] <= s.log.len()
-        &&& t.resps[rid] == OutputOperation::<DT>::Read(
-            DT::dispatch_spec(s.nrstate_at_version(r_points[rid]), s.readonly_reqs[rid].op()),
-        )
-    })
+    // a read only has a response once it has been linearized at its point
+    &&& r_points.contains_key(rid)
+    &&& s.readonly_reqs[rid]->version <= r_points[rid] && r_points[rid] <= s.version
+    &&& 0 <= r_points[rid] && r_points[rid] <= s.log.len()
+    &&& t.resps[rid] == OutputOperation::<DT>::Read(
+        DT::dispatch_spec(s.nrstate_at_version(r_points[rid]), s.readonly_reqs[rid].op()),
+    )
 }
 
 /// checks whether the update response is valid                              (Dafny: update_is_done)
@@ -524,6 +548,56 @@ proof fn readonly_finish_refines<DT: Dispatch>(
     res
 }
 
+/// Refinement Proof of the Readonly_Cancel transition of the SimpleLog
+///
+/// This corresponds to the "Cancel" transition that withdraws a request. The cancelled read
+/// has no linearization point, so it hasn't been linearized and is still a pending request.
+proof fn readonly_cancel_refines<DT: Dispatch>(
+    s: SState<DT>,
+    s2: SState<DT>,
+    aop: AsyncLabel<DT>,
+    t: AState<DT>,
+    r_points: Map<ReqId, LogIdx>,
+    rid: ReqId,
+) -> (t2: AState<DT>)
+    requires
+        SimpleLog::State::readonly_cancel(s, s2, aop, rid),
+        state_refinement_relation(s, t, r_points),
+        future_points_ok(s2, r_points),
+    ensures
+        state_refinement_relation(s2, t2, r_points),
+        AsynchronousSingleton::State::next(t, t2, aop),
+{
+    // Is' := Is.(reqs := Is.reqs - {rid});
+    let res = AsynchronousSingleton::State {
+        state: t.state,
+        reqs: t.reqs.remove(rid),
+        resps: t.resps,
+    };
+    // remind verus that the request id is known!
+    assert(t.reqs.contains_key(rid) || t.resps.contains_key(rid));
+    assert(!r_points.contains_key(rid));
+    // without a linearization point there is no response for the read
+    assert(!t.resps.contains_key(rid));
+    assert(t.reqs.contains_key(rid));
+    assert forall|r|
+        (#[trigger] s2.update_resps.contains_key(r) && s2.update_resps[r].0 < s2.version)
+            ==> update_response_is_valid(s2, res, r_points, r) by {
+        if s2.update_resps.contains_key(r) && s2.update_resps[r].0 < s2.version {
+            assert(r != rid);
+        }
+    }
+    reveal(AsynchronousSingleton::State::next_by);
+    reveal(AsynchronousSingleton::State::next);
+    assert(AsynchronousSingleton::State::next_by(
+        t,
+        res,
+        aop,
+        AsynchronousSingleton::Step::cancel(rid),
+    ));
+    res
+}
+
 // =================================================================================================
 // State Transition Refinements: Update Requests
 // =================================================================================================
@@ -633,6 +707,43 @@ proof fn update_add_update_to_log_refines<DT: Dispatch>(
     t
 }
 
+/// Refinement Proof of the Update_Cancel transition of the SimpleLog
+///
+/// This corresponds to the "Cancel" transition that withdraws a request
+proof fn update_cancel_refines<DT: Dispatch>(
+    s: SState<DT>,
+    s2: SState<DT>,
+    aop: AsyncLabel<DT>,
+    t: AState<DT>,
+    r_points: Map<ReqId, LogIdx>,
+    rid: ReqId,
+) -> (t2: AState<DT>)
+    requires
+        SimpleLog::State::update_cancel(s, s2, aop, rid),
+        state_refinement_relation(s, t, r_points),
+    ensures
+        state_refinement_relation(s2, t2, r_points),
+        AsynchronousSingleton::State::next(t, t2, aop),
+{
+    // Is' := Is.(reqs := Is.reqs - {rid});
+    let res = AsynchronousSingleton::State {
+        state: t.state,
+        reqs: t.reqs.remove(rid),
+        resps: t.resps,
+    };
+    // the update hasn't been added to the log, so there is no response for it yet
+    assert(t.reqs.contains_key(rid) && !t.resps.contains_key(rid));
+    reveal(AsynchronousSingleton::State::next_by);
+    reveal(AsynchronousSingleton::State::next);
+    assert(AsynchronousSingleton::State::next_by(
+        t,
+        res,
+        aop,
+        AsynchronousSingleton::Step::cancel(rid),
+    ));
+    res
+}
+
 /// Refinement Proof ot the Update_Finish transition of the SimpleLog
 ///
 /// This corresponds to the "End" transition that removes a response from the system
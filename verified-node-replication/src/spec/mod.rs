@@ -8,14 +8,27 @@ pub mod utils;
 // the linearization proof
 pub mod linearization;
 
+// real-time order of the linearization, ghost only
+#[cfg(verus_keep_ghost)]
+pub mod realtime;
+
+// generic refinement framework
+pub mod refinement;
+
 // the simple log model
 pub mod simple_log;
 
+// model of a log with contiguous batches, unconnected to the other state machines
+pub mod batched_log;
+
 // unbounded log and refinement
 #[macro_use]
 pub mod unbounded_log;
 pub mod unbounded_log_refines_simplelog;
 
+// commuting reorderings of the log, proof only, not used by the combiner
+pub mod commutativity;
+
 // cyclic buffer
 #[macro_use]
 pub mod cyclicbuffer;
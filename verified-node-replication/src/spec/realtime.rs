@@ -0,0 +1,242 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+// Real-Time Order of the Linearization
+#[allow(unused_imports)]
+use builtin::*;
+use vstd::prelude::*;
+
+use crate::Dispatch;
+use crate::ReqId;
+use crate::{AsyncLabel, AsynchronousSingleton, AsynchronousSingletonBehavior};
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//
+// Real-Time Order
+// ===============
+//
+// The refinement proof shows that every behavior of NR is equivalent to a behavior of the
+// `AsynchronousSingleton`, which applies each request atomically in an internal step between the
+// `Start` and the `End` label of the request. Equivalent behaviors have the same external labels
+// in the same order, so the order of the linearization steps is also an order of the
+// invocations and responses NR exposes.
+//
+// This file makes the real-time order explicit with ghost timestamps: the time of a step is its
+// position in the behavior, and every request gets the time of its invocation (`Start`), of its
+// linearization (the internal step that applies it) and of its response (`End`). The theorem
+// `real_time_order` states that a request that responded before another one was invoked is
+// linearized before it, i.e., the linearization respects the real-time order of the requests
+// and not only the sequential order of each replica.
+//
+// The timestamps identify requests by their ids, so the clients must not reuse a request id
+// (`unique_request_ids`).
+//
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+verus! {
+
+type ABehavior<DT> = AsynchronousSingletonBehavior<DT>;
+
+/// the time of the last state of the behavior, the number of steps it took
+pub open spec fn behavior_time<DT: Dispatch>(b: ABehavior<DT>) -> nat
+    decreases b,
+{
+    match b {
+        AsynchronousSingletonBehavior::Stepped(_, _, tail) => behavior_time(*tail) + 1,
+        AsynchronousSingletonBehavior::Inited(_) => 0,
+    }
+}
+
+/// the ghost invocation timestamps, the time of the `Start` step of each request
+pub open spec fn invocation_times<DT: Dispatch>(b: ABehavior<DT>) -> Map<ReqId, nat>
+    decreases b,
+{
+    match b {
+        AsynchronousSingletonBehavior::Stepped(_, label, tail) => {
+            match label {
+                AsyncLabel::Start(rid, _) => invocation_times(*tail).insert(
+                    rid,
+                    behavior_time(*tail) + 1,
+                ),
+                _ => invocation_times(*tail),
+            }
+        },
+        AsynchronousSingletonBehavior::Inited(_) => Map::empty(),
+    }
+}
+
+/// the requests the step from `pre` to `post` applied to the state
+pub open spec fn linearized_by<DT: Dispatch>(
+    pre: AsynchronousSingleton::State<DT>,
+    post: AsynchronousSingleton::State<DT>,
+    rid: ReqId,
+) -> bool {
+    &&& pre.reqs.contains_key(rid)
+    &&& !post.reqs.contains_key(rid)
+    &&& post.resps.contains_key(rid)
+}
+
+/// the ghost linearization timestamps, the time of the internal step that applied each request
+pub open spec fn linearization_times<DT: Dispatch>(b: ABehavior<DT>) -> Map<ReqId, nat>
+    decreases b,
+{
+    match b {
+        AsynchronousSingletonBehavior::Stepped(post, _, tail) => {
+            let pre = tail.get_last();
+            linearization_times(*tail).union_prefer_right(
+                Map::new(
+                    |rid: ReqId| linearized_by(pre, post, rid),
+                    |rid: ReqId| behavior_time(*tail) + 1,
+                ),
+            )
+        },
+        AsynchronousSingletonBehavior::Inited(_) => Map::empty(),
+    }
+}
+
+/// the ghost response timestamps, the time of the `End` step of each request
+pub open spec fn response_times<DT: Dispatch>(b: ABehavior<DT>) -> Map<ReqId, nat>
+    decreases b,
+{
+    match b {
+        AsynchronousSingletonBehavior::Stepped(_, label, tail) => {
+            match label {
+                AsyncLabel::End(rid, _) => response_times(*tail).insert(
+                    rid,
+                    behavior_time(*tail) + 1,
+                ),
+                _ => response_times(*tail),
+            }
+        },
+        AsynchronousSingletonBehavior::Inited(_) => Map::empty(),
+    }
+}
+
+/// the clients never start a request with an id that was used before
+pub open spec fn unique_request_ids<DT: Dispatch>(b: ABehavior<DT>) -> bool
+    decreases b,
+{
+    match b {
+        AsynchronousSingletonBehavior::Stepped(_, label, tail) => {
+            &&& unique_request_ids(*tail)
+            &&& (label.is_Start() ==> !invocation_times(*tail).contains_key(
+                label.get_Start_0(),
+            ))
+        },
+        AsynchronousSingletonBehavior::Inited(_) => true,
+    }
+}
+
+/// the timestamps of a request are ordered: invocation < linearization < response
+pub open spec fn timestamps_inv<DT: Dispatch>(b: ABehavior<DT>) -> bool {
+    let last = b.get_last();
+    let inv = invocation_times(b);
+    let lin = linearization_times(b);
+    let resp = response_times(b);
+    // all timestamps are in the past
+    &&& (forall|rid: ReqId| #[trigger] inv.contains_key(rid) ==> inv[rid] <= behavior_time(b))
+    &&& (forall|rid: ReqId| #[trigger] lin.contains_key(rid) ==> lin[rid] <= behavior_time(b))
+    // a linearized request was invoked before
+    &&& (forall|rid: ReqId| #[trigger] lin.contains_key(rid) ==> {
+        &&& inv.contains_key(rid)
+        &&& inv[rid] < lin[rid]
+    })
+    // a request that responded was linearized before
+    &&& (forall|rid: ReqId| #[trigger] resp.contains_key(rid) ==> {
+        &&& lin.contains_key(rid)
+        &&& lin[rid] < resp[rid]
+    })
+    // a pending request was invoked, but not linearized yet
+    &&& (forall|rid: ReqId| #[trigger] last.reqs.contains_key(rid) ==> {
+        &&& inv.contains_key(rid)
+        &&& !lin.contains_key(rid)
+        &&& !resp.contains_key(rid)
+    })
+    // a pending response was linearized, but has not responded yet
+    &&& (forall|rid: ReqId| #[trigger] last.resps.contains_key(rid) ==> {
+        &&& lin.contains_key(rid)
+        &&& !resp.contains_key(rid)
+    })
+}
+
+/// the timestamps of every behavior with unique request ids are ordered
+pub proof fn lemma_timestamps<DT: Dispatch>(b: ABehavior<DT>)
+    requires
+        b.wf(),
+        unique_request_ids(b),
+    ensures
+        timestamps_inv(b),
+    decreases b,
+{
+    match b {
+        AsynchronousSingletonBehavior::Stepped(post, label, tail) => {
+            lemma_timestamps(*tail);
+            reveal(AsynchronousSingleton::State::next);
+            reveal(AsynchronousSingleton::State::next_by);
+            let pre = tail.get_last();
+            let t = behavior_time(b);
+            let inv = invocation_times(b);
+            let lin = linearization_times(b);
+            let resp = response_times(b);
+            let step = choose|step: AsynchronousSingleton::Step<DT>|
+                AsynchronousSingleton::State::next_by(pre, post, label, step);
+            match step {
+                AsynchronousSingleton::Step::internal_next(rid, input, output) => {
+                    // only `rid` is linearized by the step, and it was pending
+                    assert forall|r: ReqId| linearized_by(pre, post, r) implies r == rid by {}
+                    assert(lin =~= linearization_times(*tail).insert(rid, t));
+                    assert(inv =~= invocation_times(*tail));
+                    assert(resp =~= response_times(*tail));
+                },
+                AsynchronousSingleton::Step::no_op() => {
+                    assert(lin =~= linearization_times(*tail));
+                },
+                AsynchronousSingleton::Step::cancel(rid) => {
+                    assert(lin =~= linearization_times(*tail));
+                },
+                AsynchronousSingleton::Step::start(rid, input) => {
+                    // the request id is fresh, it has no timestamps yet
+                    assert(!linearization_times(*tail).contains_key(rid));
+                    assert(!response_times(*tail).contains_key(rid));
+                    assert(lin =~= linearization_times(*tail));
+                    assert(inv =~= invocation_times(*tail).insert(rid, t));
+                },
+                AsynchronousSingleton::Step::end(rid, output) => {
+                    // the response was linearized in an earlier step
+                    assert(lin =~= linearization_times(*tail));
+                    assert(resp =~= response_times(*tail).insert(rid, t));
+                },
+                AsynchronousSingleton::Step::dummy_to_use_type_params(state) => {
+                    assert(false);
+                },
+            }
+        },
+        AsynchronousSingletonBehavior::Inited(st) => {
+            reveal(AsynchronousSingleton::State::init);
+            reveal(AsynchronousSingleton::State::init_by);
+            assert(st.reqs =~= Map::empty());
+            assert(st.resps =~= Map::empty());
+        },
+    }
+}
+
+/// Real-time order: if `r1` responded before `r2` was invoked, then `r1` was linearized before
+/// `r2`.
+pub proof fn real_time_order<DT: Dispatch>(b: ABehavior<DT>, r1: ReqId, r2: ReqId)
+    requires
+        b.wf(),
+        unique_request_ids(b),
+        response_times(b).contains_key(r1),
+        invocation_times(b).contains_key(r2),
+        response_times(b)[r1] < invocation_times(b)[r2],
+        linearization_times(b).contains_key(r2),
+    ensures
+        linearization_times(b).contains_key(r1),
+        linearization_times(b)[r1] < linearization_times(b)[r2],
+{
+    lemma_timestamps(b);
+    assert(linearization_times(b).contains_key(r1));
+    assert(invocation_times(b)[r2] < linearization_times(b)[r2]);
+}
+
+} // verus!
@@ -0,0 +1,141 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+// Generic Refinement Framework
+#[allow(unused_imports)]
+use builtin::*;
+use vstd::prelude::*;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//
+// Refinement Framework
+// ====================
+//
+// A refinement proof between a concrete state machine C and an abstract state machine A always
+// has the same structure:
+//
+//  1. an interpretation function that maps a concrete state to an abstract state,
+//  2. the interpretation of a state satisfying the concrete invariant satisfies the abstract one,
+//  3. the interpretation of an initial concrete state is an initial abstract state,
+//  4. the interpretation of every concrete step is an abstract step.
+//
+// The `Refinement` trait captures these simulation obligations, the induction driver
+// `lemma_trace_refines` lifts them from single steps to whole traces. A refinement proof only
+// needs to implement the trait, and obtains the trace refinement for free.
+//
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+verus! {
+
+/// The simulation obligations of a refinement of the state machine `C` by the state machine `A`.
+pub trait Refinement<C, A> {
+    /// the invariant of the concrete state machine
+    spec fn inv(c: C) -> bool;
+
+    /// the initial states of the concrete state machine
+    spec fn c_init(c: C) -> bool;
+
+    /// the steps of the concrete state machine
+    spec fn c_next(c: C, c2: C) -> bool;
+
+    /// the invariant of the abstract state machine
+    spec fn a_inv(a: A) -> bool;
+
+    /// the initial states of the abstract state machine
+    spec fn a_init(a: A) -> bool;
+
+    /// the steps of the abstract state machine
+    spec fn a_next(a: A, a2: A) -> bool;
+
+    /// the interpretation function, maps a concrete state to an abstract state
+    spec fn interp(c: C) -> A;
+
+    /// the concrete invariant implies the abstract invariant
+    proof fn inv_refines(c: C)
+        requires
+            Self::inv(c),
+        ensures
+            Self::a_inv(Self::interp(c)),
+    ;
+
+    /// initial states map to initial states
+    proof fn init_refines(c: C)
+        requires
+            Self::inv(c),
+            Self::c_init(c),
+        ensures
+            Self::a_init(Self::interp(c)),
+    ;
+
+    /// concrete steps map to abstract steps
+    proof fn next_refines(c: C, c2: C)
+        requires
+            Self::inv(c),
+            Self::inv(c2),
+            Self::c_next(c, c2),
+        ensures
+            Self::a_next(Self::interp(c), Self::interp(c2)),
+    ;
+}
+
+/// a non-empty trace of the concrete state machine in which every state satisfies the invariant
+pub open spec fn is_concrete_trace<C, A, R: Refinement<C, A>>(trace: Seq<C>) -> bool {
+    &&& trace.len() > 0
+    &&& R::c_init(trace[0])
+    &&& forall|i: int| 0 <= i < trace.len() ==> R::inv(#[trigger] trace[i])
+    &&& forall|i: int| 0 <= i < trace.len() - 1 ==> R::c_next(#[trigger] trace[i], trace[i + 1])
+}
+
+/// a non-empty trace of the abstract state machine in which every state satisfies the invariant
+pub open spec fn is_abstract_trace<C, A, R: Refinement<C, A>>(trace: Seq<A>) -> bool {
+    &&& trace.len() > 0
+    &&& R::a_init(trace[0])
+    &&& forall|i: int| 0 <= i < trace.len() ==> R::a_inv(#[trigger] trace[i])
+    &&& forall|i: int| 0 <= i < trace.len() - 1 ==> R::a_next(#[trigger] trace[i], trace[i + 1])
+}
+
+/// interprets every state of the concrete trace
+pub open spec fn interp_trace<C, A, R: Refinement<C, A>>(trace: Seq<C>) -> Seq<A> {
+    trace.map_values(|c: C| R::interp(c))
+}
+
+/// The induction driver: the interpretation of a concrete trace is an abstract trace.
+pub proof fn lemma_trace_refines<C, A, R: Refinement<C, A>>(trace: Seq<C>)
+    requires
+        is_concrete_trace::<C, A, R>(trace),
+    ensures
+        is_abstract_trace::<C, A, R>(interp_trace::<C, A, R>(trace)),
+    decreases trace.len(),
+{
+    let atrace = interp_trace::<C, A, R>(trace);
+    if trace.len() == 1 {
+        R::init_refines(trace[0]);
+        R::inv_refines(trace[0]);
+    } else {
+        let prefix = trace.drop_last();
+        assert(is_concrete_trace::<C, A, R>(prefix));
+        lemma_trace_refines::<C, A, R>(prefix);
+
+        let last = trace.len() - 1;
+        R::inv_refines(trace[last]);
+        R::next_refines(trace[last - 1], trace[last]);
+
+        assert(interp_trace::<C, A, R>(prefix) =~= atrace.drop_last());
+        assert forall|i: int| 0 <= i < atrace.len() implies R::a_inv(#[trigger] atrace[i]) by {
+            if i < last {
+                assert(atrace[i] == interp_trace::<C, A, R>(prefix)[i]);
+            }
+        }
+        assert forall|i: int| 0 <= i < atrace.len() - 1 implies R::a_next(
+            #[trigger] atrace[i],
+            atrace[i + 1],
+        ) by {
+            if i < last - 1 {
+                assert(atrace[i] == interp_trace::<C, A, R>(prefix)[i]);
+                assert(atrace[i + 1] == interp_trace::<C, A, R>(prefix)[i + 1]);
+            }
+        }
+    }
+}
+
+} // verus!
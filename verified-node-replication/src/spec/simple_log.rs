@@ -8,7 +8,7 @@ use state_machines_macros::*;
 use vstd::prelude::*;
 
 use crate::spec::types::*;
-use crate::{AsyncLabel, Dispatch, InputOperation, OutputOperation};
+use crate::{AsyncLabel, Dispatch, InputOperation, OutputOperation, SimpleLogBehavior};
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 //
@@ -190,6 +190,19 @@ state_machine! {
         }
     }
 
+    /// Read Request: Withdraw the request from the system
+    ///
+    /// The request is removed without producing a response.
+    transition!{
+        readonly_cancel(label: Label<DT>, rid: ReqId) {
+            require label.is_Internal();
+
+            require pre.readonly_reqs.contains_key(rid);
+
+            update readonly_reqs = pre.readonly_reqs.remove(rid);
+        }
+    }
+
 
     ////////////////////////////////////////////////////////////////////////////////////////////////
     // Update Operation Transitions
@@ -234,6 +247,19 @@ state_machine! {
         }
     }
 
+    /// Update Request: Withdraw an update request that hasn't been added to the log
+    ///
+    /// The request is removed without producing a response, the log is left unchanged.
+    transition!{
+        update_cancel(label: Label<DT>, rid: ReqId) {
+            require label.is_Internal();
+
+            require pre.update_reqs.contains_key(rid);
+
+            update update_reqs = pre.update_reqs.remove(rid);
+        }
+    }
+
     /// Update: Increasing the version of the log
     ///
     /// The version value is monotonically increasing and must not be larger than the
@@ -297,12 +323,18 @@ state_machine! {
     #[inductive(readonly_finish)]
     fn readonly_finish_inductive(pre: Self, post: Self, label: Label<DT>, rid: ReqId, version: LogIdx, ret: DT::Response) { }
 
+    #[inductive(readonly_cancel)]
+    fn readonly_cancel_inductive(pre: Self, post: Self, label: Label<DT>, rid: ReqId) { }
+
     #[inductive(update_start)]
     fn update_start_inductive(pre: Self, post: Self, label: Label<DT>, rid: ReqId, op: DT::WriteOperation) { }
 
     #[inductive(update_add_op_to_log)]
     fn update_add_op_to_log_inductive(pre: Self, post: Self, label: Label<DT>, rid: ReqId) { }
 
+    #[inductive(update_cancel)]
+    fn update_cancel_inductive(pre: Self, post: Self, label: Label<DT>, rid: ReqId) { }
+
     #[inductive(update_incr_version)]
     fn update_incr_version_inductive(pre: Self, post: Self, label: Label<DT>, new_version: LogIdx) { }
 
@@ -338,4 +370,198 @@ pub open spec fn compute_nrstate_at_version<DT: Dispatch>(
     }
 }
 
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// External Consistency
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//
+// Once an update returned, every read that starts afterwards, on any replica, observes it:
+//
+//  1. an update with log index `uidx` only finishes once the version passed it (`update_finish`
+//     requires `uidx < version`), in the implementation the version upper bound;
+//  2. the version never decreases (`version_monotonic`), so every later state has a version
+//     past `uidx` (`lemma_version_reached`);
+//  3. a read records the version when it reads it (`readonly_read_version`), and is answered on
+//     the state at a version at least as large (`readonly_finish`), which is a state the update
+//     has been applied to (`lemma_readonly_observes`).
+
+/// the version of the log never decreases
+pub proof fn version_monotonic<DT: Dispatch>(
+    pre: SimpleLog::State<DT>,
+    post: SimpleLog::State<DT>,
+    label: AsyncLabel<DT>,
+)
+    requires
+        SimpleLog::State::next(pre, post, label),
+    ensures
+        pre.version <= post.version,
+{
+    reveal(SimpleLog::State::next);
+    reveal(SimpleLog::State::next_by);
+    let step = choose|step: SimpleLog::Step<DT>| SimpleLog::State::next_by(pre, post, label, step);
+    match step {
+        SimpleLog::Step::update_incr_version(new_version) => {},
+        _ => {},
+    }
+}
+
+/// a finished update leaves the version past its log index
+pub proof fn update_finish_version<DT: Dispatch>(
+    pre: SimpleLog::State<DT>,
+    post: SimpleLog::State<DT>,
+    label: AsyncLabel<DT>,
+    rid: nat,
+    ret: DT::Response,
+)
+    requires
+        SimpleLog::State::update_finish(pre, post, label, rid, ret),
+    ensures
+        pre.update_resps[rid].0 < post.version,
+{
+}
+
+/// some state of the behavior has a version of at least `version`
+pub open spec fn version_reached<DT: Dispatch>(b: SimpleLogBehavior<DT>, version: LogIdx) -> bool
+    decreases b,
+{
+    ||| version <= b.get_last().version
+    ||| (b.is_Stepped() && version_reached(*b.get_Stepped_2(), version))
+}
+
+/// once reached, the version stays reached: the last state has a version of at least `version`
+pub proof fn lemma_version_reached<DT: Dispatch>(b: SimpleLogBehavior<DT>, version: LogIdx)
+    requires
+        b.wf(),
+        version_reached(b, version),
+    ensures
+        version <= b.get_last().version,
+    decreases b,
+{
+    if let SimpleLogBehavior::Stepped(post, label, tail) = b {
+        if version > post.version {
+            lemma_version_reached(*tail, version);
+            version_monotonic(tail.get_last(), post, label);
+        }
+    }
+}
+
+/// the read `rid` started in a state with a version of at least `version`: the last `Start` of
+/// the read in the behavior happened after the version was reached
+pub open spec fn read_started_after<DT: Dispatch>(
+    b: SimpleLogBehavior<DT>,
+    rid: ReqId,
+    version: LogIdx,
+) -> bool
+    decreases b,
+{
+    match b {
+        SimpleLogBehavior::Stepped(_, label, tail) => {
+            if label.is_Start() && label.get_Start_0() == rid && label.get_Start_1().is_Read() {
+                version <= tail.get_last().version
+            } else {
+                read_started_after(*tail, rid, version)
+            }
+        },
+        SimpleLogBehavior::Inited(_) => false,
+    }
+}
+
+/// a read that started after `version` was reached
+proof fn lemma_read_started_after_reached<DT: Dispatch>(
+    b: SimpleLogBehavior<DT>,
+    rid: ReqId,
+    version: LogIdx,
+)
+    requires
+        read_started_after(b, rid, version),
+    ensures
+        version_reached(b, version),
+    decreases b,
+{
+    if let SimpleLogBehavior::Stepped(_, label, tail) = b {
+        if !(label.is_Start() && label.get_Start_0() == rid && label.get_Start_1().is_Read()) {
+            lemma_read_started_after_reached(*tail, rid, version);
+        }
+    }
+}
+
+/// a read that started after `version` was reached records a version of at least `version`
+pub proof fn lemma_read_records_version<DT: Dispatch>(
+    b: SimpleLogBehavior<DT>,
+    rid: ReqId,
+    version: LogIdx,
+)
+    requires
+        b.wf(),
+        read_started_after(b, rid, version),
+        b.get_last().readonly_reqs.contains_key(rid),
+        b.get_last().readonly_reqs[rid] is Req,
+    ensures
+        version <= b.get_last().readonly_reqs[rid]->version,
+    decreases b,
+{
+    if let SimpleLogBehavior::Stepped(post, label, tail) = b {
+        let pre = tail.get_last();
+        reveal(SimpleLog::State::next);
+        reveal(SimpleLog::State::next_by);
+        let step = choose|step: SimpleLog::Step<DT>|
+            SimpleLog::State::next_by(pre, post, label, step);
+        if label.is_Start() && label.get_Start_0() == rid && label.get_Start_1().is_Read() {
+            // the read has just started, it hasn't recorded a version yet
+            assert(step is readonly_start);
+        } else {
+            match step {
+                SimpleLog::Step::readonly_read_version(rid2) => {
+                    if rid2 == rid {
+                        // the read records the current version, which is past `version`
+                        lemma_read_started_after_reached(*tail, rid, version);
+                        lemma_version_reached(*tail, version);
+                    } else {
+                        lemma_read_records_version(*tail, rid, version);
+                    }
+                },
+                _ => {
+                    if pre.readonly_reqs.contains_key(rid) && pre.readonly_reqs[rid] is Req {
+                        lemma_read_records_version(*tail, rid, version);
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// External consistency: a read that started after the update with log index `uidx` returned
+/// is answered on the state at a version past `uidx`, a state the update has been applied to.
+///
+/// The update returned in a state with a version past `uidx` (`update_finish_version`), the
+/// read started in that state or a later one.
+pub proof fn lemma_readonly_observes<DT: Dispatch>(
+    b: SimpleLogBehavior<DT>,
+    uidx: LogIdx,
+    rid: ReqId,
+    version: LogIdx,
+    ret: DT::Response,
+)
+    requires
+        b.wf(),
+        b.is_Stepped(),
+        read_started_after(*b.get_Stepped_2(), rid, uidx + 1),
+        SimpleLog::State::readonly_finish(
+            b.get_Stepped_2().get_last(),
+            b.get_last(),
+            b.get_Stepped_1(),
+            rid,
+            version,
+            ret,
+        ),
+    ensures
+        uidx < version,
+        ret == DT::dispatch_spec(
+            compute_nrstate_at_version::<DT>(b.get_last().log, version),
+            b.get_Stepped_2().get_last().readonly_reqs[rid].op(),
+        ),
+{
+    lemma_read_records_version(*b.get_Stepped_2(), rid, uidx + 1);
+}
+
 } // verus!
@@ -260,6 +260,20 @@ impl CombinerState {
     }
 }
 
+/// the number of updates a replica has applied, split by the node that placed them in the log
+pub ghost struct AppliedCounters {
+    /// updates placed in the log by the node of the replica
+    pub local: nat,
+    /// updates placed in the log by other nodes
+    pub remote: nat,
+}
+
+impl AppliedCounters {
+    pub open spec fn total(self) -> nat {
+        self.local + self.remote
+    }
+}
+
 } // verus!
 // end verus!
 tokenized_state_machine! {
@@ -291,7 +305,10 @@ UnboundedLog<DT: Dispatch> {
         pub local_updates: Map<ReqId, UpdateState<DT>>,
 
         #[sharding(map)]
-        pub combiner: Map<NodeId, CombinerState>
+        pub combiner: Map<NodeId, CombinerState>,
+
+        #[sharding(map)]
+        pub applied: Map<NodeId, AppliedCounters>
     }
 
 
@@ -571,6 +588,27 @@ UnboundedLog<DT: Dispatch> {
             self.replicas[node_id] == compute_nrstate_at_version(self.log, self.current_local_version(node_id))
     }
 
+    /// the applied counters of a replica must match the current version of the log
+    #[invariant]
+    pub open spec fn inv_applied_counters(&self) -> bool {
+        &&& (forall |k| self.applied.contains_key(k) <==> self.combiner.contains_key(k))
+        &&& (forall |node_id| (#[trigger] self.applied.contains_key(node_id)) ==>
+            self.applied[node_id] == compute_applied_at_version(self.log, node_id, self.current_local_version(node_id)))
+        &&& (forall |node_id| (#[trigger] self.applied.contains_key(node_id)) ==>
+            self.wf_applied_for_node_id(node_id))
+    }
+
+    /// while the combiner is in its loop, every applied local update is one of its queued ops
+    pub open spec fn wf_applied_for_node_id(&self, node_id: NodeId) -> bool {
+        match self.combiner[node_id] {
+            CombinerState::Loop { idx, .. } => {
+                self.applied[node_id].local
+                    == compute_applied_at_version(self.log, node_id, self.local_versions[node_id]).local + idx
+            }
+            _ => true,
+        }
+    }
+
 
     ////////////////////////////////////////////////////////////////////////////////////////////
     // State Machine Initialization
@@ -589,6 +627,7 @@ UnboundedLog<DT: Dispatch> {
             init local_reads = Map::empty();
             init local_updates = Map::empty();
             init combiner = Map::new(|n: NodeId| n < number_of_nodes, |n| CombinerState::Ready);
+            init applied = Map::new(|n: NodeId| n < number_of_nodes, |n| AppliedCounters { local: 0, remote: 0 });
         }
     }
 
@@ -634,6 +673,16 @@ UnboundedLog<DT: Dispatch> {
         }
     }
 
+    /// Read Request: withdraw a read request before it has been dispatched to a replica
+    ///
+    /// Only requests that have not yet read from a replica can be cancelled.
+    transition!{
+        readonly_cancel(rid: ReqId) {
+            remove local_reads -= [ rid => let r ];
+            require(r.is_Init() || r.is_VersionUpperBound() || r.is_ReadyToRead());
+        }
+    }
+
     ////////////////////////////////////////////////////////////////////////////////////////////
     // Update Transitions
     ////////////////////////////////////////////////////////////////////////////////////////////
@@ -665,6 +714,13 @@ UnboundedLog<DT: Dispatch> {
         }
     }
 
+    /// Update: withdraw an update request that has not been placed into the log yet
+    transition!{
+        update_cancel(rid: ReqId) {
+            remove local_updates -= [ rid => let UpdateState::Init { op } ];
+        }
+    }
+
     /*/// Update: Remove a finished update from the system
     transition!{
         update_finish(rid:ReqId) {
@@ -729,6 +785,7 @@ UnboundedLog<DT: Dispatch> {
         exec_dispatch_local(node_id: NodeId) {
             remove combiner      -= [ node_id => let CombinerState::Loop { queued_ops, lversion, tail, idx } ];
             remove replicas      -= [ node_id => let old_nr_state ];
+            remove applied       -= [ node_id => let counters ];
             let rid = queued_ops.index(idx as int);
             remove local_updates -= [ rid => let local_update ];
 
@@ -743,6 +800,7 @@ UnboundedLog<DT: Dispatch> {
 
             add local_updates += [ rid => UpdateState::Applied { ret, idx: lversion }];
             add replicas      += [ node_id => new_nr_state];
+            add applied       += [ node_id => AppliedCounters { local: counters.local + 1, remote: counters.remote } ];
             add combiner      += [ node_id => CombinerState::Loop { queued_ops, lversion: lversion + 1, tail, idx: idx + 1}];
         }
     }
@@ -752,6 +810,7 @@ UnboundedLog<DT: Dispatch> {
         exec_dispatch_remote(node_id: NodeId) {
             remove combiner -= [ node_id => let CombinerState:: Loop { queued_ops, lversion, tail, idx } ];
             remove replicas -= [ node_id => let old_nr_state ];
+            remove applied  -= [ node_id => let counters ];
 
             have   log      >= [ lversion => let log_entry ];
 
@@ -761,10 +820,23 @@ UnboundedLog<DT: Dispatch> {
             let (new_nr_state, ret) = DT::dispatch_mut_spec(old_nr_state, log_entry.op);
 
             add replicas    += [ node_id => new_nr_state ];
+            add applied     += [ node_id => AppliedCounters { local: counters.local, remote: counters.remote + 1 } ];
             add combiner    += [ node_id => CombinerState::Loop { queued_ops, lversion: lversion + 1, tail, idx}];
         }
     }
 
+    /// Combiner: Safety condition, the applied counters of the replica sum up to its version
+    property!{
+        pre_exec_applied_counters(node_id: NodeId) {
+            have combiner >= [ node_id => let CombinerState::Loop{ queued_ops, lversion, tail, idx } ];
+            have applied  >= [ node_id => let counters ];
+
+            assert(counters.total() == lversion) by {
+                compute_applied_at_version_total(pre.log, node_id, lversion);
+            };
+        }
+    }
+
     /// Combiner: Safety condition, if we applied all updates, idx must be the length of the list
     property!{
         pre_exec_update_version_upper_bound(node_id: NodeId) {
@@ -825,6 +897,65 @@ UnboundedLog<DT: Dispatch> {
     }
 
 
+    ////////////////////////////////////////////////////////////////////////////////////////////
+    // Replica Recovery Transitions
+    ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+    /// Replica: discard the state of a replica and rebuild it from the replica of another node
+    ///
+    /// The replica takes over the state and the version of the donor replica. It can't be reset
+    /// to the initial state: the log below its local version may contain entries of this node,
+    /// and those must not be replayed. The donor must be at least as recent as the replica, and
+    /// neither of the two may be in the middle of a combiner round.
+    transition!{
+        replica_rebuild(node_id: NodeId, donor: NodeId) {
+            require(node_id != donor);
+
+            have   combiner       >= [ node_id => CombinerState::Ready ];
+            have   combiner       >= [ donor => CombinerState::Ready ];
+            have   replicas       >= [ donor => let state ];
+            have   local_versions >= [ donor => let version ];
+
+            remove replicas       -= [ node_id => let _ ];
+            remove local_versions -= [ node_id => let lversion ];
+            remove applied        -= [ node_id => let _ ];
+
+            require(lversion <= version);
+
+            add    replicas       += [ node_id => state ];
+            add    local_versions += [ node_id => version ];
+            add    applied        += [ node_id => compute_applied_at_version(pre.log, node_id, version) ];
+        }
+    }
+
+    /// Replica: jump the replica to a snapshot instead of replaying the log up to its version
+    ///
+    /// The snapshot is the state at `version` (the abstraction function obligation), taken by
+    /// another replica or restored from storage. Like a rebuild, the replica may only move
+    /// forward, and not in the middle of a combiner round: a ready combiner has no entries of
+    /// this node between the local version and the tail, so no update waits for the skipped
+    /// entries. The version must be published (at most the version upper bound), so that the
+    /// reads on the replica stay consistent.
+    transition!{
+        replica_snapshot_jump(node_id: NodeId, version: LogIdx, snapshot: DT::View) {
+            have   combiner       >= [ node_id => CombinerState::Ready ];
+
+            remove replicas       -= [ node_id => let _ ];
+            remove local_versions -= [ node_id => let lversion ];
+            remove applied        -= [ node_id => let _ ];
+
+            require(lversion <= version);
+            require(version <= pre.version_upper_bound);
+            require(snapshot == compute_nrstate_at_version(pre.log, version));
+
+            add    replicas       += [ node_id => snapshot ];
+            add    local_versions += [ node_id => version ];
+            add    applied        += [ node_id => compute_applied_at_version(pre.log, node_id, version) ];
+        }
+    }
+
+
     ////////////////////////////////////////////////////////////////////////////////////////////
     // Inductiveness Proofs
     ////////////////////////////////////////////////////////////////////////////////////////////
@@ -885,6 +1016,9 @@ UnboundedLog<DT: Dispatch> {
         assert(rangeincl(vup, v, post.version_upper_bound));
     }
 
+    #[inductive(readonly_cancel)]
+    fn readonly_cancel_inductive(pre: Self, post: Self, rid: ReqId) { }
+
     pub proof fn add_ticket_inductive(
         pre: UnboundedLog::State<DT>,
         post: UnboundedLog::State<DT>,
@@ -896,6 +1030,8 @@ UnboundedLog<DT: Dispatch> {
             rid == get_fresh_nat(pre.local_updates.dom() + pre.local_reads.dom(), pre.combiner)
         ensures post.invariant(),
     {
+        broadcast use group_log_range;
+
         get_fresh_nat_not_in(pre.local_updates.dom() + pre.local_reads.dom(), pre.combiner);
         match input {
             crate::InputOperation::Read(op) => {
@@ -904,21 +1040,7 @@ UnboundedLog<DT: Dispatch> {
             crate::InputOperation::Write(op) => {
                 assert forall |node_id| #[trigger] post.combiner.contains_key(node_id) implies post.wf_combiner_for_node_id(node_id) by {
                     assert(post.combiner[node_id] == pre.combiner[node_id]);
-                    match post.combiner[node_id] {
-                        CombinerState::Placed { queued_ops } => {
-                            LogRangeMatchesQueue_update_change(queued_ops, post.log, 0, post.local_versions[node_id], post.tail, node_id, pre.local_updates, post.local_updates);
-                            assert(post.wf_combiner_for_node_id(node_id));
-                        }
-                        CombinerState::LoadedLocalVersion{ queued_ops, lversion } => {
-                            LogRangeMatchesQueue_update_change(queued_ops, post.log, 0, lversion, post.tail, node_id, pre.local_updates, post.local_updates);
-                            assert(post.wf_combiner_for_node_id(node_id));
-                        }
-                        CombinerState::Loop{ queued_ops, idx, lversion, tail } => {
-                            LogRangeMatchesQueue_update_change(queued_ops, post.log, idx, lversion, tail, node_id, pre.local_updates, post.local_updates);
-                        }
-                        _ => {
-                        }
-                    }
+                    assert(pre.wf_combiner_for_node_id(node_id));
                 }
             }
         }
@@ -926,19 +1048,20 @@ UnboundedLog<DT: Dispatch> {
 
     #[inductive(update_done)]
     fn update_done_inductive(pre: Self, post: Self, rid: ReqId) {
+        broadcast use group_log_range;
+
         assert forall |node_id| #[trigger] post.combiner.contains_key(node_id) implies post.wf_combiner_for_node_id(node_id) by {
-            match post.combiner[node_id] {
-                CombinerState::Placed { queued_ops } => {
-                    LogRangeMatchesQueue_update_change(queued_ops, post.log, 0, post.local_versions[node_id], post.tail, node_id, pre.local_updates, post.local_updates);
-                }
-                CombinerState::LoadedLocalVersion{ queued_ops, lversion } => {
-                    LogRangeMatchesQueue_update_change(queued_ops, post.log, 0, lversion, post.tail, node_id, pre.local_updates, post.local_updates);
-                }
-                CombinerState::Loop{ queued_ops, idx, lversion, tail } => {
-                    LogRangeMatchesQueue_update_change(queued_ops, post.log, idx, lversion, tail, node_id, pre.local_updates, post.local_updates);
-                }
-                _ => {}
-            }
+            assert(pre.wf_combiner_for_node_id(node_id));
+        }
+    }
+
+    #[inductive(update_cancel)]
+    fn update_cancel_inductive(pre: Self, post: Self, rid: ReqId) {
+        broadcast use group_log_range;
+
+        // the cancelled request is still in `Init`, so it can't be part of any combiner queue
+        assert forall |node_id| #[trigger] post.combiner.contains_key(node_id) implies post.wf_combiner_for_node_id(node_id) by {
+            assert(pre.wf_combiner_for_node_id(node_id));
         }
     }
 
@@ -952,24 +1075,15 @@ UnboundedLog<DT: Dispatch> {
             crate::consume_stub(pre, post, output, rid),
         ensures post.invariant(),
     {
+        broadcast use group_log_range;
+
         match output {
             crate::OutputOperation::Read(op) => {
                 assert(post.inv_readonly_requests_wf());
             }
             crate::OutputOperation::Write(op) => {
                 assert forall |node_id| #[trigger] post.combiner.contains_key(node_id) implies post.wf_combiner_for_node_id(node_id) by {
-                    match post.combiner[node_id] {
-                        CombinerState::Placed { queued_ops } => {
-                            LogRangeMatchesQueue_update_change_2(queued_ops, post.log, 0, post.local_versions[node_id], post.tail, node_id, pre.local_updates, post.local_updates);
-                        }
-                        CombinerState::LoadedLocalVersion{ queued_ops, lversion } => {
-                            LogRangeMatchesQueue_update_change_2(queued_ops, post.log, 0, lversion, post.tail, node_id, pre.local_updates, post.local_updates);
-                        }
-                        CombinerState::Loop { queued_ops, idx, lversion, tail } => {
-                            LogRangeMatchesQueue_update_change(queued_ops, post.log, idx, lversion, tail, node_id, pre.local_updates, post.local_updates);
-                        }
-                        _ => {}
-                    }
+                    assert(pre.wf_combiner_for_node_id(node_id));
                 }
             }
         }
@@ -977,34 +1091,22 @@ UnboundedLog<DT: Dispatch> {
 
     #[inductive(exec_trivial_start)]
     fn exec_trivial_start_inductive(pre: Self, post: Self, node_id: NodeId) {
-        concat_LogRangeNoNodeId_LogRangeMatchesQueue(
-            Seq::empty(), post.log, 0,
-            pre.local_versions[node_id],
-            pre.tail,
-            post.tail,
-            node_id,
-            post.local_updates);
+        broadcast use group_log_range;
 
+        assert(pre.wf_combiner_for_node_id(node_id));
+        assert(LogRangeMatchesQueue(Seq::empty(), post.log, 0, pre.tail, post.tail, node_id, post.local_updates));
         assert(post.wf_combiner_for_node_id(node_id));
     }
 
     #[inductive(update_place_ops_in_log_one)]
     fn update_place_ops_in_log_one_inductive(pre: Self, post: Self, node_id: NodeId, rid: ReqId) {
+        broadcast use group_log_range, group_seq_unique_disjoint;
 
-        let old_queued_ops = pre.combiner[node_id].get_Placed_queued_ops();
         let op = pre.local_updates[rid].get_Init_op();
+        assert(post.log === pre.log.insert(pre.tail, LogEntry{ op, node_id }));
 
-        assert(post.wf_combiner_for_node_id(node_id)) by {
-            match post.combiner[node_id] {
-                CombinerState::Placed{queued_ops} => {
-                    LogRangeMatchesQueue_append(old_queued_ops, pre.log, post.log, 0,
-                        post.local_versions[node_id], pre.tail,
-                        node_id, pre.local_updates, post.local_updates, rid,
-                        LogEntry{ op, node_id });
-                }
-                _ => { }
-            }
-        }
+        assert(pre.wf_combiner_for_node_id(node_id));
+        assert(post.wf_combiner_for_node_id(node_id));
 
         assert(post.inv_local_updates_wf(post.local_updates[rid]));
 
@@ -1014,31 +1116,6 @@ UnboundedLog<DT: Dispatch> {
         by {
             assert(pre.combiner[node_id1] === post.combiner[node_id1]);
             assert(pre.wf_combiner_for_node_id(node_id1));
-            match pre.combiner[node_id1] {
-                CombinerState::Ready => {
-                    LogRangeNoNodeId_append_other(pre.log, post.log,
-                        post.local_versions[node_id1], pre.tail, node_id1, LogEntry{ op, node_id });
-                }
-                CombinerState::Placed{queued_ops} => {
-                    LogRangeMatchesQueue_append_other_augment(queued_ops, pre.log, post.log,
-                        0, post.local_versions[node_id1], pre.tail, node_id1, pre.local_updates, post.local_updates, rid, LogEntry{ op, node_id });
-                }
-                CombinerState::LoadedLocalVersion{queued_ops, lversion} => {
-                    LogRangeMatchesQueue_append_other_augment(queued_ops, pre.log, post.log,
-                        0, lversion, pre.tail, node_id1, pre.local_updates, post.local_updates, rid, LogEntry{ op, node_id });
-                }
-                CombinerState::Loop{queued_ops, lversion, idx, tail} => {
-                    LogRangeMatchesQueue_append_other(queued_ops, pre.log, post.log,
-                        idx, lversion, tail, pre.tail, node_id1, pre.local_updates, post.local_updates, rid, LogEntry{ op, node_id });
-                    LogRangeNoNodeId_append_other(pre.log, post.log,
-                        tail, pre.tail,
-                        node_id1, LogEntry{ op, node_id });
-                }
-                CombinerState::UpdatedVersion{queued_ops, tail} => {
-                    LogRangeNoNodeId_append_other(pre.log, post.log,
-                        tail, pre.tail, node_id1, LogEntry{ op, node_id });
-                }
-            }
         }
 
         assert (forall |nid| (#[trigger] pre.replicas.contains_key(nid)) ==> pre.local_versions.contains_key(nid));
@@ -1049,6 +1126,14 @@ UnboundedLog<DT: Dispatch> {
             compute_nrstate_at_version_preserves(pre.log, post.log, post.current_local_version(nid));
         }
 
+        assert forall |nid| (#[trigger] post.applied.contains_key(nid)) implies {
+            &&& post.applied[nid] == compute_applied_at_version(post.log, nid, post.current_local_version(nid))
+            &&& post.wf_applied_for_node_id(nid)
+        } by {
+            compute_applied_at_version_preserves(pre.log, post.log, nid, post.current_local_version(nid));
+            compute_applied_at_version_preserves(pre.log, post.log, nid, post.local_versions[nid]);
+        }
+
         assert forall |rid| (#[trigger] post.local_updates.contains_key(rid))
             implies post.update_results_match(post.local_updates[rid]) by
         {
@@ -1086,41 +1171,30 @@ UnboundedLog<DT: Dispatch> {
 
     #[inductive(exec_dispatch_local)]
     fn exec_dispatch_local_inductive(pre: Self, post: Self, node_id: NodeId) {
-        assert(post.wf_combiner_for_node_id(node_id)) by {
-            LogRangeMatchesQueue_update_change(
-                post.combiner[node_id].get_Loop_queued_ops(),
-                post.log, post.combiner[node_id].get_Loop_idx(), post.combiner[node_id].get_Loop_lversion(),
-                pre.combiner[node_id].get_Loop_tail(), node_id, pre.local_updates, post.local_updates);
-        }
+        broadcast use group_log_range;
+
+        assert(pre.wf_combiner_for_node_id(node_id));
+        assert(post.wf_combiner_for_node_id(node_id));
 
         let c = pre.combiner[node_id];
         let rid = c.get_Loop_queued_ops().index(c.get_Loop_idx() as int);
         assert forall |node_id0| #[trigger] post.combiner.contains_key(node_id0) && node_id0 != node_id
             implies post.wf_combiner_for_node_id(node_id0)
         by {
-            match pre.combiner[node_id0] {
-            CombinerState::Ready => {
-            }
-            CombinerState::Placed{queued_ops} => {
-                LogRangeMatchesQueue_update_change_2(
-                queued_ops, post.log, 0, post.local_versions[node_id0], post.tail, node_id0, pre.local_updates, post.local_updates);
-            }
-            CombinerState::LoadedLocalVersion{queued_ops, lversion} => {
-                LogRangeMatchesQueue_update_change_2(
-                queued_ops, post.log, 0, lversion, post.tail, node_id0, pre.local_updates, post.local_updates);
-            }
-            CombinerState::Loop{queued_ops, idx, lversion, tail} => {
-                LogRangeMatchesQueue_update_change_2(
-                queued_ops, post.log, idx, lversion, tail, node_id0, pre.local_updates, post.local_updates);
-            }
-            CombinerState::UpdatedVersion{queued_ops, tail} => {
-            }
-            }
+            assert(pre.wf_combiner_for_node_id(node_id0));
         }
+
+        let lversion = c.get_Loop_lversion();
+        assert(post.log[lversion].node_id == node_id);
+        assert(post.applied[node_id] == compute_applied_at_version(post.log, node_id, lversion + 1));
     }
 
     #[inductive(exec_dispatch_remote)]
-    fn exec_dispatch_remote_inductive(pre: Self, post: Self, node_id: NodeId) { }
+    fn exec_dispatch_remote_inductive(pre: Self, post: Self, node_id: NodeId) {
+        let lversion = pre.combiner[node_id].get_Loop_lversion();
+        assert(post.log[lversion].node_id != node_id);
+        assert(post.applied[node_id] == compute_applied_at_version(post.log, node_id, lversion + 1));
+    }
 
     #[inductive(exec_update_version_upper_bound)]
     fn exec_update_version_upper_bound_inductive(pre: Self, post: Self, node_id: NodeId) {
@@ -1145,6 +1219,30 @@ UnboundedLog<DT: Dispatch> {
     #[inductive(exec_finish_no_change)]
     fn exec_finish_no_change_inductive(pre: Self, post: Self, node_id: NodeId) { }
 
+    #[inductive(replica_rebuild)]
+    fn replica_rebuild_inductive(pre: Self, post: Self, node_id: NodeId, donor: NodeId) {
+        let lversion = pre.local_versions[node_id];
+        let version = pre.local_versions[donor];
+        assert(post.wf_combiner_for_node_id(node_id)) by {
+            broadcast use group_log_range;
+
+            assert(pre.wf_combiner_for_node_id(node_id));
+        }
+        assert(post.replicas[node_id] == compute_nrstate_at_version(post.log, post.current_local_version(node_id)));
+        assert(post.applied[node_id] == compute_applied_at_version(post.log, node_id, post.current_local_version(node_id)));
+    }
+
+    #[inductive(replica_snapshot_jump)]
+    fn replica_snapshot_jump_inductive(pre: Self, post: Self, node_id: NodeId, version: LogIdx, snapshot: DT::View) {
+        assert(post.wf_combiner_for_node_id(node_id)) by {
+            broadcast use group_log_range;
+
+            assert(pre.wf_combiner_for_node_id(node_id));
+        }
+        assert(post.replicas[node_id] == compute_nrstate_at_version(post.log, post.current_local_version(node_id)));
+        assert(post.applied[node_id] == compute_applied_at_version(post.log, node_id, post.current_local_version(node_id)));
+    }
+
     ////////////////////////////////////////////////////////////////////////////////////////////////
     // Helper Functions
     ////////////////////////////////////////////////////////////////////////////////////////////////
@@ -1302,36 +1400,6 @@ pub closed spec fn get_fresh_nat(reqs: Set<ReqId>, combiner: Map<NodeId, Combine
     choose|n| !reqs.contains(n) && combiner_request_id_fresh(combiner, n)
 }
 
-proof fn max_of_set(s: Set<nat>) -> (r: nat)
-    requires
-        s.finite(),
-    ensures
-        forall|x: nat| #[trigger] s.contains(x) ==> x <= r,
-    decreases s.len(),
-{
-    if s.is_empty() {
-        0
-    } else {
-        let v1 = s.choose();
-        let v2 = max_of_set(s.remove(v1));
-        assert(forall|x: nat| #[trigger] s.contains(x) && x != v1 ==> s.remove(v1).contains(x));
-        if v1 >= v2 {
-            v1
-        } else {
-            v2
-        }
-    }
-}
-
-proof fn element_outside_set(s: Set<nat>) -> (r: nat)
-    requires
-        s.finite(),
-    ensures
-        !s.contains(r),
-{
-    max_of_set(s) + 1
-}
-
 pub proof fn get_fresh_nat_not_in(reqs: Set<ReqId>, combiner: Map<NodeId, CombinerState>)
     requires
         reqs.finite(),
@@ -1350,8 +1418,8 @@ pub proof fn get_fresh_nat_not_in(reqs: Set<ReqId>, combiner: Map<NodeId, Combin
     }
     let req_ids = reqs + combiner_req_ids;
     assert(req_ids.finite());
-    let r = element_outside_set(req_ids);
-    // let r = choose |r| !req_ids.contains(r);
+    // an allocator that handed out all request ids in use has a fresh one
+    let r = id_allocator_covering(req_ids).fresh();
     assert(!reqs.contains(r));
     assert(!combiner_req_ids.contains(r));
     assert(combiner_request_id_fresh(combiner, r)) by {
@@ -1382,9 +1450,10 @@ pub open spec fn LogRangeNoNodeId<DT: Dispatch>(
     start: LogIdx,
     end: LogIdx,
     node_id: NodeId,
-) -> bool {
-    decreases_when(start <= end);
-    decreases(end - start);
+) -> bool
+    decreases end - start,
+    when start <= end
+{
     (start < end ==> {
         &&& log.contains_key(start)
         &&& log.index(start).node_id != node_id
@@ -1401,10 +1470,13 @@ pub open spec fn LogRangeMatchesQueue<DT: Dispatch>(
     logIndexUpper: LogIdx,
     nodeId: NodeId,
     updates: Map<ReqId, UpdateState<DT>>,
-) -> bool {
-    recommends([0 <= queueIndex <= queue.len(), LogContainsEntriesUpToHere(log, logIndexUpper)]);
-    decreases_when(logIndexLower <= logIndexUpper);
-    decreases(logIndexUpper - logIndexLower);
+) -> bool
+    recommends
+        0 <= queueIndex <= queue.len(),
+        LogContainsEntriesUpToHere(log, logIndexUpper),
+    decreases logIndexUpper - logIndexLower,
+    when logIndexLower <= logIndexUpper
+{
     // if we hit the end of the log range, we should be at the end of the queue
     &&& (logIndexLower == logIndexUpper ==> queueIndex
         == queue.len())
@@ -1473,7 +1545,7 @@ pub open spec fn LogRangeMatchesQueue<DT: Dispatch>(
 //         )
 //     })
 // }
-proof fn LogRangeMatchesQueue_update_change<DT: Dispatch>(
+broadcast proof fn LogRangeMatchesQueue_update_change<DT: Dispatch>(
     queue: Seq<nat>,
     log: Map<nat, LogEntry<DT>>,
     queueIndex: nat,
@@ -1500,6 +1572,7 @@ proof fn LogRangeMatchesQueue_update_change<DT: Dispatch>(
                 <= updates1[rid].get_Placed_idx() < logIndexUpper ==> updates2.contains_key(rid)
                 && updates2[rid] === updates1[rid],
     ensures
+        #![trigger LogRangeMatchesQueue(queue, log, queueIndex, logIndexLower, logIndexUpper, nodeId, updates1), LogRangeMatchesQueue(queue, log, queueIndex, logIndexLower, logIndexUpper, nodeId, updates2)]
         LogRangeMatchesQueue(
             queue,
             log,
@@ -1539,7 +1612,7 @@ proof fn LogRangeMatchesQueue_update_change<DT: Dispatch>(
     }
 }
 
-proof fn LogRangeMatchesQueue_update_change_2<DT: Dispatch>(
+broadcast proof fn LogRangeMatchesQueue_update_change_2<DT: Dispatch>(
     queue: Seq<nat>,
     log: Map<nat, LogEntry<DT>>,
     queueIndex: nat,
@@ -1565,6 +1638,7 @@ proof fn LogRangeMatchesQueue_update_change_2<DT: Dispatch>(
             updates1.contains_key(rid) ==> queue.contains(rid) ==> updates2.contains_key(rid)
                 && updates2[rid] === updates1[rid],
     ensures
+        #![trigger LogRangeMatchesQueue(queue, log, queueIndex, logIndexLower, logIndexUpper, nodeId, updates1), LogRangeMatchesQueue(queue, log, queueIndex, logIndexLower, logIndexUpper, nodeId, updates2)]
         LogRangeMatchesQueue(
             queue,
             log,
@@ -1604,10 +1678,9 @@ proof fn LogRangeMatchesQueue_update_change_2<DT: Dispatch>(
     }
 }
 
-proof fn LogRangeMatchesQueue_append<DT: Dispatch>(
+broadcast proof fn LogRangeMatchesQueue_append<DT: Dispatch>(
     queue: Seq<nat>,
     log: Map<nat, LogEntry<DT>>,
-    new_log: Map<nat, LogEntry<DT>>,
     queueIndex: nat,
     logIndexLower: nat,
     logIndexUpper: nat,
@@ -1639,11 +1712,11 @@ proof fn LogRangeMatchesQueue_append<DT: Dispatch>(
             node_id,
             updates,
         ),
-        new_log === log.insert(logIndexUpper, log_entry),
     ensures
+        #![trigger LogRangeMatchesQueue(queue, log, queueIndex, logIndexLower, logIndexUpper, node_id, updates), LogRangeMatchesQueue(queue.push(new_rid), log.insert(logIndexUpper, log_entry), queueIndex, logIndexLower, logIndexUpper + 1, node_id, new_updates)]
         LogRangeMatchesQueue(
             queue.push(new_rid),
-            new_log,
+            log.insert(logIndexUpper, log_entry),
             queueIndex,
             logIndexLower,
             logIndexUpper + 1,
@@ -1652,6 +1725,7 @@ proof fn LogRangeMatchesQueue_append<DT: Dispatch>(
         ),
     decreases (logIndexUpper - logIndexLower),
 {
+    let new_log = log.insert(logIndexUpper, log_entry);
     if logIndexLower == logIndexUpper + 1 {
     } else if logIndexLower == logIndexUpper {
         assert(new_log.contains_key(logIndexLower));
@@ -1683,7 +1757,6 @@ proof fn LogRangeMatchesQueue_append<DT: Dispatch>(
             LogRangeMatchesQueue_append(
                 queue,
                 log,
-                new_log,
                 queueIndex + 1,
                 logIndexLower + 1,
                 logIndexUpper,
@@ -1706,7 +1779,6 @@ proof fn LogRangeMatchesQueue_append<DT: Dispatch>(
             LogRangeMatchesQueue_append(
                 queue,
                 log,
-                new_log,
                 queueIndex,
                 logIndexLower + 1,
                 logIndexUpper,
@@ -1720,10 +1792,9 @@ proof fn LogRangeMatchesQueue_append<DT: Dispatch>(
     }
 }
 
-proof fn LogRangeMatchesQueue_append_other<DT: Dispatch>(
+broadcast proof fn LogRangeMatchesQueue_append_other<DT: Dispatch>(
     queue: Seq<nat>,
     log: Map<nat, LogEntry<DT>>,
-    new_log: Map<nat, LogEntry<DT>>,
     queueIndex: nat,
     logIndexLower: nat,
     logIndexUpper: nat,
@@ -1753,11 +1824,11 @@ proof fn LogRangeMatchesQueue_append_other<DT: Dispatch>(
             node_id,
             updates,
         ),
-        new_log === log.insert(logLen, log_entry),
     ensures
+        #![trigger LogRangeMatchesQueue(queue, log, queueIndex, logIndexLower, logIndexUpper, node_id, updates), LogRangeMatchesQueue(queue, log.insert(logLen, log_entry), queueIndex, logIndexLower, logIndexUpper, node_id, new_updates), new_updates.index(new_rid)]
         LogRangeMatchesQueue(
             queue,
-            new_log,
+            log.insert(logLen, log_entry),
             queueIndex,
             logIndexLower,
             logIndexUpper,
@@ -1766,13 +1837,13 @@ proof fn LogRangeMatchesQueue_append_other<DT: Dispatch>(
         ),
     decreases (logIndexUpper - logIndexLower),
 {
+    let new_log = log.insert(logLen, log_entry);
     if logIndexLower != logIndexUpper {
         assert(new_log.index(logIndexLower) === log.index(logIndexLower));
         if new_log.index(logIndexLower).node_id == node_id {
             LogRangeMatchesQueue_append_other(
                 queue,
                 log,
-                new_log,
                 queueIndex + 1,
                 logIndexLower + 1,
                 logIndexUpper,
@@ -1787,7 +1858,6 @@ proof fn LogRangeMatchesQueue_append_other<DT: Dispatch>(
             LogRangeMatchesQueue_append_other(
                 queue,
                 log,
-                new_log,
                 queueIndex,
                 logIndexLower + 1,
                 logIndexUpper,
@@ -1802,10 +1872,9 @@ proof fn LogRangeMatchesQueue_append_other<DT: Dispatch>(
     }
 }
 
-proof fn LogRangeMatchesQueue_append_other_augment<DT: Dispatch>(
+broadcast proof fn LogRangeMatchesQueue_append_other_augment<DT: Dispatch>(
     queue: Seq<nat>,
     log: Map<nat, LogEntry<DT>>,
-    new_log: Map<nat, LogEntry<DT>>,
     queueIndex: nat,
     logIndexLower: nat,
     logIndexUpper: nat,
@@ -1837,11 +1906,11 @@ proof fn LogRangeMatchesQueue_append_other_augment<DT: Dispatch>(
             node_id,
             updates,
         ),
-        new_log === log.insert(logIndexUpper, log_entry),
     ensures
+        #![trigger LogRangeMatchesQueue(queue, log, queueIndex, logIndexLower, logIndexUpper, node_id, updates), LogRangeMatchesQueue(queue, log.insert(logIndexUpper, log_entry), queueIndex, logIndexLower, logIndexUpper + 1, node_id, new_updates), new_updates.index(new_rid)]
         LogRangeMatchesQueue(
             queue,
-            new_log,
+            log.insert(logIndexUpper, log_entry),
             queueIndex,
             logIndexLower,
             logIndexUpper + 1,
@@ -1850,6 +1919,7 @@ proof fn LogRangeMatchesQueue_append_other_augment<DT: Dispatch>(
         ),
     decreases (logIndexUpper - logIndexLower),
 {
+    let new_log = log.insert(logIndexUpper, log_entry);
     if logIndexLower == logIndexUpper + 1 {
     } else if logIndexLower == logIndexUpper {
         assert(new_log.contains_key(logIndexLower));
@@ -1869,7 +1939,6 @@ proof fn LogRangeMatchesQueue_append_other_augment<DT: Dispatch>(
             LogRangeMatchesQueue_append_other_augment(
                 queue,
                 log,
-                new_log,
                 queueIndex + 1,
                 logIndexLower + 1,
                 logIndexUpper,
@@ -1892,7 +1961,6 @@ proof fn LogRangeMatchesQueue_append_other_augment<DT: Dispatch>(
             LogRangeMatchesQueue_append_other_augment(
                 queue,
                 log,
-                new_log,
                 queueIndex,
                 logIndexLower + 1,
                 logIndexUpper,
@@ -1906,9 +1974,8 @@ proof fn LogRangeMatchesQueue_append_other_augment<DT: Dispatch>(
     }
 }
 
-proof fn LogRangeNoNodeId_append_other<DT: Dispatch>(
+broadcast proof fn LogRangeNoNodeId_append_other<DT: Dispatch>(
     log: Map<nat, LogEntry<DT>>,
-    new_log: Map<nat, LogEntry<DT>>,
     logIndexLower: nat,
     logIndexUpper: nat,
     node_id: NodeId,
@@ -1918,11 +1985,16 @@ proof fn LogRangeNoNodeId_append_other<DT: Dispatch>(
         logIndexLower <= logIndexUpper,
         log_entry.node_id != node_id,
         LogRangeNoNodeId(log, logIndexLower, logIndexUpper, node_id),
-        new_log === log.insert(logIndexUpper, log_entry),
     ensures
-        LogRangeNoNodeId(new_log, logIndexLower, logIndexUpper + 1, node_id),
+        #[trigger] LogRangeNoNodeId(
+            log.insert(logIndexUpper, log_entry),
+            logIndexLower,
+            logIndexUpper + 1,
+            node_id,
+        ),
     decreases (logIndexUpper - logIndexLower),
 {
+    let new_log = log.insert(logIndexUpper, log_entry);
     if logIndexLower == logIndexUpper + 1 {
     } else if logIndexLower == logIndexUpper {
         assert(new_log.contains_key(logIndexLower));
@@ -1933,7 +2005,6 @@ proof fn LogRangeNoNodeId_append_other<DT: Dispatch>(
         if new_log.index(logIndexLower).node_id == node_id {
             LogRangeNoNodeId_append_other(
                 log,
-                new_log,
                 logIndexLower + 1,
                 logIndexUpper,
                 node_id,
@@ -1943,7 +2014,6 @@ proof fn LogRangeNoNodeId_append_other<DT: Dispatch>(
         } else {
             LogRangeNoNodeId_append_other(
                 log,
-                new_log,
                 logIndexLower + 1,
                 logIndexUpper,
                 node_id,
@@ -1953,6 +2023,26 @@ proof fn LogRangeNoNodeId_append_other<DT: Dispatch>(
     }
 }
 
+broadcast proof fn LogRangeNoNodeId_suffix<DT: Dispatch>(
+    log: Map<nat, LogEntry<DT>>,
+    logIndexLower: nat,
+    logIndexMid: nat,
+    logIndexUpper: nat,
+    node_id: NodeId,
+)
+    requires
+        logIndexLower <= logIndexMid <= logIndexUpper,
+        LogRangeNoNodeId(log, logIndexLower, logIndexUpper, node_id),
+    ensures
+        #![trigger LogRangeNoNodeId(log, logIndexLower, logIndexUpper, node_id), LogRangeNoNodeId(log, logIndexMid, logIndexUpper, node_id)]
+        LogRangeNoNodeId(log, logIndexMid, logIndexUpper, node_id),
+    decreases (logIndexMid - logIndexLower),
+{
+    if logIndexLower < logIndexMid {
+        LogRangeNoNodeId_suffix(log, logIndexLower + 1, logIndexMid, logIndexUpper, node_id);
+    }
+}
+
 /// the updates below the current pointer are either in the applied or done state.
 pub open spec fn QueueRidsUpdateDone<DT: Dispatch>(
     queued_ops: Seq<ReqId>,
@@ -1989,7 +2079,7 @@ pub open spec fn QueueRidsUpdatePlaced<DT: Dispatch>(
         }
 }
 
-proof fn concat_LogRangeNoNodeId_LogRangeMatchesQueue<DT: Dispatch>(
+broadcast proof fn concat_LogRangeNoNodeId_LogRangeMatchesQueue<DT: Dispatch>(
     queue: Seq<ReqId>,
     log: Map<LogIdx, LogEntry<DT>>,
     queueIndex: nat,
@@ -2005,6 +2095,7 @@ proof fn concat_LogRangeNoNodeId_LogRangeMatchesQueue<DT: Dispatch>(
         LogRangeNoNodeId(log, a, b, nodeId),
         LogRangeMatchesQueue(queue, log, queueIndex, b, c, nodeId, updates),
     ensures
+        #![trigger LogRangeNoNodeId(log, a, b, nodeId), LogRangeMatchesQueue(queue, log, queueIndex, b, c, nodeId, updates)]
         LogRangeMatchesQueue(queue, log, queueIndex, a, c, nodeId, updates),
     decreases b - a,
 {
@@ -2022,6 +2113,21 @@ proof fn concat_LogRangeNoNodeId_LogRangeMatchesQueue<DT: Dispatch>(
     }
 }
 
+/// Lemmas about appending to the log and changing the updates for the log range predicates.
+///
+/// The lemmas are triggered by the predicates before and after the transition, so the
+/// inductiveness proofs only need to establish the predicates of the pre-state.
+broadcast group group_log_range {
+    LogRangeMatchesQueue_update_change,
+    LogRangeMatchesQueue_update_change_2,
+    LogRangeMatchesQueue_append,
+    LogRangeMatchesQueue_append_other,
+    LogRangeMatchesQueue_append_other_augment,
+    LogRangeNoNodeId_append_other,
+    LogRangeNoNodeId_suffix,
+    concat_LogRangeNoNodeId_LogRangeMatchesQueue,
+}
+
 /// constructs the state of the data structure at a specific version given the log
 ///
 /// This function recursively applies the update operations to the initial state of the
@@ -2059,5 +2165,312 @@ pub proof fn compute_nrstate_at_version_preserves<DT: Dispatch>(
     }
 }
 
+/// the counters of the updates applied to the replica of `node_id` at the given version
+pub open spec fn compute_applied_at_version<DT: Dispatch>(
+    log: Map<LogIdx, LogEntry<DT>>,
+    node_id: NodeId,
+    version: LogIdx,
+) -> AppliedCounters
+    recommends
+        forall|i| 0 <= i < version ==> log.contains_key(i),
+    decreases version,
+{
+    if version == 0 {
+        AppliedCounters { local: 0, remote: 0 }
+    } else {
+        let ver = (version - 1) as nat;
+        let prev = compute_applied_at_version(log, node_id, ver);
+        if log[ver].node_id == node_id {
+            AppliedCounters { local: prev.local + 1, remote: prev.remote }
+        } else {
+            AppliedCounters { local: prev.local, remote: prev.remote + 1 }
+        }
+    }
+}
+
+pub proof fn compute_applied_at_version_preserves<DT: Dispatch>(
+    a: Map<LogIdx, LogEntry<DT>>,
+    b: Map<LogIdx, LogEntry<DT>>,
+    node_id: NodeId,
+    version: LogIdx,
+)
+    requires
+        forall|i| 0 <= i < version ==> a.contains_key(i),
+        forall|i| 0 <= i < version ==> a[i] == b[i],
+    ensures
+        compute_applied_at_version(a, node_id, version) == compute_applied_at_version(
+            b,
+            node_id,
+            version,
+        ),
+    decreases version,
+{
+    if version > 0 {
+        compute_applied_at_version_preserves(a, b, node_id, (version - 1) as nat);
+    }
+}
+
+pub proof fn compute_applied_at_version_total<DT: Dispatch>(
+    log: Map<LogIdx, LogEntry<DT>>,
+    node_id: NodeId,
+    version: LogIdx,
+)
+    ensures
+        compute_applied_at_version(log, node_id, version).total() == version,
+    decreases version,
+{
+    if version > 0 {
+        compute_applied_at_version_total(log, node_id, (version - 1) as nat);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Read Characterization via Log Prefixes
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// the update operations of the first `version` entries of the log
+pub open spec fn log_prefix<DT: Dispatch>(
+    log: Map<LogIdx, LogEntry<DT>>,
+    version: LogIdx,
+) -> Seq<DT::WriteOperation> {
+    Seq::new(version, |i: int| log[i as nat].op)
+}
+
+/// applies the update operations, in order, to the initial state of the data structure
+pub open spec fn fold_ops<DT: Dispatch>(ops: Seq<DT::WriteOperation>) -> DT::View
+    decreases ops.len(),
+{
+    if ops.len() == 0 {
+        DT::init_spec()
+    } else {
+        DT::dispatch_mut_spec(fold_ops::<DT>(ops.drop_last()), ops.last()).0
+    }
+}
+
+/// The state at a version is the fold of the update operations of the log prefix up to it.
+pub proof fn lemma_nrstate_at_version_is_fold<DT: Dispatch>(
+    log: Map<LogIdx, LogEntry<DT>>,
+    version: LogIdx,
+)
+    ensures
+        compute_nrstate_at_version(log, version) == fold_ops::<DT>(log_prefix(log, version)),
+    decreases version,
+{
+    if version > 0 {
+        let ver = (version - 1) as nat;
+        lemma_nrstate_at_version_is_fold(log, ver);
+        assert(log_prefix(log, version).drop_last() =~= log_prefix(log, ver));
+    }
+}
+
+/// A completed read returns the result of the read operation on the state obtained by folding
+/// a prefix of the log. The prefix is at least as long as the version upper bound the read
+/// observed when it started, and at most as long as the current version upper bound.
+pub proof fn lemma_read_result<DT: Dispatch>(s: UnboundedLog::State<DT>, rid: ReqId)
+    requires
+        s.invariant(),
+        s.local_reads.contains_key(rid),
+        s.local_reads[rid].is_Done(),
+    ensures
+        ({
+            let read = s.local_reads[rid];
+            exists|v: nat|
+                #[trigger] rangeincl(read.get_Done_version_upper_bound(), v, s.version_upper_bound)
+                    && read.get_Done_ret() == DT::dispatch_spec(
+                    fold_ops::<DT>(log_prefix(s.log, v)),
+                    read.get_Done_op(),
+                )
+        }),
+{
+    let read = s.local_reads[rid];
+    assert(s.read_results_match(read));
+    let v = choose|v: nat|
+        #[trigger] rangeincl(read.get_Done_version_upper_bound(), v, s.version_upper_bound)
+            && read.get_Done_ret() == DT::dispatch_spec(
+            compute_nrstate_at_version(s.log, v),
+            read.get_Done_op(),
+        );
+    lemma_nrstate_at_version_is_fold(s.log, v);
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Non-Interference: Reads are Invisible to Writers
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//
+// Read-only requests only ever touch their own `local_reads` entry, they never remove or add
+// tokens of the log, the replicas, the versions, the updates or the combiners (they only `have`
+// them). Conversely, no update or combiner transition inspects `local_reads`. Together this means
+// that in-flight reads can never block an update or the combiner:
+//
+//  - `lemma_reads_do_not_block_updates`: an update/combiner transition that is enabled stays
+//    enabled, with the same effect, no matter which read requests are present.
+//  - `lemma_reads_do_not_modify_shared_state`: a read-only transition leaves everything but
+//    `local_reads` unchanged.
+//
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// whether the step is one of the update or combiner transitions
+pub open spec fn is_update_step<DT: Dispatch>(step: UnboundedLog::Step<DT>) -> bool {
+    match step {
+        UnboundedLog::Step::update_place_ops_in_log_one(..) => true,
+        UnboundedLog::Step::update_done(..) => true,
+        UnboundedLog::Step::update_cancel(..) => true,
+        UnboundedLog::Step::exec_trivial_start(..) => true,
+        UnboundedLog::Step::exec_load_local_version(..) => true,
+        UnboundedLog::Step::exec_load_global_head(..) => true,
+        UnboundedLog::Step::exec_dispatch_local(..) => true,
+        UnboundedLog::Step::exec_dispatch_remote(..) => true,
+        UnboundedLog::Step::exec_update_version_upper_bound(..) => true,
+        UnboundedLog::Step::exec_finish(..) => true,
+        UnboundedLog::Step::exec_finish_no_change(..) => true,
+        _ => false,
+    }
+}
+
+/// whether the step is one of the read-only transitions
+pub open spec fn is_readonly_step<DT: Dispatch>(step: UnboundedLog::Step<DT>) -> bool {
+    match step {
+        UnboundedLog::Step::readonly_version_upper_bound(..) => true,
+        UnboundedLog::Step::readonly_ready_to_read(..) => true,
+        UnboundedLog::Step::readonly_apply(..) => true,
+        UnboundedLog::Step::readonly_cancel(..) => true,
+        _ => false,
+    }
+}
+
+/// the state `s` with the read requests replaced by `reads`
+pub open spec fn with_reads<DT: Dispatch>(
+    s: UnboundedLog::State<DT>,
+    reads: Map<ReqId, ReadonlyState<DT>>,
+) -> UnboundedLog::State<DT> {
+    UnboundedLog::State {
+        num_replicas: s.num_replicas,
+        log: s.log,
+        tail: s.tail,
+        replicas: s.replicas,
+        local_versions: s.local_versions,
+        version_upper_bound: s.version_upper_bound,
+        local_reads: reads,
+        local_updates: s.local_updates,
+        combiner: s.combiner,
+        applied: s.applied,
+    }
+}
+
+/// the two states agree on everything but the read requests
+pub open spec fn equal_except_reads<DT: Dispatch>(
+    a: UnboundedLog::State<DT>,
+    b: UnboundedLog::State<DT>,
+) -> bool {
+    a == with_reads(b, a.local_reads)
+}
+
+/// An update or combiner transition that is enabled remains enabled, and has the same effect on
+/// the shared state, if the set of read requests is replaced by any other set of read requests.
+pub proof fn lemma_reads_do_not_block_updates<DT: Dispatch>(
+    pre: UnboundedLog::State<DT>,
+    post: UnboundedLog::State<DT>,
+    step: UnboundedLog::Step<DT>,
+    reads: Map<ReqId, ReadonlyState<DT>>,
+)
+    requires
+        is_update_step(step),
+        UnboundedLog::State::next_by(pre, post, step),
+    ensures
+        post.local_reads == pre.local_reads,
+        UnboundedLog::State::next_by(with_reads(pre, reads), with_reads(post, reads), step),
+{
+    reveal(UnboundedLog::State::next_by);
+    let pre_r = with_reads(pre, reads);
+    let post_r = with_reads(post, reads);
+    match step {
+        UnboundedLog::Step::update_place_ops_in_log_one(node_id, rid) => {
+            assert(UnboundedLog::State::update_place_ops_in_log_one(pre_r, post_r, node_id, rid));
+        },
+        UnboundedLog::Step::update_done(rid) => {
+            assert(UnboundedLog::State::update_done(pre_r, post_r, rid));
+        },
+        UnboundedLog::Step::update_cancel(rid) => {
+            assert(UnboundedLog::State::update_cancel(pre_r, post_r, rid));
+        },
+        UnboundedLog::Step::exec_trivial_start(node_id) => {
+            assert(UnboundedLog::State::exec_trivial_start(pre_r, post_r, node_id));
+        },
+        UnboundedLog::Step::exec_load_local_version(node_id) => {
+            assert(UnboundedLog::State::exec_load_local_version(pre_r, post_r, node_id));
+        },
+        UnboundedLog::Step::exec_load_global_head(node_id) => {
+            assert(UnboundedLog::State::exec_load_global_head(pre_r, post_r, node_id));
+        },
+        UnboundedLog::Step::exec_dispatch_local(node_id) => {
+            assert(UnboundedLog::State::exec_dispatch_local(pre_r, post_r, node_id));
+        },
+        UnboundedLog::Step::exec_dispatch_remote(node_id) => {
+            assert(UnboundedLog::State::exec_dispatch_remote(pre_r, post_r, node_id));
+        },
+        UnboundedLog::Step::exec_update_version_upper_bound(node_id) => {
+            assert(UnboundedLog::State::exec_update_version_upper_bound(pre_r, post_r, node_id));
+        },
+        UnboundedLog::Step::exec_finish(node_id) => {
+            assert(UnboundedLog::State::exec_finish(pre_r, post_r, node_id));
+        },
+        UnboundedLog::Step::exec_finish_no_change(node_id) => {
+            assert(UnboundedLog::State::exec_finish_no_change(pre_r, post_r, node_id));
+        },
+        _ => {},
+    }
+}
+
+/// A read-only transition only changes the read requests, the log, the replicas, the versions,
+/// the updates and the combiners are left untouched.
+pub proof fn lemma_reads_do_not_modify_shared_state<DT: Dispatch>(
+    pre: UnboundedLog::State<DT>,
+    post: UnboundedLog::State<DT>,
+    step: UnboundedLog::Step<DT>,
+)
+    requires
+        is_readonly_step(step),
+        UnboundedLog::State::next_by(pre, post, step),
+    ensures
+        equal_except_reads(pre, post),
+{
+    reveal(UnboundedLog::State::next_by);
+    assert(pre == with_reads(post, pre.local_reads));
+}
+
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Replica Rebuild: Other Replicas and In-Flight Requests are Unaffected
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Rebuilding a replica only replaces the state and the version of that replica. The log, the
+/// other replicas, the combiners and all in-flight requests are left untouched, and the rebuilt
+/// replica never goes back in time.
+pub proof fn lemma_rebuild_only_changes_replica<DT: Dispatch>(
+    pre: UnboundedLog::State<DT>,
+    post: UnboundedLog::State<DT>,
+    node_id: NodeId,
+    donor: NodeId,
+)
+    requires
+        pre.invariant(),
+        UnboundedLog::State::replica_rebuild(pre, post, node_id, donor),
+    ensures
+        post.log == pre.log,
+        post.tail == pre.tail,
+        post.version_upper_bound == pre.version_upper_bound,
+        post.local_reads == pre.local_reads,
+        post.local_updates == pre.local_updates,
+        post.combiner == pre.combiner,
+        post.replicas.dom() == pre.replicas.dom(),
+        post.local_versions.dom() == pre.local_versions.dom(),
+        forall|n| #[trigger]
+            post.replicas.contains_key(n) && n != node_id ==> post.replicas[n] == pre.replicas[n],
+        forall|n| #[trigger]
+            post.local_versions.contains_key(n) && n != node_id ==> post.local_versions[n]
+                == pre.local_versions[n],
+        pre.current_local_version(node_id) <= post.current_local_version(node_id),
+{
+    assert(post.replicas.dom() =~= pre.replicas.dom());
+    assert(post.local_versions.dom() =~= pre.local_versions.dom());
+}
+
 } // verus!
 // end verus!
@@ -24,6 +24,7 @@ use super::simple_log::{
     compute_nrstate_at_version as s_nrstate_at_version, ReadReq as SReadReq, SimpleLog,
     UpdateResp as SUpdateResp,
 };
+use super::refinement::Refinement;
 use super::types::*;
 #[cfg(verus_keep_ghost)]
 use super::unbounded_log::{
@@ -166,6 +167,49 @@ impl<DT: Dispatch> crate::UnboundedLogRefinesSimpleLog<DT> for RefinementProof<D
     }
 }
 
+/// The single-step simulation obligations, lifted to whole traces by the refinement framework.
+impl<DT: Dispatch> Refinement<UnboundedLog::State<DT>, SimpleLog::State<DT>> for RefinementProof<DT> {
+    open spec fn inv(c: UnboundedLog::State<DT>) -> bool {
+        c.invariant()
+    }
+
+    open spec fn c_init(c: UnboundedLog::State<DT>) -> bool {
+        UnboundedLog::State::init(c)
+    }
+
+    open spec fn c_next(c: UnboundedLog::State<DT>, c2: UnboundedLog::State<DT>) -> bool {
+        UnboundedLog::State::next_strong(c, c2)
+    }
+
+    open spec fn a_inv(a: SimpleLog::State<DT>) -> bool {
+        a.invariant()
+    }
+
+    open spec fn a_init(a: SimpleLog::State<DT>) -> bool {
+        SimpleLog::State::init(a)
+    }
+
+    open spec fn a_next(a: SimpleLog::State<DT>, a2: SimpleLog::State<DT>) -> bool {
+        SimpleLog::State::next(a, a2, crate::AsyncLabel::Internal)
+    }
+
+    closed spec fn interp(c: UnboundedLog::State<DT>) -> SimpleLog::State<DT> {
+        interp(c)
+    }
+
+    proof fn inv_refines(c: UnboundedLog::State<DT>) {
+        refinement_inv(c);
+    }
+
+    proof fn init_refines(c: UnboundedLog::State<DT>) {
+        refinement_init(c);
+    }
+
+    proof fn next_refines(c: UnboundedLog::State<DT>, c2: UnboundedLog::State<DT>) {
+        refinement_next(c, c2);
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Interpretation Function
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -316,6 +360,11 @@ proof fn refinement_next<DT: Dispatch>(pre: UnboundedLog::State<DT>, post: Unbou
             SimpleLog::show::no_op(interp(pre), interp(post), aop);
         }
 
+        readonly_cancel(rid) => {
+            assert_maps_equal!(interp(pre).readonly_reqs.remove(rid), interp(post).readonly_reqs);
+            SimpleLog::show::readonly_cancel(interp(pre), interp(post), aop, rid);
+        }
+
         /*readonly_finish(rid, op, ret) => {
             // corresponds toConsumeStub_Refines_End
             // let version = 0;
@@ -402,6 +451,13 @@ proof fn refinement_next<DT: Dispatch>(pre: UnboundedLog::State<DT>, post: Unbou
             SimpleLog::show::no_op(interp(pre), interp(post), aop);
         }
 
+        update_cancel(rid) => {
+            assert_maps_equal!(interp(pre).update_resps, interp(post).update_resps);
+            assert_maps_equal!(interp(pre).update_reqs.remove(rid), interp(post).update_reqs);
+
+            SimpleLog::show::update_cancel(interp(pre), interp(post), aop, rid);
+        }
+
         /*update_finish(rid) => {
             let ret = pre.local_updates.index(rid).get_Done_ret();
             let idx = pre.local_updates.index(rid).get_Done_idx();
@@ -455,6 +511,14 @@ proof fn refinement_next<DT: Dispatch>(pre: UnboundedLog::State<DT>, post: Unbou
         exec_finish_no_change(node_id) => {
             SimpleLog::show::no_op(interp(pre), interp(post), aop);
         }
+
+        replica_rebuild(node_id, donor) => {
+            SimpleLog::show::no_op(interp(pre), interp(post), aop);
+        }
+
+        replica_snapshot_jump(node_id, version, snapshot) => {
+            SimpleLog::show::no_op(interp(pre), interp(post), aop);
+        }
       }
     }
 }
@@ -26,6 +26,124 @@ pub open spec fn seq_disjoint<A>(s: Seq<A>, t: Seq<A>) -> bool {
     forall|i, j| 0 <= i < s.len() && 0 <= j < t.len() ==> s.index(i) !== t.index(j)
 }
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Unique and Disjoint Sequences
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// the empty sequence is unique
+pub proof fn seq_unique_empty<A>()
+    ensures
+        seq_unique(Seq::<A>::empty()),
+{
+}
+
+/// the elements of a unique sequence identify their index
+pub proof fn seq_unique_index_injective<A>(s: Seq<A>, i: int, j: int)
+    requires
+        seq_unique(s),
+        0 <= i < s.len(),
+        0 <= j < s.len(),
+        s[i] === s[j],
+    ensures
+        i == j,
+{
+}
+
+/// pushing an element that is not yet in the sequence keeps it unique
+pub broadcast proof fn seq_unique_push<A>(s: Seq<A>, a: A)
+    requires
+        seq_unique(s),
+        !s.contains(a),
+    ensures
+        #[trigger] seq_unique(s.push(a)),
+{
+    assert forall|i: int, j: int|
+        0 <= i < s.push(a).len() && 0 <= j < s.push(a).len() && i != j implies s.push(a)[i]
+        !== s.push(a)[j] by {
+        if i == s.len() {
+            assert(s[j] === s.push(a)[j]);
+        } else if j == s.len() {
+            assert(s[i] === s.push(a)[i]);
+        }
+    }
+}
+
+/// every subrange of a unique sequence is unique
+pub broadcast proof fn seq_unique_subrange<A>(s: Seq<A>, lo: int, hi: int)
+    requires
+        seq_unique(s),
+        0 <= lo <= hi <= s.len(),
+    ensures
+        #[trigger] seq_unique(s.subrange(lo, hi)),
+{
+    assert forall|i: int, j: int|
+        0 <= i < hi - lo && 0 <= j < hi - lo && i != j implies s.subrange(lo, hi)[i] !== s.subrange(
+            lo,
+            hi,
+        )[j] by {
+        assert(s.subrange(lo, hi)[i] === s[lo + i]);
+        assert(s.subrange(lo, hi)[j] === s[lo + j]);
+    }
+}
+
+/// disjointness is symmetric
+pub broadcast proof fn seq_disjoint_symmetric<A>(s: Seq<A>, t: Seq<A>)
+    ensures
+        #[trigger] seq_disjoint(s, t) == seq_disjoint(t, s),
+{
+}
+
+/// the empty sequence is disjoint from every sequence
+pub broadcast proof fn seq_disjoint_empty<A>(t: Seq<A>)
+    ensures
+        #[trigger] seq_disjoint(Seq::<A>::empty(), t),
+{
+}
+
+/// pushing an element that is not in the other sequence keeps the sequences disjoint
+pub broadcast proof fn seq_disjoint_push<A>(s: Seq<A>, t: Seq<A>, a: A)
+    requires
+        seq_disjoint(s, t),
+        !t.contains(a),
+    ensures
+        #[trigger] seq_disjoint(s.push(a), t),
+{
+    assert forall|i: int, j: int|
+        0 <= i < s.push(a).len() && 0 <= j < t.len() implies s.push(a)[i] !== t[j] by {
+        if i == s.len() {
+            assert(t.contains(t[j]));
+        } else {
+            assert(s.push(a)[i] === s[i]);
+        }
+    }
+}
+
+/// a subrange of a sequence is disjoint from the sequences the whole sequence is disjoint from
+pub broadcast proof fn seq_disjoint_subrange<A>(s: Seq<A>, t: Seq<A>, lo: int, hi: int)
+    requires
+        seq_disjoint(s, t),
+        0 <= lo <= hi <= s.len(),
+    ensures
+        #[trigger] seq_disjoint(s.subrange(lo, hi), t),
+{
+    assert forall|i: int, j: int| 0 <= i < hi - lo && 0 <= j < t.len() implies s.subrange(lo, hi)[i]
+        !== t[j] by {
+        assert(s.subrange(lo, hi)[i] === s[lo + i]);
+    }
+}
+
+/// Lemmas about unique and disjoint sequences under push and subrange.
+///
+/// The combiner queues are unique and pairwise disjoint, the lemmas carry these facts over the
+/// transitions that extend or shorten a queue.
+pub broadcast group group_seq_unique_disjoint {
+    seq_unique_push,
+    seq_unique_subrange,
+    seq_disjoint_symmetric,
+    seq_disjoint_empty,
+    seq_disjoint_push,
+    seq_disjoint_subrange,
+}
+
 /// recursive definition of seq to set conversion
 spec fn seq_to_set_rec<A>(seq: Seq<A>) -> Set<A>
     decreases seq.len(),
@@ -163,7 +281,131 @@ pub open spec fn rangeincl(low: nat, mid: nat, high: nat) -> bool {
     low <= mid && mid <= high
 }
 
-#[verifier(nonlinear)]
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Fresh Identifiers
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A counting allocator of fresh identifiers.
+///
+/// The counter only grows, and every identifier it handed out is below it, so the next value of
+/// the counter is never allocated. The exec counterpart is `exec::utils::IdCounter`.
+pub ghost struct IdAllocator {
+    /// the next identifier to hand out
+    pub next: nat,
+    /// the identifiers handed out so far
+    pub allocated: Set<nat>,
+}
+
+impl IdAllocator {
+    /// an allocator that hasn't handed out any identifier
+    pub open spec fn new() -> Self {
+        IdAllocator { next: 0, allocated: Set::empty() }
+    }
+
+    /// all allocated identifiers are below the counter
+    pub open spec fn wf(self) -> bool {
+        &&& self.allocated.finite()
+        &&& forall|n: nat| #[trigger] self.allocated.contains(n) ==> n < self.next
+    }
+
+    /// the identifier the allocator hands out next
+    pub open spec fn fresh(self) -> nat {
+        self.next
+    }
+
+    /// hands out the fresh identifier
+    pub open spec fn alloc(self) -> Self {
+        IdAllocator { next: self.next + 1, allocated: self.allocated.insert(self.next) }
+    }
+
+    /// an allocator that already handed out all identifiers in `s`, and is at `next`
+    pub open spec fn covering(s: Set<nat>, next: nat) -> Self {
+        IdAllocator { next, allocated: s }
+    }
+}
+
+/// the initial allocator is well-formed
+pub proof fn id_allocator_new_wf()
+    ensures
+        IdAllocator::new().wf(),
+{
+}
+
+/// allocating keeps the allocator well-formed, the handed out identifier was fresh, and the
+/// identifiers allocated before stay allocated
+pub proof fn id_allocator_alloc(a: IdAllocator)
+    requires
+        a.wf(),
+    ensures
+        a.alloc().wf(),
+        !a.allocated.contains(a.fresh()),
+        a.alloc().allocated.contains(a.fresh()),
+        a.allocated.subset_of(a.alloc().allocated),
+        a.fresh() < a.alloc().fresh(),
+{
+}
+
+/// the largest element of a finite set, 0 if the set is empty
+pub proof fn max_of_set(s: Set<nat>) -> (r: nat)
+    requires
+        s.finite(),
+    ensures
+        forall|x: nat| #[trigger] s.contains(x) ==> x <= r,
+    decreases s.len(),
+{
+    if s.is_empty() {
+        0
+    } else {
+        let v1 = s.choose();
+        let v2 = max_of_set(s.remove(v1));
+        assert(forall|x: nat| #[trigger] s.contains(x) && x != v1 ==> s.remove(v1).contains(x));
+        if v1 >= v2 {
+            v1
+        } else {
+            v2
+        }
+    }
+}
+
+/// every finite set of identifiers is covered by a well-formed allocator, whose fresh identifier
+/// is not in the set
+pub proof fn id_allocator_covering(s: Set<nat>) -> (a: IdAllocator)
+    requires
+        s.finite(),
+    ensures
+        a.wf(),
+        a.allocated == s,
+        !s.contains(a.fresh()),
+{
+    let a = IdAllocator::covering(s, max_of_set(s) + 1);
+    a
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Integer Ranges
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// the integers from `lo` up to, but excluding, `hi`
+pub open spec fn int_range(lo: int, hi: int) -> Set<int> {
+    Set::new(|i: int| lo <= i < hi)
+}
+
+/// a range of integers is finite and has `hi - lo` elements
+pub proof fn int_range_len(lo: int, hi: int)
+    requires
+        lo <= hi,
+    ensures
+        int_range(lo, hi).finite(),
+        int_range(lo, hi).len() == hi - lo,
+    decreases hi - lo,
+{
+    if lo == hi {
+        assert(int_range(lo, hi) =~= Set::empty());
+    } else {
+        int_range_len(lo, hi - 1);
+        assert(int_range(lo, hi) =~= int_range(lo, hi - 1).insert(hi - 1));
+    }
+}
+
+#[verifier::nonlinear]
 pub proof fn int_mod_less_than_same(i: int, len: int)
     requires
         0 <= i < len,
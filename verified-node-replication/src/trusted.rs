@@ -0,0 +1,1170 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! # Trusted Computing Base
+//!
+//! This module contains everything that must be trusted, all other modules are verified
+//! against the definitions in here.
+//!
+//! ## External Guarantee
+//!
+//! For any data structure implementing [`Dispatch`], the operations executed through
+//! [`NodeReplicatedT::execute`] and [`NodeReplicatedT::execute_mut`] are linearizable with
+//! respect to the sequential specification given by [`Dispatch::init_spec`],
+//! [`Dispatch::dispatch_spec`] and [`Dispatch::dispatch_mut_spec`].
+//!
+//! The guarantee is established by chaining three theorems:
+//!
+//!  1. [`NodeReplicated`](crate::NodeReplicated) refines the UnboundedLog state machine
+//!     (`theorem_3`), by the pre- and post-conditions of [`NodeReplicatedT`] and the ticket and
+//!     stub predicates.
+//!  2. The UnboundedLog refines the SimpleLog atomic state machine (`theorem_2`), by the
+//!     obligations of [`UnboundedLogRefinesSimpleLog`].
+//!  3. Every behavior of the SimpleLog has an equivalent behavior of the
+//!     [`AsynchronousSingleton`], the specification of a linearizable data structure
+//!     (`theorem_1`).
+//!
+//! ## Assumptions
+//!
+//! Fewer than `MAX_IDX` updates are appended to the log over the lifetime of an instance
+//! (`assume_operation_count_bounded`). The log indices are 64-bit counters, the proofs show that
+//! they don't wrap around below this bound, and the library stops instead of exceeding it.
+//!
+//! To audit the library, review this module, the `Dispatch` implementation of the data
+//! structure and the state machines the theorems refer to (`UnboundedLog`, `SimpleLog`).
+//!
+//! ## Changes to the Specification
+//!
+//! The [`AsynchronousSingleton`] has an internal `cancel` transition in addition to the
+//! transitions of the original linearizability specification. This is an intentional change of
+//! the trusted computing base to support withdrawing requests (e.g., `try_execute_mut`). It
+//! removes a request that has not been linearized yet, as if it never completed; a request whose
+//! response has been computed can't be cancelled, so the guarantee above still holds for every
+//! response that is returned or pending.
+#[allow(unused_imports)]
+use builtin::*;
+use state_machines_macros::state_machine;
+use vstd::prelude::*;
+
+use crate::spec::simple_log::SimpleLog;
+use crate::spec::unbounded_log::UnboundedLog;
+
+use crate::constants::{MAX_IDX, MAX_REPLICAS};
+#[cfg(feature = "exec")]
+use crate::{NodeReplicated, ParkWord};
+
+verus! {
+
+// tell the verifier that the size of usize is 8.
+global size_of usize == 8;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// GLobal Types
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// the type of a replica identifier
+pub type ReplicaId = usize;
+
+// $line_count$Trusted$
+/// the identifier of a node / replica
+pub type NodeId = nat;
+
+// $line_count$Trusted$
+/// the index into the log
+pub type LogIdx = nat;
+
+// $line_count$Trusted$
+/// the identifier of a update or read request
+pub type ReqId = nat;
+
+// $line_count$Trusted$
+/// the identifier of a thread on a given replica
+pub type ThreadId = nat;
+
+// $line_count$Trusted$
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Top-level Theorem
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// the following theorems establish the correctness of the execution adhering to the specification.
+// See the corresponding refinement proofs etc.
+// We leverage traits that establish the required pre- and post-conditions (trusted), then we use
+// the trait constraints to show that the actual types implement the trait correctly.
+/// Theorem 1: The SimpleLog atomic state machine refines the trusted specification of the data
+///            structure expressed as an asynchronous singleton.
+///
+/// This theorem shows the linearizability of the SimpleLog atomic state machine.
+#[verus::trusted]
+proof fn theorem_1<DT: Dispatch + Sync>()
+    ensures
+        implements_SimpleLogRefinesAsynchronousSingleton::<
+            DT,
+            crate::spec::linearization::RefinementProof,
+        >(),
+{
+}
+
+/// Theorem 2: The UnboundedLog Global State Machine refines the SimpleLog atomic state machine.
+///
+/// This shows that the replicas are evolving correctly with respect to the SimpleLog atomic state machine.
+#[verus::trusted]
+proof fn theorem_2<DT: Dispatch + Sync>()
+    ensures
+        implements_UnboundedLogRefinesSimpleLog::<
+            DT,
+            crate::spec::unbounded_log_refines_simplelog::RefinementProof<DT>,
+        >(),
+{
+}
+
+/// Theorem 3: The Node Replication implementation refines the Unbounded Log and establishes
+///            local/global transition system relationship.
+#[cfg(feature = "exec")]
+#[verus::trusted]
+proof fn theorem_3<DT: Dispatch + Sync>()
+    ensures
+        implements_NodeReplicated::<DT, NodeReplicated<DT>>(),
+{
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Operation Count
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Assumption: fewer than `MAX_IDX` updates are appended to the log over its lifetime.
+///
+/// The tail, the head, the local versions and the version upper bound of the log are 64-bit
+/// counters of the appended updates, which the invariants of the log keep at or below `MAX_IDX`
+/// (`counters_do_not_wrap` shows that nothing added to them wraps around). An append that would
+/// move the tail to `MAX_IDX` panics here instead; at a billion updates per second, that is
+/// reached after more than 500 years.
+#[cfg(feature = "exec")]
+#[verus::trusted]
+#[verifier::external_body]
+pub fn assume_operation_count_bounded(new_tail: u64)
+    ensures
+        new_tail < MAX_IDX,
+{
+    if new_tail >= MAX_IDX {
+        panic!("the log indices would wrap around: {new_tail} exceeds the maximum of {MAX_IDX}");
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Thread Token
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Trusted interface of a thread token used in the proofs.
+///
+/// TODO: change this to pub(trait)
+#[verus::trusted]
+pub trait ThreadTokenT<DT: Dispatch, Replica> {
+    /// returns true if the thread token is well-formed
+    spec fn wf(&self, replica: &Replica) -> bool;
+
+    /// obtains the replica identifier this thread is registered with
+    spec fn replica_id_spec(&self) -> nat;
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Dispatch Trait
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The dispatch trait defines the update/readonly operations applied to the replicate data structure
+/// and the return types. For a data structure to be used with the node-replication library, it must
+/// implement this trait.
+///
+/// Read-only Operations: These operations do not modify the state of the data structure.
+/// The node-replication library will execute [`Dispatch::dispatch`] method on the data structure
+/// with the provided `ReadOperation` argument and return a `Response` value.
+///
+/// Write Operations: These operations modify the state of the data structure. The node-replication
+/// library will execute [`Dispatch::dispatch_mut`] method on the data structure with the provided
+/// `WriteOperation` argument and return a `Response` value.
+///
+/// The dispatch trait interface is trusted by the verifier as it is the high-level interface that
+/// the data structure is verified against.
+///
+#[verus::trusted]
+pub trait Dispatch: Sized {
+    /// Type of a read-only operation. Operations of this type do not mutate the data structure.
+    type ReadOperation: Sized;
+
+    /// Type of a write operation. Operations of this type may mutate the data structure.
+    /// Write operations are sent between replicas.
+    type WriteOperation: Sized + Send;
+
+    /// Type of the response of either a read or write operation.
+    type Response: Sized;
+
+    /// Type of the view of the data structure for specs and proofs.
+    type View;
+
+    /// Constructs the view of the data structure.
+    ///
+    /// This lifts the concrete, executable representation of the data structure into a
+    /// view that can be reasoned about in specs and proofs.
+    /// This provides support for the `@` operator on the data structure
+    spec fn view(&self) -> Self::View;
+
+    /// Initializes the data structure.
+    fn init() -> (res: Self)
+        ensures
+            res@ == Self::init_spec(),
+    ;
+
+    /// Clones a write operation to be copied to and read from the shared log.
+    fn clone_write_op(op: &Self::WriteOperation) -> (res: Self::WriteOperation)
+        ensures
+            op == res,
+    ;
+
+    /// Clones a response value such that it can be returned to the waiting thread
+    fn clone_response(op: &Self::Response) -> (res: Self::Response)
+        ensures
+            op == res,
+    ;
+
+    /// Executes a read-only operation against the data structure and returns the result.
+    fn dispatch(&self, op: Self::ReadOperation) -> (result: Self::Response)
+        ensures
+            Self::dispatch_spec(self@, op) == result,
+    ;
+
+    /// Executes a write operation against the data structure and returns the result.
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> (result: Self::Response)
+        ensures
+            Self::dispatch_mut_spec(old(self)@, op) == (self@, result),
+    ;
+
+    /// specification of the [`Dispatch::init`] function.
+    spec fn init_spec() -> Self::View;
+
+    /// specification of the [`Dispatch::dispatch`] function.
+    spec fn dispatch_spec(ds: Self::View, op: Self::ReadOperation) -> Self::Response;
+
+    /// specification of the [`Dispatch::dispatch_mut`] function.
+    spec fn dispatch_mut_spec(ds: Self::View, op: Self::WriteOperation) -> (
+        Self::View,
+        Self::Response,
+    );
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Fallible Dispatch Trait
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The fallible dispatch trait is the [`Dispatch`] trait for data structures whose write
+/// operations can fail with a user-defined `Error`. Such a data structure is replicated with the
+/// `Fallible` adapter, which implements [`Dispatch`] with `Result<Response, Error>` as response.
+///
+/// A failed write operation is a linearized outcome like any other: it is appended to the log,
+/// every replica applies it and observes the same error, and the error is returned to the caller
+/// of `execute_mut`. The state after a failed operation is given by
+/// [`FallibleDispatch::try_dispatch_mut_spec`], it is not retried or re-applied.
+///
+/// The fallible dispatch trait interface is trusted by the verifier as it is the high-level
+/// interface that the data structure is verified against.
+///
+#[verus::trusted]
+pub trait FallibleDispatch: Sized {
+    /// Type of a read-only operation. Operations of this type do not mutate the data structure.
+    type ReadOperation: Sized;
+
+    /// Type of a write operation. Operations of this type may mutate the data structure.
+    /// Write operations are sent between replicas.
+    type WriteOperation: Sized + Send;
+
+    /// Type of the response of either a read or a successful write operation.
+    type Response: Sized;
+
+    /// Type of the error of a failed write operation.
+    type Error: Sized;
+
+    /// Type of the view of the data structure for specs and proofs.
+    type View;
+
+    /// Constructs the view of the data structure.
+    spec fn view(&self) -> Self::View;
+
+    /// Initializes the data structure.
+    fn init() -> (res: Self)
+        ensures
+            res@ == Self::init_spec(),
+    ;
+
+    /// Clones a write operation to be copied to and read from the shared log.
+    fn clone_write_op(op: &Self::WriteOperation) -> (res: Self::WriteOperation)
+        ensures
+            op == res,
+    ;
+
+    /// Clones a response value such that it can be returned to the waiting thread
+    fn clone_response(op: &Self::Response) -> (res: Self::Response)
+        ensures
+            op == res,
+    ;
+
+    /// Clones an error value such that it can be returned to the waiting thread
+    fn clone_error(err: &Self::Error) -> (res: Self::Error)
+        ensures
+            err == res,
+    ;
+
+    /// Executes a read-only operation against the data structure and returns the result.
+    fn dispatch(&self, op: Self::ReadOperation) -> (result: Self::Response)
+        ensures
+            Self::dispatch_spec(self@, op) == result,
+    ;
+
+    /// Executes a write operation against the data structure and returns the result or the
+    /// error of the operation.
+    fn try_dispatch_mut(&mut self, op: Self::WriteOperation) -> (result: Result<
+        Self::Response,
+        Self::Error,
+    >)
+        ensures
+            Self::try_dispatch_mut_spec(old(self)@, op) == (self@, result),
+    ;
+
+    /// specification of the [`FallibleDispatch::init`] function.
+    spec fn init_spec() -> Self::View;
+
+    /// specification of the [`FallibleDispatch::dispatch`] function.
+    spec fn dispatch_spec(ds: Self::View, op: Self::ReadOperation) -> Self::Response;
+
+    /// specification of the [`FallibleDispatch::try_dispatch_mut`] function.
+    spec fn try_dispatch_mut_spec(ds: Self::View, op: Self::WriteOperation) -> (
+        Self::View,
+        Result<Self::Response, Self::Error>,
+    );
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Snapshot Dispatch Trait
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The snapshot dispatch trait extends [`Dispatch`] with an operation that captures the whole
+/// state of the data structure, e.g., by cloning it or by a copy-on-write capture. The replicated
+/// data structure takes the snapshot on a replica that is up to date with the log while holding
+/// the reader lock of the replica, and dispatches a read on the same state. The read is
+/// linearized as usual, so the snapshot is the state the read observed at its linearization point.
+///
+#[verus::trusted]
+pub trait SnapshotDispatch: Dispatch {
+    /// Type of a snapshot of the data structure.
+    type Snapshot;
+
+    /// The state of the data structure captured by the snapshot.
+    spec fn snapshot_view(snapshot: &Self::Snapshot) -> Self::View;
+
+    /// Captures the state of the data structure.
+    fn snapshot(&self) -> (result: Self::Snapshot)
+        ensures
+            Self::snapshot_view(&result) == self@,
+    ;
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Node Replicated Trait
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Affinity Function
+///
+/// This structure is a wrapper around a function that changes the memory affinity
+/// when allocating/initializing replicas during the initialization of the data structure.
+///
+#[verifier::external_body]
+#[verus::trusted]
+pub struct AffinityFn {
+    f: Box<dyn Fn(ReplicaId)>,
+}
+
+#[verus::trusted]
+impl AffinityFn {
+    /// creates a new AffinityFn object that points to the given affinity function.
+    #[verifier::external_body]
+    pub fn new(f: impl Fn(ReplicaId) + 'static) -> Self {
+        Self { f: Box::new(f) }
+    }
+
+    /// calls the affinity function with the given replica id.
+    #[verifier::external_body]
+    pub fn call(&self, rid: ReplicaId) {
+        (self.f)(rid)
+    }
+}
+
+/// Preemption Guard
+///
+/// Hook for environments in which the thread holding the combiner lock may be interrupted,
+/// e.g., a kernel where an interrupt handler executes operations on the same replica.
+///
+/// The combiner lock is a linear ghost token: `Replica::combine` can only be entered by the
+/// holder of the token, and a failed acquire yields no token, so the combiner is never entered
+/// re-entrantly. An interrupt handler that preempts the combiner and then waits for its response
+/// would spin forever, however. The guard disables preemption for as long as the combiner lock
+/// is held, `disable` returns the previous state that is passed back to `restore`.
+#[verus::trusted]
+pub trait PreemptGuard {
+    /// disables preemption on the current core, returns the previous state
+    fn disable() -> usize;
+
+    /// restores the preemption state returned by `disable`
+    fn restore(state: usize);
+}
+
+/// Wait Strategy
+///
+/// Everything that waits in the replicas and the log goes through the strategy: threads waiting
+/// for their responses, for a replica to catch up with the log, for the reader-writer lock of a
+/// replica, for entries of the log to become alive and for lagging replicas before the log can
+/// be reused. The waits are busy loops, the strategy decides what a thread does in one round of
+/// the loop.
+///
+/// `iteration` is the number of rounds the thread has waited so far. It may start over during a
+/// long wait, and saturates at `usize::MAX`.
+///
+/// Threads waiting for their responses on a replica that parks its waiters (see
+/// `ResponseDelivery::Park`) park on a [`ParkWord`] instead, and the combiner unparks them once
+/// it published their responses.
+///
+/// The wrappers the replicas call the hooks through, and the default implementations of the
+/// traits, are in `exec::hooks`.
+#[cfg(feature = "exec")]
+#[verus::trusted]
+pub trait WaitStrategy {
+    /// called once per round of a busy loop
+    fn wait(iteration: usize);
+
+    /// Parks the calling thread while `word` holds `expected`.
+    ///
+    /// Must not park if `word` no longer holds `expected`, and must wake up once [`Self::unpark`]
+    /// is called on the word after it changed. It may return early, and it should return after a
+    /// bounded time so that the thread can try to become the combiner itself.
+    fn park(word: &ParkWord, expected: u32);
+
+    /// Wakes the threads parked on `word`, called after the value of `word` changed.
+    fn unpark(word: &ParkWord);
+}
+
+/// Node Replicated Trait
+///
+/// This is the top-level interface that users will interact with.
+///
+#[verus::trusted]
+pub trait NodeReplicatedT<DT: Dispatch + Sync>: Sized {
+    /// The type of a replica
+    type Replica;
+
+    /// The type of the replica id
+    type ReplicaId;
+
+    /// the type of the thread token
+    type TT: ThreadTokenT<DT, Self::Replica>;
+
+    /// defines the well-formedness condition on the replicated data structure
+    spec fn wf(&self) -> bool;
+
+    /// obtains a vector of replicas
+    spec fn replicas(&self) -> Vec<Box<Self::Replica>>;
+
+    /// obtainst the instance to the unbounded log
+    spec fn unbounded_log_instance(&self) -> UnboundedLog::Instance<DT>;
+
+    /// creates a new instance of the replicated data structure with the given number of replicas.
+    ///
+    /// The number of replicas must be at least 1 and not exceed the pre-defined maximum.
+    /// It ensures that the data structure is well-formed and has the correct number of replicas.
+    fn new(num_replicas: usize, chg_mem_affinity: AffinityFn) -> (res: Self)
+        requires
+            0 < num_replicas && num_replicas <= MAX_REPLICAS,
+        ensures
+            res.wf() && res.replicas().len() == num_replicas,
+    ;
+
+    /// registers a thread with the given replica id.
+    fn register(&mut self, replica_id: ReplicaId) -> (result: Option<Self::TT>)
+        requires
+            old(self).wf(),
+        ensures
+            self.wf(),
+            result.is_Some() ==> result.get_Some_0().wf(&self.replicas()[replica_id as int]),
+    ;
+
+    /// executes an update operation against the data structure.
+    fn execute_mut(
+        &self,
+        op: DT::WriteOperation,
+        tkn: Self::TT,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+    ) -> (result: Result<
+        (DT::Response, Self::TT, Tracked<UnboundedLog::local_updates<DT>>),
+        (Self::TT, Tracked<UnboundedLog::local_updates<DT>>),
+    >)
+        requires
+            self.wf(),  // wf global node
+            tkn.wf(&self.replicas().spec_index(tkn.replica_id_spec() as int)),
+            is_update_ticket(ticket@, op, self.unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> is_update_stub(
+                result.get_Ok_0().2@,
+                ticket@@.key,
+                result.get_Ok_0().0,
+                self.unbounded_log_instance(),
+            ) && result.get_Ok_0().1.wf(&self.replicas().spec_index(tkn.replica_id_spec() as int)),
+            result.is_Err() ==> result.get_Err_0().1 == ticket && result.get_Err_0().0 == tkn,
+    ;
+
+    /// executes an update operation against the data structure, combining eagerly.
+    ///
+    /// The thread tries to become the combiner right away, instead of leaving the update to the
+    /// current combiner of a contended replica, retries more often while it waits and never
+    /// parks. This lowers the latency of the update, but it doesn't order it before the other
+    /// pending updates of the replica: they are applied in the same batch in the order of their
+    /// threads, and their submitters are woken in that order.
+    fn execute_mut_eager(
+        &self,
+        op: DT::WriteOperation,
+        tkn: Self::TT,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+    ) -> (result: Result<
+        (DT::Response, Self::TT, Tracked<UnboundedLog::local_updates<DT>>),
+        (Self::TT, Tracked<UnboundedLog::local_updates<DT>>),
+    >)
+        requires
+            self.wf(),  // wf global node
+            tkn.wf(&self.replicas().spec_index(tkn.replica_id_spec() as int)),
+            is_update_ticket(ticket@, op, self.unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> is_update_stub(
+                result.get_Ok_0().2@,
+                ticket@@.key,
+                result.get_Ok_0().0,
+                self.unbounded_log_instance(),
+            ) && result.get_Ok_0().1.wf(&self.replicas().spec_index(tkn.replica_id_spec() as int)),
+            result.is_Err() ==> result.get_Err_0().1 == ticket && result.get_Err_0().0 == tkn,
+    ;
+
+    /// executes a read-only operation against the data structure.
+    fn execute(
+        &self,
+        op: DT::ReadOperation,
+        tkn: Self::TT,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+    ) -> (result: Result<
+        (DT::Response, Self::TT, Tracked<UnboundedLog::local_reads<DT>>),
+        (Self::TT, Tracked<UnboundedLog::local_reads<DT>>),
+    >)
+        requires
+            self.wf(),  // wf global node
+            tkn.wf(&self.replicas()[tkn.replica_id_spec() as int]),
+            is_readonly_ticket(ticket@, op, self.unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> is_readonly_stub(
+                result.get_Ok_0().2@,
+                ticket@@.key,
+                result.get_Ok_0().0,
+                self.unbounded_log_instance(),
+            ) && result.get_Ok_0().1.wf(&self.replicas()[tkn.replica_id_spec() as int]),
+            result.is_Err() ==> result.get_Err_0().1 == ticket && result.get_Err_0().0 == tkn,
+    ;
+
+    /// executes an update operation against the data structure, unless its deadline passes
+    /// before the combiner collects the update.
+    ///
+    /// A timed out update is withdrawn and the ticket is consumed (the error holds `None`), it
+    /// is never applied. Once collected by the combiner, the update is placed into the log and
+    /// can't be withdrawn anymore: it is applied exactly once and its response is returned,
+    /// even if the deadline passes while the caller waits for it.
+    fn execute_mut_timeout(
+        &self,
+        op: DT::WriteOperation,
+        tkn: Self::TT,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+        timeout: std::time::Duration,
+    ) -> (result: Result<
+        (DT::Response, Self::TT, Tracked<UnboundedLog::local_updates<DT>>),
+        (Self::TT, Tracked<Option<UnboundedLog::local_updates<DT>>>),
+    >)
+        requires
+            self.wf(),  // wf global node
+            tkn.wf(&self.replicas().spec_index(tkn.replica_id_spec() as int)),
+            is_update_ticket(ticket@, op, self.unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> is_update_stub(
+                result.get_Ok_0().2@,
+                ticket@@.key,
+                result.get_Ok_0().0,
+                self.unbounded_log_instance(),
+            ) && result.get_Ok_0().1.wf(&self.replicas().spec_index(tkn.replica_id_spec() as int)),
+            result.is_Err() ==> result.get_Err_0().0.wf(
+                &self.replicas().spec_index(tkn.replica_id_spec() as int),
+            ) && (result.get_Err_0().1@.is_None() || result.get_Err_0().1@ == Some(ticket@)),
+    ;
+
+    /// executes an update operation against the data structure only if it doesn't have to wait.
+    ///
+    /// The update is submitted if the log isn't full and the thread becomes the combiner of its
+    /// replica within a bounded number of attempts. It then applies its own update and never
+    /// waits for another combiner, so callers can shed load instead of queueing. Otherwise the
+    /// replica is busy: the update is withdrawn and the ticket is consumed (the error holds
+    /// `None`).
+    fn try_execute_mut(
+        &self,
+        op: DT::WriteOperation,
+        tkn: Self::TT,
+        ticket: Tracked<UnboundedLog::local_updates<DT>>,
+    ) -> (result: Result<
+        (DT::Response, Self::TT, Tracked<UnboundedLog::local_updates<DT>>),
+        (Self::TT, Tracked<Option<UnboundedLog::local_updates<DT>>>),
+    >)
+        requires
+            self.wf(),  // wf global node
+            tkn.wf(&self.replicas().spec_index(tkn.replica_id_spec() as int)),
+            is_update_ticket(ticket@, op, self.unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> is_update_stub(
+                result.get_Ok_0().2@,
+                ticket@@.key,
+                result.get_Ok_0().0,
+                self.unbounded_log_instance(),
+            ) && result.get_Ok_0().1.wf(&self.replicas().spec_index(tkn.replica_id_spec() as int)),
+            result.is_Err() ==> result.get_Err_0().0 == tkn && (result.get_Err_0().1@.is_None()
+                || result.get_Err_0().1@ == Some(ticket@)),
+    ;
+
+    /// executes a read-only operation against the data structure, unless its deadline passes
+    /// while waiting for the replica to catch up with the log.
+    ///
+    /// A timed out read is withdrawn and the ticket is consumed (the error holds `None`).
+    fn execute_timeout(
+        &self,
+        op: DT::ReadOperation,
+        tkn: Self::TT,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+        timeout: std::time::Duration,
+    ) -> (result: Result<
+        (DT::Response, Self::TT, Tracked<UnboundedLog::local_reads<DT>>),
+        (Self::TT, Tracked<Option<UnboundedLog::local_reads<DT>>>),
+    >)
+        requires
+            self.wf(),  // wf global node
+            tkn.wf(&self.replicas()[tkn.replica_id_spec() as int]),
+            is_readonly_ticket(ticket@, op, self.unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> is_readonly_stub(
+                result.get_Ok_0().2@,
+                ticket@@.key,
+                result.get_Ok_0().0,
+                self.unbounded_log_instance(),
+            ) && result.get_Ok_0().1.wf(&self.replicas()[tkn.replica_id_spec() as int]),
+            result.is_Err() ==> result.get_Err_0().0 == tkn && (result.get_Err_0().1@.is_None()
+                || result.get_Err_0().1@ == Some(ticket@)),
+    ;
+
+    /// executes a read-only operation against the data structure only if it doesn't have to
+    /// wait.
+    ///
+    /// The read is done if the replica of the thread is already up to date with the log and not
+    /// locked by the combiner. It never combines, spins, or waits for a lock, so it can be used
+    /// from interrupt handlers. Otherwise the read would block: it is withdrawn and the ticket
+    /// is consumed (the error holds `None`).
+    fn try_execute(
+        &self,
+        op: DT::ReadOperation,
+        tkn: Self::TT,
+        ticket: Tracked<UnboundedLog::local_reads<DT>>,
+    ) -> (result: Result<
+        (DT::Response, Self::TT, Tracked<UnboundedLog::local_reads<DT>>),
+        (Self::TT, Tracked<Option<UnboundedLog::local_reads<DT>>>),
+    >)
+        requires
+            self.wf(),  // wf global node
+            tkn.wf(&self.replicas()[tkn.replica_id_spec() as int]),
+            is_readonly_ticket(ticket@, op, self.unbounded_log_instance()),
+        ensures
+            result.is_Ok() ==> is_readonly_stub(
+                result.get_Ok_0().2@,
+                ticket@@.key,
+                result.get_Ok_0().0,
+                self.unbounded_log_instance(),
+            ) && result.get_Ok_0().1.wf(&self.replicas()[tkn.replica_id_spec() as int]),
+            result.is_Err() ==> result.get_Err_0().0 == tkn && (result.get_Err_0().1@.is_None()
+                || result.get_Err_0().1@ == Some(ticket@)),
+    ;
+
+    /// brings the replica of the thread up to date with the log.
+    ///
+    /// Runs the combiner until the replica has applied all updates up to the tail of the log
+    /// at the time of the call. Returns the version the replica has reached and that tail, or
+    /// `None` if the thread token does not belong to a replica of this data structure.
+    fn sync(&self, tkn: &Self::TT) -> (result: Option<(u64, u64)>)
+        requires
+            self.wf(),  // wf global node
+            tkn.wf(&self.replicas()[tkn.replica_id_spec() as int]),
+        ensures
+            result.is_Some() <==> tkn.replica_id_spec() < self.replicas().len(),
+            result.is_Some() ==> result.get_Some_0().1 <= result.get_Some_0().0,
+            result.is_Some() ==> result.get_Some_0().0 <= MAX_IDX,
+    ;
+
+    /// returns the current version of the data structure.
+    ///
+    /// The read request `ticket` is advanced to the returned version as its version upper bound,
+    /// like a read that is started at the time of the call. The version is a lower bound of the
+    /// version upper bound of the unbounded log from then on, so a read executed with the
+    /// returned ticket observes at least this version. The ticket can also be withdrawn with the
+    /// `readonly_cancel` transition.
+    fn current_version(&self, ticket: Tracked<UnboundedLog::local_reads<DT>>) -> (result: (
+        u64,
+        Tracked<UnboundedLog::local_reads<DT>>,
+    ))
+        requires
+            self.wf(),
+            ticket@@.instance == self.unbounded_log_instance(),
+            ticket@@.value.is_Init(),
+        ensures
+            result.0 <= MAX_IDX,
+            result.1@@.instance == self.unbounded_log_instance(),
+            result.1@@.key == ticket@@.key,
+            result.1@@.value.is_VersionUpperBound(),
+            result.1@@.value.get_VersionUpperBound_version_upper_bound() == result.0 as nat,
+            result.1@@.value.get_VersionUpperBound_op() == ticket@@.value.get_Init_op(),
+    ;
+
+    /// returns the version of the given replica, or `None` if there is no such replica.
+    ///
+    /// The value is for monitoring, e.g., how far the replica lags behind [`Self::log_tail`].
+    /// The spec only bounds it, it states nothing about the updates the replica had applied.
+    fn replica_version(&self, replica_id: ReplicaId) -> (result: Option<u64>)
+        requires
+            self.wf(),
+        ensures
+            result.is_Some() <==> replica_id < self.replicas().len(),
+            result.is_Some() ==> result.get_Some_0() <= MAX_IDX,
+    ;
+
+    /// returns the tail of the log.
+    ///
+    /// The value is for monitoring, e.g., the number of updates placed in the log so far. The
+    /// spec only bounds it, it states nothing about the updates in the log.
+    fn log_tail(&self) -> (result: u64)
+        requires
+            self.wf(),
+        ensures
+            result <= MAX_IDX,
+    ;
+}
+
+/// Spec function that checks whether the struct implements the trait properly.
+#[verus::trusted]
+spec fn implements_NodeReplicated<DT: Dispatch + Sync, N: NodeReplicatedT<DT>>() -> bool {
+    true
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Proof Functions for Node Replicated -> Unbounded Log Refinement Proof
+////////////////////////////////////////////////////////////////////////////////////////////////////
+#[verus::trusted]
+pub open spec fn is_readonly_ticket<DT: Dispatch>(
+    ticket: UnboundedLog::local_reads<DT>,
+    op: DT::ReadOperation,
+    log: UnboundedLog::Instance<DT>,
+) -> bool {
+    // requires ticket.val == ssm.Ticket(rid, input)
+    &&& ticket@.value.is_Init() && ticket@.value.get_Init_op()
+        == op
+    // requires ticket.loc == TicketStubSingletonLoc.loc()
+
+    &&& ticket@.instance == log
+}
+
+#[verus::trusted]
+pub open spec fn is_readonly_stub<DT: Dispatch>(
+    stub: UnboundedLog::local_reads<DT>,
+    rid: ReqId,
+    result: DT::Response,
+    log: UnboundedLog::Instance<DT>,
+) -> bool {
+    // ensures stub.loc == TicketStubSingletonLoc.loc()
+    &&& stub@.instance
+        == log
+    // ensures ssm.IsStub(rid, output, stub.val)  -> (exists ctail, op, nodeid :: stub == ReadOp(rid, ReadonlyDone(op, output, nodeid, ctail)))
+
+    &&& stub@.key == rid
+    &&& stub@.value.is_Done()
+    &&& stub@.value.get_Done_ret() == result
+}
+
+#[verus::trusted]
+pub open spec fn is_update_ticket<DT: Dispatch>(
+    ticket: UnboundedLog::local_updates<DT>,
+    op: DT::WriteOperation,
+    log: UnboundedLog::Instance<DT>,
+) -> bool {
+    // requires ticket.val == ssm.Ticket(rid, input)
+    &&& ticket@.value.is_Init() && ticket@.value.get_Init_op()
+        == op
+    // requires ticket.loc == TicketStubSingletonLoc.loc()
+
+    &&& ticket@.instance == log
+}
+
+#[verus::trusted]
+pub open spec fn is_update_stub<DT: Dispatch>(
+    stub: UnboundedLog::local_updates<DT>,
+    rid: ReqId,
+    result: DT::Response,
+    log: UnboundedLog::Instance<DT>,
+) -> bool {
+    // ensures stub.loc == TicketStubSingletonLoc.loc()
+    &&& stub@.instance
+        == log
+    // ensures ssm.IsStub(rid, output, stub.val)  -> (exists log_idx :: stub == UpdateOp(rid, UpdateDone(output, log_idx)))
+
+    &&& stub@.key == rid
+    &&& stub@.value.is_Done()
+    &&& stub@.value.get_Done_ret() == result
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// UnboundedLog -> SimpleLog Refinement Proof
+////////////////////////////////////////////////////////////////////////////////////////////////////
+#[verus::trusted]
+pub open spec fn add_ticket<DT: Dispatch>(
+    pre: UnboundedLog::State<DT>,
+    post: UnboundedLog::State<DT>,
+    input: InputOperation<DT>,
+    rid: ReqId,
+) -> bool {
+    !pre.local_reads.dom().contains(rid) && !pre.local_updates.dom().contains(rid) && (match input {
+        InputOperation::Read(read_op) => {
+            &&post == UnboundedLog::State::<DT> {
+                local_reads: pre.local_reads.insert(
+                    rid,
+                    crate::spec::unbounded_log::ReadonlyState::Init { op: read_op },
+                ),
+                ..pre
+            }
+        },
+        InputOperation::Write(write_op) => {
+            &&post == UnboundedLog::State::<DT> {
+                local_updates: pre.local_updates.insert(
+                    rid,
+                    crate::spec::unbounded_log::UpdateState::Init { op: write_op },
+                ),
+                ..pre
+            }
+        },
+    })
+}
+
+#[verus::trusted]
+pub open spec fn consume_stub<DT: Dispatch>(
+    pre: UnboundedLog::State<DT>,
+    post: UnboundedLog::State<DT>,
+    output: OutputOperation<DT>,
+    rid: ReqId,
+) -> bool {
+    match output {
+        OutputOperation::Read(response) => {
+            pre.local_reads.dom().contains(rid) && pre.local_reads[rid].is_Done()
+                && pre.local_reads[rid].get_Done_ret() == response && post == UnboundedLog::State::<
+                DT,
+            > { local_reads: pre.local_reads.remove(rid), ..pre }
+        },
+        OutputOperation::Write(response) => {
+            pre.local_updates.dom().contains(rid) && pre.local_updates[rid].is_Done()
+                && pre.local_updates[rid].get_Done_ret() == response && post
+                == UnboundedLog::State::<DT> { local_updates: pre.local_updates.remove(rid), ..pre }
+        },
+    }
+}
+
+#[verus::trusted]
+pub(crate) trait UnboundedLogRefinesSimpleLog<DT: Dispatch> {
+    spec fn interp(s: UnboundedLog::State<DT>) -> SimpleLog::State<DT>;
+
+    // Prove that it is always possible to add a new ticket
+    spec fn get_fresh_rid(pre: UnboundedLog::State<DT>) -> ReqId;
+
+    proof fn fresh_rid_is_ok(pre: UnboundedLog::State<DT>)
+        requires
+            pre.invariant(),
+        ensures
+            !pre.local_reads.dom().contains(Self::get_fresh_rid(pre)),
+            !pre.local_updates.dom().contains(Self::get_fresh_rid(pre)),
+    ;
+
+    proof fn refinement_inv(vars: UnboundedLog::State<DT>)
+        requires
+            vars.invariant(),
+        ensures
+            Self::interp(vars).invariant(),
+    ;
+
+    proof fn refinement_init(post: UnboundedLog::State<DT>)
+        requires
+            post.invariant(),
+            UnboundedLog::State::init(post),
+        ensures
+            SimpleLog::State::init(Self::interp(post)),
+    ;
+
+    proof fn refinement_next(pre: UnboundedLog::State<DT>, post: UnboundedLog::State<DT>)
+        requires
+            pre.invariant(),
+            post.invariant(),
+            UnboundedLog::State::next_strong(pre, post),
+        ensures
+            SimpleLog::State::next(Self::interp(pre), Self::interp(post), AsyncLabel::Internal),
+    ;
+
+    proof fn refinement_add_ticket(
+        pre: UnboundedLog::State<DT>,
+        post: UnboundedLog::State<DT>,
+        input: InputOperation<DT>,
+    )
+        requires
+            pre.invariant(),
+            add_ticket(pre, post, input, Self::get_fresh_rid(pre)),
+        ensures
+            post.invariant(),
+            SimpleLog::State::next(
+                Self::interp(pre),
+                Self::interp(post),
+                AsyncLabel::Start(Self::get_fresh_rid(pre), input),
+            ),
+    ;
+
+    proof fn refinement_consume_stub(
+        pre: UnboundedLog::State<DT>,
+        post: UnboundedLog::State<DT>,
+        output: OutputOperation<DT>,
+        rid: ReqId,
+    )
+        requires
+            pre.invariant(),
+            consume_stub(pre, post, output, rid),
+        ensures
+            post.invariant(),
+            SimpleLog::State::next(
+                Self::interp(pre),
+                Self::interp(post),
+                AsyncLabel::End(rid, output),
+            ),
+    ;
+}
+
+#[verus::trusted]
+spec fn implements_UnboundedLogRefinesSimpleLog<
+    DT: Dispatch,
+    RP: UnboundedLogRefinesSimpleLog<DT>,
+>() -> bool {
+    true
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// SimpleLog -> Linearization Refinement
+////////////////////////////////////////////////////////////////////////////////////////////////////
+#[is_variant]
+#[verus::trusted]
+pub enum InputOperation<DT: Dispatch> {
+    Read(DT::ReadOperation),
+    Write(DT::WriteOperation),
+}
+
+#[is_variant]
+#[verus::trusted]
+pub enum OutputOperation<DT: Dispatch> {
+    Read(DT::Response),
+    Write(DT::Response),
+}
+
+#[is_variant]
+#[verus::trusted]
+pub enum AsyncLabel<DT: Dispatch> {
+    Internal,
+    Start(ReqId, InputOperation<DT>),
+    End(ReqId, OutputOperation<DT>),
+}
+
+state_machine!{ AsynchronousSingleton<DT: Dispatch> {           // $line_count$Trusted$
+    fields {                                                    // $line_count$Trusted$
+        pub state: DT::View,                                    // $line_count$Trusted$
+        pub reqs: Map<ReqId, InputOperation<DT>>,               // $line_count$Trusted$
+        pub resps: Map<ReqId, OutputOperation<DT>>,             // $line_count$Trusted$
+    }                                                           // $line_count$Trusted$
+
+    pub type Label<DT> = AsyncLabel<DT>;                        // $line_count$Trusted$
+
+    init!{                                                      // $line_count$Trusted$
+        initialize() {                                          // $line_count$Trusted$
+            init state = DT::init_spec();                       // $line_count$Trusted$
+            init reqs = Map::empty();                           // $line_count$Trusted$
+            init resps = Map::empty();                          // $line_count$Trusted$
+        }                                                       // $line_count$Trusted$
+    }                                                           // $line_count$Trusted$
+
+    transition!{                                                // $line_count$Trusted$
+        internal_next(label: Label<DT>, rid: ReqId, input: InputOperation<DT>, output: OutputOperation<DT>) {   // $line_count$Trusted$
+            require label.is_Internal();                     // $line_count$Trusted$
+            require pre.reqs.dom().contains(rid);            // $line_count$Trusted$
+            require pre.reqs[rid] == input;                  // $line_count$Trusted$
+            update reqs = pre.reqs.remove(rid);              // $line_count$Trusted$
+            update resps = pre.resps.insert(rid, output);    // $line_count$Trusted$
+
+            match input {                                    // $line_count$Trusted$
+                InputOperation::Read(read_op) => {           // $line_count$Trusted$
+                    require output === OutputOperation::Read(DT::dispatch_spec(pre.state, read_op));  // $line_count$Trusted$
+                }                                                                           // $line_count$Trusted$
+                InputOperation::Write(write_op) => {                                        // $line_count$Trusted$
+                    let (next_state, out) = DT::dispatch_mut_spec(pre.state, write_op);     // $line_count$Trusted$
+                    require output === OutputOperation::Write(out);                         // $line_count$Trusted$
+                    update state = next_state;                                              // $line_count$Trusted$
+                }                                                                           // $line_count$Trusted$
+            }                                                                               // $line_count$Trusted$
+        }                                                                                   // $line_count$Trusted$
+    }                                                                                       // $line_count$Trusted$
+
+    transition!{                                        // $line_count$Trusted$
+        no_op(label: Label<DT>) {                       // $line_count$Trusted$
+            require label.is_Internal();                // $line_count$Trusted$
+            /* stutter step */                          // $line_count$Trusted$
+        }                                               // $line_count$Trusted$
+    }                                                   // $line_count$Trusted$
+
+    // a withdrawn request leaves the system without a response. It is indistinguishable from
+    // a request that never completes. Only requests that have not been linearized can be
+    // withdrawn, a computed response can't disappear.
+    transition!{                                                        // $line_count$Trusted$
+        cancel(label: Label<DT>, rid: ReqId) {                          // $line_count$Trusted$
+            require label.is_Internal();                                // $line_count$Trusted$
+            require pre.reqs.dom().contains(rid);                       // $line_count$Trusted$
+            update reqs = pre.reqs.remove(rid);                         // $line_count$Trusted$
+        }                                                               // $line_count$Trusted$
+    }                                                                   // $line_count$Trusted$
+
+    transition!{                                                            // $line_count$Trusted$
+        start(label: Label<DT>, rid: ReqId, input: InputOperation<DT>) {    // $line_count$Trusted$
+            require label == AsyncLabel::<DT>::Start(rid, input);           // $line_count$Trusted$
+            require !pre.reqs.dom().contains(rid);                          // $line_count$Trusted$
+            update reqs = pre.reqs.insert(rid, input);                      // $line_count$Trusted$
+        }                                                                   // $line_count$Trusted$
+    }                                                                       // $line_count$Trusted$
+
+    transition!{                                                            // $line_count$Trusted$
+        end(label: Label<DT>, rid: ReqId, output: OutputOperation<DT>) {    // $line_count$Trusted$
+            require label == AsyncLabel::<DT>::End(rid, output);            // $line_count$Trusted$
+            require pre.resps.dom().contains(rid);                          // $line_count$Trusted$
+            require pre.resps[rid] == output;                               // $line_count$Trusted$
+            update resps = pre.resps.remove(rid);                           // $line_count$Trusted$
+        }                                                                   // $line_count$Trusted$
+    }                                                                       // $line_count$Trusted$
+}}  // $line_count$Trusted$
+
+
+#[is_variant]
+#[verus::trusted]
+pub enum SimpleLogBehavior<DT: Dispatch> {
+    Stepped(SimpleLog::State<DT>, AsyncLabel<DT>, Box<SimpleLogBehavior<DT>>),
+    Inited(SimpleLog::State<DT>),
+}
+
+#[verus::trusted]
+impl<DT: Dispatch> SimpleLogBehavior<DT> {
+    pub open spec fn get_last(self) -> SimpleLog::State<DT> {
+        match self {
+            SimpleLogBehavior::Stepped(post, op, tail) => post,
+            SimpleLogBehavior::Inited(post) => post,
+        }
+    }
+
+    pub open spec fn wf(self) -> bool
+        decreases self,
+    {
+        match self {
+            SimpleLogBehavior::Stepped(post, op, tail) => {
+                tail.wf() && SimpleLog::State::next(tail.get_last(), post, op)
+            },
+            SimpleLogBehavior::Inited(post) => { SimpleLog::State::init(post) },
+        }
+    }
+}
+
+#[is_variant]
+#[verus::trusted]
+pub enum AsynchronousSingletonBehavior<DT: Dispatch> {
+    Stepped(
+        AsynchronousSingleton::State<DT>,
+        AsyncLabel<DT>,
+        Box<AsynchronousSingletonBehavior<DT>>,
+    ),
+    Inited(AsynchronousSingleton::State<DT>),
+}
+
+#[verus::trusted]
+impl<DT: Dispatch> AsynchronousSingletonBehavior<DT> {
+    pub open spec fn get_last(self) -> AsynchronousSingleton::State<DT> {
+        match self {
+            AsynchronousSingletonBehavior::Stepped(post, op, tail) => post,
+            AsynchronousSingletonBehavior::Inited(post) => post,
+        }
+    }
+
+    pub open spec fn wf(self) -> bool
+        decreases self,
+    {
+        match self {
+            AsynchronousSingletonBehavior::Stepped(post, op, tail) => {
+                tail.wf() && AsynchronousSingleton::State::next(tail.get_last(), post, op)
+            },
+            AsynchronousSingletonBehavior::Inited(post) => {
+                AsynchronousSingleton::State::init(post)
+            },
+        }
+    }
+}
+
+#[verus::trusted]
+pub open spec fn behavior_equiv<DT: Dispatch>(
+    a: SimpleLogBehavior<DT>,
+    b: AsynchronousSingletonBehavior<DT>,
+) -> bool
+    decreases a, b,
+{
+    // (a.Inited? && b.Inited?)
+    ||| (a.is_Inited()
+        && b.is_Inited())
+    // || (a.Stepped? && a.op.InternalOp? && equiv(a.tail, b))
+
+    ||| (a.is_Stepped() && a.get_Stepped_1().is_Internal() && behavior_equiv(
+        *a.get_Stepped_2(),
+        b,
+    ))
+    // || (b.Stepped? && b.op.InternalOp? && equiv(a, b.tail))
+
+    ||| (b.is_Stepped() && b.get_Stepped_1().is_Internal() && behavior_equiv(
+        a,
+        *b.get_Stepped_2(),
+    ))
+    // || (a.Stepped? && b.Stepped? && a.op == b.op && equiv(a.tail, b.tail))
+
+    ||| (a.is_Stepped() && b.is_Stepped() && a.get_Stepped_1() == b.get_Stepped_1()
+        && behavior_equiv(*a.get_Stepped_2(), *b.get_Stepped_2()))
+}
+
+#[verus::trusted]
+pub(crate) trait SimpleLogRefinesAsynchronousSingleton<DT: Dispatch> {
+    proof fn exists_equiv_behavior(a: SimpleLogBehavior<DT>) -> (b: AsynchronousSingletonBehavior<
+        DT,
+    >)
+        requires
+            a.wf(),
+        ensures
+            b.wf() && behavior_equiv(a, b),
+    ;
+}
+
+#[verus::trusted]
+spec fn implements_SimpleLogRefinesAsynchronousSingleton<
+    DT: Dispatch,
+    RP: SimpleLogRefinesAsynchronousSingleton<DT>,
+>() -> bool {
+    true
+}
+
+} // verus!
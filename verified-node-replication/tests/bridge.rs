@@ -0,0 +1,196 @@
+// Tests of the Primary-Backup Bridge
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// trustedness: ignore this file
+
+//! Ships the updates of a primary through an in-memory stream to a follower, and checks that the
+//! follower rejects streams with truncated frames and missing updates.
+
+use std::io::{self, Cursor, Write};
+
+use builtin::Tracked;
+
+use verified_node_replication::bridge::{Follower, Primary, WireDispatch, MAX_FRAME_SIZE};
+use verified_node_replication::reference::history::Encode;
+use verified_node_replication::{AffinityFn, NodeReplicated, NodeReplicatedT, ThreadToken};
+
+mod common;
+
+use common::{ReadonlyOp, Register, UpdateOp};
+
+/// the number of replicas of the primary and the follower
+const NUM_REPLICAS: usize = 2;
+
+impl WireDispatch for Register {
+    fn encode_op(op: &UpdateOp, buf: &mut Vec<u8>) {
+        op.encode(buf)
+    }
+
+    fn decode_op(mut buf: &[u8]) -> Option<UpdateOp> {
+        UpdateOp::decode(&mut buf)
+    }
+}
+
+/// creates a data structure and registers a thread with its first replica
+fn replicated() -> (NodeReplicated<Register>, ThreadToken<Register>) {
+    let mut nr = NodeReplicated::<Register>::new(NUM_REPLICAS, AffinityFn::new(|_| {}));
+    match nr.register(0) {
+        Some(tkn) => (nr, tkn),
+        None => panic!("could not register with replica 0"),
+    }
+}
+
+/// reads the register
+fn get(
+    nr: &NodeReplicated<Register>,
+    mut tkn: ThreadToken<Register>,
+) -> (u64, ThreadToken<Register>) {
+    loop {
+        match nr.execute(ReadonlyOp::Get, tkn, Tracked::assume_new()) {
+            Ok((resp, t, _)) => return (resp, t),
+            Err((t, _)) => tkn = t,
+        }
+    }
+}
+
+/// executes the updates on a primary, returns the stream and the final value of the register
+fn ship(ops: &[UpdateOp]) -> (Vec<u8>, u64) {
+    let mut stream = Vec::new();
+    let (nr, mut tkn) = replicated();
+    let primary = Primary::new(nr, &mut stream);
+    for &op in ops {
+        tkn = match primary.execute_mut(op, tkn) {
+            Ok((_, tkn)) => tkn,
+            Err((_, e)) => panic!("could not execute {:?} on the primary: {:?}", op, e),
+        };
+    }
+    assert_eq!(primary.shipped(), ops.len() as u64);
+    let (val, _) = get(primary.replicated(), tkn);
+    drop(primary);
+    (stream, val)
+}
+
+/// applies the stream to a new follower
+fn apply(stream: &[u8]) -> (io::Result<u64>, Follower<Register>) {
+    let (nr, tkn) = replicated();
+    let mut follower = Follower::new(nr);
+    let res = follower.apply_from(&mut Cursor::new(stream), tkn);
+    let res = res.map(|tkn| get(follower.replicated(), tkn).0);
+    (res, follower)
+}
+
+/// the updates the tests ship
+const OPS: [UpdateOp; 4] = [
+    UpdateOp::Set(40),
+    UpdateOp::Inc,
+    UpdateOp::Set(7),
+    UpdateOp::Inc,
+];
+
+#[test]
+fn follower_applies_the_updates_of_the_primary() {
+    let (stream, expected) = ship(&OPS);
+    let (res, follower) = apply(&stream);
+    assert_eq!(res.unwrap(), expected);
+    assert_eq!(follower.applied(), OPS.len() as u64);
+}
+
+#[test]
+fn follower_accepts_an_empty_stream() {
+    let (res, follower) = apply(&[]);
+    assert_eq!(res.unwrap(), 0);
+    assert_eq!(follower.applied(), 0);
+}
+
+#[test]
+fn truncated_frames_are_rejected() {
+    let (stream, _) = ship(&OPS);
+    // the last frame is `Inc`: 8 bytes sequence number, 4 bytes length and 1 byte operation
+    let last = stream.len() - 13;
+    for len in [last + 1, last + 8, last + 10, stream.len() - 1] {
+        let (res, follower) = apply(&stream[..len]);
+        let err = res.unwrap_err();
+        assert_eq!(
+            err.kind(),
+            io::ErrorKind::UnexpectedEof,
+            "truncated to {} bytes",
+            len
+        );
+        assert_eq!(follower.applied(), OPS.len() as u64 - 1);
+    }
+}
+
+#[test]
+fn missing_updates_are_rejected() {
+    let (stream, _) = ship(&OPS);
+    // drop the second frame, `Inc`
+    let mut gap = stream[..14].to_vec();
+    gap.extend_from_slice(&stream[27..]);
+    let (res, follower) = apply(&gap);
+    assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(follower.applied(), 1);
+}
+
+#[test]
+fn oversized_frames_are_rejected() {
+    let mut stream = 0u64.to_le_bytes().to_vec();
+    stream.extend_from_slice(&(MAX_FRAME_SIZE as u32 + 1).to_le_bytes());
+    let (res, follower) = apply(&stream);
+    assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(follower.applied(), 0);
+}
+
+/// a stream that fails all writes after the first `ok` bytes
+struct FailingSink {
+    ok: usize,
+}
+
+impl Write for FailingSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.ok == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "connection reset",
+            ));
+        }
+        let n = buf.len().min(self.ok);
+        self.ok -= n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn primary_refuses_updates_after_a_failed_write() {
+    let (nr, tkn) = replicated();
+    // the first frame, `Inc`, fits
+    let primary = Primary::new(nr, FailingSink { ok: 13 });
+    let tkn = match primary.execute_mut(UpdateOp::Inc, tkn) {
+        Ok((_, tkn)) => tkn,
+        Err((_, e)) => panic!("could not ship the first update: {:?}", e),
+    };
+
+    // the second update executes on the primary, but doesn't reach the follower
+    let tkn = match primary.execute_mut(UpdateOp::Inc, tkn) {
+        Ok(_) => panic!("the write of the second update did not fail"),
+        Err((tkn, e)) => {
+            assert_eq!(e.unwrap().kind(), io::ErrorKind::ConnectionReset);
+            tkn
+        }
+    };
+    assert!(primary.failed());
+
+    // later updates are refused without executing them
+    let tkn = match primary.execute_mut(UpdateOp::Set(9), tkn) {
+        Ok(_) => panic!("the primary accepted an update after a failed write"),
+        Err((tkn, e)) => {
+            assert_eq!(e.unwrap().kind(), io::ErrorKind::BrokenPipe);
+            tkn
+        }
+    };
+    assert_eq!(primary.shipped(), 1);
+    assert_eq!(get(primary.replicated(), tkn).0, 2);
+}
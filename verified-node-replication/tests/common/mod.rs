@@ -0,0 +1,94 @@
+// Common Test Data Structures
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// trustedness: ignore this file
+
+//! A small register shared by the integration tests.
+
+use verified_node_replication::reference::history::Encode;
+use verified_node_replication::Dispatch;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Register Data Structure
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateOp {
+    /// sets the register, returns the old value
+    Set(u8),
+    /// increments the register, returns the new value
+    Inc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadonlyOp {
+    Get,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub val: u64,
+}
+
+impl Dispatch for Register {
+    type ReadOperation = ReadonlyOp;
+    type WriteOperation = UpdateOp;
+    type Response = u64;
+    type View = Register;
+
+    fn init() -> Self {
+        Register { val: 0 }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        *op
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        *op
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            ReadonlyOp::Get => self.val,
+        }
+    }
+
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            UpdateOp::Set(v) => std::mem::replace(&mut self.val, v as u64),
+            UpdateOp::Inc => {
+                self.val += 1;
+                self.val
+            }
+        }
+    }
+}
+
+impl Encode for UpdateOp {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            UpdateOp::Set(v) => {
+                out.push(0);
+                out.push(*v);
+            }
+            UpdateOp::Inc => out.push(1),
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(UpdateOp::Set(u8::decode(input)?)),
+            1 => Some(UpdateOp::Inc),
+            _ => None,
+        }
+    }
+}
+
+impl Encode for ReadonlyOp {
+    fn encode(&self, _out: &mut Vec<u8>) {}
+
+    fn decode(_input: &mut &[u8]) -> Option<Self> {
+        Some(ReadonlyOp::Get)
+    }
+}
@@ -0,0 +1,167 @@
+// Linearizability Checks of the Verified NR Implementation
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// trustedness: ignore this file
+
+//! Records concurrent histories of `execute` and `execute_mut` on a small register and checks
+//! that they are linearizable. The proofs cover the implementation, this checks the trusted
+//! boundary between the executable and the ghost code and the unverified glue around it.
+//!
+//! Histories that are not linearizable are written to the temporary directory in the format of
+//! `reference::history`. Set `VNR_HISTORY_DIR` to record every history of the run, and
+//! `VNR_HISTORY` to the path of a recorded history to print and re-check it:
+//!
+//! `VNR_HISTORY=<file> cargo test --test linearizability --features reference -- --ignored`
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use proptest::prelude::*;
+
+use builtin::Tracked;
+
+use verified_node_replication::reference::history::{format_history, load_history, save_history};
+use verified_node_replication::reference::linearizability::{
+    check_linearizable, Clock, HistoryEntry, Operation,
+};
+use verified_node_replication::{AffinityFn, NodeReplicated, NodeReplicatedT};
+
+mod common;
+
+use common::{ReadonlyOp, Register, UpdateOp};
+
+/// the number of replicas
+const NUM_REPLICAS: usize = 2;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Recording Histories
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Read(ReadonlyOp),
+    Write(UpdateOp),
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        Just(Op::Read(ReadonlyOp::Get)),
+        Just(Op::Write(UpdateOp::Inc)),
+        any::<u8>().prop_map(|v| Op::Write(UpdateOp::Set(v))),
+    ]
+}
+
+/// runs the operations of each thread concurrently and records the history
+fn record_history(threads: Vec<Vec<Op>>) -> Vec<HistoryEntry<Register>> {
+    let mut nr = NodeReplicated::<Register>::new(NUM_REPLICAS, AffinityFn::new(|_| {}));
+    let mut tokens = Vec::with_capacity(threads.len());
+    for idx in 0..threads.len() {
+        match nr.register(idx % NUM_REPLICAS) {
+            Some(tkn) => tokens.push(tkn),
+            None => panic!("could not register with replica {}", idx % NUM_REPLICAS),
+        }
+    }
+
+    let nr = Arc::new(nr);
+    let clock = Arc::new(Clock::new());
+
+    let handles: Vec<_> = threads
+        .into_iter()
+        .zip(tokens)
+        .enumerate()
+        .map(|(thread, (ops, mut tkn))| {
+            let nr = nr.clone();
+            let clock = clock.clone();
+            std::thread::spawn(move || {
+                let mut history = Vec::with_capacity(ops.len());
+                for op in ops {
+                    let invoked = clock.tick();
+                    let (op, response) = match op {
+                        Op::Read(op) => loop {
+                            match nr.execute(op, tkn, Tracked::assume_new()) {
+                                Ok((resp, t, _)) => {
+                                    tkn = t;
+                                    break (Operation::Read(op), resp);
+                                }
+                                Err((t, _)) => tkn = t,
+                            }
+                        },
+                        Op::Write(op) => loop {
+                            match nr.execute_mut(op, tkn, Tracked::assume_new()) {
+                                Ok((resp, t, _)) => {
+                                    tkn = t;
+                                    break (Operation::Write(op), resp);
+                                }
+                                Err((t, _)) => tkn = t,
+                            }
+                        },
+                    };
+                    let returned = clock.tick();
+                    history.push(HistoryEntry { thread, op, response, invoked, returned });
+                }
+                history
+            })
+        })
+        .collect();
+
+    handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+}
+
+/// the number of histories saved by this run, to name the files
+static SAVED: AtomicUsize = AtomicUsize::new(0);
+
+/// saves the history to `dir` and returns the path of the file
+fn save(history: &[HistoryEntry<Register>], dir: PathBuf) -> PathBuf {
+    let idx = SAVED.fetch_add(1, Ordering::Relaxed);
+    let path = dir.join(format!("vnr-history-{}-{}.bin", std::process::id(), idx));
+    match save_history(history, &path) {
+        Ok(()) => path,
+        Err(e) => panic!("could not save the history to {}: {}", path.display(), e),
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn histories_are_linearizable(
+        threads in prop::collection::vec(prop::collection::vec(op_strategy(), 1..6), 2..5)
+    ) {
+        let history = record_history(threads);
+        if let Some(dir) = std::env::var_os("VNR_HISTORY_DIR") {
+            save(&history, PathBuf::from(dir));
+        }
+        let res = check_linearizable(&history);
+        if res.is_err() {
+            let path = save(&history, std::env::temp_dir());
+            prop_assert!(false, "history is not linearizable: {:?}, see {}", res, path.display());
+        }
+    }
+}
+
+#[test]
+fn recorded_histories_roundtrip() {
+    let history = record_history(vec![
+        vec![Op::Write(UpdateOp::Set(200)), Op::Read(ReadonlyOp::Get)],
+        vec![Op::Write(UpdateOp::Inc), Op::Write(UpdateOp::Inc)],
+    ]);
+
+    let path = save(&history, std::env::temp_dir());
+    let loaded = load_history::<Register>(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(format_history(&loaded), format_history(&history));
+    assert!(check_linearizable(&loaded).is_ok());
+}
+
+/// prints and re-checks the history recorded in the file at `VNR_HISTORY`
+#[test]
+#[ignore]
+fn check_recorded_history() {
+    let path = std::env::var_os("VNR_HISTORY").expect("set VNR_HISTORY to a recorded history");
+    let history = load_history::<Register>(&PathBuf::from(path)).unwrap();
+    print!("{}", format_history(&history));
+    let res = check_linearizable(&history);
+    assert!(res.is_ok(), "history is not linearizable: {:?}", res);
+}
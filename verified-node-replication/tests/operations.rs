@@ -0,0 +1,394 @@
+// Tests of the Non-Blocking and Configuration Paths of the Verified NR Implementation
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// trustedness: ignore this file
+
+//! Exercises the paths around `execute_mut` that rely on unverified glue: withdrawing updates
+//! (`cancel`, `try_execute_mut`, `execute_mut_timeout`), split submission (`submit_mut`, `poll`,
+//! `wait`), parking waiters, sharded registration and the builder.
+//!
+//! To hold the combiner lock of a replica, a thread executes a `Block` update on it, which waits
+//! in `dispatch_mut` until its gate opens. Meanwhile, the other threads of the replica can
+//! enqueue their updates, but the combiner doesn't collect them.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use builtin::Tracked;
+
+use verified_node_replication::constants::MAX_REPLICAS;
+use verified_node_replication::{
+    AffinityFn, BuildError, Dispatch, NodeReplicated, NodeReplicatedBuilder, NodeReplicatedT,
+    ResponseDelivery, ShardedNodeReplicated, ThreadToken,
+};
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Counter Data Structure
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// a gate a `Block` update waits on
+#[derive(Default)]
+pub struct Gate {
+    /// set once an update waits on the gate
+    entered: AtomicBool,
+    /// set to let the updates pass
+    open: AtomicBool,
+}
+
+#[derive(Clone)]
+pub enum UpdateOp {
+    /// increments the counter, returns the new value
+    Inc,
+    /// waits until the gate is open, returns the value
+    Block(Arc<Gate>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadonlyOp {
+    Get,
+}
+
+pub struct Counter {
+    pub val: u64,
+}
+
+impl Dispatch for Counter {
+    type ReadOperation = ReadonlyOp;
+    type WriteOperation = UpdateOp;
+    type Response = u64;
+    type View = Counter;
+
+    fn init() -> Self {
+        Counter { val: 0 }
+    }
+
+    fn clone_write_op(op: &Self::WriteOperation) -> Self::WriteOperation {
+        op.clone()
+    }
+
+    fn clone_response(op: &Self::Response) -> Self::Response {
+        *op
+    }
+
+    fn dispatch(&self, op: Self::ReadOperation) -> Self::Response {
+        match op {
+            ReadonlyOp::Get => self.val,
+        }
+    }
+
+    fn dispatch_mut(&mut self, op: Self::WriteOperation) -> Self::Response {
+        match op {
+            UpdateOp::Inc => {
+                self.val += 1;
+                self.val
+            }
+            UpdateOp::Block(gate) => {
+                gate.entered.store(true, Ordering::SeqCst);
+                while !gate.open.load(Ordering::SeqCst) {
+                    std::thread::yield_now();
+                }
+                self.val
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Helpers
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// the number of replicas
+const NUM_REPLICAS: usize = 2;
+
+/// creates a data structure and registers the given number of threads with replica 0
+fn replicated(threads: usize) -> (NodeReplicated<Counter>, Vec<ThreadToken<Counter>>) {
+    let mut nr = NodeReplicated::<Counter>::new(NUM_REPLICAS, AffinityFn::new(|_| {}));
+    let tokens = (0..threads)
+        .map(|_| match nr.register(0) {
+            Some(tkn) => tkn,
+            None => panic!("could not register with replica 0"),
+        })
+        .collect();
+    (nr, tokens)
+}
+
+/// executes the update, retrying until it is accepted
+fn update(
+    nr: &NodeReplicated<Counter>,
+    op: UpdateOp,
+    mut tkn: ThreadToken<Counter>,
+) -> (u64, ThreadToken<Counter>) {
+    loop {
+        match nr.execute_mut(op.clone(), tkn, Tracked::assume_new()) {
+            Ok((resp, t, _)) => return (resp, t),
+            Err((t, _)) => tkn = t,
+        }
+    }
+}
+
+/// reads the counter
+fn get(nr: &NodeReplicated<Counter>, mut tkn: ThreadToken<Counter>) -> (u64, ThreadToken<Counter>) {
+    loop {
+        match nr.execute(ReadonlyOp::Get, tkn, Tracked::assume_new()) {
+            Ok((resp, t, _)) => return (resp, t),
+            Err((t, _)) => tkn = t,
+        }
+    }
+}
+
+/// lets a thread become the combiner of the replica of `tkn` and keep its lock until the gate
+/// opens
+fn hold_combiner(
+    nr: &Arc<NodeReplicated<Counter>>,
+    tkn: ThreadToken<Counter>,
+) -> (Arc<Gate>, JoinHandle<ThreadToken<Counter>>) {
+    let gate = Arc::new(Gate::default());
+    let handle = {
+        let nr = nr.clone();
+        let gate = gate.clone();
+        std::thread::spawn(move || update(&nr, UpdateOp::Block(gate), tkn).1)
+    };
+    while !gate.entered.load(Ordering::SeqCst) {
+        std::thread::yield_now();
+    }
+    (gate, handle)
+}
+
+/// opens the gate and waits for the combiner to finish
+fn release_combiner(
+    gate: Arc<Gate>,
+    handle: JoinHandle<ThreadToken<Counter>>,
+) -> ThreadToken<Counter> {
+    gate.open.store(true, Ordering::SeqCst);
+    handle.join().unwrap()
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Withdrawing Updates
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn cancel_before_collection_withdraws_the_update() {
+    let (nr, mut tokens) = replicated(2);
+    let nr = Arc::new(nr);
+    let tkn = tokens.pop().unwrap();
+    let (gate, combiner) = hold_combiner(&nr, tokens.pop().unwrap());
+
+    let handle = match nr.submit_mut(UpdateOp::Inc, tkn, Tracked::assume_new()) {
+        Ok(handle) => handle,
+        Err(_) => panic!("could not submit the update"),
+    };
+    assert!(!handle.is_done());
+    let tkn = match nr.cancel(handle) {
+        Ok(tkn) => tkn,
+        Err(_) => panic!("the update was collected while the combiner was busy"),
+    };
+
+    release_combiner(gate, combiner);
+    assert_eq!(get(&nr, tkn).0, 0);
+}
+
+#[test]
+fn cancel_after_collection_returns_the_handle() {
+    let (nr, mut tokens) = replicated(1);
+    // the replica is idle, the submitting thread combines and collects its own update
+    let handle = match nr.submit_mut(UpdateOp::Inc, tokens.pop().unwrap(), Tracked::assume_new()) {
+        Ok(handle) => handle,
+        Err(_) => panic!("could not submit the update"),
+    };
+    let handle = match nr.cancel(handle) {
+        Ok(_) => panic!("an update was withdrawn after the combiner collected it"),
+        Err(handle) => handle,
+    };
+
+    let (resp, tkn, _) = nr.wait(handle);
+    assert_eq!(resp, 1);
+    assert_eq!(get(&nr, tkn).0, 1);
+}
+
+#[test]
+fn try_execute_mut_fails_while_the_combiner_is_held() {
+    let (nr, mut tokens) = replicated(2);
+    let nr = Arc::new(nr);
+    let tkn = tokens.pop().unwrap();
+    let (gate, combiner) = hold_combiner(&nr, tokens.pop().unwrap());
+
+    let tkn = match nr.try_execute_mut(UpdateOp::Inc, tkn, Tracked::assume_new()) {
+        Ok(_) => panic!("try_execute_mut succeeded while the combiner was busy"),
+        Err((tkn, _)) => tkn,
+    };
+
+    release_combiner(gate, combiner);
+    let tkn = match nr.try_execute_mut(UpdateOp::Inc, tkn, Tracked::assume_new()) {
+        Ok((resp, tkn, _)) => {
+            assert_eq!(resp, 1);
+            tkn
+        }
+        Err(_) => panic!("try_execute_mut failed on an idle replica"),
+    };
+    assert_eq!(get(&nr, tkn).0, 1);
+}
+
+#[test]
+fn execute_mut_timeout_withdraws_the_update_when_it_expires() {
+    let (nr, mut tokens) = replicated(2);
+    let nr = Arc::new(nr);
+    let tkn = tokens.pop().unwrap();
+    let (gate, combiner) = hold_combiner(&nr, tokens.pop().unwrap());
+
+    let timeout = Duration::from_millis(10);
+    let tkn = match nr.execute_mut_timeout(UpdateOp::Inc, tkn, Tracked::assume_new(), timeout) {
+        Ok(_) => panic!("the update completed while the combiner was busy"),
+        Err((tkn, _)) => tkn,
+    };
+
+    release_combiner(gate, combiner);
+    let timeout = Duration::from_secs(60);
+    let tkn = match nr.execute_timeout(ReadonlyOp::Get, tkn, Tracked::assume_new(), timeout) {
+        Ok((resp, tkn, _)) => {
+            assert_eq!(resp, 0, "the withdrawn update was applied");
+            tkn
+        }
+        Err(_) => panic!("the read timed out on an idle replica"),
+    };
+    match nr.execute_mut_timeout(UpdateOp::Inc, tkn, Tracked::assume_new(), timeout) {
+        Ok((resp, _, _)) => assert_eq!(resp, 1),
+        Err(_) => panic!("the update timed out on an idle replica"),
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Split Submission and Parking
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn submitted_updates_are_polled_to_completion() {
+    let (nr, tokens) = replicated(2);
+    let mut handles: Vec<_> = tokens
+        .into_iter()
+        .map(
+            |tkn| match nr.submit_mut(UpdateOp::Inc, tkn, Tracked::assume_new()) {
+                Ok(handle) => handle,
+                Err(_) => panic!("could not submit the update"),
+            },
+        )
+        .collect();
+
+    let mut responses = Vec::new();
+    let mut tkn = None;
+    while let Some(handle) = handles.pop() {
+        let handle = nr.poll(handle);
+        match handle.take_response() {
+            Ok((resp, t, _)) => {
+                responses.push(resp);
+                tkn = Some(t);
+            }
+            Err(handle) => handles.insert(0, handle),
+        }
+    }
+    responses.sort_unstable();
+    assert_eq!(responses, vec![1, 2]);
+    assert_eq!(get(&nr, tkn.unwrap()).0, 2);
+}
+
+#[test]
+fn parked_waiters_receive_their_responses() {
+    const THREADS: usize = 4;
+    const OPS: usize = 1000;
+
+    let mut nr = NodeReplicated::<Counter>::builder()
+        .replicas(1)
+        .threads_per_replica(THREADS)
+        .response_delivery(ResponseDelivery::Park)
+        .build()
+        .ok()
+        .unwrap();
+    let tokens: Vec<_> = (0..THREADS).map(|_| nr.register(0).unwrap()).collect();
+    let nr = Arc::new(nr);
+
+    let handles: Vec<_> = tokens
+        .into_iter()
+        .map(|mut tkn| {
+            let nr = nr.clone();
+            std::thread::spawn(move || {
+                for _ in 0..OPS {
+                    tkn = update(&nr, UpdateOp::Inc, tkn).1;
+                }
+                tkn
+            })
+        })
+        .collect();
+    let mut tokens: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    let tkn = tokens.pop().unwrap();
+    assert_eq!(get(&nr, tkn).0, (THREADS * OPS) as u64);
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Sharding and Building
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn failed_sharded_registration_unregisters_the_earlier_shards() {
+    // shard 0 has a single thread slot on replica 1, shard 1 has no replica 1
+    let shard0 = NodeReplicated::<Counter>::builder()
+        .replicas(2)
+        .threads_per_replica(1)
+        .build()
+        .ok()
+        .unwrap();
+    let shard1 = NodeReplicated::<Counter>::new(1, AffinityFn::new(|_| {}));
+    let mut sharded = ShardedNodeReplicated::new(vec![shard0, shard1]);
+
+    assert!(sharded.register(1).is_none());
+    // the slot of shard 0 was given back
+    assert!(sharded.shards[0].register(1).is_some());
+
+    let tkn = match sharded.register(0) {
+        Some(tkn) => tkn,
+        None => panic!("could not register with replica 0 of all shards"),
+    };
+    match sharded.execute_mut(1, UpdateOp::Inc, tkn, Tracked::assume_new()) {
+        Ok((resp, _, _)) => assert_eq!(resp, 1),
+        Err(_) => panic!("could not execute on shard 1"),
+    }
+}
+
+#[test]
+fn builder_rejects_invalid_configurations() {
+    let build = |b: NodeReplicatedBuilder<Counter>| b.build().err();
+    assert_eq!(
+        build(NodeReplicated::builder().replicas(0)),
+        Some(BuildError::Replicas(0))
+    );
+    assert_eq!(
+        build(NodeReplicated::builder().replicas(MAX_REPLICAS + 1)),
+        Some(BuildError::Replicas(MAX_REPLICAS + 1))
+    );
+    assert_eq!(
+        build(NodeReplicated::builder().replicas(1).threads_per_replica(0)),
+        Some(BuildError::ThreadsPerReplica(0))
+    );
+    assert_eq!(
+        build(NodeReplicated::builder().replicas(2).replica_nodes(vec![0])),
+        Some(BuildError::ReplicaNodes(1))
+    );
+}
+
+#[test]
+fn builder_limits_the_threads_per_replica() {
+    let mut nr = NodeReplicated::<Counter>::builder()
+        .replicas(2)
+        .threads_per_replica(1)
+        .replica_nodes(vec![0, 0])
+        .build()
+        .ok()
+        .unwrap();
+    assert_eq!(nr.replicas.len(), 2);
+    let tkn = nr.register(0).unwrap();
+    assert!(nr.register(0).is_none());
+    nr.unregister(tkn);
+    let tkn = nr.register(0).unwrap();
+    assert_eq!(update(&nr, UpdateOp::Inc, tkn).0, 1);
+}
@@ -0,0 +1,284 @@
+// Randomized Tests of the Reference Interpreter
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// trustedness: ignore this file
+
+//! Random walks through the `UnboundedLog` and `CyclicBuffer` reference interpreters that check
+//! the invariants after every transition, and a differential test of the executable
+//! implementation against the `UnboundedLog` interpreter.
+//!
+//! A failing random walk means the interpreter, and most likely the spec it mirrors, allows a
+//! behavior that breaks one of the invariants checked by `check_invariant`.
+
+use proptest::prelude::*;
+
+use builtin::Tracked;
+
+use verified_node_replication::reference::cyclicbuffer::{self, CyclicBuffer};
+use verified_node_replication::reference::unbounded_log::{
+    InputOperation, OutputOperation, ReadonlyState, Transition, UnboundedLog, UpdateState,
+};
+use verified_node_replication::reference::{LogEntry, NodeId, ReqId};
+use verified_node_replication::{AffinityFn, NodeReplicated, NodeReplicatedT};
+
+mod common;
+
+use common::{ReadonlyOp, Register, UpdateOp};
+
+/// the maximum number of replicas
+const MAX_REPLICAS: usize = 3;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Random Walks
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// a step of a random walk, the indices select among the enabled choices
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    /// adds a read request
+    Read,
+    /// adds an update request
+    Write(UpdateOp),
+    /// consumes the stub of a completed request
+    Consume(usize),
+    /// applies an enabled transition
+    Transition(usize),
+}
+
+fn step_strategy() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        1 => Just(Step::Read),
+        1 => Just(Step::Write(UpdateOp::Inc)),
+        1 => any::<u8>().prop_map(|v| Step::Write(UpdateOp::Set(v))),
+        1 => any::<usize>().prop_map(Step::Consume),
+        12 => any::<usize>().prop_map(Step::Transition),
+    ]
+}
+
+/// the requests of the state that are done
+fn done_requests(s: &UnboundedLog<Register>) -> Vec<ReqId> {
+    let reads = s.local_reads.iter().filter(|(_, r)| matches!(r, ReadonlyState::Done { .. }));
+    let updates = s.local_updates.iter().filter(|(_, u)| matches!(u, UpdateState::Done { .. }));
+    reads.map(|(rid, _)| *rid).chain(updates.map(|(rid, _)| *rid)).collect()
+}
+
+/// takes the steps in the unbounded log, checking the invariant after each of them
+fn walk_unbounded_log(num_replicas: usize, steps: &[Step]) -> Result<(), TestCaseError> {
+    let mut s = UnboundedLog::<Register>::initialize(num_replicas).unwrap();
+    prop_assert_eq!(s.check_invariant(), Ok(()));
+
+    for (i, step) in steps.iter().enumerate() {
+        match *step {
+            Step::Read => {
+                s.add_ticket(InputOperation::Read(ReadonlyOp::Get));
+            }
+            Step::Write(op) => {
+                s.add_ticket(InputOperation::Write(op));
+            }
+            Step::Consume(idx) => {
+                let done = done_requests(&s);
+                if !done.is_empty() {
+                    let rid = done[idx % done.len()];
+                    prop_assert!(s.consume_stub(rid).is_ok(), "stub of {} not consumed", rid);
+                }
+            }
+            Step::Transition(idx) => {
+                let enabled = s.enabled_transitions();
+                if !enabled.is_empty() {
+                    let t = enabled[idx % enabled.len()];
+                    let res = s.apply_transition(t);
+                    prop_assert!(res.is_ok(), "enabled transition {:?} rejected: {:?}", t, res);
+                }
+            }
+        }
+        let res = s.check_invariant();
+        prop_assert!(res.is_ok(), "invariant violated after step {} ({:?}): {:?}", i, step, res);
+    }
+    Ok(())
+}
+
+/// takes the steps in the cyclic buffer, checking the invariant after each of them
+///
+/// Only the `Transition` steps apply, the `AppendFlipBit` transitions deposit an entry of the
+/// appending node.
+fn walk_cyclic_buffer(
+    buffer_size: u64,
+    num_replicas: usize,
+    steps: &[Step],
+) -> Result<(), TestCaseError> {
+    let mut s = CyclicBuffer::<Register>::initialize(buffer_size, num_replicas).unwrap();
+    prop_assert_eq!(s.check_invariant(), Ok(()));
+
+    for (i, step) in steps.iter().enumerate() {
+        let idx = match *step {
+            Step::Transition(idx) => idx,
+            _ => continue,
+        };
+        let mut enabled = s.enabled_transitions();
+        for node_id in 0..num_replicas {
+            let deposited = LogEntry { op: UpdateOp::Inc, node_id };
+            let t = cyclicbuffer::Transition::AppendFlipBit { node_id, deposited };
+            if s.check_enabled(&t).is_ok() {
+                enabled.push(t);
+            }
+        }
+        if !enabled.is_empty() {
+            let pick = idx % enabled.len();
+            let res = s.apply_transition(enabled.swap_remove(pick));
+            prop_assert!(res.is_ok(), "enabled transition rejected at step {}: {:?}", i, res);
+        }
+        let res = s.check_invariant();
+        prop_assert!(res.is_ok(), "invariant violated after step {}: {:?}", i, res);
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn unbounded_log_walks_keep_the_invariant(
+        num_replicas in 1..=MAX_REPLICAS,
+        steps in prop::collection::vec(step_strategy(), 0..200)
+    ) {
+        walk_unbounded_log(num_replicas, &steps)?;
+    }
+
+    #[test]
+    fn cyclic_buffer_walks_keep_the_invariant(
+        buffer_size in 1..6u64,
+        num_replicas in 1..=MAX_REPLICAS,
+        steps in prop::collection::vec(any::<usize>().prop_map(Step::Transition), 0..200)
+    ) {
+        walk_cyclic_buffer(buffer_size, num_replicas, &steps)?;
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Differential Testing
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Read(ReadonlyOp),
+    Write(UpdateOp),
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        Just(Op::Read(ReadonlyOp::Get)),
+        Just(Op::Write(UpdateOp::Inc)),
+        any::<u8>().prop_map(|v| Op::Write(UpdateOp::Set(v))),
+    ]
+}
+
+/// applies the transition, which must be enabled
+fn apply(s: &mut UnboundedLog<Register>, t: Transition) {
+    if let Err(e) = s.apply_transition(t) {
+        panic!("{:?} is not enabled: {:?}", t, e);
+    }
+}
+
+/// runs a round of the combiner of the node, placing the updates into the log first
+fn combine(s: &mut UnboundedLog<Register>, node_id: NodeId, updates: &[ReqId]) {
+    apply(s, Transition::ExecTrivialStart { node_id });
+    for &rid in updates {
+        apply(s, Transition::UpdatePlaceOpsInLogOne { node_id, rid });
+    }
+    apply(s, Transition::ExecLoadLocalVersion { node_id });
+    apply(s, Transition::ExecLoadGlobalHead { node_id });
+    loop {
+        if s.is_enabled(Transition::ExecDispatchLocal { node_id }) {
+            apply(s, Transition::ExecDispatchLocal { node_id });
+        } else if s.is_enabled(Transition::ExecDispatchRemote { node_id }) {
+            apply(s, Transition::ExecDispatchRemote { node_id });
+        } else {
+            break;
+        }
+    }
+    apply(s, Transition::ExecUpdateVersionUpperBound { node_id });
+    apply(s, Transition::ExecFinish { node_id });
+}
+
+/// executes the operation on the replica of the node with the interpreter
+fn interpret(s: &mut UnboundedLog<Register>, node_id: NodeId, op: Op) -> u64 {
+    let rid = match op {
+        Op::Read(op) => {
+            let rid = s.add_ticket(InputOperation::Read(op));
+            apply(s, Transition::ReadonlyVersionUpperBound { rid });
+            if !s.is_enabled(Transition::ReadonlyReadyToRead { rid, node_id }) {
+                combine(s, node_id, &[]);
+            }
+            apply(s, Transition::ReadonlyReadyToRead { rid, node_id });
+            apply(s, Transition::ReadonlyApply { rid });
+            rid
+        }
+        Op::Write(op) => {
+            let rid = s.add_ticket(InputOperation::Write(op));
+            combine(s, node_id, &[rid]);
+            apply(s, Transition::UpdateDone { rid });
+            rid
+        }
+    };
+    match s.consume_stub(rid) {
+        Ok(OutputOperation::Read(ret)) | Ok(OutputOperation::Write(ret)) => ret,
+        Err(e) => panic!("request {} is not done: {:?}", rid, e),
+    }
+}
+
+/// executes the operations in order on the given replicas, returns the responses
+fn execute(ops: &[(NodeId, Op)]) -> Vec<u64> {
+    let mut nr = NodeReplicated::<Register>::new(MAX_REPLICAS, AffinityFn::new(|_| {}));
+    let mut tokens: Vec<_> = (0..MAX_REPLICAS)
+        .map(|node_id| match nr.register(node_id) {
+            Some(tkn) => Some(tkn),
+            None => panic!("could not register with replica {}", node_id),
+        })
+        .collect();
+
+    let mut responses = Vec::with_capacity(ops.len());
+    for &(node_id, op) in ops {
+        let mut tkn = tokens[node_id].take().unwrap();
+        let resp = match op {
+            Op::Read(op) => loop {
+                match nr.execute(op, tkn, Tracked::assume_new()) {
+                    Ok((resp, t, _)) => {
+                        tkn = t;
+                        break resp;
+                    }
+                    Err((t, _)) => tkn = t,
+                }
+            },
+            Op::Write(op) => loop {
+                match nr.execute_mut(op, tkn, Tracked::assume_new()) {
+                    Ok((resp, t, _)) => {
+                        tkn = t;
+                        break resp;
+                    }
+                    Err((t, _)) => tkn = t,
+                }
+            },
+        };
+        tokens[node_id] = Some(tkn);
+        responses.push(resp);
+    }
+    responses
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    /// the executable implementation agrees with the interpreter on sequential executions
+    #[test]
+    fn exec_agrees_with_the_interpreter(
+        ops in prop::collection::vec((0..MAX_REPLICAS, op_strategy()), 1..40)
+    ) {
+        let mut s = UnboundedLog::<Register>::initialize(MAX_REPLICAS).unwrap();
+        let mut expected = Vec::with_capacity(ops.len());
+        for &(node_id, op) in ops.iter() {
+            expected.push(interpret(&mut s, node_id, op));
+            prop_assert_eq!(s.check_invariant(), Ok(()));
+        }
+        prop_assert_eq!(execute(&ops), expected);
+    }
+}
@@ -0,0 +1,271 @@
+// Verified Node Replication Library
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//
+//! Development tasks for the verified-node-replication crate.
+//!
+//! Run from the crate directory with `cargo xtask <task>`:
+//!
+//!  - `verify`: verifies the crate with Verus module by module and prints the verification time
+//!    of each module. CI runs the same command.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "\
+usage: cargo xtask verify [options] [-- <verus args>...]
+
+Verifies the verified-node-replication crate with Verus, one module at a time.
+
+options:
+    -m, --module <path>  verify only the given module, e.g. `spec::cyclicbuffer`, can be repeated.
+                         `crate` is the crate root.
+        --spec-only      verify without the `exec` feature, the state machines only
+        --list           list the modules and exit
+    -h, --help           print this help
+
+The Verus binary is taken from the `VERUS` environment variable, the `PATH`, or the build in the
+`verus` submodule (`tools/build-verus.sh`), in this order.
+";
+
+/// the module path of the crate root
+const ROOT_MODULE: &str = "crate";
+
+/// modules that are not verified, relative to `src`
+const UNVERIFIED_MODULES: &[&str] = &["reference", "bridge"];
+
+/// modules that are only compiled with the `exec` feature
+const EXEC_MODULES: &[&str] = &["exec"];
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("verify") => verify(&args[1..]),
+        Some("-h") | Some("--help") | Some("help") => print!("{USAGE}"),
+        Some(task) => fail(&format!("unknown task `{task}`\n\n{USAGE}")),
+        None => fail(USAGE),
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{msg}");
+    exit(1)
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Paths
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// the directory of the verified-node-replication crate
+fn crate_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+/// the root of the repository, contains the `verus` submodule
+fn repository_root() -> PathBuf {
+    crate_root().parent().unwrap().to_path_buf()
+}
+
+/// locates the Verus binary
+fn find_verus() -> PathBuf {
+    if let Some(verus) = env::var_os("VERUS") {
+        return PathBuf::from(verus);
+    }
+    if let Some(paths) = env::var_os("PATH") {
+        for dir in env::split_paths(&paths) {
+            let verus = dir.join("verus");
+            if verus.is_file() {
+                return verus;
+            }
+        }
+    }
+    let verus = repository_root().join("verus/source/target-verus/release/verus");
+    if verus.is_file() {
+        return verus;
+    }
+    fail("Verus not found. Set `VERUS`, add it to the `PATH`, or run `tools/build-verus.sh` first.")
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Modules
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// the module paths of the crate, derived from the files in `src`
+fn modules(spec_only: bool) -> Vec<String> {
+    let mut modules = vec![ROOT_MODULE.to_string()];
+    collect_modules(&crate_root().join("src"), "", &mut modules);
+    modules.retain(|m| {
+        let top = m.split("::").next().unwrap();
+        let unverified = UNVERIFIED_MODULES.contains(&top);
+        let exec_only = EXEC_MODULES.contains(&top);
+        !(unverified || (spec_only && exec_only))
+    });
+    modules[1..].sort();
+    modules
+}
+
+fn collect_modules(dir: &Path, prefix: &str, modules: &mut Vec<String>) {
+    let entries = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| fail(&format!("cannot read `{}`: {e}", dir.display())));
+    for entry in entries {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        if path.is_dir() {
+            collect_modules(&path, &format!("{prefix}{name}::"), modules);
+        } else if path.extension().map_or(false, |e| e == "rs") {
+            match name.as_str() {
+                "lib" => (),
+                "mod" => modules.push(prefix.trim_end_matches("::").to_string()),
+                _ => modules.push(format!("{prefix}{name}")),
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Verify
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// the outcome of verifying a single module
+struct ModuleResult {
+    module: String,
+    success: bool,
+    /// the `N verified, M errors` summary of Verus, if it printed one
+    summary: Option<String>,
+    time: Duration,
+}
+
+fn verify(args: &[String]) {
+    let mut selected = Vec::new();
+    let mut spec_only = false;
+    let mut list = false;
+    let mut verus_args = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-m" | "--module" => match args.next() {
+                Some(m) => selected.push(m.trim_start_matches("crate::").to_string()),
+                None => fail(&format!("`{arg}` needs a module\n\n{USAGE}")),
+            },
+            "--spec-only" => spec_only = true,
+            "--list" => list = true,
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return;
+            }
+            "--" => {
+                verus_args.extend(args.by_ref().cloned());
+            }
+            _ => fail(&format!("unknown argument `{arg}`\n\n{USAGE}")),
+        }
+    }
+
+    let all = modules(spec_only);
+    if list {
+        all.iter().for_each(|m| println!("{m}"));
+        return;
+    }
+    for m in selected.iter() {
+        if !all.contains(m) {
+            fail(&format!(
+                "unknown module `{m}`, see `cargo xtask verify --list`"
+            ));
+        }
+    }
+    let modules = if selected.is_empty() { all } else { selected };
+
+    let verus = find_verus();
+    println!("Using Verus at '{}'", verus.display());
+
+    let mut results = Vec::with_capacity(modules.len());
+    for module in modules {
+        println!("Verifying '{module}' ...");
+        results.push(verify_module(&verus, &module, spec_only, &verus_args));
+    }
+
+    print_results(&results);
+    if results.iter().any(|r| !r.success) {
+        exit(1);
+    }
+}
+
+/// runs Verus on the crate, verifying only the given module
+fn verify_module(
+    verus: &Path,
+    module: &str,
+    spec_only: bool,
+    verus_args: &[String],
+) -> ModuleResult {
+    let mut cmd = Command::new(verus);
+    cmd.current_dir(crate_root())
+        .args([
+            "--crate-type=lib",
+            "--expand-errors",
+            "--no-report-long-running",
+        ])
+        .args(["--cfg", "feature=\"spec\""]);
+    if !spec_only {
+        cmd.args(["--cfg", "feature=\"exec\""]);
+    }
+    if module == ROOT_MODULE {
+        cmd.arg("--verify-root");
+    } else {
+        cmd.args(["--verify-module", module]);
+    }
+    cmd.args(verus_args).arg("src/lib.rs");
+
+    let start = Instant::now();
+    let output = cmd
+        .output()
+        .unwrap_or_else(|e| fail(&format!("cannot run '{}': {e}", verus.display())));
+    let time = start.elapsed();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    print!("{stdout}");
+    eprint!("{stderr}");
+
+    // verification results:: 254 verified, 0 errors
+    let summary = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("verification results::"))
+        .map(|s| s.trim().to_string());
+
+    ModuleResult {
+        module: module.to_string(),
+        success: output.status.success(),
+        summary,
+        time,
+    }
+}
+
+fn print_results(results: &[ModuleResult]) {
+    let width = results
+        .iter()
+        .map(|r| r.module.len())
+        .max()
+        .unwrap_or(0)
+        .max("module".len());
+    println!();
+    println!(
+        "{:width$}  {:>10}  {:6}  result",
+        "module", "time (s)", "status"
+    );
+    for r in results {
+        let status = if r.success { "ok" } else { "FAILED" };
+        let summary = r.summary.as_deref().unwrap_or("-");
+        println!(
+            "{:width$}  {:>10.2}  {:6}  {summary}",
+            r.module,
+            r.time.as_secs_f64(),
+            status
+        );
+    }
+    let total: Duration = results.iter().map(|r| r.time).sum();
+    println!("{:width$}  {:>10.2}", "total", total.as_secs_f64());
+}